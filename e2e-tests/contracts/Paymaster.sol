@@ -0,0 +1,88 @@
+//SPDX-License-Identifier: Unlicense
+pragma solidity ^0.8.0;
+
+// Mirrors the `Transaction` struct of the bootloader (see `TransactionHelper.sol` of the system contracts).
+struct Transaction {
+    uint256 txType;
+    uint256 from;
+    uint256 to;
+    uint256 gasLimit;
+    uint256 gasPerPubdataByteLimit;
+    uint256 maxFeePerGas;
+    uint256 maxPriorityFeePerGas;
+    uint256 paymaster;
+    uint256 nonce;
+    uint256 value;
+    uint256[4] reserved;
+    bytes data;
+    bytes signature;
+    bytes32[] factoryDeps;
+    bytes paymasterInput;
+    bytes reservedDynamic;
+}
+
+enum ExecutionResult {
+    Revert,
+    Success
+}
+
+interface IPaymaster {
+    function validateAndPayForPaymasterTransaction(
+        bytes32 _txHash,
+        bytes32 _suggestedSignedHash,
+        Transaction calldata _transaction
+    ) external payable returns (bytes4 magic, bytes memory context);
+
+    function postTransaction(
+        bytes calldata _context,
+        Transaction calldata _transaction,
+        bytes32 _txHash,
+        bytes32 _suggestedSignedHash,
+        ExecutionResult _txResult,
+        uint256 _maxRefundedGas
+    ) external payable;
+}
+
+interface IPaymasterFlow {
+    function general(bytes calldata input) external;
+}
+
+/// Pays the fee of any transaction that uses the general paymaster flow.
+contract Paymaster is IPaymaster {
+    address constant BOOTLOADER_FORMAL_ADDRESS = address(0x8001);
+
+    modifier onlyBootloader() {
+        require(msg.sender == BOOTLOADER_FORMAL_ADDRESS, "Only bootloader can call this method");
+        _;
+    }
+
+    function validateAndPayForPaymasterTransaction(
+        bytes32,
+        bytes32,
+        Transaction calldata _transaction
+    ) external payable onlyBootloader returns (bytes4 magic, bytes memory context) {
+        require(_transaction.paymasterInput.length >= 4, "The standard paymaster input must be at least 4 bytes long");
+        require(
+            bytes4(_transaction.paymasterInput[0:4]) == IPaymasterFlow.general.selector,
+            "Unsupported paymaster flow"
+        );
+
+        uint256 requiredETH = _transaction.gasLimit * _transaction.maxFeePerGas;
+        (bool success, ) = payable(BOOTLOADER_FORMAL_ADDRESS).call{value: requiredETH}("");
+        require(success, "Failed to transfer the fee to the bootloader");
+
+        magic = IPaymaster.validateAndPayForPaymasterTransaction.selector;
+        context = "";
+    }
+
+    function postTransaction(
+        bytes calldata,
+        Transaction calldata,
+        bytes32,
+        bytes32,
+        ExecutionResult,
+        uint256
+    ) external payable override onlyBootloader {}
+
+    receive() external payable {}
+}
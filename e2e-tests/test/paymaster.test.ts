@@ -0,0 +1,93 @@
+import { expect } from "chai";
+import { Wallet, utils } from "zksync-web3";
+import * as hre from "hardhat";
+import { Deployer } from "@matterlabs/hardhat-zksync-deploy";
+import { ethers } from "ethers";
+import { RichAccounts } from "../helpers/constants";
+import { deployContract, getTestProvider } from "../helpers/utils";
+
+const provider = getTestProvider();
+
+describe("EIP-712 transactions", function () {
+  it("Should charge the fee of a type 113 transaction to its paymaster", async function () {
+    // Arrange
+    const wallet = new Wallet(RichAccounts[0].PrivateKey, provider);
+    const deployer = new Deployer(hre, wallet);
+    const paymaster = await deployContract(deployer, "Paymaster");
+    await (
+      await wallet.sendTransaction({
+        to: paymaster.address,
+        value: ethers.utils.parseEther("1"),
+      })
+    ).wait();
+
+    // the user has no funds, so the fee can only be paid by the paymaster
+    const userWallet = Wallet.createRandom().connect(provider);
+    const paymasterBalanceBefore = await provider.getBalance(paymaster.address);
+
+    // Act
+    const tx = await userWallet.sendTransaction({
+      to: RichAccounts[1].Account,
+      value: 0,
+      gasLimit: 5_000_000,
+      maxFeePerGas: await provider.getGasPrice(),
+      maxPriorityFeePerGas: 0,
+      customData: {
+        gasPerPubdata: utils.DEFAULT_GAS_PER_PUBDATA_LIMIT,
+        paymasterParams: utils.getPaymasterParams(paymaster.address, {
+          type: "General",
+          innerInput: new Uint8Array(),
+        }),
+      },
+    });
+    const receipt = await tx.wait();
+
+    // Assert
+    expect(receipt.status).to.equal(1);
+    expect((await provider.getTransaction(tx.hash)).type).to.equal(utils.EIP712_TX_TYPE);
+    expect(await provider.getBalance(userWallet.address)).to.deep.equal(ethers.BigNumber.from(0));
+    expect((await provider.getBalance(paymaster.address)).lt(paymasterBalanceBefore)).to.be.true;
+  });
+
+  it("Should sponsor a transfer with the params from config_getPaymasterParams", async function () {
+    // Arrange
+    const wallet = new Wallet(RichAccounts[0].PrivateKey, provider);
+    const deployer = new Deployer(hre, wallet);
+    const paymaster = await deployContract(deployer, "Paymaster");
+    await (
+      await wallet.sendTransaction({
+        to: paymaster.address,
+        value: ethers.utils.parseEther("1"),
+      })
+    ).wait();
+
+    const userWallet = Wallet.createRandom().connect(provider);
+    await (
+      await wallet.sendTransaction({
+        to: userWallet.address,
+        value: ethers.utils.parseEther("1"),
+      })
+    ).wait();
+    const userBalanceBefore = await provider.getBalance(userWallet.address);
+    const paymasterBalanceBefore = await provider.getBalance(paymaster.address);
+
+    // Act
+    const paymasterParams = await provider.send("config_getPaymasterParams", [paymaster.address]);
+    const tx = await userWallet.sendTransaction({
+      to: RichAccounts[1].Account,
+      value: 0,
+      gasLimit: 5_000_000,
+      maxFeePerGas: await provider.getGasPrice(),
+      maxPriorityFeePerGas: 0,
+      customData: {
+        gasPerPubdata: utils.DEFAULT_GAS_PER_PUBDATA_LIMIT,
+        paymasterParams,
+      },
+    });
+    await tx.wait();
+
+    // Assert
+    expect(await provider.getBalance(userWallet.address)).to.deep.equal(userBalanceBefore);
+    expect((await provider.getBalance(paymaster.address)).lt(paymasterBalanceBefore)).to.be.true;
+  });
+});
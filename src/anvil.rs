@@ -0,0 +1,229 @@
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    fork::ForkSource,
+    hardhat::{HardhatNamespaceImpl, HardhatNamespaceT},
+    node::InMemoryNodeInner,
+};
+use jsonrpc_core::{BoxFuture, Result};
+use jsonrpc_derive::rpc;
+use zksync_basic_types::{Address, Bytes, U256};
+
+/// Implementation of AnvilNamespace - aliases of the hardhat methods, under the names used by Foundry.
+pub struct AnvilNamespaceImpl<S> {
+    node: Arc<RwLock<InMemoryNodeInner<S>>>,
+}
+
+impl<S> AnvilNamespaceImpl<S> {
+    /// Creates a new `Anvil` instance with the given `node`.
+    pub fn new(node: Arc<RwLock<InMemoryNodeInner<S>>>) -> Self {
+        Self { node }
+    }
+
+    /// Returns the hardhat implementation all the anvil methods delegate to.
+    fn hardhat(&self) -> HardhatNamespaceImpl<S> {
+        HardhatNamespaceImpl::new(Arc::clone(&self.node))
+    }
+}
+
+#[rpc]
+pub trait AnvilNamespaceT {
+    /// Dumps the chain state (blocks and storage). Alias of `hardhat_dumpState`.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the JSON-serialized state as `Bytes`.
+    #[rpc(name = "anvil_dumpState")]
+    fn dump_state(&self) -> BoxFuture<Result<Bytes>>;
+
+    /// Replaces the chain state with a previously dumped one. Alias of `hardhat_loadState`.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The JSON-serialized state as `Bytes`.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_loadState")]
+    fn load_state(&self, state: Bytes) -> BoxFuture<Result<bool>>;
+
+    /// Sets the balance of the given address. Alias of `hardhat_setBalance`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The `Address` whose balance will be edited
+    /// * `balance` - The new balance to set for the given address, in wei
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_setBalance")]
+    fn set_balance(&self, address: Address, balance: U256) -> BoxFuture<Result<bool>>;
+
+    /// Sets the nonce of the given address. Alias of `hardhat_setNonce`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The `Address` whose nonce is to be changed
+    /// * `nonce` - The new nonce to be set, must not be lower than the current one
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_setNonce")]
+    fn set_nonce(&self, address: Address, nonce: U256) -> BoxFuture<Result<bool>>;
+
+    /// Sets the value of a storage slot of the given address. Alias of `hardhat_setStorageAt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The `Address` whose storage will be edited
+    /// * `slot` - The storage slot to edit
+    /// * `value` - The new value of the storage slot
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_setStorageAt")]
+    fn set_storage_at(&self, address: Address, slot: U256, value: U256) -> BoxFuture<Result<bool>>;
+
+    /// Sets the bytecode of the given address. Alias of `hardhat_setCode`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The `Address` whose code will be edited
+    /// * `code` - The new (zkSync) bytecode, an odd number of 32-byte words
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_setCode")]
+    fn set_code(&self, address: Address, code: Bytes) -> BoxFuture<Result<bool>>;
+}
+
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> AnvilNamespaceT
+    for AnvilNamespaceImpl<S>
+{
+    fn dump_state(&self) -> BoxFuture<Result<Bytes>> {
+        self.hardhat().dump_state()
+    }
+
+    fn load_state(&self, state: Bytes) -> BoxFuture<Result<bool>> {
+        self.hardhat().load_state(state)
+    }
+
+    fn set_balance(&self, address: Address, balance: U256) -> BoxFuture<Result<bool>> {
+        self.hardhat().set_balance(address, balance)
+    }
+
+    fn set_nonce(&self, address: Address, nonce: U256) -> BoxFuture<Result<bool>> {
+        self.hardhat().set_nonce(address, nonce)
+    }
+
+    fn set_storage_at(&self, address: Address, slot: U256, value: U256) -> BoxFuture<Result<bool>> {
+        self.hardhat().set_storage_at(address, slot, value)
+    }
+
+    fn set_code(&self, address: Address, code: Bytes) -> BoxFuture<Result<bool>> {
+        self.hardhat().set_code(address, code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode, testing};
+    use zksync_basic_types::H256;
+    use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+    #[tokio::test]
+    async fn test_anvil_dump_state_can_be_loaded_by_hardhat() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let anvil = AnvilNamespaceImpl::new(node.get_inner());
+        let (block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x01));
+        let address = Address::repeat_byte(0x1);
+        node.set_rich_account(address);
+
+        let dump = anvil.dump_state().await.expect("failed dumping state");
+
+        let other_node = InMemoryNode::<HttpForkSource>::default();
+        let hardhat = HardhatNamespaceImpl::new(other_node.get_inner());
+        let loaded = hardhat
+            .load_state(dump.clone())
+            .await
+            .expect("failed loading state");
+        assert!(loaded);
+
+        assert_eq!(
+            node.get_block_number().await.unwrap(),
+            other_node.get_block_number().await.unwrap()
+        );
+        let block = other_node
+            .get_block_by_hash(block_hash, false)
+            .await
+            .expect("failed fetching block")
+            .expect("block exists");
+        assert_eq!(1, block.transactions.len());
+        assert_eq!(
+            U256::from(10u128.pow(30)),
+            other_node.get_balance(address, None).await.unwrap()
+        );
+        assert_eq!(
+            dump,
+            hardhat.dump_state().await.expect("failed dumping state")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_anvil_setters_match_hardhat() {
+        let anvil_node = InMemoryNode::<HttpForkSource>::default();
+        let hardhat_node = InMemoryNode::<HttpForkSource>::default();
+        let anvil = AnvilNamespaceImpl::new(anvil_node.get_inner());
+        let hardhat = HardhatNamespaceImpl::new(hardhat_node.get_inner());
+        let address = Address::repeat_byte(0x1);
+        let slot = U256::from(42);
+        let code = Bytes(vec![0xab; 32 * 3]);
+
+        assert!(anvil.set_balance(address, U256::from(1337)).await.unwrap());
+        assert!(hardhat
+            .set_balance(address, U256::from(1337))
+            .await
+            .unwrap());
+        assert!(anvil.set_nonce(address, U256::from(7)).await.unwrap());
+        assert!(hardhat.set_nonce(address, U256::from(7)).await.unwrap());
+        assert!(anvil
+            .set_storage_at(address, slot, U256::from(99))
+            .await
+            .unwrap());
+        assert!(hardhat
+            .set_storage_at(address, slot, U256::from(99))
+            .await
+            .unwrap());
+        assert!(anvil.set_code(address, code.clone()).await.unwrap());
+        assert!(hardhat.set_code(address, code.clone()).await.unwrap());
+
+        for node in [&anvil_node, &hardhat_node] {
+            assert_eq!(
+                U256::from(1337),
+                node.get_balance(address, None).await.unwrap()
+            );
+            assert_eq!(
+                U256::from(7),
+                node.get_transaction_count(address, None).await.unwrap()
+            );
+            assert_eq!(
+                H256::from_low_u64_be(99),
+                node.get_storage(address, slot, None).await.unwrap()
+            );
+            assert_eq!(code, node.get_code(address, None).await.unwrap());
+        }
+
+        // validation is shared with the hardhat methods
+        assert!(anvil.set_nonce(address, U256::from(6)).await.is_err());
+        assert!(anvil
+            .set_code(address, Bytes(vec![0xab; 32 * 2]))
+            .await
+            .is_err());
+    }
+}
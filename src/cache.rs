@@ -6,14 +6,21 @@ use std::io::{BufReader, BufWriter};
 use std::path::Path;
 use std::result::Result;
 use std::str::FromStr;
-use zksync_basic_types::H256;
-use zksync_types::api::{Block, Transaction, TransactionVariant};
+use zksync_basic_types::{Address, L2ChainId, H256};
+use zksync_types::api::{
+    Block, BlockNumber, BridgeAddresses, Log, Transaction, TransactionReceipt, TransactionVariant,
+};
 use zksync_types::Transaction as RawTransaction;
+use zksync_web3_decl::types::Filter;
 
 const CACHE_TYPE_BLOCKS_FULL: &str = "blocks_full";
 const CACHE_TYPE_BLOCKS_MIN: &str = "blocks_min";
 const CACHE_TYPE_BLOCK_RAW_TRANSACTIONS: &str = "block_raw_transactions";
 const CACHE_TYPE_TRANSACTIONS: &str = "transactions";
+const CACHE_TYPE_TRANSACTION_RECEIPTS: &str = "transaction_receipts";
+const CACHE_TYPE_CHAIN_METADATA: &str = "chain_metadata";
+const CHAIN_METADATA_CHAIN_ID: &str = "chain_id";
+const CHAIN_METADATA_BRIDGE_CONTRACTS: &str = "bridge_contracts";
 
 /// Cache configuration. Can be one of:
 ///
@@ -40,6 +47,81 @@ pub(crate) struct Cache {
     blocks_min: FxHashMap<H256, Block<TransactionVariant>>,
     block_raw_transactions: FxHashMap<u64, Vec<RawTransaction>>,
     transactions: FxHashMap<H256, Transaction>,
+    transaction_receipts: FxHashMap<H256, TransactionReceipt>,
+    logs: FxHashMap<LogsFilterKey, Vec<Log>>,
+    chain_metadata: ChainMetadata,
+}
+
+/// The normalized filter of an `eth_getLogs` query over an explicit block range, up to the fork block - the key of its
+/// cached logs, so that the same query is matched whatever the order or the duplicates of its addresses and topics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LogsFilterKey {
+    from_block: u64,
+    to_block: u64,
+    /// The sorted addresses, empty for any address.
+    addresses: Vec<Address>,
+    /// The sorted topics of each position, empty for any topic - without the trailing wildcards.
+    topics: Vec<Vec<H256>>,
+}
+
+impl LogsFilterKey {
+    /// Returns the key of the filter, or `None` if its range is not made of block numbers - e.g. `latest`, or a block
+    /// hash - or if it ends past the fork block, so its logs are not cached. The blocks after the fork block are still
+    /// being produced by the forked chain, so the logs of such a range may change.
+    pub(crate) fn from_filter(filter: &Filter, fork_block: u64) -> Option<Self> {
+        if filter.block_hash.is_some() {
+            return None;
+        }
+        let (from_block, to_block) = match (&filter.from_block, &filter.to_block) {
+            (Some(BlockNumber::Number(from)), Some(BlockNumber::Number(to))) => {
+                (from.as_u64(), to.as_u64())
+            }
+            _ => return None,
+        };
+        if to_block > fork_block {
+            return None;
+        }
+
+        let mut addresses = filter
+            .address
+            .as_ref()
+            .map(|addresses| addresses.0.clone())
+            .unwrap_or_default();
+        addresses.sort();
+        addresses.dedup();
+        let mut topics = filter
+            .topics
+            .iter()
+            .flatten()
+            .map(|topics| {
+                let mut topics = topics
+                    .as_ref()
+                    .map(|topics| topics.0.clone())
+                    .unwrap_or_default();
+                topics.sort();
+                topics.dedup();
+                topics
+            })
+            .collect::<Vec<_>>();
+        while topics.last().map_or(false, Vec::is_empty) {
+            topics.pop();
+        }
+
+        Some(Self {
+            from_block,
+            to_block,
+            addresses,
+            topics,
+        })
+    }
+}
+
+/// Static metadata of the forked chain. These are singletons that never change for a given chain,
+/// so once fetched they are pinned for the lifetime of the cache and never evicted.
+#[derive(Default, Debug, Clone)]
+struct ChainMetadata {
+    chain_id: Option<L2ChainId>,
+    bridge_contracts: Option<BridgeAddresses>,
 }
 
 impl Cache {
@@ -57,6 +139,8 @@ impl Cache {
                     CACHE_TYPE_BLOCKS_MIN,
                     CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
                     CACHE_TYPE_TRANSACTIONS,
+                    CACHE_TYPE_TRANSACTION_RECEIPTS,
+                    CACHE_TYPE_CHAIN_METADATA,
                 ] {
                     fs::remove_dir_all(Path::new(dir).join(cache_type)).unwrap_or_else(|err| {
                         log::warn!(
@@ -76,6 +160,8 @@ impl Cache {
                 CACHE_TYPE_BLOCKS_MIN,
                 CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
                 CACHE_TYPE_TRANSACTIONS,
+                CACHE_TYPE_TRANSACTION_RECEIPTS,
+                CACHE_TYPE_CHAIN_METADATA,
             ] {
                 fs::create_dir_all(Path::new(dir).join(cache_type)).unwrap_or_else(|err| {
                     panic!("failed creating directory {}: {:?}", cache_type, err)
@@ -186,6 +272,93 @@ impl Cache {
         self.transactions.insert(hash, transaction);
     }
 
+    /// Returns the cached transaction receipt for the provided hash.
+    pub(crate) fn get_transaction_receipt(&self, hash: &H256) -> Option<&TransactionReceipt> {
+        if matches!(self.config, CacheConfig::None) {
+            return None;
+        }
+
+        self.transaction_receipts.get(hash)
+    }
+
+    /// Cache a transaction receipt for the provided hash.
+    pub(crate) fn insert_transaction_receipt(&mut self, hash: H256, receipt: TransactionReceipt) {
+        if matches!(self.config, CacheConfig::None) {
+            return;
+        }
+
+        self.write_to_disk(
+            CACHE_TYPE_TRANSACTION_RECEIPTS,
+            format!("{:#x}", hash),
+            &receipt,
+        );
+        self.transaction_receipts.insert(hash, receipt);
+    }
+
+    /// Returns the cached logs for the provided filter. The logs are kept in memory only.
+    pub(crate) fn get_logs(&self, key: &LogsFilterKey) -> Option<&Vec<Log>> {
+        if matches!(self.config, CacheConfig::None) {
+            return None;
+        }
+
+        self.logs.get(key)
+    }
+
+    /// Cache the logs for the provided filter.
+    pub(crate) fn insert_logs(&mut self, key: LogsFilterKey, logs: Vec<Log>) {
+        if matches!(self.config, CacheConfig::None) {
+            return;
+        }
+
+        self.logs.insert(key, logs);
+    }
+
+    /// Returns the cached chain id of the forked chain.
+    pub(crate) fn get_chain_id(&self) -> Option<L2ChainId> {
+        if matches!(self.config, CacheConfig::None) {
+            return None;
+        }
+
+        self.chain_metadata.chain_id
+    }
+
+    /// Cache the chain id of the forked chain.
+    pub(crate) fn insert_chain_id(&mut self, chain_id: L2ChainId) {
+        if matches!(self.config, CacheConfig::None) {
+            return;
+        }
+
+        self.write_to_disk(
+            CACHE_TYPE_CHAIN_METADATA,
+            CHAIN_METADATA_CHAIN_ID.to_string(),
+            &chain_id.0,
+        );
+        self.chain_metadata.chain_id = Some(chain_id);
+    }
+
+    /// Returns the cached bridge contracts of the forked chain.
+    pub(crate) fn get_bridge_contracts(&self) -> Option<&BridgeAddresses> {
+        if matches!(self.config, CacheConfig::None) {
+            return None;
+        }
+
+        self.chain_metadata.bridge_contracts.as_ref()
+    }
+
+    /// Cache the bridge contracts of the forked chain.
+    pub(crate) fn insert_bridge_contracts(&mut self, bridge_contracts: BridgeAddresses) {
+        if matches!(self.config, CacheConfig::None) {
+            return;
+        }
+
+        self.write_to_disk(
+            CACHE_TYPE_CHAIN_METADATA,
+            CHAIN_METADATA_BRIDGE_CONTRACTS.to_string(),
+            &bridge_contracts,
+        );
+        self.chain_metadata.bridge_contracts = Some(bridge_contracts);
+    }
+
     /// Reads the cache contents from the disk, if available.
     fn read_all_from_disk(&mut self, dir: &str) -> Result<(), String> {
         for cache_type in [
@@ -193,6 +366,8 @@ impl Cache {
             CACHE_TYPE_BLOCKS_MIN,
             CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
             CACHE_TYPE_TRANSACTIONS,
+            CACHE_TYPE_TRANSACTION_RECEIPTS,
+            CACHE_TYPE_CHAIN_METADATA,
         ] {
             let cache_dir = Path::new(dir).join(cache_type);
             let dir_listing = fs::read_dir(cache_dir.clone())
@@ -252,6 +427,35 @@ impl Cache {
                             })?;
                         self.transactions.insert(key, transaction);
                     }
+                    CACHE_TYPE_TRANSACTION_RECEIPTS => {
+                        let key = H256::from_str(&key).map_err(|err| {
+                            format!("invalid key for cache file '{:?}': {:?}", key, err)
+                        })?;
+                        let receipt: TransactionReceipt =
+                            serde_json::from_reader(reader).map_err(|err| {
+                                format!("failed parsing json for cache file '{:?}': {:?}", key, err)
+                            })?;
+                        self.transaction_receipts.insert(key, receipt);
+                    }
+                    CACHE_TYPE_CHAIN_METADATA => match key.as_str() {
+                        CHAIN_METADATA_CHAIN_ID => {
+                            let chain_id: u16 = serde_json::from_reader(reader).map_err(|err| {
+                                format!("failed parsing json for cache file '{:?}': {:?}", key, err)
+                            })?;
+                            self.chain_metadata.chain_id = Some(L2ChainId(chain_id));
+                        }
+                        CHAIN_METADATA_BRIDGE_CONTRACTS => {
+                            let bridge_contracts: BridgeAddresses = serde_json::from_reader(reader)
+                                .map_err(|err| {
+                                    format!(
+                                        "failed parsing json for cache file '{:?}': {:?}",
+                                        key, err
+                                    )
+                                })?;
+                            self.chain_metadata.bridge_contracts = Some(bridge_contracts);
+                        }
+                        _ => return Err(format!("invalid key for cache file '{:?}'", key)),
+                    },
                     _ => return Err(format!("invalid cache_type {}", cache_type)),
                 }
             }
@@ -304,6 +508,12 @@ mod tests {
 
         cache.insert_transaction(H256::zero(), Default::default());
         assert_eq!(None, cache.get_transaction(&H256::zero()));
+
+        cache.insert_transaction_receipt(H256::zero(), Default::default());
+        assert_eq!(None, cache.get_transaction_receipt(&H256::zero()));
+
+        cache.insert_chain_id(L2ChainId(324));
+        assert_eq!(None, cache.get_chain_id());
     }
 
     #[test]
@@ -319,6 +529,10 @@ mod tests {
             ..Default::default()
         };
         let transaction = Transaction::default();
+        let receipt = TransactionReceipt {
+            transaction_hash: H256::repeat_byte(0x3),
+            ..Default::default()
+        };
         let raw_transactions = vec![RawTransaction {
             common_data: ExecuteTransactionCommon::L1(Default::default()),
             execute: Execute {
@@ -355,6 +569,9 @@ mod tests {
 
         cache.insert_transaction(H256::zero(), transaction.clone());
         assert_eq!(Some(&transaction), cache.get_transaction(&H256::zero()));
+
+        cache.insert_transaction_receipt(H256::zero(), receipt.clone());
+        assert_eq!(Some(&receipt), cache.get_transaction_receipt(&H256::zero()));
     }
 
     #[test]
@@ -370,6 +587,10 @@ mod tests {
             ..Default::default()
         };
         let transaction = Transaction::default();
+        let receipt = TransactionReceipt {
+            transaction_hash: H256::repeat_byte(0x3),
+            ..Default::default()
+        };
         let raw_transactions = vec![RawTransaction {
             common_data: ExecuteTransactionCommon::L1(Default::default()),
             execute: Execute {
@@ -416,6 +637,9 @@ mod tests {
         cache.insert_transaction(H256::zero(), transaction.clone());
         assert_eq!(Some(&transaction), cache.get_transaction(&H256::zero()));
 
+        cache.insert_transaction_receipt(H256::zero(), receipt.clone());
+        assert_eq!(Some(&receipt), cache.get_transaction_receipt(&H256::zero()));
+
         let new_cache = Cache::new(CacheConfig::Disk {
             dir: cache_dir_path,
             reset: false,
@@ -435,6 +659,10 @@ mod tests {
             new_cache.get_block_raw_transactions(&0)
         );
         assert_eq!(Some(&transaction), new_cache.get_transaction(&H256::zero()));
+        assert_eq!(
+            Some(&receipt),
+            new_cache.get_transaction_receipt(&H256::zero())
+        );
     }
 
     #[test]
@@ -545,4 +773,95 @@ mod tests {
         );
         assert!(random_file_path.exists(), "random file was reset from disk");
     }
+
+    #[test]
+    fn test_cache_config_disk_preserves_chain_metadata() {
+        let bridge_contracts: BridgeAddresses = serde_json::from_value(serde_json::json!({
+            "l1Erc20DefaultBridge": "0x0101010101010101010101010101010101010101",
+            "l2Erc20DefaultBridge": "0x0202020202020202020202020202020202020202",
+        }))
+        .expect("invalid bridge contracts");
+        let cache_dir = TempDir::new("cache-test").expect("failed creating temporary dir");
+        let cache_dir_path = cache_dir
+            .path()
+            .to_str()
+            .expect("invalid dir name")
+            .to_string();
+        let mut cache = Cache::new(CacheConfig::Disk {
+            dir: cache_dir_path.clone(),
+            reset: true,
+        });
+
+        cache.insert_chain_id(L2ChainId(324));
+        cache.insert_bridge_contracts(bridge_contracts);
+
+        let new_cache = Cache::new(CacheConfig::Disk {
+            dir: cache_dir_path,
+            reset: false,
+        });
+        assert_eq!(Some(L2ChainId(324)), new_cache.get_chain_id());
+        assert_eq!(
+            Some(zksync_basic_types::Address::repeat_byte(0x2)),
+            new_cache
+                .get_bridge_contracts()
+                .map(|contracts| contracts.l2_erc20_default_bridge)
+        );
+    }
+
+    #[test]
+    fn test_logs_are_cached_by_normalized_filter_up_to_the_fork_block() {
+        let filter = |addresses: Vec<Address>, to_block: u64| Filter {
+            from_block: Some(BlockNumber::Number(U64::from(1))),
+            to_block: Some(BlockNumber::Number(U64::from(to_block))),
+            address: Some(zksync_web3_decl::types::ValueOrArray(addresses)),
+            topics: Some(vec![None]),
+            block_hash: None,
+        };
+        let key = |addresses: Vec<Address>, to_block: u64| {
+            LogsFilterKey::from_filter(&filter(addresses, to_block), 10)
+                .expect("filter has a range up to the fork block")
+        };
+        let mut cache = Cache::new(CacheConfig::Memory);
+
+        cache.insert_logs(
+            key(
+                vec![Address::repeat_byte(0x1), Address::repeat_byte(0x2)],
+                10,
+            ),
+            vec![Default::default()],
+        );
+        cache.insert_logs(key(vec![Address::repeat_byte(0x1)], 9), vec![]);
+        assert_eq!(
+            Some(1),
+            cache
+                .get_logs(&key(
+                    vec![
+                        Address::repeat_byte(0x2),
+                        Address::repeat_byte(0x1),
+                        Address::repeat_byte(0x2)
+                    ],
+                    10
+                ))
+                .map(Vec::len)
+        );
+        assert_eq!(
+            Some(0),
+            cache
+                .get_logs(&key(vec![Address::repeat_byte(0x1)], 9))
+                .map(Vec::len)
+        );
+        assert!(LogsFilterKey::from_filter(
+            &Filter {
+                to_block: Some(BlockNumber::Latest),
+                ..filter(vec![], 10)
+            },
+            10
+        )
+        .is_none());
+
+        // the logs of a range ending past the fork block may still change, so they are not cached
+        assert!(
+            LogsFilterKey::from_filter(&filter(vec![Address::repeat_byte(0x1)], 11), 10).is_none()
+        );
+    }
 }
@@ -0,0 +1,64 @@
+//! Tracer enforcing a maximum depth of the VM call stack - e.g. to trigger the failures of deeply recursive
+//! contracts sooner.
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
+use vm::{
+    BootloaderState, DynTracer, ExecutionEndTracer, ExecutionProcessing, ExecutionResult, Halt,
+    HistoryMode, VmExecutionResultAndLogs, VmTracer, ZkSyncVmState,
+};
+use zksync_state::WriteStorage;
+use zksync_types::Address;
+
+/// Stops the execution once the calls of the transaction nest deeper than `max_depth` frames. The depth is counted
+/// from the frame of the called contract `entry_address` - which is at depth 1 - and only the calls to contracts
+/// count, not the internal calls of a contract. The depth that was reached is then set in `exceeded`.
+pub struct CallDepthTracer {
+    pub max_depth: usize,
+    pub entry_address: Address,
+    pub exceeded: Arc<OnceCell<usize>>,
+}
+
+impl<S, H: HistoryMode> DynTracer<S, H> for CallDepthTracer {}
+
+impl<H: HistoryMode> ExecutionEndTracer<H> for CallDepthTracer {
+    fn should_stop_execution(&self) -> bool {
+        self.exceeded.get().is_some()
+    }
+}
+
+impl<S: WriteStorage, H: HistoryMode> ExecutionProcessing<S, H> for CallDepthTracer {
+    fn after_cycle(
+        &mut self,
+        state: &mut ZkSyncVmState<S, H>,
+        _bootloader_state: &mut BootloaderState,
+    ) {
+        let callstack = &state.local_state.callstack;
+        // the frames of the bootloader and of the account of the sender precede the one of the called contract
+        let depth = callstack
+            .inner
+            .iter()
+            .chain(std::iter::once(&callstack.current))
+            .filter(|frame| !frame.is_local_frame)
+            .skip_while(|frame| frame.this_address != self.entry_address)
+            .count();
+        if depth > self.max_depth {
+            let _ = self.exceeded.set(depth);
+        }
+    }
+}
+
+impl<S: WriteStorage, H: HistoryMode> VmTracer<S, H> for CallDepthTracer {
+    fn save_results(&mut self, _result: &mut VmExecutionResultAndLogs) {}
+}
+
+/// Returns the result of an execution that was stopped by a [CallDepthTracer] - it is halted, so that the
+/// transaction is not persisted.
+pub fn call_depth_exceeded(max_depth: usize) -> ExecutionResult {
+    ExecutionResult::Halt {
+        reason: Halt::UnexpectedVMBehavior(format!(
+            "call depth exceeded the maximum of {}",
+            max_depth
+        )),
+    }
+}
@@ -1,20 +1,95 @@
 // Built-in uses
-use std::sync::{Arc, RwLock};
+use std::{
+    mem,
+    sync::{Arc, RwLock},
+};
 
 // External uses
+use ethabi::{ParamType, Token};
 use jsonrpc_core::Result;
 use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
 
 // Workspace uses
+use zksync_basic_types::{
+    web3::{signing::keccak256, types::SyncInfo},
+    Address, Bytes, H256, U256, U64,
+};
+use zksync_core::api_server::web3::backend_jsonrpc::error::into_jsrpc_error;
+use zksync_types::{
+    api::{Log, TransactionVariant},
+    StorageKey,
+};
+use zksync_utils::{h256_to_u256, u256_to_h256};
+use zksync_web3_decl::error::Web3Error;
 
 // Local uses
 use crate::{
+    fork::ForkSource,
+    node::ExpectedRevert,
+    node::InMemoryNode,
     node::InMemoryNodeInner,
     node::ShowCalls,
     node::ShowVMDetails,
     node::{ShowGasDetails, ShowStorageLogs},
+    utils::mine_empty_blocks,
 };
 
+/// The highest address of the system contracts, which are deployed in the kernel space.
+const MAX_SYSTEM_CONTRACT_ADDRESS: u64 = 0xffff;
+
+/// Parameters of a transaction sponsored by a paymaster, as expected in `customData.paymasterParams`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterParams {
+    pub paymaster: Address,
+    pub paymaster_input: Bytes,
+}
+
+/// Compact metadata of a block stored by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockMetadata {
+    pub number: U64,
+    pub hash: H256,
+    pub timestamp: U256,
+    pub transaction_count: U64,
+}
+
+/// Approximate number of entries, and their size in bytes, of a part of the node's state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    pub count: U64,
+    pub bytes: U64,
+}
+
+impl MemoryUsage {
+    fn add(&mut self, bytes: usize) {
+        self.count += U64::one();
+        self.bytes += U64::from(bytes);
+    }
+}
+
+/// Approximate memory usage of the node's state - estimated when requested, and not including the allocator's
+/// overhead.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+    /// Storage slots modified locally.
+    pub storage_keys: MemoryUsage,
+    /// Bytecodes deployed locally.
+    pub factory_deps: MemoryUsage,
+    /// Storage slots and bytecodes read from the fork.
+    pub fork_cache: MemoryUsage,
+    /// Blocks stored by the node.
+    pub blocks: MemoryUsage,
+    /// Executed transactions, along with their receipts.
+    pub transactions: MemoryUsage,
+    /// Transactions waiting in the pool.
+    pub mempool: MemoryUsage,
+}
+
 pub struct ConfigurationApiNamespace<S> {
     node: Arc<RwLock<InMemoryNodeInner<S>>>,
 }
@@ -90,10 +165,228 @@ pub trait ConfigurationApiNamespaceT {
     /// The updated `resolve_hashes` value for the InMemoryNodeInner.
     #[rpc(name = "config_setResolveHashes", returns = "bool")]
     fn config_set_resolve_hashes(&self, value: bool) -> Result<bool>;
+
+    /// Simulates a chain reorganization: rolls back the latest `depth` blocks, and executes the given raw
+    /// transactions instead - each one is mined as its own block (and batch). The hashes of the replaced blocks
+    /// change. If any of the transactions fails, the rolled back blocks are restored.
+    ///
+    /// # Parameters
+    /// - `depth`: The number of blocks to roll back - must end on a batch boundary
+    /// - `new_blocks`: The raw (RLP-encoded and signed) transactions to mine on top of the remaining chain
+    ///
+    /// # Returns
+    /// The hash of the new latest block.
+    #[rpc(name = "config_reorg", returns = "H256")]
+    fn config_reorg(&self, depth: u64, new_blocks: Vec<Bytes>) -> Result<H256>;
+
+    /// Builds the paymaster parameters to sponsor a transaction with the given paymaster, using the general
+    /// paymaster flow - i.e. `paymasterInput` is the encoded call of `general(bytes)`.
+    ///
+    /// # Parameters
+    /// - `paymaster`: The address of the paymaster contract
+    /// - `inner_input`: Optional input passed to the paymaster, defaults to empty bytes
+    ///
+    /// # Returns
+    /// The `paymaster` and `paymasterInput` to set in the transaction.
+    #[rpc(name = "config_getPaymasterParams", returns = "PaymasterParams")]
+    fn config_get_paymaster_params(
+        &self,
+        paymaster: Address,
+        inner_input: Option<Bytes>,
+    ) -> Result<PaymasterParams>;
+
+    /// Lists the metadata of the blocks stored by the node, in the given (inclusive) range. Blocks that are not
+    /// stored locally - e.g. the ones before the fork block - are skipped.
+    ///
+    /// # Parameters
+    /// - `from`: The number of the first block
+    /// - `to`: The number of the last block - capped to the latest block
+    ///
+    /// # Returns
+    /// The number, hash, timestamp and transaction count of each block, ordered by number.
+    #[rpc(name = "config_listBlocks", returns = "Vec<BlockMetadata>")]
+    fn config_list_blocks(&self, from: U64, to: U64) -> Result<Vec<BlockMetadata>>;
+
+    /// Simulates the node syncing, so that `eth_syncing` returns the sync progress instead of `false`.
+    ///
+    /// # Parameters
+    /// - `syncing`: Whether to simulate syncing
+    /// - `highest_block`: The highest block reported while syncing - defaults to the block after the latest one
+    ///
+    /// # Returns
+    /// Whether the node is simulating syncing.
+    #[rpc(name = "config_setSyncing", returns = "bool")]
+    fn config_set_syncing(&self, syncing: bool, highest_block: Option<U64>) -> Result<bool>;
+
+    /// Estimates the memory used by the node's state, to decide when to reset a long running node.
+    ///
+    /// # Returns
+    /// The number of entries, and their approximate size in bytes, of the locally modified storage, the deployed
+    /// bytecodes, the data cached from the fork, the blocks, the executed transactions and the transaction pool.
+    #[rpc(name = "config_getMemoryStats", returns = "MemoryStats")]
+    fn config_get_memory_stats(&self) -> Result<MemoryStats>;
+
+    /// Mines empty blocks until the chain reaches the given block number, each `--block-time` seconds apart.
+    ///
+    /// # Parameters
+    /// - `block_number`: The number of the block to mine until - must not be before the latest block
+    ///
+    /// # Returns
+    /// The number of the latest block.
+    #[rpc(name = "config_mineUntil", returns = "U64")]
+    fn config_mine_until(&self, block_number: U64) -> Result<U64>;
+
+    /// Get the value contracts read as `block.prevrandao` (or `block.difficulty`)
+    ///
+    /// # Returns
+    /// The `prev_randao` value for the InMemoryNodeInner.
+    #[rpc(name = "config_getPrevRandao", returns = "H256")]
+    fn config_get_prev_randao(&self) -> Result<H256>;
+
+    /// Set the value contracts read as `block.prevrandao` (or `block.difficulty`), from the next executed
+    /// transaction or call on.
+    ///
+    /// # Parameters
+    /// - `value`: The 32-byte value to set `prev_randao` to
+    ///
+    /// # Returns
+    /// The updated `prev_randao` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setPrevRandao", returns = "H256")]
+    fn config_set_prev_randao(&self, value: H256) -> Result<H256>;
+
+    /// Overrides the balance of an account for the next mined block only - once it is mined, the balance is
+    /// restored to its prior value, discarding the changes of the block.
+    ///
+    /// # Parameters
+    /// - `address`: The account whose balance is overridden
+    /// - `balance`: The balance of the account in the next block, in wei
+    ///
+    /// # Returns
+    /// `true` on success.
+    #[rpc(name = "config_setBalanceForNextBlock", returns = "bool")]
+    fn config_set_balance_for_next_block(&self, address: Address, balance: U256) -> Result<bool>;
+
+    /// Sets the exact transactions of the next block mined by `evm_mine`, in the given order - the pending
+    /// transactions of the mempool are left for the following blocks. Replaces the previously set transactions.
+    ///
+    /// # Parameters
+    /// - `raw_txs`: The raw (RLP-encoded and signed) transactions of the next block
+    ///
+    /// # Returns
+    /// The hashes of the transactions, in the given order.
+    #[rpc(name = "config_setNextBlockTransactions", returns = "Vec<H256>")]
+    fn config_set_next_block_transactions(&self, raw_txs: Vec<Bytes>) -> Result<Vec<H256>>;
+
+    /// Get the accounts currently impersonated with `hardhat_impersonateAccount`
+    ///
+    /// # Returns
+    /// The impersonated accounts, sorted by address.
+    #[rpc(name = "config_getImpersonatedAccounts", returns = "Vec<Address>")]
+    fn config_get_impersonated_accounts(&self) -> Result<Vec<Address>>;
+
+    /// Stops impersonating all the accounts - e.g. to not leak impersonations across test cases.
+    ///
+    /// # Returns
+    /// The number of accounts that were impersonated.
+    #[rpc(name = "config_clearImpersonations", returns = "usize")]
+    fn config_clear_impersonations(&self) -> Result<usize>;
+
+    /// Enables or disables the nonce checking of the submitted transactions. Without it, transactions are accepted
+    /// whatever their nonce, and executed in the order they were submitted.
+    ///
+    /// # Parameters
+    /// - `value`: `false` to accept transactions with any nonce
+    ///
+    /// # Returns
+    /// The updated nonce checking value.
+    #[rpc(name = "config_setNonceCheck", returns = "bool")]
+    fn config_set_nonce_check(&self, value: bool) -> Result<bool>;
+
+    /// Computes the storage slot of a mapping entry, following the Solidity storage layout - i.e.
+    /// `keccak256(key . baseSlot)`, both padded to 32 bytes.
+    ///
+    /// # Parameters
+    /// - `base_slot`: The slot of the mapping variable
+    /// - `key`: The key of the entry, left-padded to 32 bytes (e.g. an address or a `uint256`)
+    ///
+    /// # Returns
+    /// The slot to pass to `eth_getStorageAt` or `hardhat_setStorageAt`.
+    #[rpc(name = "config_getMappingSlot", returns = "H256")]
+    fn config_get_mapping_slot(&self, base_slot: U256, key: H256) -> Result<H256>;
+
+    /// Computes the storage slot of a dynamic array element, following the Solidity storage layout - i.e.
+    /// `keccak256(baseSlot) + index`, for elements taking a full slot.
+    ///
+    /// # Parameters
+    /// - `base_slot`: The slot of the array variable, holding its length
+    /// - `index`: The index of the element
+    ///
+    /// # Returns
+    /// The slot to pass to `eth_getStorageAt` or `hardhat_setStorageAt`.
+    #[rpc(name = "config_getArraySlot", returns = "H256")]
+    fn config_get_array_slot(&self, base_slot: U256, index: U256) -> Result<H256>;
+
+    /// Mocks the response of the calls to a function of a system contract (e.g. the `ContractDeployer`): the
+    /// given data is returned without executing them - whether the calls are made directly (e.g. with `eth_call`),
+    /// or by other contracts, in calls and transactions alike.
+    ///
+    /// # Parameters
+    /// - `address`: The address of the system contract
+    /// - `selector`: The 4-byte selector of the mocked function
+    /// - `return_data`: The data returned by the mocked calls
+    ///
+    /// # Returns
+    /// `true` if the mock was registered.
+    #[rpc(name = "config_mockContract", returns = "bool")]
+    fn config_mock_contract(
+        &self,
+        address: Address,
+        selector: Bytes,
+        return_data: Bytes,
+    ) -> Result<bool>;
+
+    /// Mocks the response of the calls to a contract whose calldata starts with the given `calldata` - which can be
+    /// the full calldata, or a prefix of it such as the function selector. The most specific mock matching a call
+    /// is returned without executing it. As with `config_mockContract`, the calls made by other contracts are mocked
+    /// too, in calls and transactions alike.
+    ///
+    /// # Parameters
+    /// - `address`: The address of the contract
+    /// - `calldata`: The calldata, or prefix of the calldata, of the mocked calls
+    /// - `return_data`: The data returned by the mocked calls
+    ///
+    /// # Returns
+    /// `true` if the mock was registered.
+    #[rpc(name = "config_mockCall", returns = "bool")]
+    fn config_mock_call(
+        &self,
+        address: Address,
+        calldata: Bytes,
+        return_data: Bytes,
+    ) -> Result<bool>;
+
+    /// Removes all the mocked calls, so that the calls are executed again.
+    ///
+    /// # Returns
+    /// The number of mocked calls that were removed.
+    #[rpc(name = "config_clearMocks", returns = "usize")]
+    fn config_clear_mocks(&self) -> Result<usize>;
+
+    /// Expects the next `eth_call` or `eth_sendRawTransaction` to revert - optionally with a reason. Instead of its
+    /// result (or revert error), the call then returns an ABI-encoded `bool`: `true` if it reverted as expected,
+    /// `false` otherwise. A sent transaction returns its hash if it reverted as expected, and an error otherwise.
+    ///
+    /// # Parameters
+    /// - `reason`: Optional text the human readable revert reason must contain
+    ///
+    /// # Returns
+    /// `true` once the expectation is armed.
+    #[rpc(name = "config_expectNextCallRevert", returns = "bool")]
+    fn config_expect_next_call_revert(&self, reason: Option<String>) -> Result<bool>;
 }
 
-impl<S: std::marker::Send + std::marker::Sync + 'static> ConfigurationApiNamespaceT
-    for ConfigurationApiNamespace<S>
+impl<S: std::marker::Send + std::marker::Sync + 'static + ForkSource + std::fmt::Debug>
+    ConfigurationApiNamespaceT for ConfigurationApiNamespace<S>
 {
     fn config_get_show_calls(&self) -> Result<String> {
         let reader = self.node.read().unwrap();
@@ -166,4 +459,1063 @@ impl<S: std::marker::Send + std::marker::Sync + 'static> ConfigurationApiNamespa
         inner.resolve_hashes = value;
         Ok(inner.resolve_hashes)
     }
+
+    fn config_reorg(&self, depth: u64, new_blocks: Vec<Bytes>) -> Result<H256> {
+        let node = InMemoryNode::from_inner(self.node.clone());
+        let txs = new_blocks
+            .iter()
+            .map(|tx_bytes| node.decode_raw_tx(&tx_bytes.0))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(jsonrpc_core::Error::invalid_params)?;
+
+        node.reorg(depth, txs).map_err(|err| {
+            log::error!("failed reorg: {}", err);
+            into_jsrpc_error(Web3Error::InternalError)
+        })?;
+
+        let reader = self.node.read().unwrap();
+        Ok(reader.current_miniblock_hash)
+    }
+
+    fn config_get_paymaster_params(
+        &self,
+        paymaster: Address,
+        inner_input: Option<Bytes>,
+    ) -> Result<PaymasterParams> {
+        let inner_input = inner_input.unwrap_or_default();
+        let mut paymaster_input = ethabi::short_signature("general", &[ParamType::Bytes]).to_vec();
+        paymaster_input.extend(ethabi::encode(&[Token::Bytes(inner_input.0)]));
+
+        Ok(PaymasterParams {
+            paymaster,
+            paymaster_input: Bytes(paymaster_input),
+        })
+    }
+
+    fn config_list_blocks(&self, from: U64, to: U64) -> Result<Vec<BlockMetadata>> {
+        if from > to {
+            return Err(jsonrpc_core::Error::invalid_params(format!(
+                "invalid block range: {} is after {}",
+                from, to
+            )));
+        }
+
+        let reader = self.node.read().unwrap();
+        let to = to.as_u64().min(reader.current_miniblock);
+        Ok((from.as_u64()..=to)
+            .filter_map(|number| {
+                let hash = reader.block_hashes.get(&number)?;
+                let block = reader.blocks.get(hash)?;
+                Some(BlockMetadata {
+                    number: block.number,
+                    hash: block.hash,
+                    timestamp: block.timestamp,
+                    transaction_count: U64::from(block.transactions.len()),
+                })
+            })
+            .collect())
+    }
+
+    fn config_set_syncing(&self, syncing: bool, highest_block: Option<U64>) -> Result<bool> {
+        let mut inner = self.node.write().unwrap();
+        inner.simulated_sync = if syncing {
+            let starting_block = U256::from(inner.current_miniblock);
+            Some(SyncInfo {
+                starting_block,
+                current_block: starting_block,
+                highest_block: highest_block
+                    .map(|number| U256::from(number.as_u64()))
+                    .unwrap_or_else(|| starting_block + 1),
+            })
+        } else {
+            None
+        };
+        Ok(inner.simulated_sync.is_some())
+    }
+
+    fn config_get_memory_stats(&self) -> Result<MemoryStats> {
+        let reader = self
+            .node
+            .read()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        let storage = reader
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+        let slot_size = mem::size_of::<StorageKey>() + mem::size_of::<H256>();
+        let slots = |count: usize| MemoryUsage {
+            count: U64::from(count),
+            bytes: U64::from(count * slot_size),
+        };
+        let mut stats = MemoryStats {
+            storage_keys: slots(storage.raw_storage.state.len()),
+            fork_cache: slots(storage.value_read_cache.len()),
+            ..Default::default()
+        };
+        for bytecode in storage.raw_storage.factory_deps.values() {
+            stats
+                .factory_deps
+                .add(mem::size_of::<H256>() + bytecode.len());
+        }
+        for bytecode in storage.factory_dep_cache.values() {
+            stats.fork_cache.add(
+                mem::size_of::<H256>() + bytecode.as_ref().map_or(0, |bytecode| bytecode.len()),
+            );
+        }
+        for block in reader.blocks.values() {
+            let transactions_size = block
+                .transactions
+                .iter()
+                .map(|tx| match tx {
+                    TransactionVariant::Full(tx) => mem::size_of_val(tx) + tx.input.0.len(),
+                    TransactionVariant::Hash(_) => mem::size_of::<TransactionVariant>(),
+                })
+                .sum::<usize>();
+            stats
+                .blocks
+                .add(mem::size_of_val(block) + transactions_size);
+        }
+        for result in reader.tx_results.values() {
+            stats.transactions.add(
+                mem::size_of_val(result)
+                    + result.info.tx.execute.calldata.len()
+                    + result.receipt.logs.len() * mem::size_of::<Log>(),
+            );
+        }
+        for tx in reader.pool.iter() {
+            stats
+                .mempool
+                .add(mem::size_of_val(tx) + tx.execute.calldata.len());
+        }
+
+        Ok(stats)
+    }
+
+    fn config_mine_until(&self, block_number: U64) -> Result<U64> {
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        let current_block = inner.current_miniblock;
+        if block_number.as_u64() < current_block {
+            return Err(jsonrpc_core::Error::invalid_params(format!(
+                "block #{} is before the latest block #{}",
+                block_number, current_block
+            )));
+        }
+
+        let num_blocks = block_number.as_u64() - current_block;
+        if num_blocks > 0 {
+            let interval = inner.block_time;
+            mine_empty_blocks(&mut inner, num_blocks, interval).map_err(|err| {
+                log::error!("{}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+            log::info!("👷 Mined {} blocks", num_blocks);
+        }
+        Ok(U64::from(inner.current_miniblock))
+    }
+
+    fn config_get_prev_randao(&self) -> Result<H256> {
+        let reader = self.node.read().unwrap();
+        Ok(reader.prev_randao)
+    }
+
+    fn config_set_prev_randao(&self, value: H256) -> Result<H256> {
+        let mut inner = self.node.write().unwrap();
+        inner.prev_randao = value;
+        log::info!("👷 prevrandao set to {:#x}", value);
+        Ok(inner.prev_randao)
+    }
+
+    fn config_set_balance_for_next_block(&self, address: Address, balance: U256) -> Result<bool> {
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        inner.next_block_balances.insert(address, balance);
+        log::info!(
+            "👷 Balance for address {:?} set to {} for the next block",
+            address,
+            inner.base_token.format_amount(balance)
+        );
+        Ok(true)
+    }
+
+    fn config_set_next_block_transactions(&self, raw_txs: Vec<Bytes>) -> Result<Vec<H256>> {
+        let node = InMemoryNode::from_inner(self.node.clone());
+        let txs = raw_txs
+            .iter()
+            .map(|raw_tx| node.decode_raw_tx(&raw_tx.0))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(jsonrpc_core::Error::invalid_params)?;
+        let hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        inner.next_block_txs = txs;
+        log::info!("👷 Next block set to {} transaction(s)", hashes.len());
+        Ok(hashes)
+    }
+
+    fn config_get_impersonated_accounts(&self) -> Result<Vec<Address>> {
+        let reader = self
+            .node
+            .read()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        let mut accounts = reader
+            .impersonated_accounts
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        accounts.sort();
+        Ok(accounts)
+    }
+
+    fn config_clear_impersonations(&self) -> Result<usize> {
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        let count = mem::take(&mut inner.impersonated_accounts).len();
+        log::info!("🕵️ Stopped impersonating {} account(s)", count);
+        Ok(count)
+    }
+
+    fn config_set_nonce_check(&self, value: bool) -> Result<bool> {
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        inner.nonce_check = value;
+        log::info!(
+            "👷 Nonce check {}",
+            if value { "enabled" } else { "disabled" }
+        );
+        Ok(inner.nonce_check)
+    }
+
+    fn config_get_mapping_slot(&self, base_slot: U256, key: H256) -> Result<H256> {
+        let mut preimage = key.as_bytes().to_vec();
+        preimage.extend_from_slice(u256_to_h256(base_slot).as_bytes());
+        Ok(H256(keccak256(&preimage)))
+    }
+
+    fn config_get_array_slot(&self, base_slot: U256, index: U256) -> Result<H256> {
+        let data_start = h256_to_u256(H256(keccak256(u256_to_h256(base_slot).as_bytes())));
+        Ok(u256_to_h256(data_start.overflowing_add(index).0))
+    }
+
+    fn config_mock_contract(
+        &self,
+        address: Address,
+        selector: Bytes,
+        return_data: Bytes,
+    ) -> Result<bool> {
+        if address > Address::from_low_u64_be(MAX_SYSTEM_CONTRACT_ADDRESS) {
+            return Err(jsonrpc_core::Error::invalid_params(format!(
+                "{:?} is not a system contract",
+                address
+            )));
+        }
+        if selector.0.len() != 4 {
+            return Err(jsonrpc_core::Error::invalid_params(
+                "the selector must be 4 bytes long",
+            ));
+        }
+
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        inner.mock_call(address, selector.0, return_data.0);
+        log::info!("🎭 Mocked the calls to {:?}", address);
+        Ok(true)
+    }
+
+    fn config_mock_call(
+        &self,
+        address: Address,
+        calldata: Bytes,
+        return_data: Bytes,
+    ) -> Result<bool> {
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        inner.mock_call(address, calldata.0, return_data.0);
+        log::info!("🎭 Mocked the calls to {:?}", address);
+        Ok(true)
+    }
+
+    fn config_clear_mocks(&self) -> Result<usize> {
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        let count = mem::take(&mut inner.mocked_calls).len();
+        log::info!("🎭 Cleared {} mocked call(s)", count);
+        Ok(count)
+    }
+
+    fn config_expect_next_call_revert(&self, reason: Option<String>) -> Result<bool> {
+        let mut inner = self
+            .node
+            .write()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+        inner.expected_revert = Some(ExpectedRevert { reason });
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::DEFAULT_PREV_RANDAO;
+    use crate::{http_fork_source::HttpForkSource, testing};
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::transaction::eip2718::TypedTransaction,
+    };
+    use std::str::FromStr;
+    use zksync_types::{
+        transaction_request::CallRequestBuilder, Nonce, PackedEthSignature,
+        ACCOUNT_CODE_STORAGE_ADDRESS, NONCE_HOLDER_ADDRESS, SYSTEM_CONTEXT_ADDRESS,
+    };
+
+    #[tokio::test]
+    async fn test_config_reorg_replaces_latest_blocks() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(0)))
+            .expect("failed submitting tx");
+        let replaced_tx = testing::build_l2_tx(&private_key, Nonce(1));
+        node.submit_tx(replaced_tx.clone())
+            .expect("failed submitting tx");
+
+        let (kept_hash, replaced_hashes) = {
+            let reader = node.get_inner();
+            let reader = reader.read().unwrap();
+            assert_eq!(4, reader.current_miniblock);
+            (
+                reader.block_hashes[&2],
+                vec![reader.block_hashes[&3], reader.block_hashes[&4]],
+            )
+        };
+
+        let wallet = LocalWallet::from_bytes(private_key.as_bytes())
+            .expect("failed creating wallet")
+            .with_chain_id(260u64);
+        let tx: TypedTransaction = ethers::types::TransactionRequest::new()
+            .from(wallet.address())
+            .to(ethers::types::Address::repeat_byte(0x1))
+            .value(2)
+            .gas(3_000_000)
+            .gas_price(250_000_000)
+            .nonce(1)
+            .chain_id(260)
+            .into();
+        let signature = wallet
+            .sign_transaction_sync(&tx)
+            .expect("failed signing tx");
+        let raw_tx = Bytes(tx.rlp_signed(&signature).to_vec());
+
+        let latest_hash = config_api
+            .config_reorg(2, vec![raw_tx])
+            .expect("failed reorg");
+
+        let reader = node.get_inner();
+        let reader = reader.read().unwrap();
+        assert_eq!(4, reader.current_miniblock);
+        assert_eq!(reader.current_miniblock_hash, latest_hash);
+        assert_eq!(Some(&latest_hash), reader.block_hashes.get(&4));
+        assert_eq!(kept_hash, reader.block_hashes[&2]);
+        for (number, replaced_hash) in [3u64, 4].iter().zip(replaced_hashes) {
+            assert_ne!(replaced_hash, reader.block_hashes[number]);
+            assert!(!reader.blocks.contains_key(&replaced_hash));
+        }
+        assert!(!reader.tx_results.contains_key(&replaced_tx.hash()));
+    }
+
+    #[tokio::test]
+    async fn test_config_reorg_restores_the_rolled_back_blocks_if_a_transaction_fails() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(0)))
+            .expect("failed submitting tx");
+        let kept_tx = testing::build_l2_tx(&private_key, Nonce(1));
+        node.submit_tx(kept_tx.clone())
+            .expect("failed submitting tx");
+        let (latest_hash, block_hashes) = {
+            let reader = node.get_inner();
+            let reader = reader.read().unwrap();
+            (reader.current_miniblock_hash, reader.block_hashes.clone())
+        };
+
+        let wallet = LocalWallet::from_bytes(private_key.as_bytes())
+            .expect("failed creating wallet")
+            .with_chain_id(260u64);
+        let raw_tx = |nonce: u64| {
+            let tx: TypedTransaction = ethers::types::TransactionRequest::new()
+                .from(wallet.address())
+                .to(ethers::types::Address::repeat_byte(0x1))
+                .value(2)
+                .gas(3_000_000)
+                .gas_price(250_000_000)
+                .nonce(nonce)
+                .chain_id(260)
+                .into();
+            let signature = wallet
+                .sign_transaction_sync(&tx)
+                .expect("failed signing tx");
+            Bytes(tx.rlp_signed(&signature).to_vec())
+        };
+
+        // the second transaction fails - its nonce is already used
+        config_api
+            .config_reorg(2, vec![raw_tx(1), raw_tx(0)])
+            .expect_err("reorg with a failing transaction succeeded");
+
+        let reader = node.get_inner();
+        let reader = reader.read().unwrap();
+        assert_eq!(4, reader.current_miniblock);
+        assert_eq!(latest_hash, reader.current_miniblock_hash);
+        assert_eq!(block_hashes, reader.block_hashes);
+        assert!(reader.tx_results.contains_key(&kept_tx.hash()));
+    }
+
+    #[test]
+    fn test_config_set_next_block_transactions_mines_them_in_the_given_order() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        let raw_tx = |private_key: H256| {
+            node.set_rich_account(
+                PackedEthSignature::address_from_private_key(&private_key)
+                    .expect("failed generating address"),
+            );
+            let wallet = LocalWallet::from_bytes(private_key.as_bytes())
+                .expect("failed creating wallet")
+                .with_chain_id(260u64);
+            let tx: TypedTransaction = ethers::types::TransactionRequest::new()
+                .from(wallet.address())
+                .to(ethers::types::Address::repeat_byte(0x1))
+                .value(1)
+                .gas(3_000_000)
+                .gas_price(250_000_000)
+                .nonce(0)
+                .chain_id(260)
+                .into();
+            let signature = wallet
+                .sign_transaction_sync(&tx)
+                .expect("failed signing tx");
+            Bytes(tx.rlp_signed(&signature).to_vec())
+        };
+        let raw_txs = vec![
+            raw_tx(H256::repeat_byte(0xee)),
+            raw_tx(H256::repeat_byte(0xef)),
+        ];
+
+        let hashes = config_api
+            .config_set_next_block_transactions(raw_txs)
+            .expect("failed setting the next block transactions");
+        assert_eq!(2, hashes.len());
+        node.mine_block().expect("failed mining block");
+
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        let block = &reader.blocks[&reader.current_miniblock_hash];
+        let block_hashes = block
+            .transactions
+            .iter()
+            .map(|tx| match tx {
+                TransactionVariant::Full(tx) => tx.hash,
+                TransactionVariant::Hash(hash) => *hash,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(hashes, block_hashes);
+        assert!(reader.next_block_txs.is_empty());
+    }
+
+    #[test]
+    fn test_config_clear_impersonations_stops_impersonating_all_accounts() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        let accounts = vec![Address::repeat_byte(0x1), Address::repeat_byte(0x2)];
+        {
+            let inner = node.get_inner();
+            let mut writer = inner.write().unwrap();
+            for account in accounts.iter().rev() {
+                assert!(writer.set_impersonated_account(*account));
+            }
+        }
+
+        assert_eq!(
+            accounts,
+            config_api
+                .config_get_impersonated_accounts()
+                .expect("failed getting impersonated accounts")
+        );
+        assert_eq!(
+            2,
+            config_api
+                .config_clear_impersonations()
+                .expect("failed clearing impersonations")
+        );
+        assert!(config_api
+            .config_get_impersonated_accounts()
+            .expect("failed getting impersonated accounts")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_config_get_paymaster_params_encodes_general_flow() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        let paymaster = Address::repeat_byte(0x1);
+
+        let params = config_api
+            .config_get_paymaster_params(paymaster, Some(Bytes(vec![0xab, 0xcd])))
+            .expect("failed getting paymaster params");
+
+        assert_eq!(paymaster, params.paymaster);
+        assert_eq!(
+            hex::decode(concat!(
+                "8c5a3445",
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "abcd000000000000000000000000000000000000000000000000000000000000",
+            ))
+            .unwrap(),
+            params.paymaster_input.0
+        );
+    }
+
+    #[test]
+    fn test_config_list_blocks_returns_range_metadata() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(0)))
+            .expect("failed submitting tx");
+        node.mine_block().expect("failed mining block");
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(1)))
+            .expect("failed submitting tx");
+
+        let blocks = config_api
+            .config_list_blocks(U64::from(1), U64::from(100))
+            .expect("failed listing blocks");
+
+        let reader = node.get_inner();
+        let reader = reader.read().unwrap();
+        assert_eq!(
+            (1..=reader.current_miniblock)
+                .map(U64::from)
+                .collect::<Vec<_>>(),
+            blocks.iter().map(|block| block.number).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1, 0, 0, 1, 0],
+            blocks
+                .iter()
+                .map(|block| block.transaction_count.as_u64())
+                .collect::<Vec<_>>()
+        );
+        for block in &blocks {
+            assert_eq!(reader.block_hashes[&block.number.as_u64()], block.hash);
+        }
+
+        let error = config_api
+            .config_list_blocks(U64::from(2), U64::from(1))
+            .expect_err("reversed range must be rejected");
+        assert_eq!(jsonrpc_core::ErrorCode::InvalidParams, error.code);
+    }
+
+    #[tokio::test]
+    async fn test_config_set_syncing_simulates_sync_progress() {
+        use zksync_basic_types::web3::types::SyncState;
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        node.mine_block().expect("failed mining block");
+
+        assert!(config_api
+            .config_set_syncing(true, Some(U64::from(10)))
+            .expect("failed enabling syncing"));
+        node.mine_block().expect("failed mining block");
+        match node.syncing().await.expect("failed syncing") {
+            SyncState::Syncing(sync_info) => {
+                assert_eq!(U256::from(1), sync_info.starting_block);
+                assert_eq!(U256::from(2), sync_info.current_block);
+                assert_eq!(U256::from(10), sync_info.highest_block);
+            }
+            SyncState::NotSyncing => panic!("expected the node to be syncing"),
+        }
+
+        assert!(!config_api
+            .config_set_syncing(false, None)
+            .expect("failed disabling syncing"));
+        assert!(matches!(
+            node.syncing().await.expect("failed syncing"),
+            SyncState::NotSyncing
+        ));
+    }
+
+    #[test]
+    fn test_config_get_memory_stats_counts_mined_blocks() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        let before = config_api
+            .config_get_memory_stats()
+            .expect("failed getting memory stats");
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(0)))
+            .expect("failed submitting tx");
+        node.mine_block().expect("failed mining block");
+        let after = config_api
+            .config_get_memory_stats()
+            .expect("failed getting memory stats");
+
+        let reader = node.get_inner();
+        let reader = reader.read().unwrap();
+        assert_eq!(U64::from(reader.blocks.len()), after.blocks.count);
+        assert!(after.blocks.count > before.blocks.count);
+        assert!(after.blocks.bytes > before.blocks.bytes);
+        assert_eq!(
+            before.transactions.count + U64::one(),
+            after.transactions.count
+        );
+        assert!(after.storage_keys.count > before.storage_keys.count);
+    }
+
+    #[tokio::test]
+    async fn test_config_mine_until_mines_up_to_the_target_block() {
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        let latest = config_api
+            .config_mine_until(U64::from(10))
+            .expect("failed mining blocks");
+        assert_eq!(U64::from(10), latest);
+        assert_eq!(
+            U64::from(10),
+            node.get_block_number()
+                .await
+                .expect("failed getting block number")
+        );
+
+        let error = config_api
+            .config_mine_until(U64::from(5))
+            .expect_err("mining until a past block must fail");
+        assert_eq!(jsonrpc_core::ErrorCode::InvalidParams, error.code);
+    }
+
+    #[tokio::test]
+    async fn test_config_set_prev_randao_is_read_by_contracts() {
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        // `block.prevrandao` is compiled to a call of `difficulty()` of the `SystemContext` system contract
+        let prev_randao = || {
+            let request = CallRequestBuilder::default()
+                .to(SYSTEM_CONTEXT_ADDRESS)
+                .data(hex::decode("19cae462").unwrap().into())
+                .build();
+            let result = node.call(request, None);
+            async move { H256::from_slice(&result.await.expect("failed calling").0) }
+        };
+        assert_eq!(
+            H256::from_low_u64_be(DEFAULT_PREV_RANDAO),
+            config_api.config_get_prev_randao().unwrap()
+        );
+        assert_eq!(
+            H256::from_low_u64_be(DEFAULT_PREV_RANDAO),
+            prev_randao().await
+        );
+
+        let value = H256::repeat_byte(0xab);
+        assert_eq!(value, config_api.config_set_prev_randao(value).unwrap());
+        assert_eq!(value, config_api.config_get_prev_randao().unwrap());
+        assert_eq!(value, prev_randao().await);
+    }
+
+    #[tokio::test]
+    async fn test_config_set_balance_for_next_block_is_restored_once_mined() {
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        let tx = testing::build_l2_tx(&private_key, Nonce(0));
+        let recipient = tx.recipient_account();
+        assert!(config_api
+            .config_set_balance_for_next_block(from_account, U256::from(10).pow(18.into()))
+            .unwrap());
+
+        // the account is funded for the block of its transaction only
+        node.submit_tx(tx).expect("failed submitting tx");
+        assert_eq!(
+            U256::one(),
+            node.get_balance(recipient, None).await.unwrap()
+        );
+        assert_eq!(
+            U256::zero(),
+            node.get_balance(from_account, None).await.unwrap()
+        );
+        assert!(node
+            .get_inner()
+            .read()
+            .unwrap()
+            .next_block_balances
+            .is_empty());
+    }
+
+    #[test]
+    fn test_config_get_mapping_slot_matches_solidity_layout() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        // mapping(uint256 => uint256) at slot 0, key 0
+        assert_eq!(
+            H256::from_str("0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5")
+                .unwrap(),
+            config_api
+                .config_get_mapping_slot(U256::zero(), H256::zero())
+                .unwrap()
+        );
+        // mapping(uint256 => uint256) at slot 1, key 0x42
+        assert_eq!(
+            H256::from_str("0xe99635fccc8593e18a8f8d41f3819fbbb23d116b5e979cc68c43a98e9c10e52a")
+                .unwrap(),
+            config_api
+                .config_get_mapping_slot(U256::one(), H256::from_low_u64_be(0x42))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_config_get_array_slot_matches_solidity_layout() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        // uint256[] at slot 2, its data starting at keccak256(2)
+        assert_eq!(
+            H256::from_str("0x405787fa12a823e0f2b7631cc41b3ba8828b3321ca811111fa75cd3aa3bb5ace")
+                .unwrap(),
+            config_api
+                .config_get_array_slot(U256::from(2), U256::zero())
+                .unwrap()
+        );
+        assert_eq!(
+            H256::from_str("0x405787fa12a823e0f2b7631cc41b3ba8828b3321ca811111fa75cd3aa3bb5ad1")
+                .unwrap(),
+            config_api
+                .config_get_array_slot(U256::from(2), U256::from(3))
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_config_mock_contract_returns_the_mocked_data() {
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        // `difficulty()` of the `SystemContext` system contract
+        let selector = hex::decode("19cae462").unwrap();
+        let mocked = H256::repeat_byte(0x42);
+        assert!(config_api
+            .config_mock_contract(
+                SYSTEM_CONTEXT_ADDRESS,
+                selector.clone().into(),
+                mocked.as_bytes().to_vec().into(),
+            )
+            .unwrap());
+
+        let result = node
+            .call(
+                CallRequestBuilder::default()
+                    .to(SYSTEM_CONTEXT_ADDRESS)
+                    .data(selector.clone().into())
+                    .build(),
+                None,
+            )
+            .await
+            .expect("failed calling");
+        assert_eq!(mocked.as_bytes(), &result.0[..]);
+
+        config_api
+            .config_mock_contract(
+                Address::repeat_byte(0x1),
+                selector.into(),
+                mocked.as_bytes().to_vec().into(),
+            )
+            .expect_err("mocked a contract that is not a system contract");
+        config_api
+            .config_mock_contract(
+                SYSTEM_CONTEXT_ADDRESS,
+                vec![0x19, 0xca].into(),
+                mocked.as_bytes().to_vec().into(),
+            )
+            .expect_err("mocked an invalid selector");
+    }
+
+    #[tokio::test]
+    async fn test_config_mock_contract_mocks_the_calls_made_by_other_contracts() {
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+
+        // `getCodeHash(account)` of the `AccountCodeStorage` - for an account without code, it calls the
+        // `getRawNonce(account)` of the `NonceHolder`, to return `keccak256("")` if the account sent a transaction
+        let account = Address::repeat_byte(0x5a);
+        let get_code_hash = || {
+            let request = CallRequestBuilder::default()
+                .to(ACCOUNT_CODE_STORAGE_ADDRESS)
+                .data(
+                    hex::decode(format!("e03fe177{:0>64}", hex::encode(account)))
+                        .unwrap()
+                        .into(),
+                )
+                .build();
+            let result = node.call(request, None);
+            async move { H256::from_slice(&result.await.expect("failed calling").0) }
+        };
+        assert_eq!(H256::zero(), get_code_hash().await);
+
+        assert!(config_api
+            .config_mock_contract(
+                NONCE_HOLDER_ADDRESS,
+                hex::decode("5aa9b6b5").unwrap().into(),
+                H256::from_low_u64_be(1).as_bytes().to_vec().into(),
+            )
+            .unwrap());
+        assert_eq!(H256(keccak256(&[])), get_code_hash().await);
+    }
+
+    #[tokio::test]
+    async fn test_config_mock_call_returns_the_mocked_data_until_cleared() {
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+        use zksync_types::utils::deployed_address_create;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            None,
+            Nonce(0),
+        );
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+        let call = |calldata: &str| {
+            let result = node.call(
+                CallRequestBuilder::default()
+                    .to(deployed_address)
+                    .data(hex::decode(calldata).unwrap().into())
+                    .build(),
+                None,
+            );
+            async move { U256::from_big_endian(&result.await.expect("failed calling").0) }
+        };
+        let mock = |calldata: &str, value: u64| {
+            config_api
+                .config_mock_call(
+                    deployed_address,
+                    hex::decode(calldata).unwrap().into(),
+                    H256::from_low_u64_be(value).as_bytes().to_vec().into(),
+                )
+                .expect("failed mocking call")
+        };
+
+        // retrieve1()
+        assert_eq!(U256::from(1024), call("ae2e2cce").await);
+        assert!(mock("ae2e2cce", 7));
+        assert_eq!(U256::from(7), call("ae2e2cce").await);
+
+        // a partial calldata matches all the calls starting with it, but the most specific mock wins
+        assert!(mock("ae2e", 5));
+        assert_eq!(U256::from(7), call("ae2e2cce").await);
+        assert_eq!(U256::from(5), call("ae2e0000").await);
+
+        assert_eq!(2, config_api.config_clear_mocks().unwrap());
+        assert_eq!(U256::from(1024), call("ae2e2cce").await);
+    }
+
+    #[tokio::test]
+    async fn test_config_mock_call_mocks_the_calls_made_by_other_contracts() {
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        let (primary, secondary) = testing::deploy_test_contracts(&node);
+        let calculate = || {
+            let request = CallRequestBuilder::default()
+                .to(primary)
+                .data(hex::decode(format!("b9d92de8{:064x}", 42)).unwrap().into())
+                .build();
+            let result = node.call(request, None);
+            async move { U256::from_big_endian(&result.await.expect("failed calling").0) }
+        };
+
+        // `calculate(42)` of the `Primary` returns the `multiply(42)` of the `Secondary`
+        assert_eq!(U256::from(84), calculate().await);
+        assert!(config_api
+            .config_mock_call(
+                secondary,
+                hex::decode(format!("c6888fa1{:064x}", 42)).unwrap().into(),
+                H256::from_low_u64_be(7).as_bytes().to_vec().into(),
+            )
+            .unwrap());
+        assert_eq!(U256::from(7), calculate().await);
+
+        assert_eq!(1, config_api.config_clear_mocks().unwrap());
+        assert_eq!(U256::from(84), calculate().await);
+    }
+
+    #[tokio::test]
+    async fn test_config_expect_next_call_revert_reports_whether_the_call_reverted() {
+        use crate::deps::system_contracts::bytecode_from_slice;
+        use ethers::abi::short_signature;
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+        use zksync_types::utils::deployed_address_create;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        let private_key = H256::repeat_byte(0xee);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            bytecode_from_slice(
+                "Secondary",
+                include_bytes!("deps/test-contracts/Secondary.json"),
+            ),
+            Some(ethers::abi::encode(&[ethers::abi::Token::Uint(
+                ethers::types::U256::from(2),
+            )])),
+            Nonce(0),
+        );
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+        let call = |signature: &str| {
+            node.call(
+                CallRequestBuilder::default()
+                    .to(deployed_address)
+                    .data(short_signature(signature, &[]).to_vec().into())
+                    .build(),
+                None,
+            )
+        };
+        let expect_revert = |reason: Option<&str>| {
+            assert!(config_api
+                .config_expect_next_call_revert(reason.map(String::from))
+                .unwrap());
+        };
+        let met = H256::from_low_u64_be(1).as_bytes().to_vec();
+        let not_met = H256::zero().as_bytes().to_vec();
+
+        expect_revert(None);
+        assert_eq!(met, call("shouldRevert()").await.unwrap().0);
+        expect_revert(Some("This should revert"));
+        assert_eq!(met, call("shouldRevert()").await.unwrap().0);
+        expect_revert(Some("another reason"));
+        assert_eq!(not_met, call("shouldRevert()").await.unwrap().0);
+        expect_revert(None);
+        assert_eq!(not_met, call("name()").await.unwrap().0);
+
+        // the expectation only applies to the next call
+        call("shouldRevert()")
+            .await
+            .expect_err("the revert was not surfaced once the expectation was consumed");
+    }
+
+    #[tokio::test]
+    async fn test_config_expect_next_call_revert_applies_to_sent_transactions() {
+        use ethers::abi::short_signature;
+        use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config_api = ConfigurationApiNamespace::new(node.get_inner());
+        let (_, secondary) = testing::deploy_test_contracts(&node);
+        let send = |signature: &str, nonce: u64| {
+            node.send_raw_transaction(testing::raw_transfer(
+                H256::repeat_byte(0xee),
+                testing::TransferParams {
+                    to: secondary,
+                    value: 0,
+                    nonce,
+                    data: short_signature(signature, &[]).to_vec(),
+                    ..Default::default()
+                },
+            ))
+        };
+
+        config_api
+            .config_expect_next_call_revert(Some(String::from("This should revert")))
+            .unwrap();
+        let tx_hash = send("shouldRevert()", 2)
+            .await
+            .expect("the expected revert was surfaced");
+        let receipt = node
+            .get_transaction_receipt(tx_hash)
+            .await
+            .expect("failed fetching receipt")
+            .expect("receipt exists");
+        assert_eq!(Some(U64::zero()), receipt.status);
+
+        config_api.config_expect_next_call_revert(None).unwrap();
+        let error = send("name()", 3)
+            .await
+            .expect_err("the transaction did not revert as expected");
+        assert!(
+            error.message.contains("expected the transaction to revert"),
+            "unexpected error message: {}",
+            error.message
+        );
+
+        // the expectation only applies to the next transaction
+        send("shouldRevert()", 4)
+            .await
+            .expect_err("the revert was not surfaced once the expectation was consumed");
+    }
 }
@@ -5,7 +5,9 @@ use crate::{
 };
 use itertools::Itertools;
 use jsonrpc_core::{BoxFuture, Result};
+use jsonrpc_derive::rpc;
 use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, RwLock};
 use vm::{constants::ETH_CALL_GAS_LIMIT, CallTracer, HistoryDisabled, TxExecutionMode, Vm};
 use zksync_basic_types::H256;
@@ -14,7 +16,10 @@ use zksync_core::api_server::web3::backend_jsonrpc::{
 };
 use zksync_state::StorageView;
 use zksync_types::{
-    api::{BlockId, BlockNumber, DebugCall, ResultDebugCall, TracerConfig, TransactionVariant},
+    api::{
+        BlockId, BlockNumber, DebugCall, DebugCallType, ResultDebugCall, TracerConfig,
+        TransactionVariant,
+    },
     l2::L2Tx,
     transaction_request::CallRequest,
     PackedEthSignature, Transaction, U64,
@@ -236,67 +241,164 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> DebugNamespaceT
     }
 }
 
+/// Methods of the `debug` namespace that extend the zkSync API.
+#[rpc]
+pub trait DebugExtNamespaceT {
+    /// Returns the trace of a transaction. Same as the zkSync `debug_traceTransaction` (which only supports the
+    /// `callTracer`), but additionally supports Geth's default struct logger, selected with `{ "tracer": "" }`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hash` - The hash of the transaction.
+    /// * `options` - Optional tracer options.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with either a [DebugCall], or a [StructLogTrace] for the struct logger.
+    #[rpc(name = "debug_traceTransaction")]
+    fn trace_transaction_with_tracer(
+        &self,
+        tx_hash: H256,
+        options: Option<serde_json::Value>,
+    ) -> BoxFuture<Result<Option<serde_json::Value>>>;
+}
+
+/// The trace of a transaction, in the standard JSON format of Geth's default struct logger.
+///
+/// The zkSync VM does not execute EVM opcodes, so there is a struct log per call frame (in the order
+/// the calls were made), rather than per opcode.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    /// Gas used by the transaction.
+    pub gas: u64,
+    /// Whether the transaction reverted.
+    pub failed: bool,
+    /// Hex-encoded return data, without the `0x` prefix.
+    pub return_value: String,
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// A single entry of a [StructLogTrace].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: u64,
+    /// The call opcode - `CALL` or `CREATE`.
+    pub op: String,
+    /// Gas available to the call.
+    pub gas: u64,
+    /// Gas used by the call.
+    pub gas_cost: u64,
+    /// Depth of the call, starting at `1` for the calls made by the transaction.
+    pub depth: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StructLogTrace {
+    /// Creates the struct log trace from the call trace of a transaction.
+    pub fn from_debug_call(call: &DebugCall) -> Self {
+        fn push_struct_logs(call: &DebugCall, depth: usize, struct_logs: &mut Vec<StructLog>) {
+            struct_logs.push(StructLog {
+                pc: 0,
+                op: match call.r#type {
+                    DebugCallType::Call => "CALL",
+                    DebugCallType::Create => "CREATE",
+                }
+                .to_string(),
+                gas: call.gas.low_u64(),
+                gas_cost: call.gas_used.low_u64(),
+                depth,
+                error: call.error.clone().or_else(|| call.revert_reason.clone()),
+            });
+            for subcall in &call.calls {
+                push_struct_logs(subcall, depth + 1, struct_logs);
+            }
+        }
+
+        let mut struct_logs = vec![];
+        for subcall in &call.calls {
+            push_struct_logs(subcall, 1, &mut struct_logs);
+        }
+
+        Self {
+            gas: call.gas_used.low_u64(),
+            failed: call.error.is_some() || call.revert_reason.is_some(),
+            return_value: hex::encode(&call.output.0),
+            struct_logs,
+        }
+    }
+}
+
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> DebugExtNamespaceT
+    for DebugNamespaceImpl<S>
+{
+    fn trace_transaction_with_tracer(
+        &self,
+        tx_hash: H256,
+        options: Option<serde_json::Value>,
+    ) -> BoxFuture<Result<Option<serde_json::Value>>> {
+        let struct_logger = options
+            .as_ref()
+            .and_then(|options| options.get("tracer"))
+            .map_or(false, |tracer| tracer == "");
+        if struct_logger {
+            let inner = Arc::clone(&self.node);
+            return Box::pin(async move {
+                let inner = inner
+                    .read()
+                    .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+                Ok(inner.tx_results.get(&tx_hash).map(|tx| {
+                    serde_json::to_value(StructLogTrace::from_debug_call(&tx.debug_info(false)))
+                        .expect("failed serializing trace")
+                }))
+            });
+        }
+
+        let options = match options
+            .map(serde_json::from_value::<TracerConfig>)
+            .transpose()
+        {
+            Ok(options) => options,
+            Err(err) => {
+                return Box::pin(futures::future::err(jsonrpc_core::Error::invalid_params(
+                    err.to_string(),
+                )))
+            }
+        };
+        let trace = DebugNamespaceT::trace_transaction(self, tx_hash, options);
+        Box::pin(async move {
+            Ok(trace
+                .await?
+                .map(|call| serde_json::to_value(call).expect("failed serializing trace")))
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        deps::system_contracts::bytecode_from_slice,
         http_fork_source::HttpForkSource,
         node::{InMemoryNode, TransactionResult},
         testing::{self, LogBuilder},
     };
     use ethers::abi::{short_signature, AbiEncode, HumanReadableParser, ParamType, Token};
-    use zksync_basic_types::{Address, Nonce, H160, U256};
+    use zksync_basic_types::{H160, U256};
     use zksync_types::{
         api::{Block, CallTracerConfig, SupportedTracers, TransactionReceipt},
         transaction_request::CallRequestBuilder,
-        utils::deployed_address_create,
     };
 
-    fn deploy_test_contracts(node: &InMemoryNode<HttpForkSource>) -> (Address, Address) {
-        let private_key = H256::repeat_byte(0xee);
-        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
-            .expect("failed generating address");
-        node.set_rich_account(from_account);
-
-        // first, deploy secondary contract
-        let secondary_bytecode = bytecode_from_slice(
-            "Secondary",
-            include_bytes!("deps/test-contracts/Secondary.json"),
-        );
-        let secondary_deployed_address = deployed_address_create(from_account, U256::zero());
-        testing::deploy_contract(
-            &node,
-            H256::repeat_byte(0x1),
-            private_key,
-            secondary_bytecode,
-            Some((U256::from(2),).encode()),
-            Nonce(0),
-        );
-
-        // deploy primary contract using the secondary contract address as a constructor parameter
-        let primary_bytecode = bytecode_from_slice(
-            "Primary",
-            include_bytes!("deps/test-contracts/Primary.json"),
-        );
-        let primary_deployed_address = deployed_address_create(from_account, U256::one());
-        testing::deploy_contract(
-            &node,
-            H256::repeat_byte(0x1),
-            private_key,
-            primary_bytecode,
-            Some((secondary_deployed_address).encode()),
-            Nonce(1),
-        );
-        (primary_deployed_address, secondary_deployed_address)
-    }
-
     #[tokio::test]
     async fn test_trace_deployed_contract() {
         let node = InMemoryNode::<HttpForkSource>::default();
         let debug = DebugNamespaceImpl::new(node.get_inner());
 
-        let (primary_deployed_address, secondary_deployed_address) = deploy_test_contracts(&node);
+        let (primary_deployed_address, secondary_deployed_address) =
+            testing::deploy_test_contracts(&node);
 
         // trace a call to the primary contract
         let func = HumanReadableParser::parse_function("calculate(uint)").unwrap();
@@ -347,7 +449,7 @@ mod tests {
         let node = InMemoryNode::<HttpForkSource>::default();
         let debug = DebugNamespaceImpl::new(node.get_inner());
 
-        let (primary_deployed_address, _) = deploy_test_contracts(&node);
+        let (primary_deployed_address, _) = testing::deploy_test_contracts(&node);
 
         // trace a call to the primary contract
         let func = HumanReadableParser::parse_function("calculate(uint)").unwrap();
@@ -385,7 +487,7 @@ mod tests {
         let node = InMemoryNode::<HttpForkSource>::default();
         let debug = DebugNamespaceImpl::new(node.get_inner());
 
-        let (primary_deployed_address, _) = deploy_test_contracts(&node);
+        let (primary_deployed_address, _) = testing::deploy_test_contracts(&node);
 
         // trace a call to the primary contract
         let request = CallRequestBuilder::default()
@@ -481,6 +583,46 @@ mod tests {
         assert!(result.calls.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_trace_transaction_with_struct_logger() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let inner = node.get_inner();
+        {
+            let mut writer = inner.write().unwrap();
+            writer.tx_results.insert(
+                H256::repeat_byte(0x1),
+                TransactionResult {
+                    info: testing::default_tx_execution_info(),
+                    receipt: Default::default(),
+                    debug: DebugCall {
+                        gas_used: U256::from(21000),
+                        output: vec![0xab, 0xcd].into(),
+                        ..testing::default_tx_debug_info()
+                    },
+                },
+            );
+        }
+        let result = DebugNamespaceImpl::new(inner)
+            .trace_transaction_with_tracer(
+                H256::repeat_byte(0x1),
+                Some(serde_json::json!({ "tracer": "", "disableStack": true })),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(serde_json::json!(21000), result["gas"]);
+        assert_eq!(serde_json::json!(false), result["failed"]);
+        assert_eq!(serde_json::json!("abcd"), result["returnValue"]);
+        let struct_logs = result["structLogs"].as_array().unwrap();
+        assert_eq!(1, struct_logs.len());
+        for key in ["pc", "op", "gas", "gasCost", "depth"] {
+            assert!(struct_logs[0].get(key).is_some(), "missing {}", key);
+        }
+        assert_eq!(serde_json::json!("CALL"), struct_logs[0]["op"]);
+        assert_eq!(serde_json::json!(1), struct_logs[0]["depth"]);
+    }
+
     #[tokio::test]
     async fn test_trace_transaction_not_found() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -1,9 +1,11 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
 use crate::{
     fork::ForkSource,
-    node::{InMemoryNodeInner, Snapshot},
-    utils::mine_empty_blocks,
+    node::{InMemoryNode, InMemoryNodeInner, Snapshot},
 };
 use jsonrpc_core::{BoxFuture, Result};
 use jsonrpc_derive::rpc;
@@ -46,7 +48,8 @@ pub trait EvmNamespaceT {
 
     /// Force a single block to be mined.
     ///
-    /// Will mine an empty block (containing zero transactions)
+    /// Will execute the pending transactions from the pool (one per block), or mine an empty block
+    /// (containing zero transactions) if there are none.
     ///
     /// # Returns
     /// The string "0x0".
@@ -96,6 +99,28 @@ pub trait EvmNamespaceT {
     /// `true` if a snapshot was reverted, otherwise `false`.
     #[rpc(name = "evm_revert")]
     fn revert_snapshot(&self, snapshot_id: U64) -> BoxFuture<Result<bool>>;
+
+    /// Enables or disables the automatic execution of transactions as soon as they are submitted.
+    /// When disabled, transactions are kept in the pool until a block is mined.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether automine should be enabled
+    ///
+    /// # Returns
+    /// `true` on success.
+    #[rpc(name = "evm_setAutomine")]
+    fn set_automine(&self, enabled: bool) -> BoxFuture<Result<bool>>;
+
+    /// Enables or disables the automatic mining of a block at a regular interval, including the pending
+    /// transactions.
+    ///
+    /// # Parameters
+    /// - `interval_ms`: The interval between the blocks in milliseconds, or `0` to disable interval mining
+    ///
+    /// # Returns
+    /// `true` on success.
+    #[rpc(name = "evm_setIntervalMining")]
+    fn set_interval_mining(&self, interval_ms: u64) -> BoxFuture<Result<bool>>;
 }
 
 impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EvmNamespaceT
@@ -131,6 +156,7 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EvmNamespaceT
                         Err(into_jsrpc_error(Web3Error::InternalError))
                     } else {
                         inner_guard.current_timestamp = timestamp;
+                        inner_guard.next_block_timestamp_set = true;
                         Ok(timestamp)
                     }
                 }
@@ -158,14 +184,13 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EvmNamespaceT
     fn evm_mine(&self) -> BoxFuture<Result<String>> {
         let inner = Arc::clone(&self.node);
         Box::pin(async move {
-            match inner.write() {
-                Ok(mut inner) => {
-                    mine_empty_blocks(&mut inner, 1, 1000);
-                    log::info!("👷 Mined block #{}", inner.current_miniblock);
-                    Ok("0x0".to_string())
-                }
-                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
-            }
+            InMemoryNode::from_inner(inner)
+                .mine_block()
+                .map_err(|err| {
+                    log::error!("failed mining block: {}", err);
+                    into_jsrpc_error(Web3Error::InternalError)
+                })?;
+            Ok("0x0".to_string())
         })
     }
 
@@ -254,12 +279,50 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EvmNamespaceT
                 })
         })
     }
+
+    fn set_automine(&self, enabled: bool) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+
+        Box::pin(async move {
+            match inner.write() {
+                Ok(mut inner_guard) => {
+                    inner_guard.automine = enabled;
+                    log::info!("Automine {}", if enabled { "enabled" } else { "disabled" });
+                    Ok(true)
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
+
+    fn set_interval_mining(&self, interval_ms: u64) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            match inner.write() {
+                Ok(mut inner_guard) => {
+                    inner_guard.interval_mining_ms = interval_ms;
+                    inner_guard.interval_mining_deadline = if interval_ms > 0 {
+                        log::info!("Interval mining enabled every {}ms", interval_ms);
+                        Some(Instant::now() + Duration::from_millis(interval_ms))
+                    } else {
+                        log::info!("Interval mining disabled");
+                        None
+                    };
+                    Ok(true)
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode};
+    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode, testing};
+    use zksync_basic_types::H256;
     use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+    use zksync_types::{Nonce, PackedEthSignature};
+    use zksync_web3_decl::types::FilterChanges;
 
     use super::*;
 
@@ -603,6 +666,38 @@ mod tests {
         assert_eq!(start_block.timestamp + 2, current_block.timestamp);
     }
 
+    #[tokio::test]
+    async fn test_evm_mine_executes_pending_transactions_with_automine_disabled() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let evm = EvmNamespaceImpl::new(node.get_inner());
+
+        evm.set_automine(false)
+            .await
+            .expect("failed disabling automine");
+
+        let private_key = H256::repeat_byte(0x1);
+        let sender = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(sender);
+        let tx = testing::build_l2_tx(&private_key, Nonce(0));
+        node.submit_tx(tx.clone()).expect("failed submitting tx");
+
+        let receipt = node
+            .get_transaction_receipt(tx.hash())
+            .await
+            .expect("failed fetching receipt");
+        assert!(receipt.is_none(), "transaction must not be executed yet");
+
+        evm.evm_mine().await.expect("evm_mine");
+
+        let receipt = node
+            .get_transaction_receipt(tx.hash())
+            .await
+            .expect("failed fetching receipt");
+        assert!(receipt.is_some(), "transaction must be executed");
+        assert!(node.get_inner().read().unwrap().pool.is_empty());
+    }
+
     #[tokio::test]
     async fn test_evm_snapshot_creates_incrementing_ids() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -672,4 +767,45 @@ mod tests {
         let result = evm.revert_snapshot(U64::from(100)).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_block_filter_returns_the_blocks_mined_by_interval_mining() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let evm = EvmNamespaceImpl::new(node.get_inner());
+        node.spawn_interval_miner();
+
+        let filter_id = node
+            .new_block_filter()
+            .await
+            .expect("failed creating filter");
+        assert!(evm
+            .set_interval_mining(100)
+            .await
+            .expect("failed enabling interval mining"));
+        std::thread::sleep(Duration::from_millis(350));
+        assert!(evm
+            .set_interval_mining(0)
+            .await
+            .expect("failed disabling interval mining"));
+        // let a block that was already due finish mining
+        std::thread::sleep(Duration::from_millis(100));
+
+        let hashes = match node
+            .get_filter_changes(filter_id)
+            .await
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Hashes(hashes) => hashes,
+            changes => panic!("unexpected filter changes: {:?}", changes),
+        };
+        let reader = node.get_inner().read().expect("failed acquiring reader");
+        let mined_hashes = (1..=reader.current_miniblock)
+            .map(|number| reader.block_hashes[&number])
+            .collect::<Vec<_>>();
+        assert!(
+            mined_hashes.len() >= 2,
+            "expected at least 2 interval blocks"
+        );
+        assert_eq!(mined_hashes, hashes);
+    }
 }
@@ -10,22 +10,30 @@ use std::{
     sync::{Arc, RwLock},
 };
 
+use serde::{Deserialize, Serialize};
 use tokio::runtime::Builder;
 use zksync_basic_types::{Address, L1BatchNumber, L2ChainId, MiniblockNumber, H256, U256, U64};
 
 use zksync_types::{
-    api::{Block, BlockIdVariant, BlockNumber, Transaction, TransactionVariant},
+    api::{
+        Block, BlockIdVariant, BlockNumber, BridgeAddresses, Log, ProtocolVersion, Transaction,
+        TransactionReceipt, TransactionVariant,
+    },
+    get_code_key, get_nonce_key, get_system_context_init_logs,
     l2::L2Tx,
-    ProtocolVersionId, StorageKey,
+    utils::storage_key_for_eth_balance,
+    ProtocolVersionId, StorageKey, StorageLogKind,
 };
 
 use zksync_state::ReadStorage;
 use zksync_utils::{bytecode::hash_bytecode, h256_to_u256};
 
+use zksync_web3_decl::{jsonrpsee::http_client::HttpClientBuilder, namespaces::ZksNamespaceClient};
 use zksync_web3_decl::{
-    jsonrpsee::http_client::HttpClient, namespaces::EthNamespaceClient, types::Index,
+    jsonrpsee::http_client::{HeaderMap, HttpClient},
+    namespaces::EthNamespaceClient,
+    types::{Filter, Index},
 };
-use zksync_web3_decl::{jsonrpsee::http_client::HttpClientBuilder, namespaces::ZksNamespaceClient};
 
 use crate::{cache::CacheConfig, node::TEST_NODE_NETWORK_ID};
 use crate::{deps::InMemoryStorage, http_fork_source::HttpForkSource};
@@ -66,6 +74,8 @@ pub struct ForkStorageInner<S> {
     // If set - it hold the necessary information on where to fetch the data.
     // If not set - it will simply read from underlying storage.
     pub fork: Option<ForkDetails<S>>,
+    // The first error of a read from the fork since it was last taken - the value was then read as zero.
+    pub read_error: Option<String>,
 }
 
 impl<S: ForkSource> ForkStorage<S> {
@@ -89,6 +99,7 @@ impl<S: ForkSource> ForkStorage<S> {
                 value_read_cache: Default::default(),
                 fork,
                 factory_dep_cache: Default::default(),
+                read_error: None,
             })),
             chain_id,
         }
@@ -109,24 +120,128 @@ impl<S: ForkSource> ForkStorage<S> {
             let l2_miniblock = fork.l2_miniblock;
             let key_ = *key;
 
-            let result = fork
-                .fork_source
-                .get_storage_at(
-                    *key_.account().address(),
-                    h256_to_u256(*key_.key()),
-                    Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
-                        l2_miniblock,
-                    )))),
-                )
-                .unwrap();
-
-            mutator.value_read_cache.insert(*key, result);
-            result
+            let result = fork.fork_source.get_storage_at(
+                *key_.account().address(),
+                h256_to_u256(*key_.key()),
+                Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
+                    l2_miniblock,
+                )))),
+            );
+            match result {
+                Ok(value) => {
+                    mutator.value_read_cache.insert(*key, value);
+                    value
+                }
+                // the value is not cached, so that it is read from the fork again
+                Err(err) => {
+                    let message = format!(
+                        "failed reading storage key {:?} at fork block #{}: {:?}",
+                        key, l2_miniblock, err
+                    );
+                    log::error!("{}", message);
+                    mutator.read_error.get_or_insert(message);
+                    H256::zero()
+                }
+            }
         } else {
             local_storage
         }
     }
 
+    /// Takes the error of the first read from the fork that failed since the last call, if any. The VM can not be
+    /// interrupted by a failed read, so the value is read as zero instead - and the result of the execution can not
+    /// be trusted.
+    pub fn take_read_error(&self) -> Option<String> {
+        self.inner
+            .write()
+            .ok()
+            .and_then(|mut mutator| mutator.read_error.take())
+    }
+
+    /// Changes the chain id of the network, including the one stored in the system context contract.
+    pub fn set_chain_id(&mut self, chain_id: L2ChainId) {
+        log::info!("Setting network chain id to: {:?}", chain_id);
+        let mut mutator = self.inner.write().unwrap();
+        for log in get_system_context_init_logs(chain_id) {
+            if log.kind == StorageLogKind::Write {
+                mutator.raw_storage.set_value(log.key, log.value);
+            }
+        }
+        self.chain_id = chain_id;
+    }
+
+    /// Fetches the values of the given keys from the fork in a single batch, so that subsequent reads
+    /// are served from the cache. Keys that are set locally, or were already read, are skipped.
+    pub fn prefetch(&self, keys: &[StorageKey]) {
+        let mut mutator = self.inner.write().unwrap();
+        let ForkStorageInner {
+            raw_storage,
+            value_read_cache,
+            fork,
+            ..
+        } = &mut *mutator;
+        let fork = match fork {
+            Some(fork) => fork,
+            None => return,
+        };
+
+        let mut missing_keys = vec![];
+        for key in keys {
+            if raw_storage.read_value(key).is_zero()
+                && !value_read_cache.contains_key(key)
+                && !missing_keys.contains(key)
+            {
+                missing_keys.push(*key);
+            }
+        }
+        if missing_keys.is_empty() {
+            return;
+        }
+
+        let slots = missing_keys
+            .iter()
+            .map(|key| (*key.account().address(), h256_to_u256(*key.key())))
+            .collect::<Vec<_>>();
+        let block = Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
+            fork.l2_miniblock,
+        ))));
+        match fork.fork_source.get_storage_at_batch(&slots, block) {
+            Ok(values) => {
+                log::debug!("prefetched {} storage slots from fork", values.len());
+                value_read_cache.extend(missing_keys.into_iter().zip(values));
+            }
+            Err(err) => log::warn!(
+                "failed prefetching {} storage slots from fork: {:?}",
+                missing_keys.len(),
+                err
+            ),
+        }
+    }
+
+    /// Eagerly fetches the state of the given accounts from the fork - their nonce, balance and bytecode - so that
+    /// the rest of the run reads it locally, without network requests. The storage slots of the accounts are still
+    /// read lazily, as they can not be enumerated.
+    pub fn snapshot_accounts(&self, addresses: &[Address]) {
+        let keys = addresses
+            .iter()
+            .flat_map(|address| {
+                [
+                    get_nonce_key(address),
+                    storage_key_for_eth_balance(address),
+                    get_code_key(address),
+                ]
+            })
+            .collect::<Vec<_>>();
+        self.prefetch(&keys);
+
+        for address in addresses {
+            let code_hash = self.read_value_internal(&get_code_key(address));
+            if !code_hash.is_zero() {
+                self.load_factory_dep_internal(code_hash);
+            }
+        }
+    }
+
     pub fn load_factory_dep_internal(&self, hash: H256) -> Option<Vec<u8>> {
         let mut mutator = self.inner.write().unwrap();
         let local_storage = mutator.raw_storage.load_factory_dep(hash);
@@ -138,9 +253,20 @@ impl<S: ForkSource> ForkStorage<S> {
                 return value.clone();
             }
 
-            let result = fork.fork_source.get_bytecode_by_hash(hash).unwrap();
-            mutator.factory_dep_cache.insert(hash, result.clone());
-            result
+            match fork.fork_source.get_bytecode_by_hash(hash) {
+                Ok(result) => {
+                    mutator.factory_dep_cache.insert(hash, result.clone());
+                    result
+                }
+                // the bytecode is not cached, so that it is read from the fork again
+                Err(err) => {
+                    let message =
+                        format!("failed reading bytecode {:?} from fork: {:?}", hash, err);
+                    log::error!("{}", message);
+                    mutator.read_error.get_or_insert(message);
+                    None
+                }
+            }
         } else {
             local_storage
         }
@@ -188,6 +314,129 @@ impl<S> ForkStorage<S> {
     }
 }
 
+/// Read-only view over the [ForkStorage] as it was at some past block.
+/// Values are taken from the archived local `state` first. Missing values are read from the fork -
+/// either at `fork_miniblock` (for blocks before the fork), or at the fork's own block otherwise.
+#[derive(Debug)]
+pub struct HistoricalForkStorage<'a, S> {
+    fork_storage: &'a ForkStorage<S>,
+    state: HashMap<StorageKey, zksync_types::StorageValue>,
+    fork_miniblock: Option<u64>,
+}
+
+impl<'a, S: ForkSource> HistoricalForkStorage<'a, S> {
+    /// Creates a view over a locally archived `state`.
+    pub fn archived(
+        fork_storage: &'a ForkStorage<S>,
+        state: HashMap<StorageKey, zksync_types::StorageValue>,
+    ) -> Self {
+        Self {
+            fork_storage,
+            state,
+            fork_miniblock: None,
+        }
+    }
+
+    /// Creates a view that reads everything from the fork at the given `miniblock`.
+    pub fn at_fork_miniblock(fork_storage: &'a ForkStorage<S>, miniblock: u64) -> Self {
+        Self {
+            fork_storage,
+            state: Default::default(),
+            fork_miniblock: Some(miniblock),
+        }
+    }
+
+    fn read_value_internal(&self, key: &StorageKey) -> zksync_types::StorageValue {
+        if let Some(value) = self.state.get(key) {
+            if !value.is_zero() {
+                return *value;
+            }
+        }
+
+        let mut mutator = self.fork_storage.inner.write().unwrap();
+        let ForkStorageInner {
+            fork,
+            value_read_cache,
+            read_error,
+            ..
+        } = &mut *mutator;
+        let fork = match fork {
+            Some(fork) => fork,
+            None => return H256::zero(),
+        };
+
+        // values read at the fork's own block are shared with the live storage cache.
+        let miniblock = match self.fork_miniblock {
+            Some(miniblock) => miniblock,
+            None => {
+                if let Some(value) = value_read_cache.get(key) {
+                    return *value;
+                }
+                fork.l2_miniblock
+            }
+        };
+
+        let result = fork.fork_source.get_storage_at(
+            *key.account().address(),
+            h256_to_u256(*key.key()),
+            Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
+                miniblock,
+            )))),
+        );
+        match result {
+            Ok(value) => {
+                if self.fork_miniblock.is_none() {
+                    value_read_cache.insert(*key, value);
+                }
+                value
+            }
+            // the value is not cached, so that it is read from the fork again
+            Err(err) => {
+                let message = format!(
+                    "failed reading storage key {:?} at fork block #{}: {:?}",
+                    key, miniblock, err
+                );
+                log::error!("{}", message);
+                read_error.get_or_insert(message);
+                H256::zero()
+            }
+        }
+    }
+}
+
+impl<S: std::fmt::Debug + ForkSource> ReadStorage for HistoricalForkStorage<'_, S> {
+    fn read_value(&mut self, key: &StorageKey) -> zksync_types::StorageValue {
+        self.read_value_internal(key)
+    }
+
+    fn is_write_initial(&mut self, key: &StorageKey) -> bool {
+        !self.state.contains_key(key)
+    }
+
+    fn load_factory_dep(&mut self, hash: H256) -> Option<Vec<u8>> {
+        // factory deps are content-addressed, so the latest storage is a superset of any past one.
+        self.fork_storage.load_factory_dep_internal(hash)
+    }
+}
+
+/// Merkle proof of a single storage slot, in the format of `zks_getProof`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageProof {
+    pub key: H256,
+    /// Sibling hashes along the path to the root. Empty for slots that were modified locally.
+    pub proof: Vec<H256>,
+    pub value: H256,
+    pub index: u64,
+}
+
+/// Merkle proofs of storage slots of an account, in the format of `zks_getProof`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proof {
+    pub address: Address,
+    pub storage_proof: Vec<StorageProof>,
+}
+
 /// Trait that provides necessary data when
 /// forking a remote chain.
 /// The method signatures are similar to methods from ETHNamespace and ZKNamespace.
@@ -200,10 +449,25 @@ pub trait ForkSource {
         block: Option<BlockIdVariant>,
     ) -> eyre::Result<H256>;
 
+    /// Returns the Storage values at the given indexes for the given addresses, in the same order.
+    /// By default the values are fetched one by one; implementations may fetch them concurrently instead.
+    fn get_storage_at_batch(
+        &self,
+        slots: &[(Address, U256)],
+        block: Option<BlockIdVariant>,
+    ) -> eyre::Result<Vec<H256>> {
+        slots
+            .iter()
+            .map(|(address, idx)| self.get_storage_at(*address, *idx, block.clone()))
+            .collect()
+    }
+
     /// Returns the bytecode stored under this hash (if available).
     fn get_bytecode_by_hash(&self, hash: H256) -> eyre::Result<Option<Vec<u8>>>;
     /// Returns the transaction for a given hash.
     fn get_transaction_by_hash(&self, hash: H256) -> eyre::Result<Option<Transaction>>;
+    /// Returns the receipt of the transaction for a given hash.
+    fn get_transaction_receipt(&self, hash: H256) -> eyre::Result<Option<TransactionReceipt>>;
 
     /// Gets all transactions that belong to a given miniblock.
     fn get_raw_block_transactions(
@@ -247,6 +511,33 @@ pub trait ForkSource {
         block_number: BlockNumber,
         index: Index,
     ) -> eyre::Result<Option<Transaction>>;
+
+    /// Returns the chain id of the forked chain.
+    fn get_chain_id(&self) -> eyre::Result<L2ChainId>;
+
+    /// Returns the addresses of the default bridge contracts of the forked chain.
+    fn get_bridge_contracts(&self) -> eyre::Result<BridgeAddresses>;
+
+    /// Returns the logs matching the given filter.
+    fn get_logs(&self, filter: Filter) -> eyre::Result<Vec<Log>>;
+
+    /// Returns the details of the given protocol version of the forked chain - or of its current version, if no
+    /// version is given.
+    fn get_protocol_version(
+        &self,
+        version_id: Option<u16>,
+    ) -> eyre::Result<Option<ProtocolVersion>>;
+
+    /// Returns the Merkle proofs of the given storage slots of an account, as of the given L1 batch.
+    /// By default proofs are not available, and `None` is returned.
+    fn get_proof(
+        &self,
+        _address: Address,
+        _keys: Vec<H256>,
+        _l1_batch_number: L1BatchNumber,
+    ) -> eyre::Result<Option<Proof>> {
+        Ok(None)
+    }
 }
 
 /// Holds the information about the original chain.
@@ -291,6 +582,7 @@ impl ForkDetails<HttpForkSource> {
         miniblock: u64,
         chain_id: Option<L2ChainId>,
         cache_config: CacheConfig,
+        headers: HeaderMap,
     ) -> Self {
         let block_details = client
             .get_block_details(MiniblockNumber(miniblock as u32))
@@ -332,8 +624,11 @@ impl ForkDetails<HttpForkSource> {
             );
         }
 
+        let mut fork_source = HttpForkSource::new(url.to_owned(), cache_config);
+        fork_source.set_headers(headers);
+        fork_source.set_fork_block(miniblock);
         ForkDetails {
-            fork_source: HttpForkSource::new(url.to_owned(), cache_config),
+            fork_source,
             l1_block: l1_batch_number,
             l2_block: block,
             block_timestamp: block_details.base.timestamp,
@@ -345,19 +640,48 @@ impl ForkDetails<HttpForkSource> {
     }
     /// Create a fork from a given network at a given height.
     pub async fn from_network(fork: &str, fork_at: Option<u64>, cache_config: CacheConfig) -> Self {
-        let (url, client) = Self::fork_to_url_and_client(fork);
+        Self::from_network_with_headers(fork, fork_at, cache_config, HeaderMap::new()).await
+    }
+
+    /// Create a fork from a given network at a given height, sending the given headers with every request to it.
+    pub async fn from_network_with_headers(
+        fork: &str,
+        fork_at: Option<u64>,
+        cache_config: CacheConfig,
+        headers: HeaderMap,
+    ) -> Self {
+        let (url, client) = Self::fork_to_url_and_client(fork, headers.clone());
         let l2_miniblock = if let Some(fork_at) = fork_at {
             fork_at
         } else {
             client.get_block_number().await.unwrap().as_u64()
         };
-        Self::from_url_and_miniblock_and_chain(url, client, l2_miniblock, None, cache_config).await
+        Self::from_url_and_miniblock_and_chain(
+            url,
+            client,
+            l2_miniblock,
+            None,
+            cache_config,
+            headers,
+        )
+        .await
     }
 
     /// Create a fork from a given network, at a height BEFORE a transaction.
     /// This will allow us to apply this transaction locally on top of this fork.
     pub async fn from_network_tx(fork: &str, tx: H256, cache_config: CacheConfig) -> Self {
-        let (url, client) = Self::fork_to_url_and_client(fork);
+        Self::from_network_tx_with_headers(fork, tx, cache_config, HeaderMap::new()).await
+    }
+
+    /// Create a fork from a given network, at a height BEFORE a transaction - sending the given headers with every
+    /// request to it.
+    pub async fn from_network_tx_with_headers(
+        fork: &str,
+        tx: H256,
+        cache_config: CacheConfig,
+        headers: HeaderMap,
+    ) -> Self {
+        let (url, client) = Self::fork_to_url_and_client(fork, headers.clone());
         let tx_details = client.get_transaction_by_hash(tx).await.unwrap().unwrap();
         let overwrite_chain_id = Some(L2ChainId(tx_details.chain_id.as_u32() as u16));
         let miniblock_number = MiniblockNumber(tx_details.block_number.unwrap().as_u32());
@@ -370,14 +694,15 @@ impl ForkDetails<HttpForkSource> {
             l2_miniblock,
             overwrite_chain_id,
             cache_config,
+            headers,
         )
         .await
     }
 }
 
 impl<S: ForkSource> ForkDetails<S> {
-    /// Return URL and HTTP client for a given fork name.
-    pub fn fork_to_url_and_client(fork: &str) -> (&str, HttpClient) {
+    /// Return URL and HTTP client for a given fork name, with the headers to send with every request.
+    pub fn fork_to_url_and_client(fork: &str, headers: HeaderMap) -> (&str, HttpClient) {
         let url = match fork {
             "mainnet" => "https://mainnet.era.zksync.io:443",
             "testnet" => "https://testnet.era.zksync.dev:443",
@@ -385,12 +710,21 @@ impl<S: ForkSource> ForkDetails<S> {
         };
 
         let client = HttpClientBuilder::default()
+            .set_headers(headers)
             .build(url)
             .expect("Unable to create a client for fork");
 
         (url, client)
     }
 
+    /// Returns transactions that are in the same L2 miniblock as `tx`, and were executed before it - excluding `tx`.
+    /// Replaying them on top of the fork results in the state right before `tx` was executed.
+    pub async fn get_preceding_transactions_in_same_block(&self, tx: H256) -> Vec<L2Tx> {
+        let mut txs = self.get_earlier_transactions_in_same_block(tx).await;
+        txs.pop();
+        txs
+    }
+
     /// Returns transactions that are in the same L2 miniblock as replay_tx, but were executed before it.
     pub async fn get_earlier_transactions_in_same_block(&self, replay_tx: H256) -> Vec<L2Tx> {
         let tx_details = self
@@ -423,3 +757,174 @@ impl<S: ForkSource> ForkDetails<S> {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, ForkBlockConfig, MockServer};
+
+    #[tokio::test]
+    async fn test_get_preceding_transactions_in_same_block_excludes_the_transaction() {
+        let block_number = 5u64;
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: block_number,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let first_tx = testing::build_l2_tx(&H256::repeat_byte(0x1), zksync_types::Nonce(0));
+        let second_tx = testing::build_l2_tx(&H256::repeat_byte(0x1), zksync_types::Nonce(1));
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getTransactionByHash",
+                "params": [second_tx.hash()],
+            }),
+            testing::TransactionResponseBuilder::new()
+                .set_hash(second_tx.hash())
+                .set_block_number(U64::from(block_number + 1))
+                .build(),
+        );
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "zks_getRawBlockTransactions",
+                "params": [block_number + 1],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": [
+                    zksync_types::Transaction::from(first_tx.clone()),
+                    zksync_types::Transaction::from(second_tx.clone()),
+                ],
+            }),
+        );
+
+        let fork_details =
+            ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await;
+        let txs = fork_details
+            .get_preceding_transactions_in_same_block(second_tx.hash())
+            .await;
+
+        assert_eq!(
+            vec![first_tx.hash()],
+            txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_accounts_prefetches_their_state() {
+        let block_number = 10u64;
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: block_number,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let address = Address::repeat_byte(0x42);
+        let keys = [
+            get_nonce_key(&address),
+            storage_key_for_eth_balance(&address),
+            get_code_key(&address),
+        ];
+        let values = [
+            H256::from_low_u64_be(3),
+            H256::from_low_u64_be(1_000),
+            H256::zero(),
+        ];
+        mock_server.expect(
+            serde_json::Value::Array(
+                keys.iter()
+                    .enumerate()
+                    .map(|(id, key)| {
+                        serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "method": "eth_getStorageAt",
+                            "params": [
+                                format!("{:#x}", key.account().address()),
+                                format!("{:#x}", h256_to_u256(*key.key())),
+                                format!("{:#x}", block_number),
+                            ],
+                        })
+                    })
+                    .collect(),
+            ),
+            serde_json::Value::Array(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(id, value)| {
+                        serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "result": format!("{:#x}", value),
+                        })
+                    })
+                    .collect(),
+            ),
+        );
+        let fork_details =
+            ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await;
+        let fork_storage =
+            ForkStorage::new(Some(fork_details), &system_contracts::Options::BuiltIn);
+
+        fork_storage.snapshot_accounts(&[address]);
+
+        // the mock server only expects the single batch request - any further request would fail the reads
+        for (key, value) in keys.iter().zip(values) {
+            assert_eq!(value, fork_storage.read_value_internal(key));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_historical_storage_does_not_cache_values_that_failed_to_be_read() {
+        let block_number = 10u64;
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: block_number,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let address = Address::repeat_byte(0x42);
+        let key = StorageKey::new(zksync_types::AccountTreeId::new(address), H256::zero());
+        let value = H256::repeat_byte(0xcd);
+        mock_server.expect_with_responses(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getStorageAt",
+                "params": [
+                    format!("{:#x}", address),
+                    "0x0",
+                    format!("{:#x}", block_number),
+                ],
+            }),
+            vec![
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "error": { "code": -32000, "message": "unavailable" },
+                }),
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "result": format!("{:#x}", value),
+                }),
+            ],
+        );
+        let fork_details =
+            ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await;
+        let fork_storage =
+            ForkStorage::new(Some(fork_details), &system_contracts::Options::BuiltIn);
+        let historical_storage = HistoricalForkStorage::archived(&fork_storage, Default::default());
+
+        assert_eq!(H256::zero(), historical_storage.read_value_internal(&key));
+        assert!(fork_storage.take_read_error().is_some());
+
+        // the value is read from the fork again - and then cached for the live storage
+        assert_eq!(value, historical_storage.read_value_internal(&key));
+        assert_eq!(value, fork_storage.read_value_internal(&key));
+        assert!(fork_storage.take_read_error().is_none());
+    }
+}
@@ -0,0 +1,445 @@
+//! Fork sources persisting the responses of a fork to a JSON fixture file, and replaying them from it - so that
+//! forked tests can run offline.
+//!
+//! A [RecordingForkSource] wraps another [ForkSource] (typically a [crate::http_fork_source::HttpForkSource]) and
+//! writes every successful response to the fixture, keyed by the method and its parameters. A [ReplayForkSource]
+//! then answers the same calls from the fixture, without any network access.
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use eyre::Context;
+use serde::{de::DeserializeOwned, Serialize};
+use zksync_basic_types::{Address, L1BatchNumber, L2ChainId, MiniblockNumber, H256, U256};
+use zksync_types::api::{
+    Block, BlockIdVariant, BlockNumber, BridgeAddresses, Log, ProtocolVersion, Transaction,
+    TransactionReceipt, TransactionVariant,
+};
+use zksync_web3_decl::types::{Filter, Index};
+
+use crate::fork::{ForkSource, Proof};
+
+/// The recorded responses, keyed by method and parameters - sorted, so that the fixture files are stable.
+type Fixture = BTreeMap<String, serde_json::Value>;
+
+/// Returns the key of a call in the fixture.
+fn fixture_key(method: &str, params: impl std::fmt::Debug) -> String {
+    format!("{}{:?}", method, params)
+}
+
+/// Fork source recording the successful responses of `inner` into the fixture file at `path`, which is rewritten
+/// after each new response.
+#[derive(Debug)]
+pub struct RecordingForkSource<S> {
+    inner: S,
+    path: PathBuf,
+    fixture: Mutex<Fixture>,
+}
+
+impl<S> RecordingForkSource<S> {
+    /// Creates a source recording into the fixture at `path` - any responses already recorded there are kept.
+    pub fn new(inner: S, path: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let path = path.into();
+        let fixture = if path.exists() {
+            load_fixture(&path)?
+        } else {
+            Fixture::new()
+        };
+        Ok(Self {
+            inner,
+            path,
+            fixture: Mutex::new(fixture),
+        })
+    }
+
+    /// Records the response of a call, if it succeeded.
+    fn record<T: Serialize>(
+        &self,
+        method: &str,
+        params: impl std::fmt::Debug,
+        response: eyre::Result<T>,
+    ) -> eyre::Result<T> {
+        let response = response?;
+        let value = serde_json::to_value(&response)
+            .wrap_err_with(|| format!("failed serializing {} response", method))?;
+
+        let mut fixture = self
+            .fixture
+            .lock()
+            .map_err(|err| eyre::eyre!("failed acquiring fixture lock: {}", err))?;
+        fixture.insert(fixture_key(method, params), value);
+        let file = File::create(&self.path)
+            .wrap_err_with(|| format!("failed creating fixture {}", self.path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &*fixture)
+            .wrap_err_with(|| format!("failed writing fixture {}", self.path.display()))?;
+
+        Ok(response)
+    }
+}
+
+/// Fork source answering the calls from the fixture file written by a [RecordingForkSource]. The calls that were
+/// not recorded fail.
+#[derive(Debug, Clone)]
+pub struct ReplayForkSource {
+    fixture: Fixture,
+}
+
+impl ReplayForkSource {
+    /// Loads the fixture at `path`.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Ok(Self {
+            fixture: load_fixture(path.as_ref())?,
+        })
+    }
+
+    /// Returns the recorded response of a call.
+    fn replay<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl std::fmt::Debug,
+    ) -> eyre::Result<T> {
+        let key = fixture_key(method, params);
+        let value = self
+            .fixture
+            .get(&key)
+            .ok_or_else(|| eyre::eyre!("no recorded response for {}", key))?;
+        serde_json::from_value(value.clone())
+            .wrap_err_with(|| format!("failed deserializing recorded response for {}", key))
+    }
+}
+
+fn load_fixture(path: &Path) -> eyre::Result<Fixture> {
+    let file =
+        File::open(path).wrap_err_with(|| format!("failed opening fixture {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .wrap_err_with(|| format!("failed reading fixture {}", path.display()))
+}
+
+impl<S: ForkSource> ForkSource for RecordingForkSource<S> {
+    fn get_storage_at(
+        &self,
+        address: Address,
+        idx: U256,
+        block: Option<BlockIdVariant>,
+    ) -> eyre::Result<H256> {
+        let params = (address, idx, block.clone());
+        self.record(
+            "eth_getStorageAt",
+            params,
+            self.inner.get_storage_at(address, idx, block),
+        )
+    }
+
+    fn get_bytecode_by_hash(&self, hash: H256) -> eyre::Result<Option<Vec<u8>>> {
+        self.record(
+            "zks_getBytecodeByHash",
+            (hash,),
+            self.inner.get_bytecode_by_hash(hash),
+        )
+    }
+
+    fn get_transaction_by_hash(&self, hash: H256) -> eyre::Result<Option<Transaction>> {
+        self.record(
+            "eth_getTransactionByHash",
+            (hash,),
+            self.inner.get_transaction_by_hash(hash),
+        )
+    }
+
+    fn get_transaction_receipt(&self, hash: H256) -> eyre::Result<Option<TransactionReceipt>> {
+        self.record(
+            "eth_getTransactionReceipt",
+            (hash,),
+            self.inner.get_transaction_receipt(hash),
+        )
+    }
+
+    fn get_raw_block_transactions(
+        &self,
+        block_number: MiniblockNumber,
+    ) -> eyre::Result<Vec<zksync_types::Transaction>> {
+        self.record(
+            "zks_getRawBlockTransactions",
+            (block_number,),
+            self.inner.get_raw_block_transactions(block_number),
+        )
+    }
+
+    fn get_block_by_hash(
+        &self,
+        hash: H256,
+        full_transactions: bool,
+    ) -> eyre::Result<Option<Block<TransactionVariant>>> {
+        self.record(
+            "eth_getBlockByHash",
+            (hash, full_transactions),
+            self.inner.get_block_by_hash(hash, full_transactions),
+        )
+    }
+
+    fn get_block_by_number(
+        &self,
+        block_number: BlockNumber,
+        full_transactions: bool,
+    ) -> eyre::Result<Option<Block<TransactionVariant>>> {
+        self.record(
+            "eth_getBlockByNumber",
+            (block_number, full_transactions),
+            self.inner
+                .get_block_by_number(block_number, full_transactions),
+        )
+    }
+
+    fn get_block_transaction_count_by_hash(&self, block_hash: H256) -> eyre::Result<Option<U256>> {
+        self.record(
+            "eth_getBlockTransactionCountByHash",
+            (block_hash,),
+            self.inner.get_block_transaction_count_by_hash(block_hash),
+        )
+    }
+
+    fn get_block_transaction_count_by_number(
+        &self,
+        block_number: BlockNumber,
+    ) -> eyre::Result<Option<U256>> {
+        self.record(
+            "eth_getBlockTransactionCountByNumber",
+            (block_number,),
+            self.inner
+                .get_block_transaction_count_by_number(block_number),
+        )
+    }
+
+    fn get_transaction_by_block_hash_and_index(
+        &self,
+        block_hash: H256,
+        index: Index,
+    ) -> eyre::Result<Option<Transaction>> {
+        self.record(
+            "eth_getTransactionByBlockHashAndIndex",
+            (block_hash, index),
+            self.inner
+                .get_transaction_by_block_hash_and_index(block_hash, index),
+        )
+    }
+
+    fn get_transaction_by_block_number_and_index(
+        &self,
+        block_number: BlockNumber,
+        index: Index,
+    ) -> eyre::Result<Option<Transaction>> {
+        self.record(
+            "eth_getTransactionByBlockNumberAndIndex",
+            (block_number, index),
+            self.inner
+                .get_transaction_by_block_number_and_index(block_number, index),
+        )
+    }
+
+    fn get_chain_id(&self) -> eyre::Result<L2ChainId> {
+        self.record("eth_chainId", (), self.inner.get_chain_id())
+    }
+
+    fn get_bridge_contracts(&self) -> eyre::Result<BridgeAddresses> {
+        self.record(
+            "zks_getBridgeContracts",
+            (),
+            self.inner.get_bridge_contracts(),
+        )
+    }
+
+    fn get_logs(&self, filter: Filter) -> eyre::Result<Vec<Log>> {
+        let params = (filter.clone(),);
+        self.record("eth_getLogs", params, self.inner.get_logs(filter))
+    }
+
+    fn get_protocol_version(
+        &self,
+        version_id: Option<u16>,
+    ) -> eyre::Result<Option<ProtocolVersion>> {
+        self.record(
+            "zks_getProtocolVersion",
+            (version_id,),
+            self.inner.get_protocol_version(version_id),
+        )
+    }
+
+    fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<H256>,
+        l1_batch_number: L1BatchNumber,
+    ) -> eyre::Result<Option<Proof>> {
+        let params = (address, keys.clone(), l1_batch_number);
+        self.record(
+            "zks_getProof",
+            params,
+            self.inner.get_proof(address, keys, l1_batch_number),
+        )
+    }
+}
+
+impl ForkSource for ReplayForkSource {
+    fn get_storage_at(
+        &self,
+        address: Address,
+        idx: U256,
+        block: Option<BlockIdVariant>,
+    ) -> eyre::Result<H256> {
+        self.replay("eth_getStorageAt", (address, idx, block))
+    }
+
+    fn get_bytecode_by_hash(&self, hash: H256) -> eyre::Result<Option<Vec<u8>>> {
+        self.replay("zks_getBytecodeByHash", (hash,))
+    }
+
+    fn get_transaction_by_hash(&self, hash: H256) -> eyre::Result<Option<Transaction>> {
+        self.replay("eth_getTransactionByHash", (hash,))
+    }
+
+    fn get_transaction_receipt(&self, hash: H256) -> eyre::Result<Option<TransactionReceipt>> {
+        self.replay("eth_getTransactionReceipt", (hash,))
+    }
+
+    fn get_raw_block_transactions(
+        &self,
+        block_number: MiniblockNumber,
+    ) -> eyre::Result<Vec<zksync_types::Transaction>> {
+        self.replay("zks_getRawBlockTransactions", (block_number,))
+    }
+
+    fn get_block_by_hash(
+        &self,
+        hash: H256,
+        full_transactions: bool,
+    ) -> eyre::Result<Option<Block<TransactionVariant>>> {
+        self.replay("eth_getBlockByHash", (hash, full_transactions))
+    }
+
+    fn get_block_by_number(
+        &self,
+        block_number: BlockNumber,
+        full_transactions: bool,
+    ) -> eyre::Result<Option<Block<TransactionVariant>>> {
+        self.replay("eth_getBlockByNumber", (block_number, full_transactions))
+    }
+
+    fn get_block_transaction_count_by_hash(&self, block_hash: H256) -> eyre::Result<Option<U256>> {
+        self.replay("eth_getBlockTransactionCountByHash", (block_hash,))
+    }
+
+    fn get_block_transaction_count_by_number(
+        &self,
+        block_number: BlockNumber,
+    ) -> eyre::Result<Option<U256>> {
+        self.replay("eth_getBlockTransactionCountByNumber", (block_number,))
+    }
+
+    fn get_transaction_by_block_hash_and_index(
+        &self,
+        block_hash: H256,
+        index: Index,
+    ) -> eyre::Result<Option<Transaction>> {
+        self.replay("eth_getTransactionByBlockHashAndIndex", (block_hash, index))
+    }
+
+    fn get_transaction_by_block_number_and_index(
+        &self,
+        block_number: BlockNumber,
+        index: Index,
+    ) -> eyre::Result<Option<Transaction>> {
+        self.replay(
+            "eth_getTransactionByBlockNumberAndIndex",
+            (block_number, index),
+        )
+    }
+
+    fn get_chain_id(&self) -> eyre::Result<L2ChainId> {
+        self.replay("eth_chainId", ())
+    }
+
+    fn get_bridge_contracts(&self) -> eyre::Result<BridgeAddresses> {
+        self.replay("zks_getBridgeContracts", ())
+    }
+
+    fn get_logs(&self, filter: Filter) -> eyre::Result<Vec<Log>> {
+        self.replay("eth_getLogs", (filter,))
+    }
+
+    fn get_protocol_version(
+        &self,
+        version_id: Option<u16>,
+    ) -> eyre::Result<Option<ProtocolVersion>> {
+        self.replay("zks_getProtocolVersion", (version_id,))
+    }
+
+    fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<H256>,
+        l1_batch_number: L1BatchNumber,
+    ) -> eyre::Result<Option<Proof>> {
+        self.replay("zks_getProof", (address, keys, l1_batch_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+    use zksync_basic_types::U64;
+
+    use super::*;
+    use crate::{cache::CacheConfig, http_fork_source::HttpForkSource, testing};
+
+    #[test]
+    fn test_get_block_by_hash_is_replayed_from_the_recorded_fixture() {
+        let block_hash = H256::repeat_byte(0x01);
+        let fixture_dir = TempDir::new("fork-fixture").expect("failed creating temporary dir");
+        let fixture_path = fixture_dir.path().join("fixture.json");
+
+        {
+            let mock_server = testing::MockServer::run();
+            mock_server.expect(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "method": "eth_getBlockByHash",
+                    "params": [format!("{:#x}", block_hash), true],
+                }),
+                testing::BlockResponseBuilder::new()
+                    .set_hash(block_hash)
+                    .set_number(8)
+                    .build(),
+            );
+            let fork_source = RecordingForkSource::new(
+                HttpForkSource::new(mock_server.url(), CacheConfig::None),
+                &fixture_path,
+            )
+            .expect("failed creating recording fork source");
+
+            let block = fork_source
+                .get_block_by_hash(block_hash, true)
+                .expect("failed fetching block by hash")
+                .expect("no block");
+            assert_eq!(block_hash, block.hash);
+        }
+
+        // the mock server is gone, so the block can only come from the fixture
+        let fork_source = ReplayForkSource::load(&fixture_path).expect("failed loading fixture");
+        let block = fork_source
+            .get_block_by_hash(block_hash, true)
+            .expect("failed replaying block by hash")
+            .expect("no block");
+        assert_eq!(block_hash, block.hash);
+        assert_eq!(U64::from(8), block.number);
+
+        let error = fork_source
+            .get_block_by_hash(block_hash, false)
+            .expect_err("replayed a call that was not recorded");
+        assert!(error.to_string().contains("no recorded response"));
+    }
+}
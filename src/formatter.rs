@@ -1,15 +1,20 @@
 //! Helper methods to display transaction data in more human readable way.
-use crate::{node::ShowCalls, resolver};
+use crate::{node::ShowCalls, resolver, utils::to_human_size};
 
 use colored::Colorize;
 use serde::Deserialize;
 use std::collections::HashMap;
 
 use crate::fork::block_on;
-use zksync_basic_types::H160;
+use zksync_basic_types::{AccountTreeId, Address, H160, U256};
 
-use vm::VmExecutionResultAndLogs;
-use zksync_types::{vm_trace::Call, StorageLogQuery, StorageLogQueryType, VmEvent};
+use ethabi::{param_type::Reader, Token};
+use vm::{VmExecutionResultAndLogs, VmRevertReason};
+use zksync_types::{
+    get_nonce_key, utils::storage_key_for_eth_balance, vm_trace::Call, StorageKey, StorageLogQuery,
+    StorageLogQueryType, VmEvent,
+};
+use zksync_utils::u256_to_h256;
 
 use lazy_static::lazy_static;
 
@@ -41,6 +46,42 @@ lazy_static! {
     };
 }
 
+/// The base token of the chain - the amounts paid in it (e.g. balances) are displayed in its units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BaseToken {
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl Default for BaseToken {
+    fn default() -> Self {
+        Self {
+            symbol: String::from("ETH"),
+            decimals: 18,
+        }
+    }
+}
+
+impl BaseToken {
+    /// Formats an amount of the smallest unit of the token (e.g. wei) in the units of the token - e.g. `1.5 USDC`.
+    pub fn format_amount(&self, amount: U256) -> String {
+        let decimals = self.decimals as usize;
+        let (whole, fraction) = amount.div_mod(U256::exp10(decimals));
+        let fraction = fraction.to_string();
+        let fraction = format!(
+            "{}{}",
+            "0".repeat(decimals.saturating_sub(fraction.len())),
+            fraction
+        );
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            format!("{} {}", to_human_size(whole), self.symbol)
+        } else {
+            format!("{}.{} {}", to_human_size(whole), fraction, self.symbol)
+        }
+    }
+}
+
 fn address_to_human_readable(address: H160) -> Option<String> {
     KNOWN_ADDRESSES
         .get(&address)
@@ -188,7 +229,77 @@ pub fn print_logs(log_query: &StorageLogQuery) {
     log::info!("{}", separator);
 }
 
-pub fn print_vm_details(result: &VmExecutionResultAndLogs) {
+/// The value of a storage slot before and after a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDiff {
+    pub key: StorageKey,
+    pub before: U256,
+    pub after: U256,
+}
+
+/// Returns the storage slots modified by a transaction (in the order they were first written), derived from its
+/// storage logs. Slots that were written back to their original value are skipped.
+pub fn storage_diff(storage_logs: &[StorageLogQuery]) -> Vec<StorageDiff> {
+    let mut diffs: Vec<StorageDiff> = vec![];
+    for log_query in storage_logs
+        .iter()
+        .filter(|log_query| log_query.log_type != StorageLogQueryType::Read)
+    {
+        let key = StorageKey::new(
+            AccountTreeId::new(log_query.log_query.address),
+            u256_to_h256(log_query.log_query.key),
+        );
+        match diffs.iter_mut().find(|diff| diff.key == key) {
+            Some(diff) => diff.after = log_query.log_query.written_value,
+            None => diffs.push(StorageDiff {
+                key,
+                before: log_query.log_query.read_value,
+                after: log_query.log_query.written_value,
+            }),
+        }
+    }
+    diffs.retain(|diff| diff.before != diff.after);
+
+    diffs
+}
+
+/// Returns a human readable description of a storage slot - annotated with the account it belongs to, if it is the
+/// balance or the nonce of one of the given `accounts`.
+pub fn storage_key_to_human_readable(key: &StorageKey, accounts: &[Address]) -> String {
+    for account in accounts {
+        if *key == storage_key_for_eth_balance(account) {
+            return format!("Balance of {:?}", account);
+        }
+        if *key == get_nonce_key(account) {
+            return format!("Nonce of {:?}", account);
+        }
+    }
+
+    format!(
+        "{} {:#x}",
+        address_to_human_readable(*key.address()).unwrap_or(format!("{:?}", key.address())),
+        key.key()
+    )
+}
+
+/// Formats a modified storage slot as `<slot>: <before> -> <after>`.
+pub fn format_storage_diff(diff: &StorageDiff, accounts: &[Address]) -> String {
+    format!(
+        "{}: {:#x} -> {:#x}",
+        storage_key_to_human_readable(&diff.key, accounts),
+        diff.before,
+        diff.after
+    )
+}
+
+/// Pretty-prints the storage slots modified by a transaction, along with their values before and after it.
+pub fn print_storage_diff(storage_logs: &[StorageLogQuery], accounts: &[Address]) {
+    for diff in storage_diff(storage_logs) {
+        log::info!("{}", format_storage_diff(&diff, accounts));
+    }
+}
+
+pub fn print_vm_details(result: &VmExecutionResultAndLogs, resolve_hashes: bool) {
     log::info!("");
     log::info!("┌──────────────────────────┐");
     log::info!("│   VM EXECUTION RESULTS   │");
@@ -208,7 +319,7 @@ pub fn print_vm_details(result: &VmExecutionResultAndLogs) {
                 "{}",
                 format!(
                     "\n[!] Revert Reason:    {}",
-                    output.to_user_friendly_string()
+                    revert_reason_to_human_readable(output, resolve_hashes)
                 )
                 .on_red()
             );
@@ -221,3 +332,160 @@ pub fn print_vm_details(result: &VmExecutionResultAndLogs) {
 
     log::info!("════════════════════════════");
 }
+
+/// Returns a human readable revert reason.
+/// Custom errors with a known selector are decoded into `MyError(arg1, arg2)`, while unknown ones are shown as raw hex.
+/// If resolve_hashes is true, will try to contact openchain to resolve the selector.
+pub fn revert_reason_to_human_readable(output: &VmRevertReason, resolve_hashes: bool) -> String {
+    let encoded = output.encoded_data();
+    if !matches!(output, VmRevertReason::Unknown { .. }) || encoded.len() < 4 {
+        return output.to_user_friendly_string();
+    }
+
+    let selector = format!("0x{}", hex::encode(&encoded[..4]));
+    let signature = block_on({
+        let selector = selector.clone();
+        async move {
+            if resolve_hashes {
+                resolver::decode_function_selector(&selector)
+                    .await
+                    .ok()
+                    .flatten()
+            } else {
+                resolver::known_function_selector(&selector).await
+            }
+        }
+    });
+
+    signature
+        .and_then(|signature| decode_custom_error(&signature, &encoded[4..]))
+        .unwrap_or_else(|| format!("0x{}", hex::encode(&encoded)))
+}
+
+/// Decodes the ABI-encoded arguments of a custom error with the given signature, e.g. `MyError(uint256,address)`.
+fn decode_custom_error(signature: &str, data: &[u8]) -> Option<String> {
+    let (name, params) = signature.split_once('(')?;
+    let params = params.strip_suffix(')')?;
+
+    let mut param_types = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, char) in params.char_indices() {
+        match char {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                param_types.push(Reader::read(params[start..index].trim()).ok()?);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    if !params[start..].trim().is_empty() {
+        param_types.push(Reader::read(params[start..].trim()).ok()?);
+    }
+
+    let tokens = ethabi::decode(&param_types, data).ok()?;
+    Some(format!(
+        "{}({})",
+        name,
+        tokens
+            .iter()
+            .map(token_to_human_readable)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+fn token_to_human_readable(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{:?}", address),
+        Token::Uint(value) => value.to_string(),
+        Token::Int(value) => {
+            if value.bit(255) {
+                format!("-{}", (!*value).overflowing_add(ethabi::Int::one()).0)
+            } else {
+                value.to_string()
+            }
+        }
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => format!("{:?}", value),
+        Token::Bytes(value) | Token::FixedBytes(value) => format!("0x{}", hex::encode(value)),
+        Token::Array(tokens) | Token::FixedArray(tokens) => format!(
+            "[{}]",
+            tokens
+                .iter()
+                .map(token_to_human_readable)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Token::Tuple(tokens) => format!(
+            "({})",
+            tokens
+                .iter()
+                .map(token_to_human_readable)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethabi::ParamType;
+
+    #[test]
+    fn test_base_token_formats_amounts_with_its_symbol_and_decimals() {
+        let usdc = BaseToken {
+            symbol: String::from("USDC"),
+            decimals: 6,
+        };
+
+        assert_eq!("1.5 USDC", usdc.format_amount(U256::from(1_500_000)));
+        assert_eq!("0.000001 USDC", usdc.format_amount(U256::from(1)));
+        assert_eq!("1_000 USDC", usdc.format_amount(U256::from(1_000_000_000)));
+        assert_eq!(
+            "1_000_000_000_000 ETH",
+            BaseToken::default().format_amount(U256::from(10u128.pow(30)))
+        );
+    }
+
+    #[test]
+    fn test_revert_reason_decodes_registered_custom_error() {
+        let signature = "InsufficientBalance(uint256,address)";
+        let selector = ethabi::short_signature(
+            "InsufficientBalance",
+            &[ParamType::Uint(256), ParamType::Address],
+        );
+        let selector_hex = format!("0x{}", hex::encode(selector));
+        block_on(
+            async move { resolver::register_function_selector(&selector_hex, signature).await },
+        );
+
+        let owner = ethabi::Address::repeat_byte(0x1);
+        let data = ethabi::encode(&[Token::Uint(ethabi::Uint::from(42)), Token::Address(owner)]);
+        let output = VmRevertReason::Unknown {
+            function_selector: selector.to_vec(),
+            data,
+        };
+
+        assert_eq!(
+            format!("InsufficientBalance(42, {:?})", owner),
+            revert_reason_to_human_readable(&output, false)
+        );
+    }
+
+    #[test]
+    fn test_revert_reason_shows_raw_hex_for_unknown_custom_error() {
+        let output = VmRevertReason::Unknown {
+            function_selector: vec![0xde, 0xad, 0xbe, 0xef],
+            data: vec![0x01, 0x02],
+        };
+
+        assert_eq!(
+            "0xdeadbeef0102",
+            revert_reason_to_human_readable(&output, false)
+        );
+    }
+}
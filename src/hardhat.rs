@@ -1,16 +1,21 @@
 use std::sync::{Arc, RwLock};
 
-use crate::{fork::ForkSource, node::InMemoryNodeInner, utils::mine_empty_blocks};
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNodeInner, SerializableState},
+    utils::mine_empty_blocks,
+};
 use jsonrpc_core::{BoxFuture, Result};
 use jsonrpc_derive::rpc;
-use zksync_basic_types::{Address, U256, U64};
+use zksync_basic_types::{AccountTreeId, Address, Bytes, H256, U256, U64};
 use zksync_core::api_server::web3::backend_jsonrpc::error::into_jsrpc_error;
 use zksync_state::ReadStorage;
 use zksync_types::{
-    get_nonce_key,
+    get_code_key, get_known_code_key, get_nonce_key,
     utils::{decompose_full_nonce, nonces_to_full_nonce, storage_key_for_eth_balance},
+    StorageKey,
 };
-use zksync_utils::{h256_to_u256, u256_to_h256};
+use zksync_utils::{bytecode::hash_bytecode, h256_to_u256, u256_to_h256};
 use zksync_web3_decl::error::Web3Error;
 
 /// Implementation of HardhatNamespaceImpl
@@ -99,6 +104,89 @@ pub trait HardhatNamespaceT {
     /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
     #[rpc(name = "hardhat_stopImpersonatingAccount")]
     fn stop_impersonating_account(&self, address: Address) -> BoxFuture<Result<bool>>;
+
+    /// Enables or disables the impersonation of every account - like `hardhat_impersonateAccount` for any `from`.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to impersonate every account, `false` to only impersonate the individual accounts.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "hardhat_setAutoImpersonateAccount")]
+    fn set_auto_impersonate_account(&self, enabled: bool) -> BoxFuture<Result<bool>>;
+
+    /// Sets the coinbase address, that is used as the operator (fee account) of the blocks mined afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The new coinbase address.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "hardhat_setCoinbase")]
+    fn set_coinbase(&self, address: Address) -> BoxFuture<Result<bool>>;
+
+    /// Sets the number of the next mined block. The blocks mined afterwards continue from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The number of the next block, must be greater than the current block number.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "hardhat_setNextBlockNumber")]
+    fn set_next_block_number(&self, number: U64) -> BoxFuture<Result<bool>>;
+
+    /// Dumps the chain state (blocks and storage), so it can be loaded later with `hardhat_loadState`.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the JSON-serialized state as `Bytes`.
+    #[rpc(name = "hardhat_dumpState")]
+    fn dump_state(&self) -> BoxFuture<Result<Bytes>>;
+
+    /// Replaces the chain state with one that was previously dumped with `hardhat_dumpState`.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The JSON-serialized state as `Bytes`.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "hardhat_loadState")]
+    fn load_state(&self, state: Bytes) -> BoxFuture<Result<bool>>;
+
+    /// Sets the value of a storage slot of the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The `Address` whose storage will be edited
+    /// * `slot` - The storage slot to edit
+    /// * `value` - The new value of the storage slot
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "hardhat_setStorageAt")]
+    fn set_storage_at(&self, address: Address, slot: U256, value: U256) -> BoxFuture<Result<bool>>;
+
+    /// Sets the bytecode of the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The `Address` whose code will be edited
+    /// * `code` - The new (zkSync) bytecode, an odd number of 32-byte words
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "hardhat_setCode")]
+    fn set_code(&self, address: Address, code: Bytes) -> BoxFuture<Result<bool>>;
 }
 
 impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> HardhatNamespaceT
@@ -119,9 +207,9 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> HardhatNamespaceT
                         .fork_storage
                         .set_value(balance_key, u256_to_h256(balance));
                     log::info!(
-                        "👷 Balance for address {:?} has been manually set to {} Wei",
+                        "👷 Balance for address {:?} has been manually set to {}",
                         address,
-                        balance
+                        inner_guard.base_token.format_amount(balance)
                     );
                     Ok(true)
                 }
@@ -194,7 +282,11 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> HardhatNamespaceT
                             "Number of blocks must be greater than 0".to_string(),
                         ));
                     }
-                    mine_empty_blocks(&mut inner, num_blocks.as_u64(), interval_ms.as_u64());
+                    mine_empty_blocks(&mut inner, num_blocks.as_u64(), interval_ms.as_u64())
+                        .map_err(|err| {
+                            log::error!("{}", err);
+                            into_jsrpc_error(Web3Error::InternalError)
+                        })?;
                     log::info!("👷 Mined {} blocks", num_blocks);
                     Ok(true)
                 }
@@ -241,14 +333,159 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> HardhatNamespaceT
             }
         })
     }
+
+    fn set_auto_impersonate_account(&self, enabled: bool) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            match inner.write() {
+                Ok(mut inner) => {
+                    inner.auto_impersonate = enabled;
+                    log::info!(
+                        "🕵️ Auto impersonation {}",
+                        if enabled { "enabled" } else { "disabled" }
+                    );
+                    Ok(true)
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
+
+    fn set_coinbase(&self, address: Address) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            match inner.write() {
+                Ok(mut inner) => {
+                    inner.coinbase = address;
+                    log::info!("👷 Coinbase set to {:?}", address);
+                    Ok(true)
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
+
+    fn set_next_block_number(&self, number: U64) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            match inner.write() {
+                Ok(mut inner) => {
+                    inner
+                        .set_next_block_number(number.as_u64())
+                        .map_err(jsonrpc_core::Error::invalid_params)?;
+                    log::info!("👷 Next block number set to {}", number);
+                    Ok(true)
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
+
+    fn dump_state(&self) -> BoxFuture<Result<Bytes>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            let state = reader.dump_state().map_err(|err| {
+                log::error!("failed dumping state: {}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+            let bytes = serde_json::to_vec(&state).map_err(|err| {
+                log::error!("failed serializing state: {}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+
+            log::info!("💾 Dumped state at block #{}", state.current_miniblock);
+            Ok(Bytes(bytes))
+        })
+    }
+
+    fn load_state(&self, state: Bytes) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            let state: SerializableState = serde_json::from_slice(&state.0).map_err(|err| {
+                log::error!("failed deserializing state: {}", err);
+                into_jsrpc_error(Web3Error::InvalidTransactionData(
+                    zksync_types::ethabi::Error::InvalidData,
+                ))
+            })?;
+
+            let mut writer = inner
+                .write()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            writer.load_state(state).map_err(|err| {
+                log::error!("failed loading state: {}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+
+            log::info!("💾 Loaded state at block #{}", writer.current_miniblock);
+            Ok(true)
+        })
+    }
+
+    fn set_storage_at(&self, address: Address, slot: U256, value: U256) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            match inner.write() {
+                Ok(mut inner_guard) => {
+                    let key = StorageKey::new(AccountTreeId::new(address), u256_to_h256(slot));
+                    inner_guard.fork_storage.set_value(key, u256_to_h256(value));
+                    log::info!(
+                        "👷 Storage slot {:#x} for address {:?} has been set to {:#x}",
+                        slot,
+                        address,
+                        value
+                    );
+                    Ok(true)
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
+
+    fn set_code(&self, address: Address, code: Bytes) -> BoxFuture<Result<bool>> {
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            let code = code.0;
+            // zkSync bytecode must consist of an odd number (less than 2^16) of 32-byte words
+            let words = code.len() / 32;
+            if code.len() % 32 != 0 || words % 2 == 0 || words >= (1 << 16) {
+                return Err(jsonrpc_core::Error::invalid_params(format!(
+                    "Invalid bytecode length {}: must be an odd number of 32-byte words",
+                    code.len()
+                )));
+            }
+
+            match inner.write() {
+                Ok(mut inner_guard) => {
+                    let code_hash = hash_bytecode(&code);
+                    inner_guard
+                        .fork_storage
+                        .set_value(get_code_key(&address), code_hash);
+                    inner_guard
+                        .fork_storage
+                        .set_value(get_known_code_key(&code_hash), H256::from_low_u64_be(1));
+                    inner_guard.fork_storage.store_factory_dep(code_hash, code);
+                    log::info!(
+                        "👷 Code for address {:?} has been set to bytecode {:#x}",
+                        address,
+                        code_hash
+                    );
+                    Ok(true)
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode};
+    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode, testing};
     use std::str::FromStr;
-    use zksync_basic_types::{Nonce, H256};
+    use zksync_basic_types::Nonce;
     use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
     use zksync_types::{api::BlockNumber, fee::Fee, l2::L2Tx, PackedEthSignature};
 
@@ -291,6 +528,42 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_set_next_block_number() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let hardhat = HardhatNamespaceImpl::new(node.get_inner());
+
+        // the next block number must be ahead of the current one
+        let result = hardhat.set_next_block_number(U64::from(0)).await;
+        assert!(result.is_err());
+
+        let result = hardhat
+            .set_next_block_number(U64::from(100))
+            .await
+            .expect("failed setting next block number");
+        assert!(result);
+
+        hardhat
+            .hardhat_mine(None, None)
+            .await
+            .expect("hardhat_mine");
+        let block = node
+            .get_block_by_number(BlockNumber::Latest, false)
+            .await
+            .unwrap()
+            .expect("block exists");
+        assert_eq!(U64::from(100), block.number);
+
+        let (_, tx_block_number) = testing::apply_tx(&node, H256::repeat_byte(0x1));
+        assert_eq!(U64::from(101), tx_block_number);
+        let receipt = node
+            .get_transaction_receipt(H256::repeat_byte(0x1))
+            .await
+            .unwrap()
+            .expect("receipt exists");
+        assert_eq!(Some(U64::from(101)), receipt.block_number);
+    }
+
     #[tokio::test]
     async fn test_hardhat_mine_default() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -369,6 +642,124 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_hardhat_mine_with_empty_seal_fast_is_followed_by_transactions() {
+        let node = crate::node::InMemoryNodeBuilder::<HttpForkSource>::default()
+            .empty_seal_fast(true)
+            .build();
+        let hardhat: HardhatNamespaceImpl<HttpForkSource> =
+            HardhatNamespaceImpl::new(node.get_inner());
+
+        let result = hardhat
+            .hardhat_mine(Some(U64::from(10)), None)
+            .await
+            .expect("hardhat_mine");
+        assert!(result);
+
+        // the synthesized blocks are known to the system context, so the bootloader accepts the next block
+        let tx_hash = H256::repeat_byte(0x1);
+        let (_, block_number) = testing::apply_tx(&node, tx_hash);
+        assert_eq!(U64::from(11), block_number);
+        let receipt = node
+            .get_transaction_receipt(tx_hash)
+            .await
+            .expect("failed getting receipt")
+            .expect("missing receipt");
+        assert_eq!(Some(U64::from(1)), receipt.status);
+    }
+
+    #[tokio::test]
+    async fn test_set_coinbase() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let hardhat = HardhatNamespaceImpl::new(node.get_inner());
+        let coinbase = Address::from_str("0x36615Cf349d7F6344891B1e7CA7C72883F5dc049").unwrap();
+
+        let result = hardhat.set_coinbase(coinbase).await.unwrap();
+        assert!(result);
+        assert_eq!(coinbase, node.coinbase().await.unwrap());
+
+        hardhat
+            .hardhat_mine(None, None)
+            .await
+            .expect("hardhat_mine");
+
+        let latest_block = node
+            .get_block_by_number(BlockNumber::Latest, false)
+            .await
+            .unwrap()
+            .expect("block exists");
+        assert_eq!(coinbase, latest_block.author);
+    }
+
+    #[tokio::test]
+    async fn test_set_coinbase_is_read_by_contracts() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let hardhat = HardhatNamespaceImpl::new(node.get_inner());
+        // contracts read `block.coinbase` with a call to the `coinbase()` of the `SystemContext`
+        let block_coinbase = || {
+            let request = zksync_types::transaction_request::CallRequestBuilder::default()
+                .to(zksync_types::SYSTEM_CONTEXT_ADDRESS)
+                .data(hex::decode("a6ae0aac").unwrap().into())
+                .build();
+            let result = node.call(request, None);
+            async move {
+                zksync_utils::h256_to_account_address(&H256::from_slice(
+                    &result.await.expect("failed calling").0,
+                ))
+            }
+        };
+        assert_eq!(zksync_types::BOOTLOADER_ADDRESS, block_coinbase().await);
+
+        let coinbase = Address::from_str("0x36615Cf349d7F6344891B1e7CA7C72883F5dc049").unwrap();
+        hardhat.set_coinbase(coinbase).await.unwrap();
+        hardhat
+            .hardhat_mine(None, None)
+            .await
+            .expect("hardhat_mine");
+
+        assert_eq!(coinbase, block_coinbase().await);
+    }
+
+    #[tokio::test]
+    async fn test_set_storage_at() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let hardhat = HardhatNamespaceImpl::new(node.get_inner());
+        let address = Address::repeat_byte(0x1);
+        let slot = U256::from(42);
+
+        let result = hardhat
+            .set_storage_at(address, slot, U256::from(1337))
+            .await
+            .unwrap();
+        assert!(result);
+
+        let value = node.get_storage(address, slot, None).await.unwrap();
+        assert_eq!(u256_to_h256(U256::from(1337)), value);
+    }
+
+    #[tokio::test]
+    async fn test_set_code() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let hardhat = HardhatNamespaceImpl::new(node.get_inner());
+        let address = Address::repeat_byte(0x1);
+        let code = vec![0xab; 32 * 3];
+
+        let result = hardhat
+            .set_code(address, Bytes(code.clone()))
+            .await
+            .unwrap();
+        assert!(result);
+
+        let stored = node.get_code(address, None).await.unwrap();
+        assert_eq!(code, stored.0);
+
+        // bytecode must be an odd number of 32-byte words
+        let result = hardhat.set_code(address, Bytes(vec![0xab; 32 * 2])).await;
+        assert!(result.is_err());
+        let result = hardhat.set_code(address, Bytes(vec![0xab; 33])).await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_impersonate_account() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -1,17 +1,32 @@
-use std::sync::RwLock;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    future::Future,
+    ops::RangeInclusive,
+    sync::{Arc, Condvar, Mutex, RwLock},
+    time::{Duration, Instant},
+};
 
 use eyre::Context;
-use zksync_basic_types::{H256, U256};
-use zksync_types::api::Transaction;
+use zksync_basic_types::{Address, L1BatchNumber, L2ChainId, MiniblockNumber, H256, U256, U64};
+use zksync_types::api::{
+    BlockNumber, BridgeAddresses, ProtocolVersion, Transaction, TransactionReceipt,
+};
 use zksync_web3_decl::{
-    jsonrpsee::http_client::{HttpClient, HttpClientBuilder},
+    jsonrpsee::{
+        core::Error as ClientError,
+        core::{client::ClientT, rpc_params},
+        http_client::{
+            transport::Error as TransportError, HeaderMap, HttpClient, HttpClientBuilder,
+        },
+    },
     namespaces::{EthNamespaceClient, ZksNamespaceClient},
     types::Index,
 };
 
 use crate::{
-    cache::{Cache, CacheConfig},
-    fork::{block_on, ForkSource},
+    cache::{Cache, CacheConfig, LogsFilterKey},
+    fork::{block_on, ForkSource, Proof},
 };
 
 #[derive(Debug)]
@@ -21,6 +36,151 @@ pub struct HttpForkSource {
     pub fork_url: String,
     /// Cache for network data.
     pub(crate) cache: RwLock<Cache>,
+    /// If set, data that is missing from the cache results in an error instead of a network request.
+    offline: bool,
+    /// If set, every request to the network and every cache hit is logged - to diagnose slow forks.
+    log_calls: bool,
+    /// Headers sent with every request to the network - e.g. to authenticate with the RPC provider.
+    headers: HeaderMap,
+    /// If set, the requests to the network are throttled - to stay within the rate limit of the RPC provider.
+    rate_limiter: Option<Mutex<RateLimiter>>,
+    /// If set, responses from the network larger than this many bytes are rejected.
+    max_response_bytes: Option<u32>,
+    /// Storage reads being fetched from the network, by their params - see [coalesce].
+    in_flight_storage_requests: InFlightRequests<H256>,
+    /// Bytecodes being fetched from the network, by their hash - see [coalesce].
+    in_flight_bytecode_requests: InFlightRequests<Option<Vec<u8>>>,
+    /// The miniblock the node is forked at, if known. Only the logs of the ranges up to it are cached.
+    fork_block: Option<u64>,
+}
+
+/// A request to the network, whose response is shared with the identical requests made while it is in flight.
+#[derive(Debug)]
+struct InFlightRequest<T> {
+    response: Mutex<Option<Result<T, String>>>,
+    done: Condvar,
+}
+
+type InFlightRequests<T> = Mutex<HashMap<String, Arc<InFlightRequest<T>>>>;
+
+/// Sends the request identified by `key`, unless an identical request is already in flight - in which case its
+/// response is awaited and shared instead. This avoids redundant network requests when concurrent calls (e.g. the
+/// `eth_call`s of a multicall) read the same fork data.
+fn coalesce<T: Clone>(
+    in_flight: &InFlightRequests<T>,
+    key: String,
+    request: impl FnOnce() -> eyre::Result<T>,
+) -> eyre::Result<T> {
+    let (in_flight_request, is_first) = {
+        let mut in_flight = in_flight
+            .lock()
+            .map_err(|err| eyre::eyre!("failed acquiring in-flight requests lock: {}", err))?;
+        match in_flight.get(&key) {
+            Some(in_flight_request) => (in_flight_request.clone(), false),
+            None => {
+                let in_flight_request = Arc::new(InFlightRequest {
+                    response: Mutex::new(None),
+                    done: Condvar::new(),
+                });
+                in_flight.insert(key.clone(), in_flight_request.clone());
+                (in_flight_request, true)
+            }
+        }
+    };
+
+    if is_first {
+        let response = request();
+        if let Ok(mut in_flight) = in_flight.lock() {
+            in_flight.remove(&key);
+        }
+        if let Ok(mut shared_response) = in_flight_request.response.lock() {
+            *shared_response = Some(match &response {
+                Ok(value) => Ok(value.clone()),
+                Err(err) => Err(format!("{:?}", err)),
+            });
+        }
+        in_flight_request.done.notify_all();
+        return response;
+    }
+
+    let mut response = in_flight_request
+        .response
+        .lock()
+        .map_err(|err| eyre::eyre!("failed acquiring in-flight request lock: {}", err))?;
+    while response.is_none() {
+        response = in_flight_request
+            .done
+            .wait(response)
+            .map_err(|err| eyre::eyre!("failed waiting for in-flight request: {}", err))?;
+    }
+    match response.clone() {
+        Some(Ok(value)) => Ok(value),
+        Some(Err(err)) => Err(eyre::eyre!(err)),
+        None => unreachable!("waited for the response"),
+    }
+}
+
+/// Token bucket throttling the requests to the network, which allows bursts of up to a second worth of requests.
+/// Requests above the limit are queued rather than failed: the bucket goes into debt, and each request waits for
+/// the tokens it reserved.
+#[derive(Debug)]
+struct RateLimiter {
+    requests_per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: u32) -> Self {
+        let requests_per_second = f64::from(requests_per_second.max(1));
+        Self {
+            requests_per_second,
+            tokens: requests_per_second,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes a token for a request, returning how long the request has to wait for it.
+    fn acquire(&mut self) -> Duration {
+        let now = Instant::now();
+        let refilled =
+            now.duration_since(self.refilled_at).as_secs_f64() * self.requests_per_second;
+        self.tokens = (self.tokens + refilled).min(self.requests_per_second) - 1.0;
+        self.refilled_at = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.requests_per_second)
+        }
+    }
+}
+
+/// Returns true if the error is caused by a response exceeding the maximum size of the client - which jsonrpsee
+/// reports as a too large request.
+fn is_response_too_large(err: &eyre::Report) -> bool {
+    match err.downcast_ref::<ClientError>() {
+        Some(ClientError::Transport(err)) => matches!(
+            err.downcast_ref::<TransportError>(),
+            Some(TransportError::RequestTooLarge)
+        ),
+        _ => false,
+    }
+}
+
+/// Parses the `Key: Value` headers to send with every request to the fork.
+pub fn parse_fork_headers(headers: &[String]) -> eyre::Result<HeaderMap> {
+    let headers = headers
+        .iter()
+        .map(|header| {
+            header
+                .split_once(':')
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .ok_or_else(|| {
+                    eyre::eyre!("invalid fork header '{}', expected 'Key: Value'", header)
+                })
+        })
+        .collect::<eyre::Result<HashMap<_, _>>>()?;
+    HeaderMap::try_from(&headers).map_err(|err| eyre::eyre!("invalid fork header: {}", err))
 }
 
 impl HttpForkSource {
@@ -28,11 +188,166 @@ impl HttpForkSource {
         Self {
             fork_url,
             cache: RwLock::new(Cache::new(cache_config)),
+            offline: false,
+            log_calls: false,
+            headers: HeaderMap::new(),
+            rate_limiter: None,
+            max_response_bytes: None,
+            in_flight_storage_requests: Default::default(),
+            in_flight_bytecode_requests: Default::default(),
+            fork_block: None,
+        }
+    }
+
+    /// Enables or disables the offline mode, in which only cached data can be served.
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Enables or disables the logging of the requests made by the fork source, with their params and latency.
+    pub fn set_log_calls(&mut self, log_calls: bool) {
+        self.log_calls = log_calls;
+    }
+
+    /// Sets the headers sent with every request to the network.
+    pub fn set_headers(&mut self, headers: HeaderMap) {
+        self.headers = headers;
+    }
+
+    /// Throttles the requests to the network to the given number of requests per second. Batch requests count as a
+    /// single request.
+    pub fn set_rate_limit(&mut self, requests_per_second: u32) {
+        self.rate_limiter = Some(Mutex::new(RateLimiter::new(requests_per_second)));
+    }
+
+    /// Sets the miniblock the node is forked at - the logs of the ranges up to it are then cached.
+    pub fn set_fork_block(&mut self, fork_block: u64) {
+        self.fork_block = Some(fork_block);
+    }
+
+    /// Rejects the responses from the network larger than the given number of bytes, instead of reading them whole.
+    pub fn set_max_response_bytes(&mut self, max_response_bytes: u32) {
+        self.max_response_bytes = Some(max_response_bytes);
+    }
+
+    /// Fetches and caches the full blocks, and their raw transactions, in the given range. This allows
+    /// serving them later without network requests (e.g. in offline mode). Already cached entries are skipped.
+    pub fn warmup(&self, blocks: RangeInclusive<u64>) -> eyre::Result<()> {
+        let total = blocks.clone().count();
+        for (index, number) in blocks.enumerate() {
+            let (block_cached, raw_transactions_cached) = self
+                .cache
+                .read()
+                .map(|guard| {
+                    (
+                        guard
+                            .get_block_hash(&number)
+                            .and_then(|hash| guard.get_block(hash, true))
+                            .is_some(),
+                        guard.get_block_raw_transactions(&number).is_some(),
+                    )
+                })
+                .unwrap_or_default();
+
+            if !block_cached {
+                self.get_block_by_number(BlockNumber::Number(U64::from(number)), true)?
+                    .ok_or_else(|| eyre::eyre!("block #{} does not exist", number))?;
+            }
+            if !raw_transactions_cached {
+                let transactions =
+                    self.get_raw_block_transactions(MiniblockNumber(number as u32))?;
+                // blocks without transactions are not cached on regular requests
+                if transactions.is_empty() {
+                    self.cache
+                        .write()
+                        .map(|mut guard| guard.insert_block_raw_transactions(number, transactions))
+                        .unwrap_or_else(|err| {
+                            log::warn!("failed writing to cache for 'warmup': {:?}", err)
+                        });
+                }
+            }
+
+            if block_cached && raw_transactions_cached {
+                log::info!(
+                    "warmup: block #{} already cached ({}/{})",
+                    number,
+                    index + 1,
+                    total
+                );
+            } else {
+                log::info!("warmup: cached block #{} ({}/{})", number, index + 1, total);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if the fork source is offline, as `method` would require a network request.
+    fn ensure_online(&self, method: &str) -> eyre::Result<()> {
+        if self.offline {
+            eyre::bail!(
+                "offline mode: '{}' is not cached and network requests are disabled",
+                method
+            )
+        }
+        Ok(())
+    }
+
+    /// Sends a request to the network and waits for its response - after waiting for the rate limit, if any. With
+    /// `log_calls`, the request is logged along with its latency.
+    fn send<T, E, F>(&self, method: &str, params: String, request: F) -> eyre::Result<T>
+    where
+        F: Future<Output = Result<T, E>> + Send + 'static,
+        T: Send,
+        E: Into<eyre::Report> + Send,
+    {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            let wait = rate_limiter
+                .lock()
+                .map(|mut rate_limiter| rate_limiter.acquire())
+                .unwrap_or_default();
+            if !wait.is_zero() {
+                log::debug!("fork call {}: rate limited, waiting {:?}", method, wait);
+                std::thread::sleep(wait);
+            }
+        }
+
+        let started = Instant::now();
+        let response = block_on(request);
+        if self.log_calls {
+            log::info!(
+                "fork call {}({}): cache miss, took {:?}",
+                method,
+                params,
+                started.elapsed()
+            );
+        }
+        response.map_err(|err| {
+            let err = err.into();
+            match self.max_response_bytes {
+                Some(max_response_bytes) if is_response_too_large(&err) => eyre::eyre!(
+                    "response to {} exceeded the maximum of {} bytes set by --fork-max-response-bytes",
+                    method,
+                    max_response_bytes
+                ),
+                _ => err,
+            }
+        })
+    }
+
+    /// Logs a request served from the cache, if `log_calls` is enabled.
+    fn log_cache_hit(&self, method: &str, params: impl FnOnce() -> String) {
+        if self.log_calls {
+            log::info!("fork call {}({}): cache hit", method, params());
         }
     }
 
     pub fn create_client(&self) -> HttpClient {
-        HttpClientBuilder::default()
+        let mut builder = HttpClientBuilder::default().set_headers(self.headers.clone());
+        if let Some(max_response_bytes) = self.max_response_bytes {
+            builder = builder.max_response_size(max_response_bytes);
+        }
+        builder
             .build(self.fork_url.clone())
             .unwrap_or_else(|_| panic!("Unable to create a client for fork: {}", self.fork_url))
     }
@@ -45,18 +360,53 @@ impl ForkSource for HttpForkSource {
         idx: zksync_basic_types::U256,
         block: Option<zksync_types::api::BlockIdVariant>,
     ) -> eyre::Result<zksync_basic_types::H256> {
-        let client = self.create_client();
-        block_on(async move { client.get_storage_at(address, idx, block).await })
+        self.ensure_online("eth_getStorageAt")?;
+        let params = format!("{:?}, {:#x}, {:?}", address, idx, block);
+        coalesce(&self.in_flight_storage_requests, params.clone(), || {
+            let client = self.create_client();
+            self.send("eth_getStorageAt", params, async move {
+                client.get_storage_at(address, idx, block).await
+            })
             .wrap_err("fork http client failed")
+        })
+    }
+
+    /// Fetches the storage slots concurrently, to avoid a network round trip per slot.
+    fn get_storage_at_batch(
+        &self,
+        slots: &[(zksync_basic_types::Address, zksync_basic_types::U256)],
+        block: Option<zksync_types::api::BlockIdVariant>,
+    ) -> eyre::Result<Vec<zksync_basic_types::H256>> {
+        self.ensure_online("eth_getStorageAt")?;
+        let requests = slots
+            .iter()
+            .map(|(address, idx)| {
+                let client = self.create_client();
+                let (address, idx, block) = (*address, *idx, block.clone());
+                async move { client.get_storage_at(address, idx, block).await }
+            })
+            .collect::<Vec<_>>();
+        self.send(
+            "eth_getStorageAt",
+            format!("{} slots, {:?}", slots.len(), block),
+            futures::future::try_join_all(requests),
+        )
+        .wrap_err("fork http client failed")
     }
 
     fn get_bytecode_by_hash(
         &self,
         hash: zksync_basic_types::H256,
     ) -> eyre::Result<Option<Vec<u8>>> {
-        let client = self.create_client();
-        block_on(async move { client.get_bytecode_by_hash(hash).await })
+        self.ensure_online("zks_getBytecodeByHash")?;
+        let params = format!("{:#x}", hash);
+        coalesce(&self.in_flight_bytecode_requests, params.clone(), || {
+            let client = self.create_client();
+            self.send("zks_getBytecodeByHash", params, async move {
+                client.get_bytecode_by_hash(hash).await
+            })
             .wrap_err("fork http client failed")
+        })
     }
 
     fn get_transaction_by_hash(
@@ -69,26 +419,67 @@ impl ForkSource for HttpForkSource {
             .map(|guard| guard.get_transaction(&hash).cloned())
         {
             log::debug!("using cached transaction for {hash}");
+            self.log_cache_hit("eth_getTransactionByHash", || format!("{:#x}", hash));
             return Ok(Some(transaction));
         }
 
+        self.ensure_online("eth_getTransactionByHash")?;
         let client = self.create_client();
-        block_on(async move { client.get_transaction_by_hash(hash).await })
-            .map(|maybe_transaction| {
-                if let Some(transaction) = &maybe_transaction {
-                    self.cache
-                        .write()
-                        .map(|mut guard| guard.insert_transaction(hash, transaction.clone()))
-                        .unwrap_or_else(|err| {
-                            log::warn!(
-                                "failed writing to cache for 'get_transaction_by_hash': {:?}",
-                                err
-                            )
-                        });
-                }
-                maybe_transaction
-            })
-            .wrap_err("fork http client failed")
+        self.send(
+            "eth_getTransactionByHash",
+            format!("{:#x}", hash),
+            async move { client.get_transaction_by_hash(hash).await },
+        )
+        .map(|maybe_transaction| {
+            if let Some(transaction) = &maybe_transaction {
+                self.cache
+                    .write()
+                    .map(|mut guard| guard.insert_transaction(hash, transaction.clone()))
+                    .unwrap_or_else(|err| {
+                        log::warn!(
+                            "failed writing to cache for 'get_transaction_by_hash': {:?}",
+                            err
+                        )
+                    });
+            }
+            maybe_transaction
+        })
+        .wrap_err("fork http client failed")
+    }
+
+    fn get_transaction_receipt(&self, hash: H256) -> eyre::Result<Option<TransactionReceipt>> {
+        if let Ok(Some(receipt)) = self
+            .cache
+            .read()
+            .map(|guard| guard.get_transaction_receipt(&hash).cloned())
+        {
+            log::debug!("using cached transaction receipt for {hash}");
+            self.log_cache_hit("eth_getTransactionReceipt", || format!("{:#x}", hash));
+            return Ok(Some(receipt));
+        }
+
+        self.ensure_online("eth_getTransactionReceipt")?;
+        let client = self.create_client();
+        self.send(
+            "eth_getTransactionReceipt",
+            format!("{:#x}", hash),
+            async move { client.get_transaction_receipt(hash).await },
+        )
+        .map(|maybe_receipt| {
+            if let Some(receipt) = &maybe_receipt {
+                self.cache
+                    .write()
+                    .map(|mut guard| guard.insert_transaction_receipt(hash, receipt.clone()))
+                    .unwrap_or_else(|err| {
+                        log::warn!(
+                            "failed writing to cache for 'get_transaction_receipt': {:?}",
+                            err
+                        )
+                    });
+            }
+            maybe_receipt
+        })
+        .wrap_err("fork http client failed")
     }
 
     fn get_raw_block_transactions(
@@ -102,28 +493,36 @@ impl ForkSource for HttpForkSource {
             .map(|guard| guard.get_block_raw_transactions(&number).cloned())
         {
             log::debug!("using cached raw transactions for block {block_number}");
+            self.log_cache_hit("zks_getRawBlockTransactions", || {
+                format!("{}", block_number)
+            });
             return Ok(transaction);
         }
 
+        self.ensure_online("zks_getRawBlockTransactions")?;
         let client = self.create_client();
-        block_on(async move { client.get_raw_block_transactions(block_number).await })
-            .wrap_err("fork http client failed")
-            .map(|transactions| {
-                if !transactions.is_empty() {
-                    self.cache
-                        .write()
-                        .map(|mut guard| {
-                            guard.insert_block_raw_transactions(number, transactions.clone())
-                        })
-                        .unwrap_or_else(|err| {
-                            log::warn!(
-                                "failed writing to cache for 'get_raw_block_transactions': {:?}",
-                                err
-                            )
-                        });
-                }
-                transactions
-            })
+        self.send(
+            "zks_getRawBlockTransactions",
+            format!("{}", block_number),
+            async move { client.get_raw_block_transactions(block_number).await },
+        )
+        .wrap_err("fork http client failed")
+        .map(|transactions| {
+            if !transactions.is_empty() {
+                self.cache
+                    .write()
+                    .map(|mut guard| {
+                        guard.insert_block_raw_transactions(number, transactions.clone())
+                    })
+                    .unwrap_or_else(|err| {
+                        log::warn!(
+                            "failed writing to cache for 'get_raw_block_transactions': {:?}",
+                            err
+                        )
+                    });
+            }
+            transactions
+        })
     }
 
     fn get_block_by_hash(
@@ -137,23 +536,31 @@ impl ForkSource for HttpForkSource {
             .map(|guard| guard.get_block(&hash, full_transactions).cloned())
         {
             log::debug!("using cached block for {hash}");
+            self.log_cache_hit("eth_getBlockByHash", || {
+                format!("{:#x}, {}", hash, full_transactions)
+            });
             return Ok(Some(block));
         }
 
+        self.ensure_online("eth_getBlockByHash")?;
         let client = self.create_client();
-        block_on(async move { client.get_block_by_hash(hash, full_transactions).await })
-            .map(|block| {
-                if let Some(block) = &block {
-                    self.cache
-                        .write()
-                        .map(|mut guard| guard.insert_block(hash, full_transactions, block.clone()))
-                        .unwrap_or_else(|err| {
-                            log::warn!("failed writing to cache for 'get_block_by_hash': {:?}", err)
-                        });
-                }
-                block
-            })
-            .wrap_err("fork http client failed")
+        self.send(
+            "eth_getBlockByHash",
+            format!("{:#x}, {}", hash, full_transactions),
+            async move { client.get_block_by_hash(hash, full_transactions).await },
+        )
+        .map(|block| {
+            if let Some(block) = &block {
+                self.cache
+                    .write()
+                    .map(|mut guard| guard.insert_block(hash, full_transactions, block.clone()))
+                    .unwrap_or_else(|err| {
+                        log::warn!("failed writing to cache for 'get_block_by_hash': {:?}", err)
+                    });
+            }
+            block
+        })
+        .wrap_err("fork http client failed")
     }
 
     fn get_block_by_number(
@@ -174,15 +581,23 @@ impl ForkSource for HttpForkSource {
             })
         }) {
             log::debug!("using cached block for {block_number}");
+            self.log_cache_hit("eth_getBlockByNumber", || {
+                format!("{}, {}", block_number, full_transactions)
+            });
             return Ok(Some(block));
         }
 
+        self.ensure_online("eth_getBlockByNumber")?;
         let client = self.create_client();
-        block_on(async move {
-            client
-                .get_block_by_number(block_number, full_transactions)
-                .await
-        })
+        self.send(
+            "eth_getBlockByNumber",
+            format!("{}, {}", block_number, full_transactions),
+            async move {
+                client
+                    .get_block_by_number(block_number, full_transactions)
+                    .await
+            },
+        )
         .map(|block| {
             if let Some(block) = &block {
                 self.cache
@@ -204,9 +619,14 @@ impl ForkSource for HttpForkSource {
 
     /// Returns the  transaction count for a given block hash.
     fn get_block_transaction_count_by_hash(&self, block_hash: H256) -> eyre::Result<Option<U256>> {
+        self.ensure_online("eth_getBlockTransactionCountByHash")?;
         let client = self.create_client();
-        block_on(async move { client.get_block_transaction_count_by_hash(block_hash).await })
-            .wrap_err("fork http client failed")
+        self.send(
+            "eth_getBlockTransactionCountByHash",
+            format!("{:#x}", block_hash),
+            async move { client.get_block_transaction_count_by_hash(block_hash).await },
+        )
+        .wrap_err("fork http client failed")
     }
 
     /// Returns the transaction count for a given block number.
@@ -214,12 +634,17 @@ impl ForkSource for HttpForkSource {
         &self,
         block_number: zksync_types::api::BlockNumber,
     ) -> eyre::Result<Option<U256>> {
+        self.ensure_online("eth_getBlockTransactionCountByNumber")?;
         let client = self.create_client();
-        block_on(async move {
-            client
-                .get_block_transaction_count_by_number(block_number)
-                .await
-        })
+        self.send(
+            "eth_getBlockTransactionCountByNumber",
+            format!("{}", block_number),
+            async move {
+                client
+                    .get_block_transaction_count_by_number(block_number)
+                    .await
+            },
+        )
         .wrap_err("fork http client failed")
     }
 
@@ -229,12 +654,17 @@ impl ForkSource for HttpForkSource {
         block_hash: H256,
         index: Index,
     ) -> eyre::Result<Option<Transaction>> {
+        self.ensure_online("eth_getTransactionByBlockHashAndIndex")?;
         let client = self.create_client();
-        block_on(async move {
-            client
-                .get_transaction_by_block_hash_and_index(block_hash, index)
-                .await
-        })
+        self.send(
+            "eth_getTransactionByBlockHashAndIndex",
+            format!("{:#x}, {}", block_hash, index),
+            async move {
+                client
+                    .get_transaction_by_block_hash_and_index(block_hash, index)
+                    .await
+            },
+        )
         .wrap_err("fork http client failed")
     }
 
@@ -244,21 +674,154 @@ impl ForkSource for HttpForkSource {
         block_number: zksync_types::api::BlockNumber,
         index: Index,
     ) -> eyre::Result<Option<Transaction>> {
+        self.ensure_online("eth_getTransactionByBlockNumberAndIndex")?;
+        let client = self.create_client();
+        self.send(
+            "eth_getTransactionByBlockNumberAndIndex",
+            format!("{}, {}", block_number, index),
+            async move {
+                client
+                    .get_transaction_by_block_number_and_index(block_number, index)
+                    .await
+            },
+        )
+        .wrap_err("fork http client failed")
+    }
+
+    /// Returns the chain id of the forked chain.
+    fn get_chain_id(&self) -> eyre::Result<L2ChainId> {
+        if let Ok(Some(chain_id)) = self.cache.read().map(|guard| guard.get_chain_id()) {
+            log::debug!("using cached chain id {}", chain_id.0);
+            self.log_cache_hit("eth_chainId", String::new);
+            return Ok(chain_id);
+        }
+
+        self.ensure_online("eth_chainId")?;
         let client = self.create_client();
-        block_on(async move {
-            client
-                .get_transaction_by_block_number_and_index(block_number, index)
-                .await
+        self.send("eth_chainId", String::new(), async move {
+            client.chain_id().await
+        })
+        .map(|chain_id| {
+            let chain_id = L2ChainId(chain_id.as_u64() as u16);
+            self.cache
+                .write()
+                .map(|mut guard| guard.insert_chain_id(chain_id))
+                .unwrap_or_else(|err| {
+                    log::warn!("failed writing to cache for 'get_chain_id': {:?}", err)
+                });
+            chain_id
         })
         .wrap_err("fork http client failed")
     }
+
+    /// Returns the addresses of the default bridge contracts of the forked chain.
+    fn get_bridge_contracts(&self) -> eyre::Result<BridgeAddresses> {
+        if let Ok(Some(bridge_contracts)) = self
+            .cache
+            .read()
+            .map(|guard| guard.get_bridge_contracts().cloned())
+        {
+            log::debug!("using cached bridge contracts");
+            self.log_cache_hit("zks_getBridgeContracts", String::new);
+            return Ok(bridge_contracts);
+        }
+
+        self.ensure_online("zks_getBridgeContracts")?;
+        let client = self.create_client();
+        self.send("zks_getBridgeContracts", String::new(), async move {
+            client.get_bridge_contracts().await
+        })
+        .map(|bridge_contracts| {
+            self.cache
+                .write()
+                .map(|mut guard| guard.insert_bridge_contracts(bridge_contracts.clone()))
+                .unwrap_or_else(|err| {
+                    log::warn!(
+                        "failed writing to cache for 'get_bridge_contracts': {:?}",
+                        err
+                    )
+                });
+            bridge_contracts
+        })
+        .wrap_err("fork http client failed")
+    }
+
+    fn get_logs(
+        &self,
+        filter: zksync_web3_decl::types::Filter,
+    ) -> eyre::Result<Vec<zksync_types::api::Log>> {
+        let key = self
+            .fork_block
+            .and_then(|fork_block| LogsFilterKey::from_filter(&filter, fork_block));
+        if let Some(key) = &key {
+            if let Ok(Some(logs)) = self.cache.read().map(|guard| guard.get_logs(key).cloned()) {
+                log::debug!("using cached logs for {:?}", key);
+                self.log_cache_hit("eth_getLogs", || format!("{:?}", filter));
+                return Ok(logs);
+            }
+        }
+
+        self.ensure_online("eth_getLogs")?;
+        let client = self.create_client();
+        self.send("eth_getLogs", format!("{:?}", filter), async move {
+            client.get_logs(filter).await
+        })
+        .map(|logs| {
+            if let Some(key) = key {
+                self.cache
+                    .write()
+                    .map(|mut guard| guard.insert_logs(key, logs.clone()))
+                    .unwrap_or_else(|err| {
+                        log::warn!("failed writing to cache for 'get_logs': {:?}", err)
+                    });
+            }
+            logs
+        })
+        .wrap_err("fork http client failed")
+    }
+
+    fn get_protocol_version(
+        &self,
+        version_id: Option<u16>,
+    ) -> eyre::Result<Option<ProtocolVersion>> {
+        self.ensure_online("zks_getProtocolVersion")?;
+        let client = self.create_client();
+        self.send(
+            "zks_getProtocolVersion",
+            format!("{:?}", version_id),
+            async move {
+                client
+                    .request("zks_getProtocolVersion", rpc_params![version_id])
+                    .await
+            },
+        )
+        .wrap_err("fork http client failed")
+    }
+
+    /// Fetches the proofs via `zks_getProof`, which is not part of the namespace clients.
+    fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<H256>,
+        l1_batch_number: L1BatchNumber,
+    ) -> eyre::Result<Option<Proof>> {
+        self.ensure_online("zks_getProof")?;
+        let client = self.create_client();
+        self.send(
+            "zks_getProof",
+            format!("{:?}, {} keys, {}", address, keys.len(), l1_batch_number.0),
+            async move {
+                client
+                    .request("zks_getProof", rpc_params![address, keys, l1_batch_number])
+                    .await
+            },
+        )
+        .wrap_err("fork http client failed")
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use zksync_basic_types::{MiniblockNumber, H256, U64};
-    use zksync_types::api::BlockNumber;
-
     use crate::testing;
 
     use super::*;
@@ -498,4 +1061,614 @@ mod tests {
             .expect("no transaction");
         assert_eq!(input_tx_hash, actual_transaction.hash);
     }
+
+    #[test]
+    fn test_get_transaction_receipt_is_cached() {
+        let input_tx_hash = H256::repeat_byte(0x01);
+        let input_block_hash = H256::repeat_byte(0x02);
+
+        let mock_server = testing::MockServer::run();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getTransactionReceipt",
+                "params": [
+                    input_tx_hash,
+                ],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": serde_json::to_value(TransactionReceipt {
+                    transaction_hash: input_tx_hash,
+                    block_hash: Some(input_block_hash),
+                    block_number: Some(U64::from(10)),
+                    status: Some(U64::from(1)),
+                    ..Default::default()
+                })
+                .unwrap(),
+            }),
+        );
+
+        let fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+
+        let actual_receipt = fork_source
+            .get_transaction_receipt(input_tx_hash)
+            .expect("failed fetching transaction receipt")
+            .expect("no transaction receipt");
+        assert_eq!(input_tx_hash, actual_receipt.transaction_hash);
+        assert_eq!(Some(input_block_hash), actual_receipt.block_hash);
+
+        // the mock server only expects a single request
+        let actual_receipt = fork_source
+            .get_transaction_receipt(input_tx_hash)
+            .expect("failed fetching cached transaction receipt")
+            .expect("no transaction receipt");
+        assert_eq!(input_tx_hash, actual_receipt.transaction_hash);
+        assert_eq!(Some(input_block_hash), actual_receipt.block_hash);
+    }
+
+    #[test]
+    fn test_get_chain_id_is_cached() {
+        let mock_server = testing::MockServer::run();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_chainId",
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": "0x144",
+            }),
+        );
+
+        let fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+
+        let actual_chain_id = fork_source
+            .get_chain_id()
+            .expect("failed fetching chain id");
+        assert_eq!(L2ChainId(324), actual_chain_id);
+
+        let actual_chain_id = fork_source
+            .get_chain_id()
+            .expect("failed fetching cached chain id");
+        assert_eq!(L2ChainId(324), actual_chain_id);
+    }
+
+    #[test]
+    fn test_get_bridge_contracts_is_cached() {
+        let input_bridge_address = zksync_basic_types::Address::repeat_byte(0x01);
+
+        let mock_server = testing::MockServer::run();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "zks_getBridgeContracts",
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": {
+                    "l1Erc20DefaultBridge": format!("{:#x}", input_bridge_address),
+                    "l2Erc20DefaultBridge": format!("{:#x}", input_bridge_address),
+                },
+            }),
+        );
+
+        let fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+
+        let actual_bridge_contracts = fork_source
+            .get_bridge_contracts()
+            .expect("failed fetching bridge contracts");
+        assert_eq!(
+            input_bridge_address,
+            actual_bridge_contracts.l2_erc20_default_bridge
+        );
+
+        let actual_bridge_contracts = fork_source
+            .get_bridge_contracts()
+            .expect("failed fetching cached bridge contracts");
+        assert_eq!(
+            input_bridge_address,
+            actual_bridge_contracts.l2_erc20_default_bridge
+        );
+    }
+
+    #[test]
+    fn test_get_logs_is_cached() {
+        let mock_server = testing::MockServer::run();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getLogs",
+                "params": [{
+                    "fromBlock": "0x1",
+                    "toBlock": "0x5",
+                    "address": format!("{:#x}", Address::repeat_byte(0x1)),
+                }],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": [
+                    testing::LogBuilder::new()
+                        .set_block(U64::from(3))
+                        .set_address(Address::repeat_byte(0x1))
+                        .build(),
+                ],
+            }),
+        );
+
+        let mut fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+        fork_source.set_fork_block(5);
+        let filter = zksync_web3_decl::types::Filter {
+            from_block: Some(BlockNumber::Number(U64::from(1))),
+            to_block: Some(BlockNumber::Number(U64::from(5))),
+            address: Some(zksync_web3_decl::types::ValueOrArray(vec![
+                Address::repeat_byte(0x1),
+            ])),
+            topics: None,
+            block_hash: None,
+        };
+
+        let actual_logs = fork_source
+            .get_logs(filter.clone())
+            .expect("failed fetching logs");
+        assert_eq!(1, actual_logs.len());
+
+        // the mock server expects a single request, so the logs are served from the cache
+        let actual_logs = fork_source
+            .get_logs(filter)
+            .expect("failed fetching cached logs");
+        assert_eq!(1, actual_logs.len());
+        assert_eq!(Some(U64::from(3)), actual_logs[0].block_number);
+    }
+
+    #[test]
+    fn test_get_logs_past_the_fork_block_are_fetched_again() {
+        let mock_server = testing::MockServer::run();
+        let log = |number: u64| {
+            testing::LogBuilder::new()
+                .set_block(U64::from(number))
+                .set_address(Address::repeat_byte(0x1))
+                .build()
+        };
+        // the head of the forked chain advances between the two queries, and a new log is found
+        mock_server.expect_with_responses(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getLogs",
+                "params": [{
+                    "fromBlock": "0x1",
+                    "toBlock": "0xa",
+                    "address": format!("{:#x}", Address::repeat_byte(0x1)),
+                }],
+            }),
+            vec![
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "result": [log(3)],
+                }),
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "result": [log(3), log(7)],
+                }),
+            ],
+        );
+
+        let mut fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+        fork_source.set_fork_block(5);
+        let filter = zksync_web3_decl::types::Filter {
+            from_block: Some(BlockNumber::Number(U64::from(1))),
+            to_block: Some(BlockNumber::Number(U64::from(10))),
+            address: Some(zksync_web3_decl::types::ValueOrArray(vec![
+                Address::repeat_byte(0x1),
+            ])),
+            topics: None,
+            block_hash: None,
+        };
+
+        let actual_logs = fork_source
+            .get_logs(filter.clone())
+            .expect("failed fetching logs");
+        assert_eq!(1, actual_logs.len());
+
+        let actual_logs = fork_source
+            .get_logs(filter)
+            .expect("failed fetching logs again");
+        assert_eq!(2, actual_logs.len());
+        assert_eq!(Some(U64::from(7)), actual_logs[1].block_number);
+    }
+
+    #[test]
+    fn test_offline_mode_serves_only_cached_data() {
+        let input_block_hash = H256::repeat_byte(0x01);
+        let input_block_number = 8;
+
+        // an unreachable url, as no network requests are expected
+        let mut fork_source =
+            HttpForkSource::new(String::from("http://offline.invalid"), CacheConfig::Memory);
+        fork_source.set_offline(true);
+        fork_source
+            .cache
+            .write()
+            .expect("failed acquiring cache lock")
+            .insert_block(
+                input_block_hash,
+                true,
+                zksync_types::api::Block {
+                    hash: input_block_hash,
+                    number: U64::from(input_block_number),
+                    ..Default::default()
+                },
+            );
+
+        let actual_block = fork_source
+            .get_block_by_hash(input_block_hash, true)
+            .expect("failed fetching cached block by hash")
+            .expect("no block");
+        assert_eq!(U64::from(input_block_number), actual_block.number);
+
+        let error = fork_source
+            .get_block_by_hash(H256::repeat_byte(0x02), true)
+            .expect_err("cache miss must fail in offline mode");
+        assert!(
+            error.to_string().contains("offline mode"),
+            "unexpected error: {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_warmup_caches_blocks_in_range() {
+        let mock_server = testing::MockServer::run();
+        for input_block_number in [1u32, 2] {
+            mock_server.expect(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "method": "eth_getBlockByNumber",
+                    "params": [
+                        format!("{input_block_number:#x}"),
+                        true
+                    ],
+                }),
+                testing::BlockResponseBuilder::new()
+                    .set_hash(H256::repeat_byte(input_block_number as u8))
+                    .set_number(input_block_number as u64)
+                    .build(),
+            );
+            mock_server.expect(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "method": "zks_getRawBlockTransactions",
+                    "params": [
+                        input_block_number,
+                    ],
+                }),
+                testing::RawTransactionsResponseBuilder::new()
+                    .add(input_block_number as u64)
+                    .build(),
+            );
+        }
+
+        let mut fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+        fork_source.warmup(1..=2).expect("failed warming up cache");
+        // warming up again must only use the cache
+        fork_source.warmup(1..=2).expect("failed warming up cache");
+
+        fork_source.set_offline(true);
+        for input_block_number in [1u32, 2] {
+            let actual_block = fork_source
+                .get_block_by_number(BlockNumber::Number(U64::from(input_block_number)), true)
+                .expect("failed fetching cached block by number")
+                .expect("no block");
+            assert_eq!(
+                H256::repeat_byte(input_block_number as u8),
+                actual_block.hash
+            );
+
+            let actual_raw_transactions = fork_source
+                .get_raw_block_transactions(MiniblockNumber(input_block_number))
+                .expect("failed fetching cached block raw transactions");
+            assert_eq!(1, actual_raw_transactions.len());
+        }
+    }
+
+    #[test]
+    fn test_get_storage_at_batch_fetches_slots_concurrently() {
+        let slot_count = 5u64;
+        let response_delay = Duration::from_millis(500);
+        let address = zksync_basic_types::Address::repeat_byte(0x01);
+
+        let mock_server = testing::MockServer::run();
+        for idx in 0..slot_count {
+            mock_server.expect_with_delay(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "method": "eth_getStorageAt",
+                    "params": [
+                        format!("{:#x}", address),
+                        format!("{:#x}", idx),
+                        "0xa",
+                    ],
+                }),
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "result": format!("{:#x}", H256::from_low_u64_be(idx)),
+                }),
+                response_delay,
+            );
+        }
+
+        let fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+        let slots = (0..slot_count)
+            .map(|idx| (address, U256::from(idx)))
+            .collect::<Vec<_>>();
+
+        let started_at = Instant::now();
+        let values = fork_source
+            .get_storage_at_batch(
+                &slots,
+                Some(zksync_types::api::BlockIdVariant::BlockNumber(
+                    BlockNumber::Number(U64::from(10)),
+                )),
+            )
+            .expect("failed fetching storage slots");
+        let elapsed = started_at.elapsed();
+
+        assert_eq!(
+            (0..slot_count)
+                .map(H256::from_low_u64_be)
+                .collect::<Vec<_>>(),
+            values
+        );
+        // sequential requests would take at least `slot_count * response_delay`
+        assert!(
+            elapsed < response_delay * (slot_count as u32 - 1),
+            "fetching {} slots took {:?}",
+            slot_count,
+            elapsed
+        );
+    }
+
+    #[test]
+    fn test_get_storage_at_batch_returns_the_values_of_all_slots_in_order() {
+        let first_address = zksync_basic_types::Address::repeat_byte(0x01);
+        let second_address = zksync_basic_types::Address::repeat_byte(0x02);
+        let slots = vec![
+            (second_address, U256::from(7)),
+            (first_address, U256::zero()),
+            (first_address, U256::from(7)),
+        ];
+
+        let mock_server = testing::MockServer::run();
+        for (value, (address, idx)) in slots.iter().enumerate() {
+            mock_server.expect(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "method": "eth_getStorageAt",
+                    "params": [
+                        format!("{:#x}", address),
+                        format!("{:#x}", idx),
+                        "0xa",
+                    ],
+                }),
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 0,
+                    "result": format!("{:#x}", H256::from_low_u64_be(value as u64 + 1)),
+                }),
+            );
+        }
+
+        let fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+        let values = fork_source
+            .get_storage_at_batch(
+                &slots,
+                Some(zksync_types::api::BlockIdVariant::BlockNumber(
+                    BlockNumber::Number(U64::from(10)),
+                )),
+            )
+            .expect("failed fetching storage slots");
+
+        assert_eq!(
+            vec![
+                H256::from_low_u64_be(1),
+                H256::from_low_u64_be(2),
+                H256::from_low_u64_be(3)
+            ],
+            values
+        );
+    }
+
+    #[test]
+    fn test_fork_headers_are_sent_with_every_request() {
+        let address = zksync_basic_types::Address::repeat_byte(0x42);
+
+        let mock_server = testing::MockServer::run();
+        mock_server.expect_with_header(
+            ("x-api-key", "secret"),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getStorageAt",
+                "params": [format!("{:#x}", address), "0x0", "0xa"],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", H256::repeat_byte(0x1)),
+            }),
+        );
+
+        let mut fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+        fork_source.set_headers(
+            parse_fork_headers(&[String::from("X-Api-Key: secret")])
+                .expect("failed parsing fork headers"),
+        );
+        let value = fork_source
+            .get_storage_at(
+                address,
+                U256::zero(),
+                Some(zksync_types::api::BlockIdVariant::BlockNumber(
+                    BlockNumber::Number(U64::from(10)),
+                )),
+            )
+            .expect("failed fetching storage with the fork headers");
+        assert_eq!(H256::repeat_byte(0x1), value);
+
+        parse_fork_headers(&[String::from("X-Api-Key")])
+            .expect_err("header without a value should be rejected");
+    }
+
+    #[test]
+    fn test_concurrent_identical_storage_reads_share_one_request() {
+        let address = zksync_basic_types::Address::repeat_byte(0x42);
+
+        // the mock server only accepts the request once, and answers it slowly enough for the second read to be
+        // made while the first one is in flight
+        let mock_server = testing::MockServer::run();
+        mock_server.expect_with_delay(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getStorageAt",
+                "params": [format!("{:#x}", address), "0x0", "0xa"],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", H256::repeat_byte(0x1)),
+            }),
+            Duration::from_millis(500),
+        );
+
+        let fork_source = Arc::new(HttpForkSource::new(mock_server.url(), CacheConfig::Memory));
+        let reads = (0..2)
+            .map(|_| {
+                let fork_source = fork_source.clone();
+                std::thread::spawn(move || {
+                    fork_source.get_storage_at(
+                        address,
+                        U256::zero(),
+                        Some(zksync_types::api::BlockIdVariant::BlockNumber(
+                            BlockNumber::Number(U64::from(10)),
+                        )),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for read in reads {
+            let value = read
+                .join()
+                .expect("read panicked")
+                .expect("failed fetching storage");
+            assert_eq!(H256::repeat_byte(0x1), value);
+        }
+    }
+
+    #[test]
+    fn test_responses_above_max_response_bytes_are_rejected() {
+        let mock_server = testing::MockServer::run();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_chainId",
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("0x{}104", "0".repeat(4096)),
+            }),
+        );
+
+        let mut fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::None);
+        fork_source.set_max_response_bytes(1024);
+        let error = fork_source
+            .get_chain_id()
+            .expect_err("oversized response was accepted");
+
+        assert!(
+            format!("{:#}", error)
+                .contains("exceeded the maximum of 1024 bytes set by --fork-max-response-bytes"),
+            "unexpected error: {:#}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter_spaces_out_bursts_above_the_limit() {
+        let mut rate_limiter = RateLimiter::new(10);
+
+        let waits = (0..20).map(|_| rate_limiter.acquire()).collect::<Vec<_>>();
+
+        // the first second worth of requests is sent right away, the next ones are queued 100ms apart
+        assert!(waits[..10].iter().all(|wait| wait.is_zero()));
+        for (index, pair) in waits[9..].windows(2).enumerate() {
+            let spacing = pair[1] - pair[0];
+            assert!(
+                spacing > Duration::from_millis(90) && spacing <= Duration::from_millis(100),
+                "request #{} was spaced {:?} after the previous one",
+                index + 10,
+                spacing
+            );
+        }
+        assert!(waits[19] >= Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_log_calls_logs_requests_with_their_latency() {
+        let address = zksync_basic_types::Address::repeat_byte(0x5c);
+        let logs = testing::capture_logs(format!("{:?}", address));
+
+        let mock_server = testing::MockServer::run();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getStorageAt",
+                "params": [format!("{:#x}", address), "0x0", "0xa"],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", H256::zero()),
+            }),
+        );
+
+        let mut fork_source = HttpForkSource::new(mock_server.url(), CacheConfig::Memory);
+        fork_source.set_log_calls(true);
+        fork_source
+            .get_storage_at(
+                address,
+                U256::zero(),
+                Some(zksync_types::api::BlockIdVariant::BlockNumber(
+                    BlockNumber::Number(U64::from(10)),
+                )),
+            )
+            .expect("failed fetching storage");
+
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains(&format!("{:?}", address)))
+            .expect("request was not logged");
+        assert!(line.starts_with("fork call eth_getStorageAt("));
+        assert!(line.contains("cache miss, took "));
+    }
 }
@@ -42,14 +42,18 @@
 //! Contributions to improve `era-test-node` are welcome. Please refer to the [contribution guidelines](https://github.com/matter-labs/era-test-node/blob/main/.github/CONTRIBUTING.md) for more details.
 
 pub mod bootloader_debug;
+pub mod call_depth;
 pub mod configuration_api;
 pub mod console_log;
 pub mod deps;
 pub mod filters;
 pub mod fork;
+pub mod fork_fixture;
 pub mod formatter;
 pub mod http_fork_source;
+pub mod mock_call;
 pub mod node;
+pub mod pool;
 pub mod resolver;
 pub mod system_contracts;
 pub mod utils;
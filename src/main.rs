@@ -1,20 +1,31 @@
+use crate::anvil::{AnvilNamespaceImpl, AnvilNamespaceT};
 use crate::cache::CacheConfig;
+use crate::formatter::BaseToken;
 use crate::hardhat::{HardhatNamespaceImpl, HardhatNamespaceT};
+use crate::http_fork_source::{parse_fork_headers, HttpForkSource};
 use crate::node::{ShowGasDetails, ShowStorageLogs, ShowVMDetails};
 use clap::{Parser, Subcommand, ValueEnum};
 use configuration_api::ConfigurationApiNamespaceT;
-use debug::DebugNamespaceImpl;
+use debug::{DebugExtNamespaceT, DebugNamespaceImpl};
 use evm::{EvmNamespaceImpl, EvmNamespaceT};
 use fork::{ForkDetails, ForkSource};
 use logging_middleware::LoggingMiddleware;
+use net::NetNamespaceImpl;
 use node::ShowCalls;
+use pool::MempoolOrder;
+use request_log::RequestRecorder;
+use rpc_filter::RpcMethodFilter;
 use simplelog::{
     ColorChoice, CombinedLogger, ConfigBuilder, LevelFilter, TermLogger, TerminalMode, WriteLogger,
 };
-use zks::ZkMockNamespaceImpl;
+use txpool::{TxPoolNamespaceImpl, TxPoolNamespaceT};
+use unknown_methods::UnknownMethodHandler;
+use zks::{ZkMockNamespaceImpl, ZksExtNamespaceT};
 
+mod anvil;
 mod bootloader_debug;
 mod cache;
+mod call_depth;
 mod configuration_api;
 mod console_log;
 mod debug;
@@ -26,21 +37,35 @@ mod formatter;
 mod hardhat;
 mod http_fork_source;
 mod logging_middleware;
+mod mock_call;
+mod net;
 mod node;
+mod pool;
+mod request_log;
 mod resolver;
+mod rpc_filter;
 mod system_contracts;
 mod testing;
+mod txpool;
+mod unknown_methods;
 mod utils;
 mod zks;
 
-use node::InMemoryNode;
-use zksync_core::api_server::web3::namespaces::NetNamespace;
+use node::{
+    EthExtNamespaceT, InMemoryNode, InMemoryNodeBuilder, InMemoryNodeInner, RICH_ACCOUNT_BALANCE,
+};
 
 use std::{
+    convert::TryFrom,
     env,
-    fs::File,
+    fs::{self, File},
+    io::Write,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use tracing::Level;
@@ -49,16 +74,21 @@ use tracing_subscriber::{EnvFilter, FmtSubscriber};
 use futures::{
     channel::oneshot,
     future::{self},
-    FutureExt,
+    Future, FutureExt,
 };
-use jsonrpc_core::MetaIoHandler;
-use zksync_basic_types::{L2ChainId, H160, H256};
+use jsonrpc_core::{MetaIoHandler, Middleware};
+use serde::Deserialize;
+use zksync_basic_types::{H160, H256, U256};
+use zksync_types::ProtocolVersionId;
 
-use crate::{configuration_api::ConfigurationApiNamespace, node::TEST_NODE_NETWORK_ID};
+use crate::configuration_api::ConfigurationApiNamespace;
 use zksync_core::api_server::web3::backend_jsonrpc::namespaces::{
     debug::DebugNamespaceT, eth::EthNamespaceT, net::NetNamespaceT, zks::ZksNamespaceT,
 };
 
+/// Number of dev accounts derived from the mnemonic, and seeded with tokens at start - same as Hardhat.
+const MNEMONIC_ACCOUNTS_COUNT: u32 = 20;
+
 /// List of wallets (address, private key) that we seed with tokens at start.
 pub const RICH_WALLETS: [(&str, &str); 10] = [
     (
@@ -103,33 +133,104 @@ pub const RICH_WALLETS: [(&str, &str); 10] = [
     ),
 ];
 
+/// Handle to the running JSON-RPC HTTP server.
+struct ServerHandle {
+    /// The address the server is bound to - holds the port assigned by the OS when binding to port `0`.
+    addr: SocketAddr,
+    close_handle: jsonrpc_http_server::CloseHandle,
+    /// Completes once the server stops.
+    stopped: tokio::task::JoinHandle<()>,
+}
+
+/// Builds the handler of the JSON-RPC requests, with all the namespaces and middlewares of the node.
+#[allow(clippy::too_many_arguments)]
+fn build_io_handler<
+    S: std::marker::Sync + std::marker::Send + 'static + ForkSource + std::fmt::Debug,
+>(
+    log_level_filter: LevelFilter,
+    rpc_filter: RpcMethodFilter,
+    request_recorder: RequestRecorder,
+    unknown_methods: UnknownMethodHandler,
+    node: InMemoryNode<S>,
+    net: NetNamespaceImpl<S>,
+    config_api: ConfigurationApiNamespace<S>,
+    evm: EvmNamespaceImpl<S>,
+    zks: ZkMockNamespaceImpl<S>,
+    zks_ext: ZkMockNamespaceImpl<S>,
+    hardhat: HardhatNamespaceImpl<S>,
+    debug: DebugNamespaceImpl<S>,
+    debug_ext: DebugNamespaceImpl<S>,
+    txpool: TxPoolNamespaceImpl<S>,
+    anvil: AnvilNamespaceImpl<S>,
+) -> MetaIoHandler<(), impl Middleware<()>> {
+    let mut io = MetaIoHandler::with_middleware((
+        rpc_filter,
+        request_recorder,
+        unknown_methods.clone(),
+        LoggingMiddleware::new(log_level_filter),
+    ));
+    io.extend_with(EthExtNamespaceT::to_delegate(InMemoryNode::from_inner(
+        node.get_inner(),
+    )));
+    io.extend_with(EthNamespaceT::to_delegate(node));
+    io.extend_with(net.to_delegate());
+    io.extend_with(config_api.to_delegate());
+    io.extend_with(evm.to_delegate());
+    io.extend_with(ZksNamespaceT::to_delegate(zks));
+    io.extend_with(ZksExtNamespaceT::to_delegate(zks_ext));
+    io.extend_with(hardhat.to_delegate());
+    io.extend_with(DebugNamespaceT::to_delegate(debug));
+    // overrides `debug_traceTransaction`, so it must be registered after the zkSync `debug` namespace
+    io.extend_with(DebugExtNamespaceT::to_delegate(debug_ext));
+    io.extend_with(txpool.to_delegate());
+    io.extend_with(anvil.to_delegate());
+    unknown_methods.set_known_methods(io.iter().map(|(name, _)| name.clone()));
+    io
+}
+
+/// Starts the JSON-RPC HTTP server, and returns a [ServerHandle] to it.
 #[allow(clippy::too_many_arguments)]
 async fn build_json_http<
     S: std::marker::Sync + std::marker::Send + 'static + ForkSource + std::fmt::Debug,
 >(
     addr: SocketAddr,
     log_level_filter: LevelFilter,
+    rpc_filter: RpcMethodFilter,
+    request_recorder: RequestRecorder,
+    unknown_methods: UnknownMethodHandler,
     node: InMemoryNode<S>,
-    net: NetNamespace,
+    net: NetNamespaceImpl<S>,
     config_api: ConfigurationApiNamespace<S>,
     evm: EvmNamespaceImpl<S>,
     zks: ZkMockNamespaceImpl<S>,
+    zks_ext: ZkMockNamespaceImpl<S>,
     hardhat: HardhatNamespaceImpl<S>,
     debug: DebugNamespaceImpl<S>,
-) -> tokio::task::JoinHandle<()> {
+    debug_ext: DebugNamespaceImpl<S>,
+    txpool: TxPoolNamespaceImpl<S>,
+    anvil: AnvilNamespaceImpl<S>,
+) -> ServerHandle {
     let (sender, recv) = oneshot::channel::<()>();
+    let (started_sender, started_recv) =
+        oneshot::channel::<(SocketAddr, jsonrpc_http_server::CloseHandle)>();
 
-    let io_handler = {
-        let mut io = MetaIoHandler::with_middleware(LoggingMiddleware::new(log_level_filter));
-        io.extend_with(node.to_delegate());
-        io.extend_with(net.to_delegate());
-        io.extend_with(config_api.to_delegate());
-        io.extend_with(evm.to_delegate());
-        io.extend_with(zks.to_delegate());
-        io.extend_with(hardhat.to_delegate());
-        io.extend_with(debug.to_delegate());
-        io
-    };
+    let io_handler = build_io_handler(
+        log_level_filter,
+        rpc_filter,
+        request_recorder,
+        unknown_methods,
+        node,
+        net,
+        config_api,
+        evm,
+        zks,
+        zks_ext,
+        hardhat,
+        debug,
+        debug_ext,
+        txpool,
+        anvil,
+    );
 
     std::thread::spawn(move || {
         let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -144,11 +245,141 @@ async fn build_json_http<
             .start_http(&addr)
             .unwrap();
 
+        let _ = started_sender.send((*server.address(), server.close_handle()));
         server.wait();
         let _ = sender;
     });
 
-    tokio::spawn(recv.map(drop))
+    let (addr, close_handle) = started_recv
+        .await
+        .expect("server thread stopped before binding");
+    ServerHandle {
+        addr,
+        close_handle,
+        stopped: tokio::spawn(recv.map(drop)),
+    }
+}
+
+/// Completes once the process receives SIGINT (Ctrl+C) or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            log::error!("failed listening for Ctrl+C: {}", err);
+            future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                log::error!("failed listening for SIGTERM: {}", err);
+                future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Returns the status printed by `--json-status` once the node listens on `port`.
+fn json_status(port: u16, node: &RwLock<InMemoryNodeInner<HttpForkSource>>) -> serde_json::Value {
+    let reader = node.read().expect("failed reading node");
+    let fork = reader
+        .fork_storage
+        .inner
+        .read()
+        .expect("failed reading fork storage")
+        .fork
+        .as_ref()
+        .map(|fork| (fork.fork_source.fork_url.clone(), fork.l2_miniblock));
+    serde_json::json!({
+        "port": port,
+        "chainId": reader.fork_storage.chain_id.0,
+        "forkUrl": fork.as_ref().map(|(fork_url, _)| fork_url),
+        "forkBlockNumber": fork.as_ref().map(|(_, block_number)| block_number),
+    })
+}
+
+/// Serves requests until the server stops on its own, or until `shutdown` completes - in which case the server is
+/// stopped gracefully. Afterwards, the chain state is dumped to `dump_state_on_exit`, if set.
+async fn run_until_shutdown<S: ForkSource + std::fmt::Debug>(
+    server: ServerHandle,
+    shutdown: impl Future<Output = ()>,
+    node: Arc<RwLock<InMemoryNodeInner<S>>>,
+    dump_state_on_exit: Option<&Path>,
+) -> anyhow::Result<()> {
+    let ServerHandle {
+        close_handle,
+        mut stopped,
+        ..
+    } = server;
+
+    tokio::select! {
+        result = &mut stopped => result?,
+        _ = shutdown => {
+            log::info!("Shutting down...");
+            close_handle.close();
+            stopped.await?;
+        }
+    }
+
+    if let Some(path) = dump_state_on_exit {
+        node.read()
+            .map_err(|err| anyhow::anyhow!("failed acquiring read lock: {}", err))?
+            .dump_state_to_file(path)
+            .map_err(anyhow::Error::msg)?;
+        log::info!("💾 Dumped state to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// A single call of a script run with `--exec`.
+#[derive(Debug, Deserialize)]
+struct ScriptCall {
+    method: String,
+    #[serde(default)]
+    params: Option<serde_json::Value>,
+}
+
+/// Runs the script at the given path - a JSON array of `{"method": ..., "params": [...]}` calls - against the
+/// handler, in order, and writes the result of each call to `out` as a line of JSON. Stops at the first failing call.
+///
+/// Returns the number of executed calls.
+async fn exec_script(
+    io: &MetaIoHandler<(), impl Middleware<()>>,
+    script: &Path,
+    out: &mut impl Write,
+) -> anyhow::Result<usize> {
+    let calls: Vec<ScriptCall> = serde_json::from_slice(&fs::read(script)?)?;
+    for (id, call) in calls.iter().enumerate() {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": call.method,
+            "params": call.params.clone().unwrap_or_else(|| serde_json::json!([])),
+        });
+        let response = io
+            .handle_request(&request.to_string(), ())
+            .await
+            .ok_or_else(|| anyhow::anyhow!("no response to {} call", call.method))?;
+        let response: serde_json::Value = serde_json::from_str(&response)?;
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("{} call failed: {}", call.method, error);
+        }
+        writeln!(out, "{}", response["result"])?;
+    }
+
+    Ok(calls.len())
 }
 
 /// Log filter level for the node.
@@ -187,8 +418,15 @@ struct Cli {
     #[command(subcommand)]
     command: Command,
     #[arg(long, default_value = "8011")]
-    /// Port to listen on - default: 8011
+    /// Port to listen on - default: 8011. Use `0` to bind a free port assigned by the OS.
     port: u16,
+    #[arg(long)]
+    /// File to write the port the node listens on to, once it is ready.
+    port_file: Option<PathBuf>,
+    #[arg(long)]
+    /// Prints a single JSON line with the `port`, `chainId`, `forkUrl` and `forkBlockNumber` of the node once it is
+    /// ready, instead of the banner - for harnesses to know when and where to connect.
+    json_status: bool,
     #[arg(long, default_value = "none")]
     /// Show call debug information
     show_calls: ShowCalls,
@@ -231,6 +469,214 @@ struct Cli {
     /// Cache directory location for `disk` cache - default: ".cache"
     #[arg(long, default_value = ".cache")]
     cache_dir: String,
+
+    /// Range of fork blocks (e.g. `100..200`, inclusive) to fetch and cache before serving requests.
+    /// Useful to populate a `disk` cache for `--offline` runs.
+    #[arg(long, value_parser = parse_block_range)]
+    warmup_blocks: Option<RangeInclusive<u64>>,
+
+    /// Comma-separated addresses whose state (nonce, balance and bytecode) is fetched from the fork on startup, so
+    /// that the rest of the run reads it locally.
+    #[arg(long, value_delimiter = ',')]
+    eager_fork_snapshot: Vec<H160>,
+
+    /// If true, fork data that is missing from the cache results in an error instead of a network request.
+    /// The fork block itself is still fetched on startup.
+    #[arg(long)]
+    offline: bool,
+
+    /// If true, logs every request made to the forked network, with its params and latency, and every request
+    /// served from the cache - e.g. to find why a forked test is slow.
+    #[arg(long)]
+    log_fork_calls: bool,
+
+    /// Header sent with every request to the forked network, as `Key: Value` - e.g. an API key of the RPC provider,
+    /// to keep it out of the URL. Can be repeated.
+    #[arg(long = "fork-header")]
+    fork_headers: Vec<String>,
+
+    /// Maximum number of requests per second sent to the forked network - requests above it are queued rather than
+    /// failed, to avoid being rate limited by the RPC provider. Default: unlimited.
+    #[arg(long)]
+    fork_rate_limit: Option<u32>,
+
+    /// Maximum size (in bytes) of a response from the forked network - larger responses are rejected with an error,
+    /// instead of being read whole into memory. Default: the limit of the HTTP client (10 MB).
+    #[arg(long)]
+    fork_max_response_bytes: Option<u32>,
+
+    /// Timestamp of the genesis block - default: 1000, or the fork block timestamp when forking
+    #[arg(long)]
+    genesis_timestamp: Option<u64>,
+
+    /// Number of the genesis block - default: 0, or the fork block number when forking (it can only be moved forward).
+    #[arg(long)]
+    genesis_block_number: Option<u64>,
+
+    /// Chain id of the network - default: 260, or the chain id of the replayed transaction.
+    #[arg(long)]
+    chain_id: Option<u16>,
+
+    /// Maximum gas limit of a single transaction - transactions above it are rejected before execution.
+    #[arg(long)]
+    max_tx_gas: Option<u64>,
+    /// Maximum gas the account (and paymaster) validation of a transaction can use - transactions above it fail
+    /// the validation.
+    #[arg(long)]
+    validation_gas_limit: Option<u64>,
+    /// Disables the refunds of unused gas - transactions are charged for their whole gas limit.
+    #[arg(long)]
+    no_refunds: bool,
+    /// Accepts transactions whatever their nonce, and executes them in the order they were submitted - e.g. for
+    /// fuzzing. The nonce of the sender is set to the nonce of each transaction right before it is executed.
+    #[arg(long)]
+    no_nonce_check: bool,
+    /// Accepts transactions with a zero gas price - e.g. to test gasless flows. They are rejected by default, like on
+    /// the network.
+    #[arg(long)]
+    allow_zero_gas_price: bool,
+    /// Impersonates every account, so that `eth_sendTransaction` accepts any `from` without a signature - like
+    /// `hardhat_impersonateAccount` for all the accounts.
+    #[arg(long)]
+    auto_impersonate: bool,
+    /// Burns the fees paid by transactions, instead of crediting them to the coinbase (operator) address.
+    #[arg(long)]
+    no_fee_accrual: bool,
+    /// Seals a block once the pubdata published by its transactions reaches this many bytes - the remaining
+    /// transactions are mined in the following blocks.
+    #[arg(long)]
+    max_pubdata_per_block: Option<u64>,
+    /// Maximum number of logs an `eth_getLogs` request can return - requests matching more fail, asking for a
+    /// narrower block range.
+    #[arg(long)]
+    max_logs_returned: Option<usize>,
+    /// Disables gas metering of calls - they ignore their gas limit and run with the maximum gas, to debug their
+    /// logic regardless of gas. For debugging only, never rely on it to test production behavior.
+    #[arg(long)]
+    disable_gas_metering: bool,
+    /// Sender of the `eth_call` requests that do not set `from` - default: the zero address.
+    #[arg(long)]
+    default_call_sender: Option<H160>,
+    /// Order in which the pending transactions of different senders are mined - the transactions of a sender are
+    /// always mined in the order of their nonce.
+    #[arg(long, value_enum, default_value = "fifo")]
+    mempool_order: MempoolOrder,
+    /// Maximum number of transactions waiting in the mempool. Once full, new transactions are rejected, unless they
+    /// pay a higher priority fee than the cheapest one, which is evicted.
+    #[arg(long)]
+    mempool_max: Option<usize>,
+    /// Maximum depth of the calls, counted from the called contract (at depth 1) - deeper calls are halted.
+    /// Default: bounded only by gas.
+    #[arg(long)]
+    max_call_depth: Option<usize>,
+
+    /// Symbol of the base token of the chain, in which the balances are logged.
+    #[arg(long, default_value = "ETH")]
+    base_token_symbol: String,
+    /// Decimals of the base token of the chain, used to log the balances in its units.
+    #[arg(long, default_value_t = 18, value_parser = clap::value_parser!(u8).range(0..=77))]
+    base_token_decimals: u8,
+
+    /// Mnemonic to derive additional dev accounts from, along `m/44'/60'/0'/0/i` - default: the Hardhat mnemonic,
+    /// resulting in the same accounts as Hardhat.
+    #[arg(long)]
+    mnemonic: Option<String>,
+
+    /// L1 gas price in wei - default: 50 gwei, or the L1 gas price of the fork block when forking.
+    #[arg(long)]
+    l1_gas_price: Option<u64>,
+
+    /// With automine, batches the transactions submitted within this window (in milliseconds) into a single block,
+    /// instead of mining a block per transaction - default: 0 (no batching).
+    #[arg(long, default_value = "0")]
+    automine_batch_window_ms: u64,
+
+    /// Timestamp increment (in seconds) of the empty blocks mined by `evm_mine`, unless overridden by
+    /// `evm_setNextBlockTimestamp` - default: 1.
+    #[arg(long, default_value = "1")]
+    block_time: u64,
+
+    /// Speeds up the mining of many empty blocks at once (e.g. with `hardhat_mine`): only the last block is sealed by
+    /// the VM, the preceding ones are synthesized.
+    #[arg(long)]
+    empty_seal_fast: bool,
+
+    /// Derives the addresses of the locally deployed contracts from a deployment counter, instead of the deployment
+    /// nonce of their deployer - so the same deployments yield the same addresses across runs and forks.
+    #[arg(long)]
+    deterministic_addresses: bool,
+
+    /// Protocol version the transactions are executed with - default: the latest supported version.
+    #[arg(long)]
+    protocol_version: Option<u16>,
+
+    /// File to dump the chain state to when the node shuts down (on SIGINT or SIGTERM), in the format of
+    /// `hardhat_dumpState`.
+    #[arg(long)]
+    dump_state_on_exit: Option<PathBuf>,
+
+    /// Interval (in seconds) at which the chain state is dumped into a new file in `--state-dir`, in the format of
+    /// `hardhat_dumpState` - default: no periodic dumps.
+    #[arg(long)]
+    state_interval: Option<u64>,
+
+    /// Directory the periodic state dumps of `--state-interval` are written to.
+    #[arg(long, default_value = "state")]
+    state_dir: PathBuf,
+
+    /// Number of the latest periodic state dumps to keep - older dumps are removed.
+    #[arg(long, default_value = "5")]
+    state_keep: usize,
+
+    /// Comma-separated JSON-RPC methods that can be called, either by name (`eth_chainId`) or by namespace
+    /// (`eth_*`) - default: all methods. Other methods fail with a `method not found` error.
+    #[arg(long, value_delimiter = ',')]
+    rpc_allow: Vec<String>,
+
+    /// Comma-separated JSON-RPC methods that can not be called, either by name (`hardhat_setBalance`) or by
+    /// namespace (`hardhat_*`). They fail with a `method not found` error.
+    #[arg(long, value_delimiter = ',')]
+    rpc_deny: Vec<String>,
+
+    /// Adds the closest known method to the `method not found` errors of unknown methods, to help spotting typos.
+    #[arg(long)]
+    strict_methods: bool,
+
+    /// File to append every incoming JSON-RPC request to (as a line of JSON, with its method, params and timestamp),
+    /// to reproduce a session with the `replay_requests` command.
+    #[arg(long)]
+    record_requests: Option<PathBuf>,
+
+    /// Comma-separated JSON-RPC methods whose params are not recorded by `--record-requests`, either by name
+    /// (`eth_sendRawTransaction`) or by namespace (`eth_*`). They are skipped when replaying.
+    #[arg(long, value_delimiter = ',')]
+    redact_params: Vec<String>,
+
+    /// File with a JSON array of JSON-RPC calls (`{"method": ..., "params": [...]}`) to run against the node, in
+    /// order. The result of each call is printed as a line of JSON, and the node exits without starting the server.
+    #[arg(long)]
+    exec: Option<PathBuf>,
+}
+
+/// Parses an inclusive block range in the `<from>..<to>` format.
+fn parse_block_range(range: &str) -> Result<RangeInclusive<u64>, String> {
+    let (from, to) = range
+        .split_once("..")
+        .ok_or_else(|| format!("invalid block range '{}', expected <from>..<to>", range))?;
+    let from = from
+        .parse::<u64>()
+        .map_err(|err| format!("invalid block number '{}': {}", from, err))?;
+    let to = to
+        .parse::<u64>()
+        .map_err(|err| format!("invalid block number '{}': {}", to, err))?;
+    if from > to {
+        return Err(format!(
+            "invalid block range '{}', <from> is after <to>",
+            range
+        ));
+    }
+
+    Ok(from..=to)
 }
 
 #[derive(Debug, Subcommand)]
@@ -244,6 +690,9 @@ enum Command {
     /// Starts a local network that is a fork of another network, and replays a given TX on it.
     #[command(name = "replay_tx")]
     ReplayTx(ReplayArgs),
+    /// Starts a new empty local network, and replays the requests recorded by `--record-requests` on it, in order.
+    #[command(name = "replay_requests")]
+    ReplayRequests(ReplayRequestsArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -259,6 +708,10 @@ struct ForkArgs {
     // Fork at a given L2 miniblock height.
     // If not set - will use the current finalized block from the network.
     fork_at: Option<u64>,
+    /// Fork at the block of the given transaction, and replay the transactions preceding it in that block,
+    /// so the state matches the one right before the transaction executes.
+    #[arg(long, conflicts_with = "fork_at")]
+    fork_transaction_hash: Option<H256>,
 }
 #[derive(Debug, Parser)]
 struct ReplayArgs {
@@ -272,6 +725,11 @@ struct ReplayArgs {
     /// Transaction hash to replay.
     tx: H256,
 }
+#[derive(Debug, Parser)]
+struct ReplayRequestsArgs {
+    /// File with the requests recorded by `--record-requests`.
+    path: PathBuf,
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -319,26 +777,110 @@ async fn main() -> anyhow::Result<()> {
     // Initialize the subscriber
     tracing::subscriber::set_global_default(subscriber).expect("failed to set tracing subscriber");
 
-    let fork_details = match &opt.command {
-        Command::Run => None,
-        Command::Fork(fork) => {
-            Some(ForkDetails::from_network(&fork.network, fork.fork_at, cache_config).await)
+    let fork_headers = parse_fork_headers(&opt.fork_headers)
+        .map_err(|err| anyhow::anyhow!("failed parsing --fork-header: {}", err))?;
+    if !fork_headers.is_empty() && matches!(opt.command, Command::Run | Command::ReplayRequests(_))
+    {
+        log::warn!("--fork-header is ignored, as the node is not forking");
+    }
+    let mut fork_details = match &opt.command {
+        Command::Run | Command::ReplayRequests(_) => None,
+        Command::Fork(fork) => match fork.fork_transaction_hash {
+            Some(tx) => Some(
+                ForkDetails::from_network_tx_with_headers(
+                    &fork.network,
+                    tx,
+                    cache_config,
+                    fork_headers,
+                )
+                .await,
+            ),
+            None => Some(
+                ForkDetails::from_network_with_headers(
+                    &fork.network,
+                    fork.fork_at,
+                    cache_config,
+                    fork_headers,
+                )
+                .await,
+            ),
+        },
+        Command::ReplayTx(replay_tx) => Some(
+            ForkDetails::from_network_tx_with_headers(
+                &replay_tx.network,
+                replay_tx.tx,
+                cache_config,
+                fork_headers,
+            )
+            .await,
+        ),
+    };
+    if let Some(blocks) = opt.warmup_blocks.clone() {
+        match fork_details.as_ref() {
+            Some(fork_details) => {
+                log::info!(
+                    "Warming up cache for blocks #{} to #{}",
+                    blocks.start(),
+                    blocks.end()
+                );
+                if let Err(err) = fork_details.fork_source.warmup(blocks) {
+                    return Err(anyhow::anyhow!("failed warming up cache: {:?}", err));
+                }
+            }
+            None => log::warn!("--warmup-blocks is ignored, as the node is not forking"),
         }
-        Command::ReplayTx(replay_tx) => {
-            Some(ForkDetails::from_network_tx(&replay_tx.network, replay_tx.tx, cache_config).await)
+    }
+    if !opt.eager_fork_snapshot.is_empty() && fork_details.is_none() {
+        log::warn!("--eager-fork-snapshot is ignored, as the node is not forking");
+    }
+    if opt.log_fork_calls {
+        match fork_details.as_mut() {
+            Some(fork_details) => fork_details.fork_source.set_log_calls(true),
+            None => log::warn!("--log-fork-calls is ignored, as the node is not forking"),
         }
-    };
+    }
+    if let Some(fork_rate_limit) = opt.fork_rate_limit {
+        match fork_details.as_mut() {
+            Some(fork_details) => fork_details.fork_source.set_rate_limit(fork_rate_limit),
+            None => log::warn!("--fork-rate-limit is ignored, as the node is not forking"),
+        }
+    }
+    if let Some(fork_max_response_bytes) = opt.fork_max_response_bytes {
+        match fork_details.as_mut() {
+            Some(fork_details) => fork_details
+                .fork_source
+                .set_max_response_bytes(fork_max_response_bytes),
+            None => log::warn!("--fork-max-response-bytes is ignored, as the node is not forking"),
+        }
+    }
+    if opt.offline {
+        if let Some(fork_details) = fork_details.as_mut() {
+            log::info!("Offline mode enabled, only cached fork data will be used");
+            fork_details.fork_source.set_offline(true);
+        }
+    }
 
     // If we're replaying the transaction, we need to sync to the previous block
     // and then replay all the transactions that happened in
-    let transactions_to_replay = if let Command::ReplayTx(replay_tx) = &opt.command {
-        fork_details
-            .as_ref()
-            .unwrap()
-            .get_earlier_transactions_in_same_block(replay_tx.tx)
-            .await
-    } else {
-        vec![]
+    let transactions_to_replay = match &opt.command {
+        Command::ReplayTx(replay_tx) => {
+            fork_details
+                .as_ref()
+                .unwrap()
+                .get_earlier_transactions_in_same_block(replay_tx.tx)
+                .await
+        }
+        Command::Fork(ForkArgs {
+            fork_transaction_hash: Some(tx),
+            ..
+        }) => {
+            fork_details
+                .as_ref()
+                .unwrap()
+                .get_preceding_transactions_in_same_block(*tx)
+                .await
+        }
+        _ => vec![],
     };
     let system_contracts_options = if opt.dev_use_local_contracts {
         system_contracts::Options::Local
@@ -346,56 +888,590 @@ async fn main() -> anyhow::Result<()> {
         system_contracts::Options::BuiltIn
     };
 
-    let node = InMemoryNode::new(
-        fork_details,
-        opt.show_calls,
-        opt.show_storage_logs,
-        opt.show_vm_details,
-        opt.show_gas_details,
-        opt.resolve_hashes,
-        &system_contracts_options,
-    );
+    let mut node_builder = InMemoryNodeBuilder::default()
+        .show_calls(opt.show_calls)
+        .show_storage_logs(opt.show_storage_logs)
+        .show_vm_details(opt.show_vm_details)
+        .show_gas_details(opt.show_gas_details)
+        .resolve_hashes(opt.resolve_hashes)
+        .system_contracts_options(system_contracts_options);
+    if let Some(fork_details) = fork_details {
+        node::validate_fork_genesis(
+            fork_details.l2_miniblock,
+            fork_details.block_timestamp,
+            opt.genesis_timestamp,
+            opt.genesis_block_number,
+        )
+        .map_err(|err| anyhow::anyhow!("invalid genesis override: {}", err))?;
+        node_builder = node_builder.fork(fork_details);
+    }
+    if let Some(timestamp) = opt.genesis_timestamp {
+        node_builder = node_builder.genesis_timestamp(timestamp);
+    }
+    if let Some(block_number) = opt.genesis_block_number {
+        node_builder = node_builder.genesis_block_number(block_number);
+    }
+    if let Some(chain_id) = opt.chain_id {
+        node_builder = node_builder.chain_id(chain_id);
+    }
+    if let Some(max_tx_gas) = opt.max_tx_gas {
+        node_builder = node_builder.max_tx_gas(max_tx_gas);
+    }
+    if let Some(validation_gas_limit) = opt.validation_gas_limit {
+        node_builder = node_builder.validation_gas_limit(validation_gas_limit);
+    }
+    if opt.no_refunds {
+        node_builder = node_builder.refunds_enabled(false);
+    }
+    if opt.no_nonce_check {
+        node_builder = node_builder.nonce_check(false);
+    }
+    if opt.allow_zero_gas_price {
+        node_builder = node_builder.allow_zero_gas_price(true);
+    }
+    if opt.auto_impersonate {
+        node_builder = node_builder.auto_impersonate(true);
+    }
+    if opt.no_fee_accrual {
+        node_builder = node_builder.fee_accrual(false);
+    }
+    if let Some(max_pubdata_per_block) = opt.max_pubdata_per_block {
+        node_builder = node_builder.max_pubdata_per_block(max_pubdata_per_block);
+    }
+    if let Some(max_logs_returned) = opt.max_logs_returned {
+        node_builder = node_builder.max_logs_returned(max_logs_returned);
+    }
+    if opt.disable_gas_metering {
+        log::warn!(
+            "Gas metering of calls is disabled - for debugging only, not for production use"
+        );
+        node_builder = node_builder.gas_metering(false);
+    }
+    if let Some(default_call_sender) = opt.default_call_sender {
+        node_builder = node_builder.default_call_sender(default_call_sender);
+    }
+    node_builder = node_builder.mempool_order(opt.mempool_order);
+    if let Some(mempool_max) = opt.mempool_max {
+        node_builder = node_builder.mempool_max(mempool_max);
+    }
+    if let Some(max_call_depth) = opt.max_call_depth {
+        node_builder = node_builder.max_call_depth(max_call_depth);
+    }
+    let base_token = BaseToken {
+        symbol: opt.base_token_symbol.clone(),
+        decimals: opt.base_token_decimals,
+    };
+    node_builder = node_builder.base_token(base_token.clone());
+    if let Some(l1_gas_price) = opt.l1_gas_price {
+        node_builder = node_builder.l1_gas_price(l1_gas_price);
+    }
+    node_builder = node_builder.automine_batch_window_ms(opt.automine_batch_window_ms);
+    node_builder = node_builder.block_time(opt.block_time);
+    node_builder = node_builder.empty_seal_fast(opt.empty_seal_fast);
+    node_builder = node_builder.deterministic_addresses(opt.deterministic_addresses);
+    if let Some(protocol_version) = opt.protocol_version {
+        let protocol_version = ProtocolVersionId::try_from(protocol_version)
+            .ok()
+            .filter(|version| fork::supported_protocol_versions(*version))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unsupported protocol version {}, this binary supports versions {}",
+                    protocol_version,
+                    fork::supported_versions_to_string()
+                )
+            })?;
+        node_builder = node_builder.protocol_version(protocol_version);
+    }
+    let node = node_builder.build();
+
+    if !opt.eager_fork_snapshot.is_empty() {
+        log::info!(
+            "Fetching the state of {} accounts from the fork",
+            opt.eager_fork_snapshot.len()
+        );
+        match node.get_inner().read() {
+            Ok(inner) => inner
+                .fork_storage
+                .snapshot_accounts(&opt.eager_fork_snapshot),
+            Err(err) => log::error!("failed acquiring read lock: {}", err),
+        }
+    }
 
     if !transactions_to_replay.is_empty() {
         let _ = node.apply_txs(transactions_to_replay);
     }
+    if opt.automine_batch_window_ms > 0 {
+        node.spawn_automine_batch_miner();
+    }
+    node.spawn_interval_miner();
+    if let Some(state_interval) = opt.state_interval {
+        fs::create_dir_all(&opt.state_dir)?;
+        node.spawn_state_dumper(
+            opt.state_dir.clone(),
+            Duration::from_secs(state_interval),
+            opt.state_keep,
+        );
+    }
 
-    log::info!("Rich Accounts");
-    log::info!("=============");
+    let show_banner = !opt.json_status;
+    if show_banner {
+        log::info!("Rich Accounts");
+        log::info!("=============");
+    }
     for (index, wallet) in RICH_WALLETS.iter().enumerate() {
         let address = wallet.0;
         let private_key = wallet.1;
         node.set_rich_account(H160::from_str(address).unwrap());
-        log::info!("Account #{}: {} (1_000_000_000_000 ETH)", index, address);
-        log::info!("Private Key: {}", private_key);
-        log::info!("");
+        if show_banner {
+            log::info!(
+                "Account #{}: {} ({})",
+                index,
+                address,
+                base_token.format_amount(U256::from(RICH_ACCOUNT_BALANCE))
+            );
+            log::info!("Private Key: {}", private_key);
+            log::info!("");
+        }
+    }
+    let mnemonic = opt.mnemonic.as_deref().unwrap_or(utils::DEFAULT_MNEMONIC);
+    let mnemonic_accounts = utils::derive_dev_accounts(mnemonic, MNEMONIC_ACCOUNTS_COUNT)
+        .map_err(anyhow::Error::msg)?;
+    for (index, (address, private_key)) in mnemonic_accounts.into_iter().enumerate() {
+        node.set_rich_account(address);
+        if show_banner {
+            log::info!(
+                "Account #{}: {:?} ({})",
+                RICH_WALLETS.len() + index,
+                address,
+                base_token.format_amount(U256::from(RICH_ACCOUNT_BALANCE))
+            );
+            log::info!("Private Key: {:?}", private_key);
+            log::info!("");
+        }
     }
 
-    let net = NetNamespace::new(L2ChainId(TEST_NODE_NETWORK_ID));
+    let net = NetNamespaceImpl::new(node.get_inner());
     let config_api = ConfigurationApiNamespace::new(node.get_inner());
     let evm = EvmNamespaceImpl::new(node.get_inner());
     let zks = ZkMockNamespaceImpl::new(node.get_inner());
+    let zks_ext = ZkMockNamespaceImpl::new(node.get_inner());
     let hardhat = HardhatNamespaceImpl::new(node.get_inner());
     let debug = DebugNamespaceImpl::new(node.get_inner());
+    let debug_ext = DebugNamespaceImpl::new(node.get_inner());
+    let txpool = TxPoolNamespaceImpl::new(node.get_inner());
+    let anvil = AnvilNamespaceImpl::new(node.get_inner());
+
+    let request_recorder = match &opt.record_requests {
+        Some(path) => RequestRecorder::new(path, opt.redact_params.clone())?,
+        None => RequestRecorder::default(),
+    };
 
-    let threads = build_json_http(
+    if let Some(script) = &opt.exec {
+        let io = build_io_handler(
+            log_level_filter,
+            RpcMethodFilter::new(opt.rpc_allow.clone(), opt.rpc_deny.clone()),
+            request_recorder,
+            UnknownMethodHandler::new(opt.strict_methods),
+            node,
+            net,
+            config_api,
+            evm,
+            zks,
+            zks_ext,
+            hardhat,
+            debug,
+            debug_ext,
+            txpool,
+            anvil,
+        );
+        let executed = exec_script(&io, script, &mut std::io::stdout().lock()).await?;
+        log::info!("Executed {} calls from {}", executed, script.display());
+        return Ok(());
+    }
+
+    let inner = node.get_inner();
+    let server = build_json_http(
         SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), opt.port),
         log_level_filter,
+        RpcMethodFilter::new(opt.rpc_allow.clone(), opt.rpc_deny.clone()),
+        request_recorder,
+        UnknownMethodHandler::new(opt.strict_methods),
         node,
         net,
         config_api,
         evm,
         zks,
+        zks_ext,
         hardhat,
         debug,
+        debug_ext,
+        txpool,
+        anvil,
     )
     .await;
 
-    log::info!("========================================");
-    log::info!("  Node is ready at 127.0.0.1:{}", opt.port);
-    log::info!("========================================");
+    if let Command::ReplayRequests(ReplayRequestsArgs { path }) = &opt.command {
+        let replayed =
+            request_log::replay_requests(path, &format!("http://127.0.0.1:{}", server.addr.port()))
+                .await?;
+        log::info!("Replayed {} requests from {}", replayed, path.display());
+    }
 
-    future::select_all(vec![threads]).await.0.unwrap();
+    if show_banner {
+        log::info!("========================================");
+        log::info!("  Node is ready at 127.0.0.1:{}", server.addr.port());
+        log::info!("========================================");
 
-    Ok(())
+        // Printed regardless of the log level, so that test harnesses can discover the port assigned by the OS.
+        println!("Listening on 127.0.0.1:{}", server.addr.port());
+    } else {
+        println!("{}", json_status(server.addr.port(), &inner));
+    }
+    if let Some(port_file) = &opt.port_file {
+        fs::write(port_file, server.addr.port().to_string())?;
+    }
+
+    run_until_shutdown(
+        server,
+        shutdown_signal(),
+        inner,
+        opt.dump_state_on_exit.as_deref(),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The options of the servers started by [start_server] - by default, the ones of the CLI.
+    #[derive(Default)]
+    struct ServerOptions {
+        rpc_filter: RpcMethodFilter,
+        request_recorder: RequestRecorder,
+    }
+
+    /// Starts the JSON-RPC HTTP server of `node` on a port assigned by the OS.
+    async fn start_server(
+        node: InMemoryNode<HttpForkSource>,
+        options: ServerOptions,
+    ) -> ServerHandle {
+        let inner = node.get_inner();
+        build_json_http(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            LevelFilter::Off,
+            options.rpc_filter,
+            options.request_recorder,
+            UnknownMethodHandler::new(false),
+            node,
+            NetNamespaceImpl::new(inner.clone()),
+            ConfigurationApiNamespace::new(inner.clone()),
+            EvmNamespaceImpl::new(inner.clone()),
+            ZkMockNamespaceImpl::new(inner.clone()),
+            ZkMockNamespaceImpl::new(inner.clone()),
+            HardhatNamespaceImpl::new(inner.clone()),
+            DebugNamespaceImpl::new(inner.clone()),
+            DebugNamespaceImpl::new(inner.clone()),
+            TxPoolNamespaceImpl::new(inner.clone()),
+            AnvilNamespaceImpl::new(inner),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_build_json_http_binds_os_assigned_port() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let server = start_server(node, Default::default()).await;
+        assert_ne!(0, server.addr.port());
+
+        let response = reqwest::Client::new()
+            .post(format!("http://127.0.0.1:{}", server.addr.port()))
+            .header("content-type", "application/json")
+            .body(r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#)
+            .send()
+            .await
+            .expect("failed sending request")
+            .text()
+            .await
+            .expect("failed reading response");
+        let response: serde_json::Value =
+            serde_json::from_str(&response).expect("failed parsing response");
+
+        assert_eq!(serde_json::json!("0x104"), response["result"]);
+    }
+
+    #[tokio::test]
+    async fn test_json_status_holds_the_port_and_chain_id() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let inner = node.get_inner();
+
+        let server = start_server(node, Default::default()).await;
+
+        let status = json_status(server.addr.port(), &inner);
+        assert_eq!(
+            serde_json::json!({
+                "port": server.addr.port(),
+                "chainId": 260,
+                "forkUrl": null,
+                "forkBlockNumber": null,
+            }),
+            status
+        );
+        // printed as a single line
+        assert!(!status.to_string().contains('\n'));
+    }
+
+    #[tokio::test]
+    async fn test_json_status_holds_the_fork_details() {
+        let mock_server = testing::MockServer::run_with_config(testing::ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let status = json_status(8011, &node.get_inner());
+        assert_eq!(serde_json::json!(8011), status["port"]);
+        assert_eq!(serde_json::json!(mock_server.url()), status["forkUrl"]);
+        assert_eq!(serde_json::json!(10), status["forkBlockNumber"]);
+    }
+
+    #[tokio::test]
+    async fn test_build_json_http_rejects_denied_methods() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let server = start_server(
+            node,
+            ServerOptions {
+                rpc_filter: RpcMethodFilter::new(vec![], vec!["hardhat_setBalance".to_string()]),
+                ..Default::default()
+            },
+        )
+        .await;
+        let port = server.addr.port();
+        let call = |body: &'static str| async move {
+            let response = reqwest::Client::new()
+                .post(format!("http://127.0.0.1:{}", port))
+                .header("content-type", "application/json")
+                .body(body)
+                .send()
+                .await
+                .expect("failed sending request")
+                .text()
+                .await
+                .expect("failed reading response");
+            serde_json::from_str::<serde_json::Value>(&response).expect("failed parsing response")
+        };
+
+        let denied = call(
+            r#"{"jsonrpc":"2.0","id":1,"method":"hardhat_setBalance","params":["0x0000000000000000000000000000000000000001","0x1"]}"#,
+        )
+        .await;
+        let allowed = call(r#"{"jsonrpc":"2.0","id":2,"method":"eth_chainId","params":[]}"#).await;
+
+        assert_eq!(
+            serde_json::json!(jsonrpc_core::ErrorCode::MethodNotFound.code()),
+            denied["error"]["code"]
+        );
+        assert_eq!(serde_json::json!("0x104"), allowed["result"]);
+    }
+
+    #[tokio::test]
+    async fn test_replay_requests_reproduces_recorded_state() {
+        async fn start(request_recorder: RequestRecorder) -> ServerHandle {
+            start_server(
+                InMemoryNode::<HttpForkSource>::default(),
+                ServerOptions {
+                    request_recorder,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+        async fn call(port: u16, method: &str, params: serde_json::Value) -> serde_json::Value {
+            let response = reqwest::Client::new()
+                .post(format!("http://127.0.0.1:{}", port))
+                .header("content-type", "application/json")
+                .body(
+                    serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params})
+                        .to_string(),
+                )
+                .send()
+                .await
+                .expect("failed sending request")
+                .text()
+                .await
+                .expect("failed reading response");
+            serde_json::from_str::<serde_json::Value>(&response).expect("failed parsing response")
+                ["result"]
+                .clone()
+        }
+
+        let temp_dir = tempdir::TempDir::new("requests").expect("failed creating temporary dir");
+        let path = temp_dir.path().join("requests.jsonl");
+        let address = "0x0000000000000000000000000000000000000001";
+        let recorded =
+            start(RequestRecorder::new(&path, vec![]).expect("failed creating recorder"))
+                .await
+                .addr
+                .port();
+        call(
+            recorded,
+            "hardhat_setBalance",
+            serde_json::json!([address, "0x1234"]),
+        )
+        .await;
+        call(recorded, "evm_mine", serde_json::json!([])).await;
+        call(recorded, "evm_mine", serde_json::json!([])).await;
+
+        let replayed = start(RequestRecorder::default()).await.addr.port();
+        let replayed_count =
+            request_log::replay_requests(&path, &format!("http://127.0.0.1:{}", replayed))
+                .await
+                .expect("failed replaying requests");
+
+        assert_eq!(3, replayed_count);
+        let balance_params = serde_json::json!([address, "latest"]);
+        assert_eq!(
+            serde_json::json!("0x1234"),
+            call(replayed, "eth_getBalance", balance_params.clone()).await
+        );
+        assert_eq!(
+            call(recorded, "eth_getBalance", balance_params).await,
+            call(
+                replayed,
+                "eth_getBalance",
+                serde_json::json!([address, "latest"])
+            )
+            .await
+        );
+        assert_eq!(
+            call(recorded, "eth_blockNumber", serde_json::json!([])).await,
+            call(replayed, "eth_blockNumber", serde_json::json!([])).await
+        );
+    }
+
+    #[test]
+    fn test_replay_requests_is_a_command() {
+        let cli = Cli::try_parse_from(["era_test_node", "replay_requests", "requests.jsonl"])
+            .expect("failed parsing arguments");
+        assert!(matches!(
+            cli.command,
+            Command::ReplayRequests(ReplayRequestsArgs { path }) if path.as_path() == Path::new("requests.jsonl")
+        ));
+        assert!(
+            Cli::try_parse_from(["era_test_node", "--replay-requests=requests.jsonl", "run"])
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_script_prints_the_result_of_each_call() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let inner = node.get_inner();
+        let io = build_io_handler(
+            LevelFilter::Off,
+            RpcMethodFilter::default(),
+            RequestRecorder::default(),
+            UnknownMethodHandler::new(false),
+            node,
+            NetNamespaceImpl::new(inner.clone()),
+            ConfigurationApiNamespace::new(inner.clone()),
+            EvmNamespaceImpl::new(inner.clone()),
+            ZkMockNamespaceImpl::new(inner.clone()),
+            ZkMockNamespaceImpl::new(inner.clone()),
+            HardhatNamespaceImpl::new(inner.clone()),
+            DebugNamespaceImpl::new(inner.clone()),
+            DebugNamespaceImpl::new(inner.clone()),
+            TxPoolNamespaceImpl::new(inner.clone()),
+            AnvilNamespaceImpl::new(inner),
+        );
+        let temp_dir = tempdir::TempDir::new("exec").expect("failed creating temporary dir");
+        let script = temp_dir.path().join("script.json");
+        let address = "0x0000000000000000000000000000000000000001";
+        fs::write(
+            &script,
+            serde_json::json!([
+                {"method": "eth_chainId"},
+                {"method": "hardhat_setBalance", "params": [address, "0x1234"]},
+                {"method": "eth_getBalance", "params": [address, "latest"]},
+            ])
+            .to_string(),
+        )
+        .expect("failed writing script");
+
+        let mut out = Vec::new();
+        let executed = exec_script(&io, &script, &mut out)
+            .await
+            .expect("failed executing script");
+
+        assert_eq!(3, executed);
+        assert_eq!(
+            "\"0x104\"\ntrue\n\"0x1234\"\n",
+            String::from_utf8(out).expect("invalid output")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_until_shutdown_stops_server_and_dumps_state() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let inner = node.get_inner();
+        let private_key = H256::repeat_byte(0x1);
+        let sender = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(sender);
+        node.submit_tx(testing::build_l2_tx(&private_key, zksync_types::Nonce(0)))
+            .expect("failed submitting tx");
+
+        let server = start_server(node, Default::default()).await;
+        let port = server.addr.port();
+        let temp_dir = tempdir::TempDir::new("state").expect("failed creating temporary dir");
+        let dump_path = temp_dir.path().join("state.json");
+
+        let (shutdown_sender, shutdown_recv) = oneshot::channel::<()>();
+        shutdown_sender
+            .send(())
+            .expect("failed sending shutdown signal");
+        run_until_shutdown(
+            server,
+            shutdown_recv.map(drop),
+            inner.clone(),
+            Some(&dump_path),
+        )
+        .await
+        .expect("failed shutting down");
+
+        let state: node::SerializableState =
+            serde_json::from_slice(&fs::read(&dump_path).expect("failed reading dump file"))
+                .expect("failed parsing dump file");
+        assert_eq!(
+            inner.read().unwrap().current_miniblock,
+            state.current_miniblock
+        );
+        assert_eq!(
+            inner.read().unwrap().current_miniblock_hash,
+            state.current_miniblock_hash
+        );
+        let restored = InMemoryNode::<HttpForkSource>::default();
+        restored
+            .get_inner()
+            .write()
+            .unwrap()
+            .load_state(state)
+            .expect("failed loading dumped state");
+
+        assert!(
+            std::net::TcpStream::connect(("127.0.0.1", port)).is_err(),
+            "server must not accept connections after shutdown"
+        );
+    }
 }
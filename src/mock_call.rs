@@ -0,0 +1,134 @@
+//! Tracer returning the mocked responses of calls instead of executing them - whether the calls are made directly by
+//! the transaction, or by the contracts it calls.
+use vm::{
+    zk_evm::{
+        abstractions::{EventSink, Memory, PrecompilesProcessor, Storage},
+        aux_structures::Timestamp,
+        flags::Flags,
+        vm_state::PrimitiveValue,
+        zkevm_opcode_defs::FatPointer,
+    },
+    BootloaderState, DynTracer, ExecutionEndTracer, ExecutionProcessing, HistoryMode,
+    VmExecutionResultAndLogs, VmTracer, ZkSyncVmState,
+};
+use zksync_basic_types::{Address, U256};
+use zksync_state::WriteStorage;
+
+use crate::node::MockedCall;
+
+/// The heap of a frame is the page following its calldata page, which follows its code page - see
+/// `CallStackEntry::heap_page_from_base` of the VM.
+const HEAP_PAGE_FROM_BASE: u32 = 2;
+
+/// Returns the mocked response of a call to `address` with the given `calldata`, if any - the most specific one
+/// (i.e. with the longest calldata) if several of the `mocked_calls` match.
+pub fn find_mocked_call<'a>(
+    mocked_calls: &'a [MockedCall],
+    address: Address,
+    calldata: &[u8],
+) -> Option<&'a MockedCall> {
+    mocked_calls
+        .iter()
+        .filter(|mock| mock.address == address && calldata.starts_with(&mock.calldata))
+        .max_by_key(|mock| mock.calldata.len())
+}
+
+/// Returns from the frames of the calls matching the `mocked_calls` as soon as they are entered - with the mocked
+/// data, as if the called contract returned it. The gas of the frame is refunded to its caller.
+pub struct MockedCallsTracer {
+    mocked_calls: Vec<MockedCall>,
+    depth: usize,
+}
+
+impl MockedCallsTracer {
+    pub fn new(mocked_calls: Vec<MockedCall>) -> Self {
+        Self {
+            mocked_calls,
+            depth: 0,
+        }
+    }
+}
+
+impl<S, H: HistoryMode> DynTracer<S, H> for MockedCallsTracer {}
+
+impl<H: HistoryMode> ExecutionEndTracer<H> for MockedCallsTracer {}
+
+impl<S: WriteStorage, H: HistoryMode> ExecutionProcessing<S, H> for MockedCallsTracer {
+    fn after_cycle(
+        &mut self,
+        state: &mut ZkSyncVmState<S, H>,
+        _bootloader_state: &mut BootloaderState,
+    ) {
+        let depth = state.local_state.callstack.depth();
+        let entered_frame = depth > self.depth;
+        self.depth = depth;
+        let frame = &state.local_state.callstack.current;
+        // the calldata of the frame is passed in r1
+        let calldata_register = state.local_state.registers[0];
+        if !entered_frame || frame.is_local_frame || !calldata_register.is_pointer {
+            return;
+        }
+        let (code_address, base_page) = (frame.code_address, frame.base_memory_page);
+
+        let calldata_pointer = FatPointer::from_u256(calldata_register.value);
+        let calldata = state.memory.read_unaligned_bytes(
+            calldata_pointer.memory_page as usize,
+            (calldata_pointer.start + calldata_pointer.offset) as usize,
+            (calldata_pointer.length - calldata_pointer.offset) as usize,
+        );
+        let mock = match find_mocked_call(&self.mocked_calls, code_address, &calldata) {
+            Some(mock) => mock,
+            None => return,
+        };
+        log::info!(
+            "returning the mocked response of the call to {:?}",
+            mock.address
+        );
+
+        // the return data is written to the heap of the frame, as the called contract would do
+        let timestamp = Timestamp(state.local_state.timestamp);
+        let heap_page = base_page.0 + HEAP_PAGE_FROM_BASE;
+        let words = mock
+            .return_data
+            .chunks(32)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut word = [0u8; 32];
+                word[..chunk.len()].copy_from_slice(chunk);
+                (index, U256::from_big_endian(&word))
+            })
+            .collect();
+        state
+            .memory
+            .populate_page(heap_page as usize, words, timestamp);
+        let returndata_pointer = FatPointer {
+            offset: 0,
+            memory_page: heap_page,
+            start: 0,
+            length: mock.return_data.len() as u32,
+        };
+
+        // then the frame returns to its caller - which resumes with the pointer to the return data in r1
+        let mocked_frame = state.local_state.callstack.pop_and_get();
+        state.local_state.callstack.current.ergs_remaining += mocked_frame.ergs_remaining;
+        for register in state.local_state.registers.iter_mut() {
+            *register = PrimitiveValue::empty();
+        }
+        state.local_state.registers[0] = PrimitiveValue {
+            value: returndata_pointer.to_u256(),
+            is_pointer: true,
+        };
+        state.local_state.flags = Flags::empty();
+        state
+            .memory
+            .finish_global_frame(base_page, returndata_pointer, timestamp);
+        state.storage.finish_frame(timestamp, false);
+        state.event_sink.finish_frame(false, timestamp);
+        state.precompiles_processor.finish_frame(false);
+        self.depth = state.local_state.callstack.depth();
+    }
+}
+
+impl<S: WriteStorage, H: HistoryMode> VmTracer<S, H> for MockedCallsTracer {
+    fn save_results(&mut self, _result: &mut VmExecutionResultAndLogs) {}
+}
@@ -1,38 +1,63 @@
-// Built-in uses
+use std::sync::{Arc, RwLock};
 
-// External uses
 use jsonrpc_core::Result;
-use jsonrpc_derive::rpc;
+use zksync_basic_types::U256;
+use zksync_core::api_server::web3::backend_jsonrpc::{
+    error::into_jsrpc_error, namespaces::net::NetNamespaceT,
+};
+use zksync_web3_decl::error::Web3Error;
 
-// Workspace uses
-use zksync_types::U256;
+use crate::{fork::ForkSource, node::InMemoryNodeInner};
 
-// Local uses
-use crate::web3::namespaces::NetNamespace;
-use crate::node::TEST_NODE_NETWORK_ID;
-
-#[rpc]
-pub trait NetNamespaceT {
-    #[rpc(name = "net_version", returns = "String")]
-    fn net_version(&self) -> Result<String>;
-
-    #[rpc(name = "net_peerCount", returns = "U256")]
-    fn net_peer_count(&self) -> Result<U256>;
+/// Implementation of NetNamespace, reporting the chain id configured for the node.
+pub struct NetNamespaceImpl<S> {
+    node: Arc<RwLock<InMemoryNodeInner<S>>>,
+}
 
-    #[rpc(name = "net_listening", returns = "bool")]
-    fn net_listening(&self) -> Result<bool>;
+impl<S> NetNamespaceImpl<S> {
+    /// Creates a new `Net` instance with the given `node`.
+    pub fn new(node: Arc<RwLock<InMemoryNodeInner<S>>>) -> Self {
+        Self { node }
+    }
 }
 
-impl NetNamespaceT for NetNamespace {
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> NetNamespaceT
+    for NetNamespaceImpl<S>
+{
+    /// Returns the chain id of the node as a decimal string, matching `eth_chainId`.
     fn net_version(&self) -> Result<String> {
-        Ok(String::From(TEST_NODE_NETWORK_ID))
+        self.node
+            .read()
+            .map(|reader| reader.fork_storage.chain_id.0.to_string())
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
     }
 
     fn net_peer_count(&self) -> Result<U256> {
-        Ok(U256::From(0))
+        Ok(U256::from(0))
     }
 
     fn net_listening(&self) -> Result<bool> {
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, node::InMemoryNodeBuilder};
+    use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+
+    #[tokio::test]
+    async fn test_net_version_returns_configured_chain_id() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .chain_id(1337)
+            .build();
+        let net = NetNamespaceImpl::new(node.get_inner());
+
+        assert_eq!("1337", net.net_version().unwrap());
+        assert_eq!(
+            net.net_version().unwrap(),
+            node.chain_id().await.unwrap().to_string()
+        );
+    }
+}
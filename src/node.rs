@@ -1,15 +1,18 @@
 //! In-memory node, that supports forking other networks.
 use crate::{
     bootloader_debug::{BootloaderDebug, BootloaderDebugTracer},
+    call_depth::{call_depth_exceeded, CallDepthTracer},
     console_log::ConsoleLogHandler,
     deps::InMemoryStorage,
     filters::{EthFilters, FilterType, LogFilter},
-    fork::{ForkDetails, ForkSource, ForkStorage},
-    formatter,
+    fork::{ForkDetails, ForkSource, ForkStorage, HistoricalForkStorage, Proof, StorageProof},
+    formatter::{self, BaseToken},
+    mock_call::MockedCallsTracer,
+    pool::{MempoolOrder, TxPool},
     system_contracts::{self, Options, SystemContracts},
     utils::{
         self, adjust_l1_gas_price_for_tx, bytecode_to_factory_dep, create_debug_output,
-        not_implemented, to_human_size, IntoBoxedFuture,
+        not_implemented, to_human_size, IntoBoxedFuture, TxFailureReason,
     },
 };
 use clap::Parser;
@@ -19,12 +22,16 @@ use futures::FutureExt;
 use indexmap::IndexMap;
 use itertools::Itertools;
 use jsonrpc_core::BoxFuture;
+use jsonrpc_derive::rpc;
 use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 use std::{
     cmp::{self},
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
     str::FromStr,
     sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use vm::{
@@ -36,8 +43,8 @@ use vm::{
         l2_blocks::load_last_l2_block,
         overhead::{derive_overhead, OverheadCoeficients},
     },
-    CallTracer, ExecutionResult, HistoryDisabled, L1BatchEnv, SystemEnv, TxExecutionMode, Vm,
-    VmExecutionResultAndLogs, VmTracer,
+    CallTracer, ExecutionResult, Halt, HistoryDisabled, L1BatchEnv, SystemEnv, TxExecutionMode, Vm,
+    VmExecutionResultAndLogs, VmRevertReason, VmTracer,
 };
 use zksync_basic_types::{
     web3::{self, signing::keccak256},
@@ -50,21 +57,27 @@ use zksync_core::api_server::web3::backend_jsonrpc::{
 use zksync_state::{ReadStorage, StoragePtr, StorageView, WriteStorage};
 use zksync_types::vm_trace::Call;
 use zksync_types::{
-    api::{Block, DebugCall, Log, TransactionReceipt, TransactionVariant},
-    block::legacy_miniblock_hash,
+    api::{
+        Block, BlockIdVariant, BlockNumber, DebugCall, Log, TransactionReceipt, TransactionVariant,
+    },
+    block::{legacy_miniblock_hash, pack_block_info, unpack_block_info},
     fee::Fee,
     get_code_key, get_nonce_key,
     l2::L2Tx,
-    transaction_request::TransactionRequest,
+    transaction_request::{SerializationTransactionError, TransactionRequest},
     utils::{
         decompose_full_nonce, nonces_to_full_nonce, storage_key_for_eth_balance,
         storage_key_for_standard_token_balance,
     },
-    PackedEthSignature, StorageKey, StorageLogQueryType, StorageValue, Transaction,
-    ACCOUNT_CODE_STORAGE_ADDRESS, EIP_712_TX_TYPE, L2_ETH_TOKEN_ADDRESS, MAX_GAS_PER_PUBDATA_BYTE,
-    MAX_L2_TX_GAS_LIMIT,
+    L2ChainId, Nonce, PackedEthSignature, ProtocolVersionId, StorageKey, StorageLogQueryType,
+    StorageValue, Transaction, ACCOUNT_CODE_STORAGE_ADDRESS, BOOTLOADER_ADDRESS,
+    CONTRACT_DEPLOYER_ADDRESS, EIP_712_TX_TYPE, L2_ETH_TOKEN_ADDRESS, MAX_GAS_PER_PUBDATA_BYTE,
+    MAX_L2_TX_GAS_LIMIT, SYSTEM_CONTEXT_ADDRESS, SYSTEM_CONTEXT_CURRENT_L2_BLOCK_HASHES_POSITION,
+    SYSTEM_CONTEXT_CURRENT_L2_BLOCK_INFO_POSITION, SYSTEM_CONTEXT_CURRENT_TX_ROLLING_HASH_POSITION,
+    SYSTEM_CONTEXT_STORED_L2_BLOCK_HASHES,
 };
 use zksync_utils::{
+    address_to_h256,
     bytecode::{compress_bytecode, hash_bytecode},
     h256_to_account_address, h256_to_u256, h256_to_u64, u256_to_h256,
 };
@@ -93,8 +106,62 @@ pub const ESTIMATE_GAS_ACCEPTABLE_OVERESTIMATION: u32 = 1_000;
 pub const ESTIMATE_GAS_SCALE_FACTOR: f32 = 1.3;
 /// The maximum number of previous blocks to store the state for.
 pub const MAX_PREVIOUS_STATES: u16 = 128;
-/// The zks protocol version.
-pub const PROTOCOL_VERSION: &str = "zks/1";
+/// The ethereum protocol version reported by `eth_protocolVersion` - a fixed value, for compatibility with the
+/// libraries that query it on connect.
+pub const PROTOCOL_VERSION: &str = "0x41";
+/// How often the automine batch miner checks whether the current batch window has elapsed.
+pub const AUTOMINE_BATCH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// How often the interval miner checks whether the next interval block is due.
+pub const INTERVAL_MINING_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Storage slot of the `gasPrice` (i.e. `tx.gasprice`) of the `SystemContext` system contract.
+const SYSTEM_CONTEXT_GAS_PRICE_POSITION: u64 = 2;
+/// Storage slot of the `coinbase` (i.e. `block.coinbase`) of the `SystemContext` system contract.
+const SYSTEM_CONTEXT_COINBASE_POSITION: u64 = 4;
+/// Storage slot of the `difficulty` (i.e. `block.prevrandao`) of the `SystemContext` system contract.
+const SYSTEM_CONTEXT_DIFFICULTY_POSITION: u64 = 5;
+/// The default `block.prevrandao`, the constant returned by zkSync Era.
+pub const DEFAULT_PREV_RANDAO: u64 = 2_500_000_000_000_000;
+/// The balance of the rich accounts, in the smallest unit of the base token.
+pub const RICH_ACCOUNT_BALANCE: u128 = 10u128.pow(30);
+/// Gas limit of the transactions sent with `eth_sendTransaction` that do not set one.
+pub const SEND_TRANSACTION_GAS_LIMIT: u64 = 80_000_000;
+
+/// Half of the order of the secp256k1 curve. Signatures with a higher `s` value are malleable (see EIP-2).
+const SECP256K1_HALF_ORDER: &str =
+    "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0";
+
+/// Decodes a raw (RLP-encoded and signed) transaction for the given chain.
+/// Transactions signed for another chain, and malleable signatures (with a high `s` value), are rejected with a
+/// specific error.
+pub fn decode_raw_tx_request(
+    tx_bytes: &[u8],
+    chain_id: L2ChainId,
+) -> Result<(TransactionRequest, H256), Web3Error> {
+    let (tx_req, hash) =
+        TransactionRequest::from_bytes(tx_bytes, chain_id.0).map_err(|err| match err {
+            SerializationTransactionError::WrongChainId(tx_chain_id) => {
+                Web3Error::SubmitTransactionError(
+                    format!(
+                        "invalid chain id: expected {}, got {}",
+                        chain_id.0,
+                        tx_chain_id.map_or_else(|| "none".to_string(), |id| id.to_string())
+                    ),
+                    vec![],
+                )
+            }
+            err => Web3Error::SerializationError(err),
+        })?;
+
+    let half_order = U256::from_str_radix(SECP256K1_HALF_ORDER, 16).expect("valid constant");
+    if tx_req.s.map_or(false, |s| s > half_order) {
+        return Err(Web3Error::SubmitTransactionError(
+            "invalid signature: `s` value is in the upper half of the curve order".to_string(),
+            vec![],
+        ));
+    }
+
+    Ok((tx_req, hash))
+}
 
 pub fn compute_hash(block_number: u64, tx_hash: H256) -> H256 {
     let digest = [&block_number.to_be_bytes()[..], tx_hash.as_bytes()].concat();
@@ -111,10 +178,135 @@ pub fn create_empty_block<TX>(block_number: u64, timestamp: u64, batch: u32) ->
         transactions: vec![],
         gas_used: U256::from(0),
         gas_limit: U256::from(BLOCK_GAS_LIMIT),
+        base_fee_per_gas: U256::from(L2_GAS_PRICE),
         ..Default::default()
     }
 }
 
+/// Returns the JSON-RPC error of a reverted execution, with the given `message` and the raw revert payload
+/// (0x-prefixed) as `data` - so that clients can decode custom errors.
+pub fn revert_error(message: String, output: &VmRevertReason) -> jsonrpc_core::Error {
+    let mut error = into_jsrpc_error(Web3Error::SubmitTransactionError(message, vec![]));
+    let data = output.encoded_data();
+    error.data = if data.is_empty() {
+        None
+    } else {
+        Some(format!("0x{}", hex::encode(data)).into())
+    };
+    error
+}
+
+/// Validates the genesis `timestamp` and `block_number` overrides of a fork - they cannot precede the fork block,
+/// as the forked system context only accepts blocks that follow it.
+pub fn validate_fork_genesis(
+    fork_block_number: u64,
+    fork_timestamp: u64,
+    timestamp: Option<u64>,
+    block_number: Option<u64>,
+) -> Result<(), String> {
+    if let Some(timestamp) = timestamp {
+        if timestamp < fork_timestamp {
+            return Err(format!(
+                "genesis timestamp {} is before the fork block timestamp {}",
+                timestamp, fork_timestamp
+            ));
+        }
+    }
+    if let Some(block_number) = block_number {
+        if block_number < fork_block_number {
+            return Err(format!(
+                "genesis block number {} is before the fork block number {}",
+                block_number, fork_block_number
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the hash of a transaction of an impersonated account - as it is not signed, it is derived from all the
+/// fields of the transaction instead, so that transactions differing in any of them get different hashes.
+fn impersonated_tx_hash(tx: &L2Tx) -> H256 {
+    let u256_bytes = |value: U256| {
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+        bytes
+    };
+    let fee = &tx.common_data.fee;
+    let paymaster_params = &tx.common_data.paymaster_params;
+    let factory_deps = tx
+        .execute
+        .factory_deps
+        .iter()
+        .flatten()
+        .map(|dep| keccak256(dep))
+        .collect::<Vec<_>>();
+    H256(keccak256(
+        &[
+            tx.initiator_account().as_bytes(),
+            &tx.nonce().0.to_be_bytes()[..],
+            tx.recipient_account().as_bytes(),
+            &u256_bytes(tx.execute.value)[..],
+            &keccak256(&tx.execute.calldata)[..],
+            &u256_bytes(fee.gas_limit)[..],
+            &u256_bytes(fee.max_fee_per_gas)[..],
+            &u256_bytes(fee.max_priority_fee_per_gas)[..],
+            &u256_bytes(fee.gas_per_pubdata_limit)[..],
+            paymaster_params.paymaster.as_bytes(),
+            &keccak256(&paymaster_params.paymaster_input)[..],
+            &keccak256(&factory_deps.concat())[..],
+        ]
+        .concat(),
+    ))
+}
+
+/// Returns the key of the slot of the `SystemContext` system contract holding the hash of the L2 block `number` -
+/// only the hashes of the latest blocks are stored, in a ring buffer.
+fn l2_block_hash_key(number: u64) -> StorageKey {
+    StorageKey::new(
+        AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+        u256_to_h256(
+            h256_to_u256(SYSTEM_CONTEXT_CURRENT_L2_BLOCK_HASHES_POSITION)
+                + U256::from(number % SYSTEM_CONTEXT_STORED_L2_BLOCK_HASHES as u64),
+        ),
+    )
+}
+
+/// Returns the result of an execution during which a value failed to be read from the fork - it is halted, as the
+/// value was read as zero.
+fn fork_read_failed(err: String) -> ExecutionResult {
+    ExecutionResult::Halt {
+        reason: Halt::UnexpectedVMBehavior(format!("failed reading from the fork: {}", err)),
+    }
+}
+
+/// A mocked response to the calls to `address` whose calldata starts with `calldata` (e.g. a function selector).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockedCall {
+    pub address: Address,
+    pub calldata: Vec<u8>,
+    pub return_data: Vec<u8>,
+}
+
+/// A revert expected from the next `eth_call` or `eth_sendRawTransaction`, optionally with a reason - see
+/// `config_expectNextCallRevert`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedRevert {
+    /// If set - the human readable revert reason must contain it.
+    pub reason: Option<String>,
+}
+
+impl ExpectedRevert {
+    /// Returns true if the execution reverted as expected.
+    pub fn is_met_by(&self, result: &ExecutionResult, resolve_hashes: bool) -> bool {
+        match result {
+            ExecutionResult::Revert { output } => self.reason.as_ref().map_or(true, |reason| {
+                formatter::revert_reason_to_human_readable(output, resolve_hashes).contains(reason)
+            }),
+            ExecutionResult::Success { .. } | ExecutionResult::Halt { .. } => false,
+        }
+    }
+}
+
 /// Information about the executed transaction.
 #[derive(Debug, Clone)]
 pub struct TxExecutionInfo {
@@ -303,18 +495,105 @@ pub struct InMemoryNodeInner<S> {
     pub console_log_handler: ConsoleLogHandler,
     pub system_contracts: SystemContracts,
     pub impersonated_accounts: HashSet<Address>,
+    /// If true - every account is impersonated, so transactions are accepted from any `from` without a signature.
+    pub auto_impersonate: bool,
     pub rich_accounts: HashSet<H160>,
     /// Keeps track of historical states indexed via block hash. Limited to [MAX_PREVIOUS_STATES].
     pub previous_states: IndexMap<H256, HashMap<StorageKey, StorageValue>>,
+    /// Transactions that were submitted, but not executed yet.
+    pub pool: TxPool,
+    /// If true - transactions are executed as soon as they are submitted (and executable).
+    /// Otherwise they are kept in the [TxPool] until a block is mined.
+    pub automine: bool,
+    /// The address that is set as the operator (fee account) of the produced blocks, and that contracts read as
+    /// `block.coinbase` - the bootloader, as on zkSync Era, if not set.
+    pub coinbase: Address,
+    /// The value contracts read as `block.prevrandao` (or `block.difficulty`) - see [DEFAULT_PREV_RANDAO].
+    pub prev_randao: H256,
+    /// If set - transactions with a higher gas limit are rejected when submitted.
+    pub max_tx_gas: Option<u64>,
+    /// If set - transactions whose account (and paymaster) validation uses more gas are halted as failing the
+    /// validation.
+    pub validation_gas_limit: Option<u64>,
+    /// If false - the unused gas of a transaction is not refunded, and the payer is charged for its whole gas limit.
+    pub refunds_enabled: bool,
+    /// If false - transactions are accepted whatever their nonce, and executed in the order they were submitted:
+    /// the nonce of the sender is set to the nonce of each transaction right before it is executed.
+    pub nonce_check: bool,
+    /// If true - transactions with a zero gas price are accepted, and the blocks mining them are executed with a zero
+    /// base fee. Off by default, like on the network, where they are rejected.
+    pub allow_zero_gas_price: bool,
+    /// If false - the fees paid by the transactions are burned, instead of being credited to the [Self::coinbase].
+    pub fee_accrual: bool,
+    /// If set - a block is sealed once the pubdata published by its transactions reaches this many bytes, and the
+    /// remaining transactions are mined in the following blocks.
+    pub max_pubdata_per_block: Option<u64>,
+    /// If set - `eth_getLogs` requests matching more logs fail, asking for a narrower block range.
+    pub max_logs_returned: Option<usize>,
+    /// If false - calls ignore their gas limit and run with the maximum gas, to debug their logic regardless of the
+    /// gas they use. Not meant for production-like testing.
+    pub gas_metering: bool,
+    /// The sender of the calls that do not set `from`.
+    pub default_call_sender: Address,
+    /// The order in which the pending transactions of different senders are mined.
+    pub mempool_order: MempoolOrder,
+    /// If set - the maximum number of transactions in the [TxPool]. Once full, new transactions are rejected, unless
+    /// they pay a higher priority fee than the cheapest one, which is evicted.
+    pub mempool_max: Option<usize>,
+    /// If set - calls and transactions nesting their calls deeper than this many frames, counted from the called
+    /// contract, are halted. Otherwise (the default) the depth is only bounded by the gas of the execution.
+    pub max_call_depth: Option<usize>,
+    /// The base token of the chain, in whose units the balances are logged.
+    pub base_token: BaseToken,
+    /// Balances that override the state of the next mined block only - the prior balances are restored once it is
+    /// mined.
+    pub next_block_balances: HashMap<Address, U256>,
+    /// Transactions that make up the next block mined by [InMemoryNode::mine_block], in this order - instead of the
+    /// pending transactions of the [TxPool], which are left for the following blocks.
+    pub next_block_txs: Vec<L2Tx>,
+    /// The mocked responses of calls, returned instead of executing them - see [MockedCallsTracer].
+    pub mocked_calls: Vec<MockedCall>,
+    /// If set - the next `eth_call` or `eth_sendRawTransaction` is expected to revert, and reports whether it did
+    /// instead of surfacing the revert.
+    pub expected_revert: Option<ExpectedRevert>,
+    /// If non-zero - with automine enabled, the transactions submitted within this many milliseconds of each other
+    /// are batched, and mined together in a single block. Otherwise each transaction is mined in its own block.
+    pub automine_batch_window_ms: u64,
+    /// The time when the current automine batch is due to be mined, if there is one.
+    pub automine_batch_deadline: Option<Instant>,
+    /// If non-zero - a block is mined every this many milliseconds, with the pending transactions (see
+    /// [InMemoryNode::spawn_interval_miner]).
+    pub interval_mining_ms: u64,
+    /// The time when the next interval block is due to be mined, if interval mining is enabled.
+    pub interval_mining_deadline: Option<Instant>,
+    /// If set - `eth_syncing` reports the node as syncing from `starting_block` to `highest_block` (the current
+    /// block is always the latest block), to test the sync-wait logic of clients.
+    pub simulated_sync: Option<web3::types::SyncInfo>,
+    /// The timestamp increment (in seconds) of the empty blocks mined by `evm_mine`.
+    pub block_time: u64,
+    /// If true - the timestamp of the next block was set by `evm_setNextBlockTimestamp`, so the
+    /// [InMemoryNodeInner::block_time] does not apply to it.
+    pub next_block_timestamp_set: bool,
+    /// If true - when mining several empty blocks at once, only the last one is sealed by the VM, and the preceding
+    /// ones are synthesized - see [crate::utils::mine_empty_blocks].
+    pub empty_seal_fast: bool,
+    /// If true - the deployment nonce of the sender of a deployment transaction is set to
+    /// [InMemoryNodeInner::deployment_counter] before it is executed, so the deployed addresses only depend on the
+    /// deployer and the order of the local deployments - and not on the deployments in the forked state.
+    pub deterministic_addresses: bool,
+    /// The number of contracts deployed locally with [InMemoryNodeInner::deterministic_addresses] enabled.
+    pub deployment_counter: u64,
+    /// The protocol version the transactions are executed with.
+    pub protocol_version: ProtocolVersionId,
 }
 
-type L2TxResult = (
+type L2TxsResult = (
     HashMap<StorageKey, H256>,
-    VmExecutionResultAndLogs,
-    Vec<Call>,
+    Vec<(VmExecutionResultAndLogs, Vec<Call>)>,
     Block<TransactionVariant>,
     HashMap<U256, Vec<U256>>,
     BlockContext,
+    u64,
 );
 
 impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
@@ -322,19 +601,56 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         &self,
         storage: StoragePtr<ST>,
     ) -> (L1BatchEnv, BlockContext) {
+        let block_ctx = BlockContext::from_current(
+            self.current_batch,
+            self.current_miniblock,
+            self.current_timestamp,
+        );
+        self.create_l1_batch_env_after(storage, block_ctx)
+    }
+
+    /// Creates the [L1BatchEnv] for a new batch that follows the block described by `last_block_ctx`.
+    /// The `storage` must hold the state as it was at that block - the configured [InMemoryNodeInner::prev_randao]
+    /// and [InMemoryNodeInner::coinbase] are written to it.
+    pub fn create_l1_batch_env_after<ST: ReadStorage>(
+        &self,
+        storage: StoragePtr<ST>,
+        last_block_ctx: BlockContext,
+    ) -> (L1BatchEnv, BlockContext) {
+        // contracts read `block.prevrandao` from the `SystemContext` - only written when it changes, so that the
+        // slot is not reported as modified by every block
+        let prev_randao_key = StorageKey::new(
+            AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+            H256::from_low_u64_be(SYSTEM_CONTEXT_DIFFICULTY_POSITION),
+        );
+        if storage.borrow_mut().read_value(&prev_randao_key) != self.prev_randao {
+            storage
+                .borrow_mut()
+                .set_value(prev_randao_key, self.prev_randao);
+        }
+        let coinbase_key = StorageKey::new(
+            AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+            H256::from_low_u64_be(SYSTEM_CONTEXT_COINBASE_POSITION),
+        );
+        let coinbase = if self.coinbase.is_zero() {
+            BOOTLOADER_ADDRESS
+        } else {
+            self.coinbase
+        };
+        if storage.borrow_mut().read_value(&coinbase_key) != address_to_h256(&coinbase) {
+            storage
+                .borrow_mut()
+                .set_value(coinbase_key, address_to_h256(&coinbase));
+        }
+
         let last_l2_block_hash = if let Some(last_l2_block) = load_last_l2_block(storage) {
             last_l2_block.hash
         } else {
             // This is the scenario of either the first L2 block ever or
             // the first block after the upgrade for support of L2 blocks.
-            legacy_miniblock_hash(MiniblockNumber(self.current_miniblock as u32))
+            legacy_miniblock_hash(MiniblockNumber(last_block_ctx.miniblock as u32))
         };
-        let block_ctx = BlockContext::from_current(
-            self.current_batch,
-            self.current_miniblock,
-            self.current_timestamp,
-        );
-        let block_ctx = block_ctx.new_batch();
+        let block_ctx = last_block_ctx.new_batch();
         let batch_env = L1BatchEnv {
             // TODO: set the previous batch hash properly (take from fork, when forking, and from local storage, when this is not the first block).
             previous_batch_hash: None,
@@ -342,7 +658,7 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
             timestamp: block_ctx.timestamp,
             l1_gas_price: self.l1_gas_price,
             fair_l2_gas_price: L2_GAS_PRICE,
-            fee_account: H160::zero(),
+            fee_account: self.coinbase,
             enforced_base_fee: None,
             first_l2_block: vm::L2BlockEnv {
                 // the 'current_miniblock' contains the block that was already produced.
@@ -372,7 +688,7 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         SystemEnv {
             zk_porter_available: false,
             // TODO: when forking, we could consider taking the protocol version id from the fork itself.
-            version: zksync_types::ProtocolVersionId::latest(),
+            version: self.protocol_version,
             base_system_smart_contracts: base_system_contracts,
             gas_limit: BLOCK_GAS_LIMIT,
             execution_mode,
@@ -381,6 +697,12 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         }
     }
 
+    /// Returns the base fee charged by the bootloader at the current L1 gas price, as reported by the mined blocks.
+    pub fn base_fee(&self) -> u64 {
+        let (base_fee, _) = derive_base_fee_and_gas_per_pubdata(self.l1_gas_price, L2_GAS_PRICE);
+        base_fee
+    }
+
     /// Estimates the gas required for a given call request.
     ///
     /// # Arguments
@@ -562,12 +884,8 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
                     if message.is_empty() { "" } else { ": " },
                     message
                 );
-                let data = output.encoded_data();
                 log::info!("{}", pretty_message.on_red());
-                Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                    pretty_message,
-                    data,
-                )))
+                Err(revert_error(pretty_message, &output))
             }
             ExecutionResult::Halt { reason } => {
                 log::info!("{}", format!("Unable to estimate gas for the request with our suggested gas limit of {}. The transaction is most likely unexecutable. Breakdown of estimation:", suggested_gas_limit + overhead).red());
@@ -709,6 +1027,355 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         self.impersonated_accounts.remove(&address)
     }
 
+    /// Returns true if the account is impersonated - either explicitly, or as every account with
+    /// [InMemoryNodeInner::auto_impersonate].
+    pub fn is_impersonated(&self, address: &Address) -> bool {
+        self.auto_impersonate || self.impersonated_accounts.contains(address)
+    }
+
+    /// Mocks the response of the calls to `address` whose calldata starts with `calldata` - replacing any previous
+    /// mock of the same calls.
+    pub fn mock_call(&mut self, address: Address, calldata: Vec<u8>, return_data: Vec<u8>) {
+        self.mocked_calls
+            .retain(|mock| mock.address != address || mock.calldata != calldata);
+        self.mocked_calls.push(MockedCall {
+            address,
+            calldata,
+            return_data,
+        });
+    }
+
+    /// Returns the number of the genesis block. When forking, this is the genesis of the forked chain,
+    /// otherwise the first block of the node (which can be moved with `--genesis-block-number`).
+    pub fn genesis_block_number(&self) -> u64 {
+        let is_fork = self
+            .fork_storage
+            .inner
+            .read()
+            .map(|storage| storage.fork.is_some())
+            .unwrap_or_default();
+        if is_fork {
+            return 0;
+        }
+
+        self.block_hashes.keys().min().copied().unwrap_or_default()
+    }
+
+    /// Converts a [BlockNumber] to a real block number, resolving `earliest` to the genesis block.
+    pub fn to_real_block_number(&self, block_number: BlockNumber) -> U64 {
+        match block_number {
+            BlockNumber::Earliest => U64::from(self.genesis_block_number()),
+            _ => utils::to_real_block_number(block_number, U64::from(self.current_miniblock)),
+        }
+    }
+
+    /// Resolves an optional [BlockIdVariant] to a block number, defaulting to the latest block.
+    /// Block hashes that are not known locally are looked up in the fork (if any).
+    pub fn resolve_block_number(&self, block: Option<BlockIdVariant>) -> Result<u64, String> {
+        match block {
+            None => Ok(self.current_miniblock),
+            Some(BlockIdVariant::BlockNumber(number)) => {
+                Ok(self.to_real_block_number(number).as_u64())
+            }
+            Some(BlockIdVariant::BlockNumberObject(o)) => {
+                Ok(self.to_real_block_number(o.block_number).as_u64())
+            }
+            Some(BlockIdVariant::BlockHashObject(o)) => {
+                if let Some(block) = self.blocks.get(&o.block_hash) {
+                    return Ok(block.number.as_u64());
+                }
+
+                self.fork_storage
+                    .inner
+                    .read()
+                    .map_err(|err| format!("failed acquiring read lock on storage: {:?}", err))?
+                    .fork
+                    .as_ref()
+                    .and_then(|fork| {
+                        fork.fork_source
+                            .get_block_by_hash(o.block_hash, false)
+                            .ok()
+                            .flatten()
+                    })
+                    .map(|block| block.number.as_u64())
+                    .ok_or_else(|| format!("unable to find block {:#x}", o.block_hash))
+            }
+        }
+    }
+
+    /// Returns a read-only view over the storage as it was at `block_number`, together with the
+    /// context of the last block of its batch. Returns `None` if `block_number` is the latest block.
+    ///
+    /// Local blocks are served from [InMemoryNodeInner::previous_states], while blocks before the fork
+    /// are read directly from the fork source.
+    pub fn historical_storage(
+        &self,
+        block_number: u64,
+    ) -> Result<Option<(HistoricalForkStorage<'_, S>, BlockContext)>, String> {
+        if block_number >= self.current_miniblock {
+            return Ok(None);
+        }
+
+        if let Some(block_hash) = self.block_hashes.get(&block_number) {
+            let state = self
+                .previous_states
+                .get(block_hash)
+                .cloned()
+                .ok_or_else(|| {
+                    format!("state for block #{} is no longer available", block_number)
+                })?;
+            let block_ctx = self
+                .last_block_context_in_batch(block_number)
+                .ok_or_else(|| format!("block #{} does not exist", block_number))?;
+
+            return Ok(Some((
+                HistoricalForkStorage::archived(&self.fork_storage, state),
+                block_ctx,
+            )));
+        }
+
+        let block = {
+            let reader = self
+                .fork_storage
+                .inner
+                .read()
+                .map_err(|err| format!("failed acquiring read lock on storage: {:?}", err))?;
+            let fork = reader
+                .fork
+                .as_ref()
+                .filter(|fork| block_number < fork.l2_miniblock)
+                .ok_or_else(|| format!("block #{} does not exist", block_number))?;
+            fork.fork_source
+                .get_block_by_number(BlockNumber::Number(U64::from(block_number)), false)
+                .map_err(|err| {
+                    format!(
+                        "failed fetching block #{} from fork: {:?}",
+                        block_number, err
+                    )
+                })?
+                .ok_or_else(|| format!("block #{} does not exist", block_number))?
+        };
+        let block_ctx = BlockContext::from_current(
+            block.l1_batch_number.unwrap_or_default().as_u32(),
+            block_number,
+            block.timestamp.as_u64(),
+        );
+
+        Ok(Some((
+            HistoricalForkStorage::at_fork_miniblock(&self.fork_storage, block_number),
+            block_ctx,
+        )))
+    }
+
+    /// Reads a storage value as it was at the given block, defaulting to the latest one.
+    pub fn read_value_at_block(
+        &self,
+        key: &StorageKey,
+        block: Option<BlockIdVariant>,
+    ) -> Result<StorageValue, String> {
+        let block_number = self.resolve_block_number(block)?;
+        match self.historical_storage(block_number)? {
+            Some((mut storage, _)) => Ok(storage.read_value(key)),
+            None => Ok((&self.fork_storage).read_value(key)),
+        }
+    }
+
+    /// Returns the Merkle proofs of the given storage slots of an account, at the latest block.
+    ///
+    /// When forking, the proofs are fetched from the fork source. Slots that were modified locally have no
+    /// proof (there is no local Merkle tree), and report their local value instead of the upstream one.
+    pub fn get_proof(&self, address: Address, keys: Vec<H256>) -> Result<Proof, String> {
+        let storage_key = |key: H256| StorageKey::new(AccountTreeId::new(address), key);
+        let (local_values, upstream) = {
+            let fork_storage = self
+                .fork_storage
+                .inner
+                .read()
+                .map_err(|err| format!("failed acquiring lock: {:?}", err))?;
+            let local_values = keys
+                .iter()
+                .map(|key| {
+                    fork_storage
+                        .raw_storage
+                        .state
+                        .get(&storage_key(*key))
+                        .copied()
+                })
+                .collect::<Vec<_>>();
+            let upstream = match &fork_storage.fork {
+                Some(fork) => fork
+                    .fork_source
+                    .get_proof(address, keys.clone(), fork.l1_block)
+                    .map_err(|err| format!("failed fetching proof from fork: {:?}", err))?,
+                None => None,
+            };
+            (local_values, upstream)
+        };
+
+        let storage_proof = keys
+            .into_iter()
+            .zip(local_values)
+            .map(|(key, local_value)| {
+                let upstream_proof = upstream.as_ref().and_then(|proof| {
+                    proof
+                        .storage_proof
+                        .iter()
+                        .find(|slot| slot.key == key)
+                        .cloned()
+                });
+                match (local_value, upstream_proof) {
+                    (None, Some(upstream_proof)) => upstream_proof,
+                    (local_value, _) => StorageProof {
+                        key,
+                        proof: vec![],
+                        value: local_value
+                            .unwrap_or_else(|| (&self.fork_storage).read_value(&storage_key(key))),
+                        index: 0,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(Proof {
+            address,
+            storage_proof,
+        })
+    }
+
+    /// Returns the [BlockContext] of the last locally known block that belongs to the same batch as `block_number`.
+    /// This matches the state that was archived for any block of that batch.
+    fn last_block_context_in_batch(&self, block_number: u64) -> Option<BlockContext> {
+        let block_at = |number: u64| {
+            self.block_hashes
+                .get(&number)
+                .and_then(|hash| self.blocks.get(hash))
+        };
+
+        let mut last = block_at(block_number)?;
+        while let Some(next) = block_at(last.number.as_u64().saturating_add(1)) {
+            if next.l1_batch_number != last.l1_batch_number {
+                break;
+            }
+            last = next;
+        }
+
+        Some(BlockContext::from_current(
+            last.l1_batch_number.unwrap_or_default().as_u32(),
+            last.number.as_u64(),
+            last.timestamp.as_u64(),
+        ))
+    }
+
+    /// Moves the genesis block of the node to the given timestamp and block number.
+    /// When forking, only a timestamp and block number after the fork block can be applied, as the forked
+    /// system context only accepts blocks that follow the fork block.
+    fn override_genesis(
+        &mut self,
+        is_fork: bool,
+        timestamp: Option<u64>,
+        block_number: Option<u64>,
+    ) -> Result<(), String> {
+        if is_fork {
+            validate_fork_genesis(
+                self.current_miniblock,
+                self.current_timestamp,
+                timestamp,
+                block_number,
+            )?;
+            if let Some(block_number) = block_number {
+                if block_number > self.current_miniblock {
+                    self.set_next_block_number(block_number + 1)?;
+                }
+            }
+            self.current_timestamp = timestamp.unwrap_or(self.current_timestamp);
+            return Ok(());
+        }
+
+        let timestamp = timestamp.unwrap_or(self.current_timestamp);
+        let block_number = block_number.unwrap_or(self.current_miniblock);
+        let genesis_hash = self.current_miniblock_hash;
+        self.block_hashes.remove(&self.current_miniblock);
+        self.block_hashes.insert(block_number, genesis_hash);
+        if let Some(genesis_block) = self.blocks.get_mut(&genesis_hash) {
+            genesis_block.number = U64::from(block_number);
+            genesis_block.l1_batch_number = Some(U64::from(block_number));
+            genesis_block.timestamp = U256::from(timestamp);
+        }
+        // The first mined block must share its number with its batch, as it is the first L2 block seen
+        // by the system context.
+        self.current_batch = block_number as u32;
+        self.current_miniblock = block_number;
+        self.current_timestamp = timestamp;
+        Ok(())
+    }
+
+    /// Sets the number of the next mined block - the blocks mined afterwards follow it.
+    ///
+    /// The current L2 block info of the `SystemContext` system contract is moved along, so that the VM sees the
+    /// next block as following the current one.
+    pub fn set_next_block_number(&mut self, number: u64) -> Result<(), String> {
+        if number <= self.current_miniblock {
+            return Err(format!(
+                "next block number {} must be greater than the current block number {}",
+                number, self.current_miniblock
+            ));
+        }
+
+        let block_info_key = StorageKey::new(
+            AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+            SYSTEM_CONTEXT_CURRENT_L2_BLOCK_INFO_POSITION,
+        );
+        let (last_number, last_timestamp) = unpack_block_info(h256_to_u256(
+            (&self.fork_storage).read_value(&block_info_key),
+        ));
+        let last_hash = (&self.fork_storage).read_value(&l2_block_hash_key(last_number));
+        let new_last_number = number - 1;
+        self.fork_storage.set_value(
+            block_info_key,
+            u256_to_h256(pack_block_info(new_last_number, last_timestamp)),
+        );
+        self.fork_storage
+            .set_value(l2_block_hash_key(new_last_number), last_hash);
+
+        self.current_miniblock = new_last_number;
+        Ok(())
+    }
+
+    /// Starts the L2 block `number` in the `SystemContext` system contract, as the bootloader does - so that a
+    /// block produced without running the VM is followed by the next ones.
+    pub fn start_system_context_l2_block(&mut self, number: u64, timestamp: u64) {
+        let storage = StorageView::new(&self.fork_storage).to_rc_ptr();
+        let prev_block_hash = if let Some(last_l2_block) = load_last_l2_block(storage) {
+            last_l2_block.hash
+        } else {
+            legacy_miniblock_hash(MiniblockNumber(number.saturating_sub(1) as u32))
+        };
+
+        self.fork_storage.set_value(
+            StorageKey::new(
+                AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+                SYSTEM_CONTEXT_CURRENT_L2_BLOCK_INFO_POSITION,
+            ),
+            u256_to_h256(pack_block_info(number, timestamp)),
+        );
+        self.fork_storage
+            .set_value(l2_block_hash_key(number.saturating_sub(1)), prev_block_hash);
+        self.fork_storage.set_value(
+            StorageKey::new(
+                AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+                SYSTEM_CONTEXT_CURRENT_TX_ROLLING_HASH_POSITION,
+            ),
+            H256::zero(),
+        );
+    }
+
+    /// Returns the current transaction nonce of the given account.
+    pub fn get_account_nonce(&self, address: &Address) -> Nonce {
+        let full_nonce = (&self.fork_storage).read_value(&get_nonce_key(address));
+        let (account_nonce, _) = decompose_full_nonce(h256_to_u256(full_nonce));
+        Nonce(account_nonce.as_u32())
+    }
+
     /// Archives the current state for later queries.
     pub fn archive_state(&mut self) -> Result<(), String> {
         if self.previous_states.len() > MAX_PREVIOUS_STATES as usize {
@@ -756,6 +1423,8 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
             impersonated_accounts: self.impersonated_accounts.clone(),
             rich_accounts: self.rich_accounts.clone(),
             previous_states: self.previous_states.clone(),
+            pool: self.pool.clone(),
+            deployment_counter: self.deployment_counter,
             raw_storage: storage.raw_storage.clone(),
             value_read_cache: storage.value_read_cache.clone(),
             factory_dep_cache: storage.factory_dep_cache.clone(),
@@ -782,1957 +1451,3050 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         self.impersonated_accounts = snapshot.impersonated_accounts;
         self.rich_accounts = snapshot.rich_accounts;
         self.previous_states = snapshot.previous_states;
+        self.pool = snapshot.pool;
+        self.deployment_counter = snapshot.deployment_counter;
         storage.raw_storage = snapshot.raw_storage;
         storage.value_read_cache = snapshot.value_read_cache;
         storage.factory_dep_cache = snapshot.factory_dep_cache;
 
         Ok(())
     }
-}
-
-/// Creates a restorable snapshot for the [InMemoryNodeInner]. The snapshot contains all the necessary
-/// data required to restore the [InMemoryNodeInner] state to a previous point in time.
-#[derive(Debug, Clone)]
-pub struct Snapshot {
-    pub(crate) current_timestamp: u64,
-    pub(crate) current_batch: u32,
-    pub(crate) current_miniblock: u64,
-    pub(crate) current_miniblock_hash: H256,
-    pub(crate) l1_gas_price: u64,
-    pub(crate) tx_results: HashMap<H256, TransactionResult>,
-    pub(crate) blocks: HashMap<H256, Block<TransactionVariant>>,
-    pub(crate) block_hashes: HashMap<u64, H256>,
-    pub(crate) filters: EthFilters,
-    pub(crate) impersonated_accounts: HashSet<Address>,
-    pub(crate) rich_accounts: HashSet<H160>,
-    pub(crate) previous_states: IndexMap<H256, HashMap<StorageKey, StorageValue>>,
-    pub(crate) raw_storage: InMemoryStorage,
-    pub(crate) value_read_cache: HashMap<StorageKey, H256>,
-    pub(crate) factory_dep_cache: HashMap<H256, Option<Vec<u8>>>,
-}
 
-/// In-memory node, that can be used for local & unit testing.
-/// It also supports the option of forking testnet/mainnet.
-/// All contents are removed when object is destroyed.
-pub struct InMemoryNode<S> {
-    inner: Arc<RwLock<InMemoryNodeInner<S>>>,
-}
-
-fn contract_address_from_tx_result(execution_result: &VmExecutionResultAndLogs) -> Option<H160> {
-    for query in execution_result.logs.storage_logs.iter().rev() {
-        if query.log_type == StorageLogQueryType::InitialWrite
-            && query.log_query.address == ACCOUNT_CODE_STORAGE_ADDRESS
+    /// Removes the latest `depth` local blocks (and their transaction results), and restores the state of the block
+    /// before them. The remaining latest block must be the last block of its batch.
+    /// Returns the numbers and hashes of the removed blocks.
+    pub fn rollback_blocks(&mut self, depth: u64) -> Result<Vec<(u64, H256)>, String> {
+        let target_number = self.current_miniblock.checked_sub(depth).ok_or_else(|| {
+            format!(
+                "cannot roll back {} blocks, the latest block is #{}",
+                depth, self.current_miniblock
+            )
+        })?;
+        let target_block = self
+            .block_hashes
+            .get(&target_number)
+            .and_then(|hash| self.blocks.get(hash))
+            .cloned()
+            .ok_or_else(|| format!("block #{} is not a local block", target_number))?;
+        if depth == 0 {
+            return Ok(vec![]);
+        }
+        // the archived state of a block is the state at the end of its batch
+        if self
+            .last_block_context_in_batch(target_number)
+            .map(|block_ctx| block_ctx.miniblock)
+            != Some(target_number)
         {
-            return Some(h256_to_account_address(&u256_to_h256(query.log_query.key)));
+            return Err(format!(
+                "cannot roll back to block #{}, as it is not the last block of its batch",
+                target_number
+            ));
+        }
+        let target_state = self
+            .previous_states
+            .get(&target_block.hash)
+            .cloned()
+            .ok_or_else(|| format!("state for block #{} is no longer available", target_number))?;
+
+        let mut removed = vec![];
+        for number in (target_number + 1)..=self.current_miniblock {
+            if let Some(hash) = self.block_hashes.remove(&number) {
+                self.previous_states.shift_remove(&hash);
+                if let Some(block) = self.blocks.remove(&hash) {
+                    for tx in block.transactions {
+                        let tx_hash = match tx {
+                            TransactionVariant::Full(tx) => tx.hash,
+                            TransactionVariant::Hash(hash) => hash,
+                        };
+                        self.tx_results.remove(&tx_hash);
+                    }
+                }
+                removed.push((number, hash));
+            }
         }
+
+        self.fork_storage
+            .inner
+            .write()
+            .map_err(|err| format!("failed acquiring write lock on storage: {:?}", err))?
+            .raw_storage
+            .state = target_state;
+        self.current_miniblock = target_number;
+        self.current_miniblock_hash = target_block.hash;
+        self.current_timestamp = target_block.timestamp.as_u64();
+        self.current_batch = target_block
+            .l1_batch_number
+            .map(|batch| batch.as_u32())
+            .unwrap_or(self.current_batch);
+
+        Ok(removed)
     }
-    None
-}
 
-impl<S: ForkSource + std::fmt::Debug> Default for InMemoryNode<S> {
-    fn default() -> Self {
-        InMemoryNode::new(
-            None,
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
-        )
+    /// Changes the hash of a local block, keeping everything that is indexed by it consistent.
+    fn rehash_block(&mut self, number: u64, new_hash: H256) {
+        let old_hash = match self.block_hashes.insert(number, new_hash) {
+            Some(old_hash) => old_hash,
+            None => return,
+        };
+        if let Some(mut block) = self.blocks.remove(&old_hash) {
+            block.hash = new_hash;
+            self.blocks.insert(new_hash, block);
+        }
+        if let Some(state) = self.previous_states.shift_remove(&old_hash) {
+            self.previous_states.insert(new_hash, state);
+        }
+        if self.current_miniblock_hash == old_hash {
+            self.current_miniblock_hash = new_hash;
+        }
+        self.filters.notify_new_block(new_hash);
     }
-}
 
-impl<S: ForkSource + std::fmt::Debug> InMemoryNode<S> {
-    pub fn new(
-        fork: Option<ForkDetails<S>>,
-        show_calls: ShowCalls,
-        show_storage_logs: ShowStorageLogs,
-        show_vm_details: ShowVMDetails,
-        show_gas_details: ShowGasDetails,
-        resolve_hashes: bool,
-        system_contracts_options: &system_contracts::Options,
-    ) -> Self {
-        let inner = if let Some(f) = &fork {
-            let mut block_hashes = HashMap::<u64, H256>::new();
-            block_hashes.insert(f.l2_block.number.as_u64(), f.l2_block.hash);
-            let mut blocks = HashMap::<H256, Block<TransactionVariant>>::new();
-            blocks.insert(f.l2_block.hash, f.l2_block.clone());
+    /// Dumps the chain state (blocks and storage) into a [SerializableState].
+    pub fn dump_state(&self) -> Result<SerializableState, String> {
+        let storage = self
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| format!("failed acquiring read lock on storage: {:?}", err))?;
 
-            InMemoryNodeInner {
-                current_timestamp: f.block_timestamp,
-                current_batch: f.l1_block.0,
-                current_miniblock: f.l2_miniblock,
-                current_miniblock_hash: f.l2_miniblock_hash,
-                l1_gas_price: f.l1_gas_price,
-                tx_results: Default::default(),
-                blocks,
-                block_hashes,
-                filters: Default::default(),
-                fork_storage: ForkStorage::new(fork, system_contracts_options),
-                show_calls,
-                show_storage_logs,
-                show_vm_details,
-                show_gas_details,
-                resolve_hashes,
-                console_log_handler: ConsoleLogHandler::default(),
-                system_contracts: SystemContracts::from_options(system_contracts_options),
-                impersonated_accounts: Default::default(),
-                rich_accounts: HashSet::new(),
-                previous_states: Default::default(),
-            }
-        } else {
-            let mut block_hashes = HashMap::<u64, H256>::new();
-            block_hashes.insert(0, H256::zero());
-            let mut blocks = HashMap::<H256, Block<TransactionVariant>>::new();
-            blocks.insert(
-                H256::zero(),
-                create_empty_block(0, NON_FORK_FIRST_BLOCK_TIMESTAMP, 0),
-            );
-
-            InMemoryNodeInner {
-                current_timestamp: NON_FORK_FIRST_BLOCK_TIMESTAMP,
-                current_batch: 0,
-                current_miniblock: 0,
-                current_miniblock_hash: H256::zero(),
-                l1_gas_price: L1_GAS_PRICE,
-                tx_results: Default::default(),
-                blocks,
-                block_hashes,
-                filters: Default::default(),
-                fork_storage: ForkStorage::new(fork, system_contracts_options),
-                show_calls,
-                show_storage_logs,
-                show_vm_details,
-                show_gas_details,
-                resolve_hashes,
-                console_log_handler: ConsoleLogHandler::default(),
-                system_contracts: SystemContracts::from_options(system_contracts_options),
-                impersonated_accounts: Default::default(),
-                rich_accounts: HashSet::new(),
-                previous_states: Default::default(),
-            }
-        };
+        let mut blocks = self.blocks.values().cloned().collect::<Vec<_>>();
+        blocks.sort_by_key(|block| block.number);
+        let mut storage_slots = storage
+            .raw_storage
+            .state
+            .iter()
+            .map(|(key, value)| SerializableStorageSlot {
+                address: *key.address(),
+                key: *key.key(),
+                value: *value,
+            })
+            .collect::<Vec<_>>();
+        storage_slots.sort_by_key(|slot| (slot.address, slot.key));
 
-        InMemoryNode {
-            inner: Arc::new(RwLock::new(inner)),
-        }
+        Ok(SerializableState {
+            current_timestamp: self.current_timestamp,
+            current_batch: self.current_batch,
+            current_miniblock: self.current_miniblock,
+            current_miniblock_hash: self.current_miniblock_hash,
+            blocks,
+            storage: storage_slots,
+            factory_deps: storage
+                .raw_storage
+                .factory_deps
+                .iter()
+                .map(|(hash, bytecode)| (*hash, Bytes(bytecode.clone())))
+                .collect(),
+        })
     }
 
-    pub fn get_inner(&self) -> Arc<RwLock<InMemoryNodeInner<S>>> {
-        self.inner.clone()
+    /// Returns the transactions of a block either as full objects, or as their hashes. Transactions stored only by
+    /// their hash are hydrated from the executed transactions, or else from the fork source.
+    pub fn block_transactions(
+        &self,
+        transactions: Vec<TransactionVariant>,
+        full_transactions: bool,
+    ) -> Vec<TransactionVariant> {
+        transactions
+            .into_iter()
+            .map(|transaction| match transaction {
+                TransactionVariant::Full(tx) if !full_transactions => {
+                    TransactionVariant::Hash(tx.hash)
+                }
+                TransactionVariant::Hash(hash) if full_transactions => self
+                    .hydrate_transaction(hash)
+                    .map(TransactionVariant::Full)
+                    .unwrap_or_else(|| {
+                        log::warn!(
+                            "transaction {:#x} is not available, returning its hash",
+                            hash
+                        );
+                        TransactionVariant::Hash(hash)
+                    }),
+                transaction => transaction,
+            })
+            .collect()
     }
 
-    /// Applies multiple transactions - but still one per L1 batch.
-    pub fn apply_txs(&self, txs: Vec<L2Tx>) -> Result<(), String> {
-        log::info!("Running {:?} transactions (one per batch)", txs.len());
+    /// Returns the full transaction at `index` of a block, or `None` if the index is out of range.
+    pub fn block_transaction(
+        &self,
+        block: &Block<TransactionVariant>,
+        index: usize,
+    ) -> Option<zksync_types::api::Transaction> {
+        match block.transactions.get(index)? {
+            TransactionVariant::Full(tx) => Some(tx.clone()),
+            TransactionVariant::Hash(hash) => self.hydrate_transaction(*hash),
+        }
+    }
 
-        for tx in txs {
-            self.run_l2_tx(tx, TxExecutionMode::VerifyExecute)?;
+    /// Returns the full transaction with the given hash, from the executed transactions or the fork source.
+    fn hydrate_transaction(&self, hash: H256) -> Option<zksync_types::api::Transaction> {
+        if let Some(result) = self.tx_results.get(&hash) {
+            let mut transaction = zksync_types::api::Transaction::from(result.info.tx.clone());
+            transaction.block_hash = result.receipt.block_hash;
+            transaction.block_number = result.receipt.block_number;
+            transaction.transaction_index = Some(result.receipt.transaction_index);
+            return Some(transaction);
         }
 
-        Ok(())
+        self.fork_storage
+            .inner
+            .read()
+            .expect("failed reading fork storage")
+            .fork
+            .as_ref()
+            .and_then(|fork| {
+                fork.fork_source
+                    .get_transaction_by_hash(hash)
+                    .ok()
+                    .flatten()
+            })
     }
 
-    /// Adds a lot of tokens to a given account.
-    pub fn set_rich_account(&self, address: H160) {
-        let key = storage_key_for_eth_balance(&address);
+    /// Dumps the chain state into a JSON file, in the same format as `hardhat_dumpState`.
+    pub fn dump_state_to_file(&self, path: &Path) -> Result<(), String> {
+        let state = self.dump_state()?;
+        let bytes = serde_json::to_vec(&state)
+            .map_err(|err| format!("failed serializing state: {}", err))?;
+        std::fs::write(path, bytes)
+            .map_err(|err| format!("failed writing state to {}: {}", path.display(), err))
+    }
 
-        let mut inner = match self.inner.write() {
-            Ok(guard) => guard,
-            Err(e) => {
-                log::info!("Failed to acquire write lock: {}", e);
-                return;
-            }
-        };
+    /// Replaces the chain state (blocks and storage) with a previously dumped [SerializableState].
+    /// Execution results of the transactions are not part of the state, and are cleared.
+    pub fn load_state(&mut self, state: SerializableState) -> Result<(), String> {
+        let mut storage = self
+            .fork_storage
+            .inner
+            .write()
+            .map_err(|err| format!("failed acquiring write lock on storage: {:?}", err))?;
 
-        let keys = {
-            let mut storage_view = StorageView::new(&inner.fork_storage);
-            storage_view.set_value(key, u256_to_h256(U256::from(10u128.pow(30))));
-            storage_view.modified_storage_keys().clone()
-        };
+        self.current_timestamp = state.current_timestamp;
+        self.current_batch = state.current_batch;
+        self.current_miniblock = state.current_miniblock;
+        self.current_miniblock_hash = state.current_miniblock_hash;
+        self.block_hashes = state
+            .blocks
+            .iter()
+            .map(|block| (block.number.as_u64(), block.hash))
+            .collect();
+        self.blocks = state
+            .blocks
+            .into_iter()
+            .map(|block| (block.hash, block))
+            .collect();
+        self.tx_results.clear();
+        self.previous_states.clear();
+        self.pool = Default::default();
+        storage.raw_storage.state = state
+            .storage
+            .into_iter()
+            .map(|slot| {
+                (
+                    StorageKey::new(AccountTreeId::new(slot.address), slot.key),
+                    slot.value,
+                )
+            })
+            .collect();
+        storage.raw_storage.factory_deps = state
+            .factory_deps
+            .into_iter()
+            .map(|(hash, bytecode)| (hash, bytecode.0))
+            .collect();
+        // values read from the fork before loading would otherwise shadow the loaded (zero) slots
+        storage.value_read_cache.clear();
+        storage.factory_dep_cache.clear();
 
-        for (key, value) in keys.iter() {
-            inner.fork_storage.set_value(*key, *value);
-        }
-        inner.rich_accounts.insert(address);
+        Ok(())
     }
+}
 
-    /// Runs L2 'eth call' method - that doesn't commit to a block.
-    fn run_l2_call(&self, mut l2_tx: L2Tx) -> Result<ExecutionResult, String> {
-        let execution_mode = TxExecutionMode::EthCall;
+/// Chain state of the [InMemoryNodeInner] that can be dumped to, and loaded from, an external format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerializableState {
+    pub current_timestamp: u64,
+    pub current_batch: u32,
+    pub current_miniblock: u64,
+    pub current_miniblock_hash: H256,
+    pub blocks: Vec<Block<TransactionVariant>>,
+    pub storage: Vec<SerializableStorageSlot>,
+    pub factory_deps: BTreeMap<H256, Bytes>,
+}
 
-        let inner = self
-            .inner
-            .write()
-            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+/// A single storage slot of the [SerializableState].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerializableStorageSlot {
+    pub address: Address,
+    pub key: H256,
+    pub value: H256,
+}
 
-        let storage = StorageView::new(&inner.fork_storage).to_rc_ptr();
+/// Creates a restorable snapshot for the [InMemoryNodeInner]. The snapshot contains all the necessary
+/// data required to restore the [InMemoryNodeInner] state to a previous point in time.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub(crate) current_timestamp: u64,
+    pub(crate) current_batch: u32,
+    pub(crate) current_miniblock: u64,
+    pub(crate) current_miniblock_hash: H256,
+    pub(crate) l1_gas_price: u64,
+    pub(crate) tx_results: HashMap<H256, TransactionResult>,
+    pub(crate) blocks: HashMap<H256, Block<TransactionVariant>>,
+    pub(crate) block_hashes: HashMap<u64, H256>,
+    pub(crate) filters: EthFilters,
+    pub(crate) impersonated_accounts: HashSet<Address>,
+    pub(crate) rich_accounts: HashSet<H160>,
+    pub(crate) previous_states: IndexMap<H256, HashMap<StorageKey, StorageValue>>,
+    pub(crate) pool: TxPool,
+    pub(crate) deployment_counter: u64,
+    pub(crate) raw_storage: InMemoryStorage,
+    pub(crate) value_read_cache: HashMap<StorageKey, H256>,
+    pub(crate) factory_dep_cache: HashMap<H256, Option<Vec<u8>>>,
+}
 
-        let bootloader_code = inner.system_contracts.contracts_for_l2_call();
+/// In-memory node, that can be used for local & unit testing.
+/// It also supports the option of forking testnet/mainnet.
+/// All contents are removed when object is destroyed.
+pub struct InMemoryNode<S> {
+    inner: Arc<RwLock<InMemoryNodeInner<S>>>,
+}
 
-        // init vm
+fn contract_address_from_tx_result(execution_result: &VmExecutionResultAndLogs) -> Option<H160> {
+    for query in execution_result.logs.storage_logs.iter().rev() {
+        if query.log_type == StorageLogQueryType::InitialWrite
+            && query.log_query.address == ACCOUNT_CODE_STORAGE_ADDRESS
+        {
+            return Some(h256_to_account_address(&u256_to_h256(query.log_query.key)));
+        }
+    }
+    None
+}
 
-        let (batch_env, _) = inner.create_l1_batch_env(storage.clone());
-        let system_env = inner.create_system_env(bootloader_code.clone(), execution_mode);
+impl<S: ForkSource + std::fmt::Debug> Default for InMemoryNode<S> {
+    fn default() -> Self {
+        InMemoryNode::new(
+            None,
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        )
+    }
+}
 
-        let mut vm = Vm::new(batch_env, system_env, storage, HistoryDisabled);
+/// Builds an [InMemoryNode] - using the default configuration for everything that was not set explicitly.
+pub struct InMemoryNodeBuilder<S> {
+    fork: Option<ForkDetails<S>>,
+    show_calls: ShowCalls,
+    show_storage_logs: ShowStorageLogs,
+    show_vm_details: ShowVMDetails,
+    show_gas_details: ShowGasDetails,
+    resolve_hashes: bool,
+    system_contracts_options: system_contracts::Options,
+    genesis_timestamp: Option<u64>,
+    genesis_block_number: Option<u64>,
+    chain_id: Option<u16>,
+    max_tx_gas: Option<u64>,
+    validation_gas_limit: Option<u64>,
+    refunds_enabled: bool,
+    nonce_check: bool,
+    allow_zero_gas_price: bool,
+    auto_impersonate: bool,
+    fee_accrual: bool,
+    max_pubdata_per_block: Option<u64>,
+    max_logs_returned: Option<usize>,
+    gas_metering: bool,
+    default_call_sender: Option<Address>,
+    mempool_order: MempoolOrder,
+    mempool_max: Option<usize>,
+    max_call_depth: Option<usize>,
+    base_token: Option<BaseToken>,
+    l1_gas_price: Option<u64>,
+    automine_batch_window_ms: u64,
+    block_time: u64,
+    empty_seal_fast: bool,
+    deterministic_addresses: bool,
+    protocol_version: Option<ProtocolVersionId>,
+}
 
-        // We must inject *some* signature (otherwise bootloader code fails to generate hash).
-        if l2_tx.common_data.signature.is_empty() {
-            l2_tx.common_data.signature = PackedEthSignature::default().serialize_packed().into();
+impl<S> Default for InMemoryNodeBuilder<S> {
+    fn default() -> Self {
+        Self {
+            fork: None,
+            show_calls: ShowCalls::None,
+            show_storage_logs: ShowStorageLogs::None,
+            show_vm_details: ShowVMDetails::None,
+            show_gas_details: ShowGasDetails::None,
+            resolve_hashes: false,
+            system_contracts_options: system_contracts::Options::BuiltIn,
+            genesis_timestamp: None,
+            genesis_block_number: None,
+            chain_id: None,
+            max_tx_gas: None,
+            validation_gas_limit: None,
+            refunds_enabled: true,
+            nonce_check: true,
+            allow_zero_gas_price: false,
+            auto_impersonate: false,
+            fee_accrual: true,
+            max_pubdata_per_block: None,
+            max_logs_returned: None,
+            gas_metering: true,
+            default_call_sender: None,
+            mempool_order: MempoolOrder::Fifo,
+            mempool_max: None,
+            max_call_depth: None,
+            base_token: None,
+            l1_gas_price: None,
+            automine_batch_window_ms: 0,
+            block_time: 1,
+            empty_seal_fast: false,
+            deterministic_addresses: false,
+            protocol_version: None,
         }
+    }
+}
 
-        let tx: Transaction = l2_tx.into();
-        vm.push_transaction(tx);
+impl<S: ForkSource + std::fmt::Debug> InMemoryNodeBuilder<S> {
+    /// Forks the node from the given network.
+    pub fn fork(mut self, fork: ForkDetails<S>) -> Self {
+        self.fork = Some(fork);
+        self
+    }
 
-        let call_tracer_result = Arc::new(OnceCell::default());
+    pub fn show_calls(mut self, show_calls: ShowCalls) -> Self {
+        self.show_calls = show_calls;
+        self
+    }
 
-        let custom_tracers =
-            vec![
-                Box::new(CallTracer::new(call_tracer_result.clone(), HistoryDisabled))
-                    as Box<dyn VmTracer<StorageView<&ForkStorage<S>>, HistoryDisabled>>,
-            ];
+    pub fn show_storage_logs(mut self, show_storage_logs: ShowStorageLogs) -> Self {
+        self.show_storage_logs = show_storage_logs;
+        self
+    }
 
-        let tx_result = vm.inspect(custom_tracers, vm::VmExecutionMode::OneTx);
+    pub fn show_vm_details(mut self, show_vm_details: ShowVMDetails) -> Self {
+        self.show_vm_details = show_vm_details;
+        self
+    }
 
-        let call_traces = Arc::try_unwrap(call_tracer_result)
-            .unwrap()
-            .take()
-            .unwrap_or_default();
+    pub fn show_gas_details(mut self, show_gas_details: ShowGasDetails) -> Self {
+        self.show_gas_details = show_gas_details;
+        self
+    }
 
-        match &tx_result.result {
-            ExecutionResult::Success { output } => {
-                log::info!("Call: {}", "SUCCESS".green());
-                let output_bytes = zksync_basic_types::Bytes::from(output.clone());
-                log::info!("Output: {}", serde_json::to_string(&output_bytes).unwrap());
-            }
-            ExecutionResult::Revert { output } => {
-                log::info!("Call: {}: {}", "FAILED".red(), output);
-            }
-            ExecutionResult::Halt { reason } => log::info!("Call: {} {}", "HALTED".red(), reason),
-        };
+    /// If true - will contact openchain to resolve the ABI to function names.
+    pub fn resolve_hashes(mut self, resolve_hashes: bool) -> Self {
+        self.resolve_hashes = resolve_hashes;
+        self
+    }
 
-        log::info!("=== Console Logs: ");
-        for call in &call_traces {
-            inner.console_log_handler.handle_call_recursive(call);
-        }
+    pub fn system_contracts_options(mut self, options: system_contracts::Options) -> Self {
+        self.system_contracts_options = options;
+        self
+    }
 
-        log::info!("=== Call traces:");
-        for call in &call_traces {
-            formatter::print_call(call, 0, &inner.show_calls, inner.resolve_hashes);
-        }
+    /// Sets the timestamp of the genesis block. When forking, defaults to the timestamp of the fork block and must not
+    /// be before it - see [validate_fork_genesis], an invalid override is logged and ignored.
+    pub fn genesis_timestamp(mut self, timestamp: u64) -> Self {
+        self.genesis_timestamp = Some(timestamp);
+        self
+    }
 
-        Ok(tx_result.result)
+    /// Sets the number of the genesis block. When forking, defaults to the number of the fork block and must not
+    /// be before it - see [validate_fork_genesis], an invalid override is logged and ignored.
+    pub fn genesis_block_number(mut self, block_number: u64) -> Self {
+        self.genesis_block_number = Some(block_number);
+        self
     }
 
-    fn display_detailed_gas_info(
-        &self,
-        bootloader_debug_result: Option<&eyre::Result<BootloaderDebug, String>>,
-        spent_on_pubdata: u32,
-    ) -> eyre::Result<(), String> {
-        if let Some(bootloader_result) = bootloader_debug_result {
-            let debug = bootloader_result.clone()?;
+    /// Sets the chain id of the network. Defaults to `260`, or to the chain id of the replayed transaction.
+    pub fn chain_id(mut self, chain_id: u16) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
 
-            log::info!("┌─────────────────────────┐");
-            log::info!("│       GAS DETAILS       │");
-            log::info!("└─────────────────────────┘");
+    /// Sets the maximum gas limit of a single transaction. Transactions above it are rejected when submitted.
+    pub fn max_tx_gas(mut self, max_tx_gas: u64) -> Self {
+        self.max_tx_gas = Some(max_tx_gas);
+        self
+    }
 
-            // Total amount of gas (should match tx.gas_limit).
-            let total_gas_limit = debug
-                .total_gas_limit_from_user
-                .saturating_sub(debug.reserved_gas);
+    /// Sets the maximum gas the account (and paymaster) validation of a transaction can use. Transactions above it
+    /// are halted as failing the validation.
+    pub fn validation_gas_limit(mut self, validation_gas_limit: u64) -> Self {
+        self.validation_gas_limit = Some(validation_gas_limit);
+        self
+    }
 
-            let intrinsic_gas = total_gas_limit - debug.gas_limit_after_intrinsic;
-            let gas_for_validation = debug.gas_limit_after_intrinsic - debug.gas_after_validation;
+    /// If false - the unused gas of transactions is not refunded, to test the worst-case fee scenarios.
+    pub fn refunds_enabled(mut self, refunds_enabled: bool) -> Self {
+        self.refunds_enabled = refunds_enabled;
+        self
+    }
 
-            let gas_spent_on_compute =
-                debug.gas_spent_on_execution - debug.gas_spent_on_bytecode_preparation;
-
-            let gas_used = intrinsic_gas
-                + gas_for_validation
-                + debug.gas_spent_on_bytecode_preparation
-                + gas_spent_on_compute;
-
-            log::info!(
-                "Gas - Limit: {} | Used: {} | Refunded: {}",
-                to_human_size(total_gas_limit),
-                to_human_size(gas_used),
-                to_human_size(debug.refund_by_operator)
-            );
-
-            if debug.total_gas_limit_from_user != total_gas_limit {
-                log::info!(
-                    "{}",
-                    format!(
-                "  WARNING: user actually provided more gas {}, but system had a lower max limit.",
-                to_human_size(debug.total_gas_limit_from_user)
-            )
-                    .yellow()
-                );
-            }
-            if debug.refund_computed != debug.refund_by_operator {
-                log::info!(
-                    "{}",
-                    format!(
-                        "  WARNING: Refund by VM: {}, but operator refunded more: {}",
-                        to_human_size(debug.refund_computed),
-                        to_human_size(debug.refund_by_operator)
-                    )
-                    .yellow()
-                );
-            }
-
-            if debug.refund_computed + gas_used != total_gas_limit {
-                log::info!(
-                    "{}",
-                    format!(
-                        "  WARNING: Gas totals don't match. {} != {} , delta: {}",
-                        to_human_size(debug.refund_computed + gas_used),
-                        to_human_size(total_gas_limit),
-                        to_human_size(total_gas_limit.abs_diff(debug.refund_computed + gas_used))
-                    )
-                    .yellow()
-                );
-            }
-
-            let bytes_published = spent_on_pubdata / debug.gas_per_pubdata.as_u32();
-
-            log::info!(
-                "During execution published {} bytes to L1, @{} each - in total {} gas",
-                to_human_size(bytes_published.into()),
-                to_human_size(debug.gas_per_pubdata),
-                to_human_size(spent_on_pubdata.into())
-            );
-
-            log::info!("Out of {} gas used, we spent:", to_human_size(gas_used));
-            log::info!(
-                "  {:>15} gas ({:>2}%) for transaction setup",
-                to_human_size(intrinsic_gas),
-                to_human_size(intrinsic_gas * 100 / gas_used)
-            );
-            log::info!(
-                "  {:>15} gas ({:>2}%) for bytecode preparation (decompression etc)",
-                to_human_size(debug.gas_spent_on_bytecode_preparation),
-                to_human_size(debug.gas_spent_on_bytecode_preparation * 100 / gas_used)
-            );
-            log::info!(
-                "  {:>15} gas ({:>2}%) for account validation",
-                to_human_size(gas_for_validation),
-                to_human_size(gas_for_validation * 100 / gas_used)
-            );
-            log::info!(
-                "  {:>15} gas ({:>2}%) for computations (opcodes)",
-                to_human_size(gas_spent_on_compute),
-                to_human_size(gas_spent_on_compute * 100 / gas_used)
-            );
-
-            log::info!("");
-            log::info!("");
-            log::info!(
-                "{}",
-                "=== Transaction setup cost breakdown ===".to_owned().bold(),
-            );
+    /// If false - transactions are accepted and executed whatever their nonce, in the order they were submitted.
+    pub fn nonce_check(mut self, nonce_check: bool) -> Self {
+        self.nonce_check = nonce_check;
+        self
+    }
 
-            log::info!("Total cost: {}", to_human_size(intrinsic_gas).bold());
-            log::info!(
-                "  {:>15} gas ({:>2}%) fixed cost",
-                to_human_size(debug.intrinsic_overhead),
-                to_human_size(debug.intrinsic_overhead * 100 / intrinsic_gas)
-            );
-            log::info!(
-                "  {:>15} gas ({:>2}%) operator cost",
-                to_human_size(debug.operator_overhead),
-                to_human_size(debug.operator_overhead * 100 / intrinsic_gas)
-            );
+    /// If true - transactions with a zero gas price are accepted instead of being rejected, e.g. to test gasless
+    /// flows. Off by default.
+    pub fn allow_zero_gas_price(mut self, allow_zero_gas_price: bool) -> Self {
+        self.allow_zero_gas_price = allow_zero_gas_price;
+        self
+    }
 
-            log::info!("");
-            log::info!(
-                "  FYI: operator could have charged up to: {}, so you got {}% discount",
-                to_human_size(debug.required_overhead),
-                to_human_size(
-                    (debug.required_overhead - debug.operator_overhead) * 100
-                        / debug.required_overhead
-                )
-            );
+    /// If true - every account is impersonated, so that transactions are accepted from any `from` without a
+    /// signature. Off by default.
+    pub fn auto_impersonate(mut self, auto_impersonate: bool) -> Self {
+        self.auto_impersonate = auto_impersonate;
+        self
+    }
 
-            let publish_block_l1_bytes = BLOCK_OVERHEAD_PUBDATA;
-            log::info!(
-            "Publishing full block costs the operator up to: {}, where {} is due to {} bytes published to L1",
-            to_human_size(debug.total_overhead_for_block),
-            to_human_size(debug.gas_per_pubdata * publish_block_l1_bytes),
-            to_human_size(publish_block_l1_bytes.into())
-        );
-            log::info!("Your transaction has contributed to filling up the block in the following way (we take the max contribution as the cost):");
-            log::info!(
-                "  Circuits overhead:{:>15} ({}% of the full block: {})",
-                to_human_size(debug.overhead_for_circuits),
-                to_human_size(debug.overhead_for_circuits * 100 / debug.total_overhead_for_block),
-                to_human_size(debug.total_overhead_for_block)
-            );
-            log::info!(
-                "  Length overhead:  {:>15}",
-                to_human_size(debug.overhead_for_length)
-            );
-            log::info!(
-                "  Slot overhead:    {:>15}",
-                to_human_size(debug.overhead_for_slot)
-            );
-            Ok(())
-        } else {
-            Err("Booloader tracer didn't finish.".to_owned())
-        }
+    /// If false - the fees paid by transactions are burned, instead of being credited to the coinbase.
+    pub fn fee_accrual(mut self, fee_accrual: bool) -> Self {
+        self.fee_accrual = fee_accrual;
+        self
     }
 
-    /// Executes the given L2 transaction and returns all the VM logs.
-    pub fn run_l2_tx_inner(
-        &self,
-        l2_tx: L2Tx,
-        execution_mode: TxExecutionMode,
-    ) -> Result<L2TxResult, String> {
-        let inner = self
-            .inner
-            .write()
-            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+    /// Seals a block once the pubdata published by its transactions reaches this many bytes.
+    pub fn max_pubdata_per_block(mut self, max_pubdata_per_block: u64) -> Self {
+        self.max_pubdata_per_block = Some(max_pubdata_per_block);
+        self
+    }
 
-        let storage = StorageView::new(&inner.fork_storage).to_rc_ptr();
+    /// Fails the `eth_getLogs` requests matching more than this many logs.
+    pub fn max_logs_returned(mut self, max_logs_returned: usize) -> Self {
+        self.max_logs_returned = Some(max_logs_returned);
+        self
+    }
 
-        let (batch_env, block_ctx) = inner.create_l1_batch_env(storage.clone());
+    /// If false - calls ignore their gas limit and run with the maximum gas. For debugging only.
+    pub fn gas_metering(mut self, gas_metering: bool) -> Self {
+        self.gas_metering = gas_metering;
+        self
+    }
 
-        // if we are impersonating an account, we need to use non-verifying system contracts
-        let nonverifying_contracts;
-        let bootloader_code = {
-            if inner
-                .impersonated_accounts
-                .contains(&l2_tx.common_data.initiator_address)
-            {
-                tracing::info!(
-                    "🕵️ Executing tx from impersonated account {:?}",
-                    l2_tx.common_data.initiator_address
-                );
-                nonverifying_contracts =
-                    SystemContracts::from_options(&Options::BuiltInWithoutSecurity);
-                nonverifying_contracts.contracts(execution_mode)
-            } else {
-                inner.system_contracts.contracts(execution_mode)
-            }
-        };
-        let system_env = inner.create_system_env(bootloader_code.clone(), execution_mode);
+    /// Sets the sender of the calls that do not set `from` - default: the zero address.
+    pub fn default_call_sender(mut self, default_call_sender: Address) -> Self {
+        self.default_call_sender = Some(default_call_sender);
+        self
+    }
 
-        let mut vm = Vm::new(
-            batch_env.clone(),
-            system_env,
-            storage.clone(),
-            HistoryDisabled,
-        );
+    /// Sets the order in which the pending transactions of different senders are mined - default: FIFO.
+    pub fn mempool_order(mut self, mempool_order: MempoolOrder) -> Self {
+        self.mempool_order = mempool_order;
+        self
+    }
 
-        let tx: Transaction = l2_tx.clone().into();
+    /// Sets the maximum number of transactions in the pool - unbounded by default.
+    pub fn mempool_max(mut self, mempool_max: usize) -> Self {
+        self.mempool_max = Some(mempool_max);
+        self
+    }
 
-        vm.push_transaction(tx.clone());
+    /// Sets the maximum depth of the calls - counted from the called contract, whose frame is at depth 1.
+    /// Unbounded by default, besides the gas of the execution.
+    pub fn max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = Some(max_call_depth);
+        self
+    }
 
-        let call_tracer_result = Arc::new(OnceCell::default());
-        let bootloader_debug_result = Arc::new(OnceCell::default());
+    /// Sets the base token of the chain, in whose units the balances are logged. Defaults to ETH.
+    pub fn base_token(mut self, base_token: BaseToken) -> Self {
+        self.base_token = Some(base_token);
+        self
+    }
 
-        let custom_tracers = vec![
-            Box::new(CallTracer::new(call_tracer_result.clone(), HistoryDisabled))
-                as Box<dyn VmTracer<StorageView<&ForkStorage<S>>, HistoryDisabled>>,
-            Box::new(BootloaderDebugTracer {
-                result: bootloader_debug_result.clone(),
-            }) as Box<dyn VmTracer<StorageView<&ForkStorage<S>>, HistoryDisabled>>,
-        ];
+    /// Sets the L1 gas price. Defaults to `50 gwei`, or to the L1 gas price of the fork block.
+    pub fn l1_gas_price(mut self, l1_gas_price: u64) -> Self {
+        self.l1_gas_price = Some(l1_gas_price);
+        self
+    }
 
-        let tx_result = vm.inspect(custom_tracers, vm::VmExecutionMode::OneTx);
+    /// Batches the transactions submitted within the given window (in milliseconds) into a single block,
+    /// instead of mining a block per transaction. Requires [InMemoryNode::spawn_automine_batch_miner].
+    pub fn automine_batch_window_ms(mut self, automine_batch_window_ms: u64) -> Self {
+        self.automine_batch_window_ms = automine_batch_window_ms;
+        self
+    }
 
-        let call_traces = call_tracer_result.get().unwrap();
+    /// Sets the timestamp increment (in seconds) of the empty blocks mined by `evm_mine`. Defaults to `1`.
+    pub fn block_time(mut self, block_time: u64) -> Self {
+        self.block_time = block_time;
+        self
+    }
 
-        let spent_on_pubdata =
-            tx_result.statistics.gas_used - tx_result.statistics.computational_gas_used;
+    /// If true - when mining several empty blocks at once (e.g. with `hardhat_mine`), only the last one is sealed by
+    /// the VM, and the preceding ones are synthesized. Off by default.
+    pub fn empty_seal_fast(mut self, empty_seal_fast: bool) -> Self {
+        self.empty_seal_fast = empty_seal_fast;
+        self
+    }
 
-        log::info!("┌─────────────────────────┐");
-        log::info!("│   TRANSACTION SUMMARY   │");
-        log::info!("└─────────────────────────┘");
+    /// If true - the addresses of the locally deployed contracts are derived from a deployment counter instead of
+    /// the deployment nonce of their deployer, so the same deployments always yield the same addresses.
+    pub fn deterministic_addresses(mut self, deterministic_addresses: bool) -> Self {
+        self.deterministic_addresses = deterministic_addresses;
+        self
+    }
 
-        match &tx_result.result {
-            ExecutionResult::Success { .. } => log::info!("Transaction: {}", "SUCCESS".green()),
-            ExecutionResult::Revert { .. } => log::info!("Transaction: {}", "FAILED".red()),
-            ExecutionResult::Halt { .. } => log::info!("Transaction: {}", "HALTED".red()),
-        }
+    /// Sets the protocol version the transactions are executed with. Defaults to the latest version.
+    pub fn protocol_version(mut self, protocol_version: ProtocolVersionId) -> Self {
+        self.protocol_version = Some(protocol_version);
+        self
+    }
 
-        log::info!("Initiator: {:?}", tx.initiator_account());
-        log::info!("Payer: {:?}", tx.payer());
-        log::info!(
-            "Gas - Limit: {} | Used: {} | Refunded: {}",
-            to_human_size(tx.gas_limit()),
-            to_human_size(tx.gas_limit() - tx_result.refunds.gas_refunded),
-            to_human_size(tx_result.refunds.gas_refunded.into())
+    /// Creates the [InMemoryNode].
+    pub fn build(self) -> InMemoryNode<S> {
+        let is_fork = self.fork.is_some();
+        let node = InMemoryNode::new(
+            self.fork,
+            self.show_calls,
+            self.show_storage_logs,
+            self.show_vm_details,
+            self.show_gas_details,
+            self.resolve_hashes,
+            &self.system_contracts_options,
         );
-
-        match inner.show_gas_details {
-            ShowGasDetails::None => log::info!(
-                "Use --show-gas-details flag or call config_setShowGasDetails to display more info"
-            ),
-            ShowGasDetails::All => {
-                if self
-                    .display_detailed_gas_info(bootloader_debug_result.get(), spent_on_pubdata)
-                    .is_err()
-                {
-                    log::info!(
-                        "{}",
-                        "!!! FAILED TO GET DETAILED GAS INFO !!!".to_owned().red()
-                    );
+        if self.genesis_timestamp.is_some() || self.genesis_block_number.is_some() {
+            match node.inner.write() {
+                Ok(mut inner) => {
+                    if let Err(err) = inner.override_genesis(
+                        is_fork,
+                        self.genesis_timestamp,
+                        self.genesis_block_number,
+                    ) {
+                        log::error!("Failed overriding the genesis block: {}", err);
+                    }
                 }
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
             }
         }
-
-        if inner.show_storage_logs != ShowStorageLogs::None {
-            log::info!("");
-            log::info!("┌──────────────────┐");
-            log::info!("│   STORAGE LOGS   │");
-            log::info!("└──────────────────┘");
+        if let Some(chain_id) = self.chain_id {
+            match node.inner.write() {
+                Ok(mut inner) => inner.fork_storage.set_chain_id(L2ChainId(chain_id)),
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
         }
-
-        for log_query in &tx_result.logs.storage_logs {
-            match inner.show_storage_logs {
-                ShowStorageLogs::Write => {
-                    if matches!(
-                        log_query.log_type,
-                        StorageLogQueryType::RepeatedWrite | StorageLogQueryType::InitialWrite
-                    ) {
-                        formatter::print_logs(log_query);
-                    }
-                }
-                ShowStorageLogs::Read => {
-                    if log_query.log_type == StorageLogQueryType::Read {
-                        formatter::print_logs(log_query);
-                    }
-                }
-                ShowStorageLogs::All => {
-                    formatter::print_logs(log_query);
-                }
-                _ => {}
+        if self.max_tx_gas.is_some() {
+            match node.inner.write() {
+                Ok(mut inner) => inner.max_tx_gas = self.max_tx_gas,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
             }
         }
-
-        if inner.show_vm_details != ShowVMDetails::None {
-            formatter::print_vm_details(&tx_result);
+        if self.validation_gas_limit.is_some() {
+            match node.inner.write() {
+                Ok(mut inner) => inner.validation_gas_limit = self.validation_gas_limit,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
         }
-
-        log::info!("");
-        log::info!("==== Console logs: ");
-        for call in call_traces {
-            inner.console_log_handler.handle_call_recursive(call);
+        if !self.refunds_enabled {
+            match node.inner.write() {
+                Ok(mut inner) => inner.refunds_enabled = false,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
         }
-        log::info!("");
-        let call_traces_count = if !call_traces.is_empty() {
-            // All calls/sub-calls are stored within the first call trace
-            call_traces[0].calls.len()
-        } else {
-            0
-        };
-        log::info!(
-            "==== {} Use --show-calls flag or call config_setShowCalls to display more info.",
-            format!("{:?} call traces. ", call_traces_count).bold()
-        );
-
-        if inner.show_calls != ShowCalls::None {
-            for call in call_traces {
-                formatter::print_call(call, 0, &inner.show_calls, inner.resolve_hashes);
+        if !self.nonce_check {
+            match node.inner.write() {
+                Ok(mut inner) => inner.nonce_check = false,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
             }
         }
-        log::info!("");
-        log::info!(
-            "==== {}",
-            format!("{} events", tx_result.logs.events.len()).bold()
-        );
-        for event in &tx_result.logs.events {
-            formatter::print_event(event, inner.resolve_hashes);
+        if self.allow_zero_gas_price {
+            match node.inner.write() {
+                Ok(mut inner) => inner.allow_zero_gas_price = true,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.auto_impersonate {
+            match node.inner.write() {
+                Ok(mut inner) => inner.auto_impersonate = true,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if !self.fee_accrual {
+            match node.inner.write() {
+                Ok(mut inner) => inner.fee_accrual = false,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.max_pubdata_per_block.is_some() {
+            match node.inner.write() {
+                Ok(mut inner) => inner.max_pubdata_per_block = self.max_pubdata_per_block,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.max_logs_returned.is_some() {
+            match node.inner.write() {
+                Ok(mut inner) => inner.max_logs_returned = self.max_logs_returned,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if !self.gas_metering {
+            match node.inner.write() {
+                Ok(mut inner) => inner.gas_metering = false,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if let Some(default_call_sender) = self.default_call_sender {
+            match node.inner.write() {
+                Ok(mut inner) => inner.default_call_sender = default_call_sender,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.mempool_order != MempoolOrder::Fifo {
+            match node.inner.write() {
+                Ok(mut inner) => inner.mempool_order = self.mempool_order,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if let Some(mempool_max) = self.mempool_max {
+            match node.inner.write() {
+                Ok(mut inner) => inner.mempool_max = Some(mempool_max),
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if let Some(max_call_depth) = self.max_call_depth {
+            match node.inner.write() {
+                Ok(mut inner) => inner.max_call_depth = Some(max_call_depth),
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if let Some(base_token) = self.base_token {
+            match node.inner.write() {
+                Ok(mut inner) => inner.base_token = base_token,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if let Some(l1_gas_price) = self.l1_gas_price {
+            match node.inner.write() {
+                Ok(mut inner) => inner.l1_gas_price = l1_gas_price,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.automine_batch_window_ms > 0 {
+            match node.inner.write() {
+                Ok(mut inner) => inner.automine_batch_window_ms = self.automine_batch_window_ms,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.block_time != 1 {
+            match node.inner.write() {
+                Ok(mut inner) => inner.block_time = self.block_time,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.empty_seal_fast {
+            match node.inner.write() {
+                Ok(mut inner) => inner.empty_seal_fast = true,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if self.deterministic_addresses {
+            match node.inner.write() {
+                Ok(mut inner) => inner.deterministic_addresses = true,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
+        }
+        if let Some(protocol_version) = self.protocol_version {
+            match node.inner.write() {
+                Ok(mut inner) => inner.protocol_version = protocol_version,
+                Err(e) => log::error!("Failed to acquire write lock: {}", e),
+            }
         }
 
-        // The computed block hash here will be different than that in production.
-        let hash = compute_hash(block_ctx.miniblock, l2_tx.hash());
+        node
+    }
 
-        let mut transaction = zksync_types::api::Transaction::from(l2_tx);
-        let block_hash = inner
-            .block_hashes
-            .get(&inner.current_miniblock)
-            .ok_or(format!(
-                "Block hash not found for block: {}",
-                inner.current_miniblock
-            ))?;
-        transaction.block_hash = Some(*block_hash);
-        transaction.block_number = Some(U64::from(inner.current_miniblock));
+    /// Creates the [InMemoryNode], and returns a [NodeHandle] to drive it.
+    pub fn build_handle(self) -> NodeHandle<S> {
+        NodeHandle::new(self.build())
+    }
+}
 
-        let block = Block {
-            hash,
-            number: U64::from(block_ctx.miniblock),
-            timestamp: U256::from(batch_env.timestamp),
-            l1_batch_number: Some(U64::from(batch_env.number.0)),
-            transactions: vec![TransactionVariant::Full(transaction)],
-            gas_used: U256::from(tx_result.statistics.gas_used),
-            gas_limit: U256::from(BLOCK_GAS_LIMIT),
-            ..Default::default()
-        };
+/// Handle to drive an [InMemoryNode] in-process (e.g. from Rust integration tests), without going through
+/// a JSON-RPC server.
+pub struct NodeHandle<S> {
+    node: InMemoryNode<S>,
+}
 
-        log::info!("");
-        log::info!("");
+impl<S: ForkSource + std::fmt::Debug> NodeHandle<S> {
+    /// Creates a handle for the given `node`.
+    pub fn new(node: InMemoryNode<S>) -> Self {
+        Self { node }
+    }
 
-        let bytecodes = vm
-            .get_last_tx_compressed_bytecodes()
-            .iter()
-            .map(|b| bytecode_to_factory_dep(b.original.clone()))
-            .collect();
+    /// Returns the underlying node - e.g. to call the RPC namespaces it implements directly.
+    pub fn node(&self) -> &InMemoryNode<S> {
+        &self.node
+    }
 
-        vm.execute(vm::VmExecutionMode::Bootloader);
+    /// Decodes a raw (RLP-encoded and signed) transaction, and submits it to the node.
+    /// Returns the hash of the transaction.
+    pub fn send_raw_transaction(&self, tx_bytes: &[u8]) -> Result<H256, String> {
+        let l2_tx = self.node.decode_raw_tx(tx_bytes)?;
+        self.submit_tx(l2_tx)
+    }
 
-        let modified_keys = storage.borrow().modified_storage_keys().clone();
-        Ok((
-            modified_keys,
-            tx_result,
-            call_traces.clone(),
-            block,
-            bytecodes,
-            block_ctx,
-        ))
+    /// Submits a transaction to the node - see [InMemoryNode::submit_tx].
+    /// Returns the hash of the transaction.
+    pub fn submit_tx(&self, l2_tx: L2Tx) -> Result<H256, String> {
+        let hash = l2_tx.hash();
+        self.node.submit_tx(l2_tx)?;
+        Ok(hash)
     }
 
-    /// Runs L2 transaction and commits it to a new block.
-    fn run_l2_tx(&self, l2_tx: L2Tx, execution_mode: TxExecutionMode) -> Result<(), String> {
-        let tx_hash = l2_tx.hash();
-        log::info!("");
-        log::info!("Executing {}", format!("{:?}", tx_hash).bold());
+    /// Enables or disables the automatic execution of submitted transactions.
+    pub fn set_automine(&self, enabled: bool) -> Result<(), String> {
+        self.node
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+            .automine = enabled;
+        Ok(())
+    }
 
-        {
-            let mut inner = self
-                .inner
-                .write()
-                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
-            inner.filters.notify_new_pending_transaction(tx_hash);
-        }
+    /// Mines a single block - see [InMemoryNode::mine_block].
+    pub fn mine(&self) -> Result<(), String> {
+        self.node.mine_block()
+    }
 
-        let (keys, result, call_traces, block, bytecodes, block_ctx) =
-            self.run_l2_tx_inner(l2_tx.clone(), execution_mode)?;
+    /// Returns the number of the latest block.
+    pub fn block_number(&self) -> Result<u64, String> {
+        self.read(|inner| inner.current_miniblock)
+    }
 
-        if let ExecutionResult::Halt { reason } = result.result {
-            // Halt means that something went really bad with the transaction execution (in most cases invalid signature,
-            // but it could also be bootloader panic etc).
-            // In such case, we should not persist the VM data, and we should pretend that transaction never existed.
-            return Err(format!("Transaction HALT: {}", reason));
-        }
-        // Write all the mutated keys (storage slots).
-        let mut inner = self
+    /// Returns the latest balance of the given account, in wei.
+    pub fn balance(&self, address: Address) -> Result<U256, String> {
+        self.read(|inner| {
+            let balance = (&inner.fork_storage).read_value(&storage_key_for_eth_balance(&address));
+            h256_to_u256(balance)
+        })
+    }
+
+    /// Returns the latest transaction nonce of the given account.
+    pub fn nonce(&self, address: Address) -> Result<Nonce, String> {
+        self.read(|inner| inner.get_account_nonce(&address))
+    }
+
+    /// Returns the latest value of the given storage slot.
+    pub fn storage_at(&self, address: Address, slot: U256) -> Result<H256, String> {
+        self.read(|inner| {
+            let key = StorageKey::new(AccountTreeId::new(address), u256_to_h256(slot));
+            (&inner.fork_storage).read_value(&key)
+        })
+    }
+
+    /// Returns the receipt of an executed transaction.
+    pub fn transaction_receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>, String> {
+        self.read(|inner| {
+            inner
+                .tx_results
+                .get(&hash)
+                .map(|tx_result| tx_result.receipt.clone())
+        })
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&InMemoryNodeInner<S>) -> T) -> Result<T, String> {
+        self.node
             .inner
-            .write()
-            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
-        for (key, value) in keys.iter() {
-            inner.fork_storage.set_value(*key, *value);
-        }
+            .read()
+            .map(|inner| f(&inner))
+            .map_err(|e| format!("Failed to acquire read lock: {}", e))
+    }
+}
 
-        // Write all the factory deps.
-        for (hash, code) in bytecodes.iter() {
-            inner.fork_storage.store_factory_dep(
-                u256_to_h256(*hash),
-                code.iter()
-                    .flat_map(|entry| {
-                        let mut bytes = vec![0u8; 32];
-                        entry.to_big_endian(&mut bytes);
-                        bytes.to_vec()
-                    })
-                    .collect(),
-            )
-        }
+impl<S: ForkSource + std::fmt::Debug> InMemoryNode<S> {
+    pub fn new(
+        fork: Option<ForkDetails<S>>,
+        show_calls: ShowCalls,
+        show_storage_logs: ShowStorageLogs,
+        show_vm_details: ShowVMDetails,
+        show_gas_details: ShowGasDetails,
+        resolve_hashes: bool,
+        system_contracts_options: &system_contracts::Options,
+    ) -> Self {
+        let inner = if let Some(f) = &fork {
+            let mut block_hashes = HashMap::<u64, H256>::new();
+            block_hashes.insert(f.l2_block.number.as_u64(), f.l2_block.hash);
+            let mut blocks = HashMap::<H256, Block<TransactionVariant>>::new();
+            blocks.insert(f.l2_block.hash, f.l2_block.clone());
 
-        for (log_idx, event) in result.logs.events.iter().enumerate() {
-            inner.filters.notify_new_log(
-                &Log {
-                    address: event.address,
-                    topics: event.indexed_topics.clone(),
-                    data: Bytes(event.value.clone()),
-                    block_hash: Some(block.hash),
-                    block_number: Some(block.number),
-                    l1_batch_number: block.l1_batch_number,
-                    transaction_hash: Some(tx_hash),
-                    transaction_index: Some(U64::zero()),
-                    log_index: Some(U256::from(log_idx)),
-                    transaction_log_index: Some(U256::from(log_idx)),
-                    log_type: None,
-                    removed: None,
-                },
-                block.number,
+            InMemoryNodeInner {
+                current_timestamp: f.block_timestamp,
+                current_batch: f.l1_block.0,
+                current_miniblock: f.l2_miniblock,
+                current_miniblock_hash: f.l2_miniblock_hash,
+                l1_gas_price: f.l1_gas_price,
+                tx_results: Default::default(),
+                blocks,
+                block_hashes,
+                filters: Default::default(),
+                fork_storage: ForkStorage::new(fork, system_contracts_options),
+                show_calls,
+                show_storage_logs,
+                show_vm_details,
+                show_gas_details,
+                resolve_hashes,
+                console_log_handler: ConsoleLogHandler::default(),
+                system_contracts: SystemContracts::from_options(system_contracts_options),
+                impersonated_accounts: Default::default(),
+                auto_impersonate: false,
+                rich_accounts: HashSet::new(),
+                previous_states: Default::default(),
+                pool: Default::default(),
+                automine: true,
+                coinbase: Address::zero(),
+                prev_randao: H256::from_low_u64_be(DEFAULT_PREV_RANDAO),
+                max_tx_gas: None,
+                validation_gas_limit: None,
+                refunds_enabled: true,
+                nonce_check: true,
+                allow_zero_gas_price: false,
+                fee_accrual: true,
+                max_pubdata_per_block: None,
+                max_logs_returned: None,
+                gas_metering: true,
+                default_call_sender: Address::zero(),
+                mempool_order: MempoolOrder::Fifo,
+                mempool_max: None,
+                max_call_depth: None,
+                base_token: BaseToken::default(),
+                next_block_balances: Default::default(),
+                next_block_txs: Default::default(),
+                mocked_calls: Default::default(),
+                expected_revert: None,
+                automine_batch_window_ms: 0,
+                automine_batch_deadline: None,
+                interval_mining_ms: 0,
+                interval_mining_deadline: None,
+                simulated_sync: None,
+                block_time: 1,
+                next_block_timestamp_set: false,
+                empty_seal_fast: false,
+                deterministic_addresses: false,
+                deployment_counter: 0,
+                protocol_version: ProtocolVersionId::latest(),
+            }
+        } else {
+            let mut block_hashes = HashMap::<u64, H256>::new();
+            block_hashes.insert(0, H256::zero());
+            let mut blocks = HashMap::<H256, Block<TransactionVariant>>::new();
+            blocks.insert(
+                H256::zero(),
+                create_empty_block(0, NON_FORK_FIRST_BLOCK_TIMESTAMP, 0),
             );
-        }
-        let tx_receipt = TransactionReceipt {
-            transaction_hash: tx_hash,
-            transaction_index: U64::from(0),
-            block_hash: Some(block.hash),
-            block_number: Some(block.number),
-            l1_batch_tx_index: None,
-            l1_batch_number: block.l1_batch_number,
-            from: l2_tx.initiator_account(),
-            to: Some(l2_tx.recipient_account()),
-            cumulative_gas_used: Default::default(),
-            gas_used: Some(l2_tx.common_data.fee.gas_limit - result.refunds.gas_refunded),
-            contract_address: contract_address_from_tx_result(&result),
-            logs: result
-                .logs
-                .events
-                .iter()
-                .enumerate()
-                .map(|(log_idx, log)| Log {
-                    address: log.address,
-                    topics: log.indexed_topics.clone(),
-                    data: Bytes(log.value.clone()),
-                    block_hash: Some(block.hash),
-                    block_number: Some(block.number),
-                    l1_batch_number: block.l1_batch_number,
-                    transaction_hash: Some(tx_hash),
-                    transaction_index: Some(U64::zero()),
-                    log_index: Some(U256::from(log_idx)),
-                    transaction_log_index: Some(U256::from(log_idx)),
-                    log_type: None,
-                    removed: None,
-                })
-                .collect(),
-            l2_to_l1_logs: vec![],
-            status: Some(if result.result.is_failed() {
-                U64::from(0)
-            } else {
-                U64::from(1)
-            }),
-            effective_gas_price: Some(L2_GAS_PRICE.into()),
-            ..Default::default()
-        };
-        let debug = create_debug_output(&l2_tx, &result, call_traces).expect("create debug output"); // OK to unwrap here as Halt is handled above
-        inner.tx_results.insert(
-            tx_hash,
-            TransactionResult {
-                info: TxExecutionInfo {
-                    tx: l2_tx,
-                    batch_number: block.l1_batch_number.unwrap_or_default().as_u32(),
-                    miniblock_number: block.number.as_u64(),
-                    result,
-                },
-                receipt: tx_receipt,
-                debug,
-            },
-        );
 
-        // With the introduction of 'l2 blocks' (and virtual blocks),
-        // we are adding one l2 block at the end of each batch (to handle things like remaining events etc).
-        //  You can look at insert_fictive_l2_block function in VM to see how this fake block is inserted.
-        let block_ctx = block_ctx.new_block();
-        let empty_block_at_end_of_batch =
-            create_empty_block(block_ctx.miniblock, block_ctx.timestamp, block_ctx.batch);
+            InMemoryNodeInner {
+                current_timestamp: NON_FORK_FIRST_BLOCK_TIMESTAMP,
+                current_batch: 0,
+                current_miniblock: 0,
+                current_miniblock_hash: H256::zero(),
+                l1_gas_price: L1_GAS_PRICE,
+                tx_results: Default::default(),
+                blocks,
+                block_hashes,
+                filters: Default::default(),
+                fork_storage: ForkStorage::new(fork, system_contracts_options),
+                show_calls,
+                show_storage_logs,
+                show_vm_details,
+                show_gas_details,
+                resolve_hashes,
+                console_log_handler: ConsoleLogHandler::default(),
+                system_contracts: SystemContracts::from_options(system_contracts_options),
+                impersonated_accounts: Default::default(),
+                auto_impersonate: false,
+                rich_accounts: HashSet::new(),
+                previous_states: Default::default(),
+                pool: Default::default(),
+                automine: true,
+                coinbase: Address::zero(),
+                prev_randao: H256::from_low_u64_be(DEFAULT_PREV_RANDAO),
+                max_tx_gas: None,
+                validation_gas_limit: None,
+                refunds_enabled: true,
+                nonce_check: true,
+                allow_zero_gas_price: false,
+                fee_accrual: true,
+                max_pubdata_per_block: None,
+                max_logs_returned: None,
+                gas_metering: true,
+                default_call_sender: Address::zero(),
+                mempool_order: MempoolOrder::Fifo,
+                mempool_max: None,
+                max_call_depth: None,
+                base_token: BaseToken::default(),
+                next_block_balances: Default::default(),
+                next_block_txs: Default::default(),
+                mocked_calls: Default::default(),
+                expected_revert: None,
+                automine_batch_window_ms: 0,
+                automine_batch_deadline: None,
+                interval_mining_ms: 0,
+                interval_mining_deadline: None,
+                simulated_sync: None,
+                block_time: 1,
+                next_block_timestamp_set: false,
+                empty_seal_fast: false,
+                deterministic_addresses: false,
+                deployment_counter: 0,
+                protocol_version: ProtocolVersionId::latest(),
+            }
+        };
 
-        inner.current_batch = inner.current_batch.saturating_add(1);
+        InMemoryNode {
+            inner: Arc::new(RwLock::new(inner)),
+        }
+    }
 
-        for block in vec![block, empty_block_at_end_of_batch] {
-            // archive current state before we produce new batch/blocks
-            if let Err(err) = inner.archive_state() {
-                log::error!(
-                    "failed archiving state for block {}: {}",
-                    inner.current_miniblock,
-                    err
-                );
-            }
+    pub fn get_inner(&self) -> Arc<RwLock<InMemoryNodeInner<S>>> {
+        self.inner.clone()
+    }
 
-            inner.current_miniblock = inner.current_miniblock.saturating_add(1);
-            inner.current_timestamp = inner.current_timestamp.saturating_add(1);
+    /// Creates a node that operates on an already existing (shared) [InMemoryNodeInner].
+    pub fn from_inner(inner: Arc<RwLock<InMemoryNodeInner<S>>>) -> Self {
+        InMemoryNode { inner }
+    }
 
-            let actual_l1_batch_number = block
-                .l1_batch_number
-                .expect("block must have a l1_batch_number");
-            if actual_l1_batch_number.as_u32() != inner.current_batch {
-                panic!(
-                    "expected next block to have batch_number {}, got {}",
-                    inner.current_batch,
-                    actual_l1_batch_number.as_u32()
-                );
+    /// Submits a transaction to the node.
+    /// With automine enabled, the transaction is executed right away if its nonce follows the sender's account nonce,
+    /// otherwise it is kept in the [TxPool] until the gap is filled. With an automine batch window, the transaction is
+    /// kept in the [TxPool] until the window elapses, and then mined in a single block with the rest of the batch.
+    /// With automine disabled, all transactions are kept in the [TxPool] until a block is mined.
+    /// Transactions above the configured maximum gas limit are rejected, and so are the transactions that do not fit
+    /// in a full [TxPool], and the transactions with a zero gas price unless they are allowed.
+    pub fn submit_tx(&self, l2_tx: L2Tx) -> Result<(), String> {
+        let execute_now = {
+            let mut inner = self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            if let Some(max_tx_gas) = inner.max_tx_gas {
+                let gas_limit = l2_tx.common_data.fee.gas_limit;
+                if gas_limit > U256::from(max_tx_gas) {
+                    return Err(format!(
+                        "transaction gas limit {} exceeds the maximum of {}",
+                        gas_limit, max_tx_gas
+                    ));
+                }
             }
-
-            if block.number.as_u64() != inner.current_miniblock {
-                panic!(
-                    "expected next block to have miniblock {}, got {}",
-                    inner.current_miniblock,
-                    block.number.as_u64()
+            if !inner.allow_zero_gas_price && l2_tx.common_data.fee.max_fee_per_gas.is_zero() {
+                return Err(
+                    "transaction gas price is zero, which is only accepted with --allow-zero-gas-price"
+                        .to_string(),
                 );
             }
+            let account_nonce = inner.get_account_nonce(&l2_tx.initiator_account());
+            let batching = inner.automine && inner.automine_batch_window_ms > 0;
 
-            if block.timestamp.as_u64() != inner.current_timestamp {
-                panic!(
-                    "expected next block to have timestamp {}, got {}",
-                    inner.current_timestamp,
-                    block.timestamp.as_u64()
+            if inner.automine && !batching && (l2_tx.nonce() <= account_nonce || !inner.nonce_check)
+            {
+                true
+            } else if inner.nonce_check && l2_tx.nonce() < account_nonce {
+                return Err(format!(
+                    "nonce too low: expected {}, got {}",
+                    account_nonce.0,
+                    l2_tx.nonce().0
+                ));
+            } else {
+                match inner.mempool_max {
+                    Some(mempool_max) => {
+                        if let Some(evicted) = inner.pool.add_bounded(l2_tx.clone(), mempool_max)? {
+                            log::info!(
+                                "Evicted transaction {:#x} from the full pool",
+                                evicted.hash()
+                            );
+                        }
+                    }
+                    None => {
+                        inner.pool.add(l2_tx.clone());
+                    }
+                }
+                log::info!(
+                    "Added transaction {} to the pool",
+                    format!("{:?}", l2_tx.hash()).bold()
                 );
+                if batching && inner.automine_batch_deadline.is_none() {
+                    inner.automine_batch_deadline = Some(
+                        Instant::now() + Duration::from_millis(inner.automine_batch_window_ms),
+                    );
+                }
+                false
             }
+        };
 
-            let block_hash = block.hash;
-            inner.current_miniblock_hash = block_hash;
-            inner.block_hashes.insert(block.number.as_u64(), block.hash);
-            inner.blocks.insert(block.hash, block);
-            inner.filters.notify_new_block(block_hash);
+        if execute_now {
+            self.run_l2_tx(l2_tx, TxExecutionMode::VerifyExecute)?;
+            // the executed transaction could have filled a nonce gap
+            self.mine_pending_txs()?;
         }
 
         Ok(())
     }
-}
 
-/// Keeps track of a block's batch number, miniblock number and timestamp.
-/// Useful for keeping track of the current context when creating multiple blocks.
-pub struct BlockContext {
-    pub batch: u32,
-    pub miniblock: u64,
-    pub timestamp: u64,
-}
+    /// Executes all the pending transactions from the [TxPool] - still one per L1 batch.
+    /// Returns the number of transactions that were executed successfully.
+    pub fn mine_pending_txs(&self) -> Result<usize, String> {
+        let txs = {
+            let mut inner = self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            let account_nonces = inner
+                .pool
+                .senders()
+                .map(|sender| (*sender, inner.get_account_nonce(sender)))
+                .collect::<HashMap<_, _>>();
+            let order = inner.mempool_order;
+            if inner.nonce_check {
+                inner.pool.take_pending(order, |sender| {
+                    account_nonces.get(sender).copied().unwrap_or_default()
+                })
+            } else {
+                inner.pool.take_all()
+            }
+        };
 
-impl BlockContext {
-    /// Create the current instance that represents the latest block.
-    pub fn from_current(batch: u32, miniblock: u64, timestamp: u64) -> Self {
-        Self {
-            batch,
-            miniblock,
-            timestamp,
+        let mut executed = 0;
+        for tx in txs {
+            let tx_hash = tx.hash();
+            match self.run_l2_tx(tx, TxExecutionMode::VerifyExecute) {
+                Ok(_) => executed += 1,
+                Err(err) => log::error!(
+                    "failed executing pending transaction {:#x}: {}",
+                    tx_hash,
+                    err
+                ),
+            }
         }
+
+        Ok(executed)
     }
 
-    /// Create the next batch instance that has all parameters incremented by `1`.
-    pub fn new_batch(&self) -> Self {
-        Self {
-            batch: self.batch.saturating_add(1),
-            miniblock: self.miniblock.saturating_add(1),
-            timestamp: self.timestamp.saturating_add(1),
+    /// Mines the current automine batch: executes all the pending transactions from the [TxPool] in a single block
+    /// (transactions of impersonated accounts go in a separate block, as they need non-verifying system contracts).
+    /// Returns the number of transactions that were executed successfully.
+    pub fn mine_automine_batch(&self) -> Result<usize, String> {
+        let (impersonated_txs, txs): (Vec<_>, Vec<_>) = {
+            let mut inner = self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            inner.automine_batch_deadline = None;
+            let account_nonces = inner
+                .pool
+                .senders()
+                .map(|sender| (*sender, inner.get_account_nonce(sender)))
+                .collect::<HashMap<_, _>>();
+            let order = inner.mempool_order;
+            let txs = if inner.nonce_check {
+                inner.pool.take_pending(order, |sender| {
+                    account_nonces.get(sender).copied().unwrap_or_default()
+                })
+            } else {
+                inner.pool.take_all()
+            };
+            txs.into_iter()
+                .partition(|tx| inner.is_impersonated(&tx.initiator_account()))
+        };
+
+        let mut executed = 0;
+        for txs in vec![txs, impersonated_txs] {
+            if txs.is_empty() {
+                continue;
+            }
+            match self.run_l2_txs(txs, TxExecutionMode::VerifyExecute) {
+                Ok(count) => executed += count,
+                Err(err) => log::error!("failed mining the automine batch: {}", err),
+            }
         }
+
+        Ok(executed)
     }
 
-    /// Create the next batch instance that uses the same batch number, and has all other parameters incremented by `1`.
-    pub fn new_block(&self) -> BlockContext {
-        Self {
-            batch: self.batch,
-            miniblock: self.miniblock.saturating_add(1),
-            timestamp: self.timestamp.saturating_add(1),
+    /// Simulates a chain reorganization: the latest `depth` blocks are rolled back, and the given transactions are
+    /// executed on top of the remaining chain instead (still one per L1 batch).
+    ///
+    /// Block hashes only depend on the block number and the transaction, so a replacement block without transactions
+    /// would get the hash of the block it replaces. Such blocks are re-hashed, so that consumers observe the reorg.
+    ///
+    /// The reorg is atomic: if any of the transactions fails, the node is restored to its state before the reorg.
+    pub fn reorg(&self, depth: u64, txs: Vec<L2Tx>) -> Result<(), String> {
+        let (snapshot, removed) = {
+            let mut inner = self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            let snapshot = inner.snapshot()?;
+            (snapshot, inner.rollback_blocks(depth)?)
+        };
+        log::info!("Rolled back {} blocks", removed.len());
+
+        for tx in txs {
+            if let Err(err) = self.run_l2_tx(tx, TxExecutionMode::VerifyExecute) {
+                self.inner
+                    .write()
+                    .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+                    .restore_snapshot(snapshot)?;
+                log::info!("Restored the {} rolled back blocks", removed.len());
+                return Err(err);
+            }
         }
-    }
-}
 
-impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EthNamespaceT for InMemoryNode<S> {
-    /// Returns the chain ID of the node.
-    fn chain_id(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::U64>> {
-        match self.inner.read() {
-            Ok(inner) => Ok(U64::from(inner.fork_storage.chain_id.0 as u64)).into_boxed_future(),
-            Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        for (number, removed_hash) in removed {
+            let reused = inner.block_hashes.get(&number) == Some(&removed_hash)
+                && inner
+                    .blocks
+                    .get(&removed_hash)
+                    .map_or(false, |block| block.transactions.is_empty());
+            if reused {
+                inner.rehash_block(number, compute_hash(number, removed_hash));
+            }
         }
+
+        Ok(())
     }
 
-    /// Calls the specified function on the L2 contract with the given arguments.
-    ///
-    /// # Arguments
-    ///
-    /// * `req` - The call request containing the function name and arguments.
-    /// * `_block` - The block ID variant (unused).
-    ///
-    /// # Returns
-    ///
-    /// A boxed future containing the result of the function call.
-    fn call(
+    /// Decodes a raw (RLP-encoded and signed) transaction, using the chain id of the node.
+    pub fn decode_raw_tx(&self, tx_bytes: &[u8]) -> Result<L2Tx, String> {
+        let chain_id = self
+            .inner
+            .read()
+            .map_err(|e| format!("Failed to acquire read lock: {}", e))?
+            .fork_storage
+            .chain_id;
+        let (tx_req, hash) = decode_raw_tx_request(tx_bytes, chain_id)
+            .map_err(|e| format!("Failed decoding transaction: {}", e))?;
+        let mut l2_tx = L2Tx::from_request(tx_req, MAX_TX_SIZE)
+            .map_err(|e| format!("Invalid transaction: {}", e))?;
+        l2_tx.set_input(tx_bytes.to_vec(), hash);
+
+        Ok(l2_tx)
+    }
+
+    /// Builds an unsigned transaction from the request, and submits it to the node - see [InMemoryNode::submit_tx].
+    /// Only the impersonated accounts can send unsigned transactions. Unset fields are filled with defaults: the
+    /// next nonce of the sender (including its pending transactions), and the L2 gas price.
+    /// Returns the hash of the transaction.
+    pub fn send_impersonated_tx(
         &self,
-        req: zksync_types::transaction_request::CallRequest,
-        _block: Option<zksync_types::api::BlockIdVariant>,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::Bytes>> {
-        match L2Tx::from_request(req.into(), MAX_TX_SIZE) {
-            Ok(mut tx) => {
-                tx.common_data.fee.gas_limit = ETH_CALL_GAS_LIMIT.into();
-                let result = self.run_l2_call(tx);
+        request: zksync_types::transaction_request::CallRequest,
+    ) -> Result<H256, String> {
+        let from = request
+            .from
+            .ok_or_else(|| "missing `from` in the transaction".to_string())?;
+        let nonce = {
+            let reader = self
+                .inner
+                .read()
+                .map_err(|e| format!("Failed to acquire read lock: {}", e))?;
+            if !reader.is_impersonated(&from) {
+                return Err(format!(
+                    "account {:?} is not impersonated, sign the transaction and use eth_sendRawTransaction instead",
+                    from
+                ));
+            }
+            let account_nonce = reader.get_account_nonce(&from);
+            Nonce(account_nonce.0 + reader.pool.pending_count(&from, account_nonce) as u32)
+        };
+        let nonce_is_set = request.nonce.is_some();
 
-                match result {
-                    Ok(execution_result) => match execution_result {
-                        ExecutionResult::Success { output } => {
-                            Ok(output.into()).into_boxed_future()
-                        }
-                        ExecutionResult::Revert { output } => {
-                            let message = output.to_user_friendly_string();
-                            let pretty_message = format!(
-                                "execution reverted{}{}",
-                                if message.is_empty() { "" } else { ": " },
-                                message
-                            );
+        let mut l2_tx = L2Tx::from_request(request.into(), MAX_TX_SIZE)
+            .map_err(|e| format!("Invalid transaction: {}", e))?;
+        if !nonce_is_set {
+            l2_tx.common_data.nonce = nonce;
+        }
+        let fee = &mut l2_tx.common_data.fee;
+        if fee.gas_limit.is_zero() {
+            fee.gas_limit = U256::from(SEND_TRANSACTION_GAS_LIMIT);
+        }
+        if fee.max_fee_per_gas.is_zero() {
+            fee.max_fee_per_gas = U256::from(L2_GAS_PRICE);
+        }
+        fee.max_priority_fee_per_gas = fee.max_priority_fee_per_gas.min(fee.max_fee_per_gas);
+        if fee.gas_per_pubdata_limit.is_zero() {
+            fee.gas_per_pubdata_limit = U256::from(MAX_GAS_PER_PUBDATA_BYTE);
+        }
+        // the signature is not verified for impersonated accounts, but the bootloader needs *some* signature
+        l2_tx.common_data.signature = PackedEthSignature::default().serialize_packed().into();
+        let hash = impersonated_tx_hash(&l2_tx);
+        l2_tx.set_input(vec![], hash);
 
-                            log::info!("{}", pretty_message.on_red());
-                            Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                                pretty_message,
-                                output.encoded_data(),
-                            )))
-                            .into_boxed_future()
-                        }
-                        ExecutionResult::Halt { reason } => {
-                            let message = reason.to_string();
-                            let pretty_message = format!(
-                                "execution halted {}{}",
-                                if message.is_empty() { "" } else { ": " },
-                                message
-                            );
+        self.submit_tx(l2_tx)?;
+        Ok(hash)
+    }
 
-                            log::info!("{}", pretty_message.on_red());
-                            Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                                pretty_message,
-                                vec![],
-                            )))
-                            .into_boxed_future()
-                        }
-                    },
-                    Err(e) => {
-                        let error = Web3Error::InvalidTransactionData(
-                            zksync_types::ethabi::Error::InvalidName(e),
-                        );
-                        Err(into_jsrpc_error(error)).into_boxed_future()
-                    }
-                }
-            }
+    /// Mines a single block: executes the pending transactions from the [TxPool] (still one per L1 batch),
+    /// or produces an empty block if there are none. If [InMemoryNodeInner::next_block_txs] are set, they are
+    /// mined in a single block instead, and the pending transactions are left in the [TxPool].
+    pub fn mine_block(&self) -> Result<(), String> {
+        let next_block_txs = std::mem::take(
+            &mut self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+                .next_block_txs,
+        );
+        if !next_block_txs.is_empty() {
+            self.run_l2_txs(next_block_txs, TxExecutionMode::VerifyExecute)?;
+            return Ok(());
+        }
+
+        if self.mine_pending_txs()? > 0 {
+            return Ok(());
+        }
+
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        utils::mine_empty_blocks(&mut inner, 1, 1000)?;
+        log::info!("👷 Mined block #{}", inner.current_miniblock);
+        Ok(())
+    }
+
+    /// Applies multiple transactions - but still one per L1 batch.
+    pub fn apply_txs(&self, txs: Vec<L2Tx>) -> Result<(), String> {
+        log::info!("Running {:?} transactions (one per batch)", txs.len());
+
+        for tx in txs {
+            self.run_l2_tx(tx, TxExecutionMode::VerifyExecute)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a lot of tokens to a given account.
+    pub fn set_rich_account(&self, address: H160) {
+        let key = storage_key_for_eth_balance(&address);
+
+        let mut inner = match self.inner.write() {
+            Ok(guard) => guard,
             Err(e) => {
-                let error = Web3Error::SerializationError(e);
-                Err(into_jsrpc_error(error)).into_boxed_future()
+                log::info!("Failed to acquire write lock: {}", e);
+                return;
             }
+        };
+
+        let keys = {
+            let mut storage_view = StorageView::new(&inner.fork_storage);
+            storage_view.set_value(key, u256_to_h256(U256::from(RICH_ACCOUNT_BALANCE)));
+            storage_view.modified_storage_keys().clone()
+        };
+
+        for (key, value) in keys.iter() {
+            inner.fork_storage.set_value(*key, *value);
         }
+        inner.rich_accounts.insert(address);
     }
 
-    /// Returns the balance of the specified address.
-    ///
-    /// # Arguments
-    ///
-    /// * `address` - The address to get the balance of.
-    /// * `_block` - The block ID variant (optional).
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` that resolves to a `Result` containing the balance of the specified address as a `U256` or a `jsonrpc_core::Error` if an error occurred.
-    fn get_balance(
+    /// Takes the revert expected by `config_expectNextCallRevert`, if any. The write lock is only acquired when an
+    /// expectation is armed, so that calls do not contend on it otherwise.
+    fn take_expected_revert(&self) -> Option<ExpectedRevert> {
+        let is_armed = self
+            .inner
+            .read()
+            .map(|reader| reader.expected_revert.is_some())
+            .unwrap_or_default();
+        if !is_armed {
+            return None;
+        }
+
+        self.inner
+            .write()
+            .ok()
+            .and_then(|mut writer| writer.expected_revert.take())
+    }
+
+    /// Runs L2 'eth call' method - that doesn't commit to a block.
+    /// The call is executed against the state at `block`, or the latest state if not set.
+    fn run_l2_call(
         &self,
-        address: zksync_basic_types::Address,
-        _block: Option<zksync_types::api::BlockIdVariant>,
-    ) -> BoxFuture<Result<U256, jsonrpc_core::Error>> {
-        let inner = Arc::clone(&self.inner);
+        mut l2_tx: L2Tx,
+        block: Option<BlockIdVariant>,
+    ) -> Result<ExecutionResult, String> {
+        let inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
 
-        Box::pin(async move {
-            let balance_key = storage_key_for_standard_token_balance(
-                AccountTreeId::new(L2_ETH_TOKEN_ADDRESS),
-                &address,
-            );
+        // We must inject *some* signature (otherwise bootloader code fails to generate hash).
+        if l2_tx.common_data.signature.is_empty() {
+            l2_tx.common_data.signature = PackedEthSignature::default().serialize_packed().into();
+        }
 
-            match inner.write() {
-                Ok(mut inner_guard) => {
-                    let balance = inner_guard.fork_storage.read_value(&balance_key);
-                    Ok(h256_to_u256(balance))
-                }
-                Err(_) => {
-                    let web3_error = Web3Error::InternalError;
-                    Err(into_jsrpc_error(web3_error))
-                }
+        let block_number = inner.resolve_block_number(block)?;
+        match inner.historical_storage(block_number)? {
+            Some((storage, last_block_ctx)) => {
+                log::debug!("running call against the state at block #{}", block_number);
+                Self::run_l2_call_with_storage(&inner, storage, Some(last_block_ctx), l2_tx)
             }
-        })
+            None => Self::run_l2_call_with_storage(&inner, &inner.fork_storage, None, l2_tx),
+        }
     }
 
-    /// Returns a block by its number.
-    ///
-    /// # Arguments
-    ///
-    /// * `block_number` - A `BlockNumber` enum variant representing the block number to retrieve.
-    /// * `full_transactions` - A boolean value indicating whether to retrieve full transactions or not.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `Option` of `zksync_types::api::Block<zksync_types::api::TransactionVariant>`.
-    fn get_block_by_number(
+    /// Simulates the transactions of a bundle in order, on top of the state at `state_block`, without committing
+    /// them. Halted transactions are reported, and the bundle is executed again without them - as the VM state
+    /// can not be reused after a halt.
+    pub fn simulate_bundle(
         &self,
-        block_number: zksync_types::api::BlockNumber,
-        full_transactions: bool,
-    ) -> BoxFuture<
-        jsonrpc_core::Result<
-            Option<zksync_types::api::Block<zksync_types::api::TransactionVariant>>,
-        >,
-    > {
-        let inner = Arc::clone(&self.inner);
+        txs: Vec<L2Tx>,
+        block: u64,
+        state_block: Option<BlockIdVariant>,
+    ) -> Result<Vec<BundleTransactionResult>, String> {
+        if txs.is_empty() {
+            return Err("the bundle has no transactions".to_string());
+        }
+        let inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        let state_block_number = inner.resolve_block_number(state_block)?;
+        if block <= state_block_number {
+            return Err(format!(
+                "block #{} of the bundle must follow the state block #{}",
+                block, state_block_number
+            ));
+        }
 
-        Box::pin(async move {
-            let maybe_block = {
-                let reader = match inner.read() {
-                    Ok(r) => r,
-                    Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)),
-                };
-                let number =
-                    utils::to_real_block_number(block_number, U64::from(reader.current_miniblock))
-                        .as_u64();
+        let mut halted = HashMap::new();
+        let mut executed = txs.clone();
+        let results = loop {
+            let results = match inner.historical_storage(state_block_number)? {
+                Some((storage, last_block_ctx)) => Self::run_l2_bundle_with_storage(
+                    &inner,
+                    storage,
+                    Some(last_block_ctx),
+                    &executed,
+                ),
+                None => {
+                    Self::run_l2_bundle_with_storage(&inner, &inner.fork_storage, None, &executed)
+                }
+            };
 
-                reader
-                    .block_hashes
-                    .get(&number)
-                    .and_then(|hash| reader.blocks.get(hash))
-                    .cloned()
-                    .or_else(|| {
-                        reader
-                            .fork_storage
-                            .inner
-                            .read()
-                            .expect("failed reading fork storage")
-                            .fork
-                            .as_ref()
-                            .and_then(|fork| {
-                                fork.fork_source
-                                    .get_block_by_number(block_number, true)
-                                    .ok()
-                                    .flatten()
-                            })
-                    })
-            };
+            let halted_indices = results
+                .iter()
+                .enumerate()
+                .filter_map(|(index, result)| match &result.result {
+                    ExecutionResult::Halt { reason } => Some((index, reason.to_string())),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if halted_indices.is_empty() {
+                break results;
+            }
+            for (index, reason) in halted_indices.into_iter().rev() {
+                let l2_tx = executed.remove(index);
+                halted.insert(l2_tx.hash(), reason);
+            }
+        };
 
-            match maybe_block {
-                Some(mut block) => {
-                    let block_hash = block.hash;
-                    block.transactions = block
-                        .transactions
-                        .into_iter()
-                        .map(|transaction| match &transaction {
-                            TransactionVariant::Full(inner) => {
-                                if full_transactions {
-                                    transaction
-                                } else {
-                                    TransactionVariant::Hash(inner.hash)
-                                }
+        let mut results = executed
+            .iter()
+            .map(|l2_tx| l2_tx.hash())
+            .zip(results)
+            .collect::<HashMap<_, _>>();
+        Ok(txs
+            .iter()
+            .map(|l2_tx| {
+                let tx_hash = l2_tx.hash();
+                match results.remove(&tx_hash) {
+                    Some(result) => {
+                        let gas_used =
+                            l2_tx.common_data.fee.gas_limit - result.refunds.gas_refunded;
+                        let (return_data, error) = match result.result {
+                            ExecutionResult::Success { output } => (Bytes(output), None),
+                            ExecutionResult::Revert { output } => {
+                                (Bytes::default(), Some(format!("Revert: {}", output)))
                             }
-                            TransactionVariant::Hash(_) => {
-                                if full_transactions {
-                                    panic!(
-                                        "unexpected non full transaction for block {}",
-                                        block_hash
-                                    )
-                                } else {
-                                    transaction
-                                }
+                            ExecutionResult::Halt { reason } => {
+                                (Bytes::default(), Some(format!("Halt: {}", reason)))
                             }
-                        })
-                        .collect();
-
-                    Ok(Some(block))
+                        };
+                        BundleTransactionResult {
+                            tx_hash,
+                            gas_used,
+                            return_data,
+                            error,
+                        }
+                    }
+                    None => BundleTransactionResult {
+                        tx_hash,
+                        gas_used: U256::zero(),
+                        return_data: Bytes::default(),
+                        error: halted
+                            .get(&tx_hash)
+                            .map(|reason| format!("Halt: {}", reason)),
+                    },
                 }
-                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+            })
+            .collect())
+    }
+
+    /// Executes the transactions of a bundle in a single block against the given storage, and returns their
+    /// results. If `last_block_ctx` is set, the block follows it, otherwise it follows the latest block.
+    fn run_l2_bundle_with_storage<ST: ReadStorage + std::fmt::Debug>(
+        inner: &InMemoryNodeInner<S>,
+        storage: ST,
+        last_block_ctx: Option<BlockContext>,
+        txs: &[L2Tx],
+    ) -> Vec<VmExecutionResultAndLogs> {
+        let execution_mode = TxExecutionMode::VerifyExecute;
+        let storage = StorageView::new(storage).to_rc_ptr();
+        let bootloader_code = inner.system_contracts.contracts(execution_mode);
+
+        let (batch_env, _) = match last_block_ctx {
+            Some(last_block_ctx) => {
+                inner.create_l1_batch_env_after(storage.clone(), last_block_ctx)
             }
-        })
+            None => inner.create_l1_batch_env(storage.clone()),
+        };
+        let system_env = inner.create_system_env(bootloader_code.clone(), execution_mode);
+        let mut vm = Vm::new(batch_env, system_env, storage, HistoryDisabled);
+
+        txs.iter()
+            .map(|l2_tx| {
+                vm.push_transaction(l2_tx.clone().into());
+                vm.execute(vm::VmExecutionMode::OneTx)
+            })
+            .collect()
     }
 
-    /// Returns the code stored at the specified address.
-    ///
-    /// # Arguments
-    ///
-    /// * `address` - The address to retrieve the code from.
-    /// * `_block` - An optional block ID variant.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing the result of the operation, which is a `jsonrpc_core::Result` containing
-    /// the code as a `zksync_basic_types::Bytes` object.
-    fn get_code(
-        &self,
-        address: zksync_basic_types::Address,
-        _block: Option<zksync_types::api::BlockIdVariant>,
-    ) -> BoxFuture<jsonrpc_core::Result<zksync_basic_types::Bytes>> {
-        let inner = Arc::clone(&self.inner);
+    /// Runs L2 'eth call' method against the given storage.
+    /// If `last_block_ctx` is set, the call is executed in a batch that follows this block,
+    /// otherwise in the one following the latest block.
+    fn run_l2_call_with_storage<ST: ReadStorage + std::fmt::Debug>(
+        inner: &InMemoryNodeInner<S>,
+        storage: ST,
+        last_block_ctx: Option<BlockContext>,
+        l2_tx: L2Tx,
+    ) -> Result<ExecutionResult, String> {
+        let execution_mode = TxExecutionMode::EthCall;
+        // only the reads of this call are checked
+        inner.fork_storage.take_read_error();
 
-        Box::pin(async move {
-            let code_key = get_code_key(&address);
+        let storage = StorageView::new(storage).to_rc_ptr();
 
-            match inner.write() {
-                Ok(mut guard) => {
-                    let code_hash = guard.fork_storage.read_value(&code_key);
+        let bootloader_code = inner.system_contracts.contracts_for_l2_call();
 
-                    let code = guard
-                        .fork_storage
-                        .load_factory_dep_internal(code_hash)
-                        .unwrap_or_default();
+        // init vm
 
-                    Ok(Bytes::from(code))
-                }
-                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+        let (batch_env, _) = match last_block_ctx {
+            Some(last_block_ctx) => {
+                inner.create_l1_batch_env_after(storage.clone(), last_block_ctx)
             }
-        })
-    }
+            None => inner.create_l1_batch_env(storage.clone()),
+        };
+        let system_env = inner.create_system_env(bootloader_code.clone(), execution_mode);
 
-    /// Returns the transaction count for a given address.
-    ///
-    /// # Arguments
-    ///
-    /// * `address` - The address to get the transaction count for.
-    /// * `_block` - Optional block ID variant.
-    ///
-    /// # Returns
-    ///
-    /// Returns a `BoxFuture` containing the transaction count as a `U256` wrapped in a `jsonrpc_core::Result`.
-    fn get_transaction_count(
-        &self,
-        address: zksync_basic_types::Address,
-        _block: Option<zksync_types::api::BlockIdVariant>,
-    ) -> BoxFuture<jsonrpc_core::Result<U256>> {
-        let inner = Arc::clone(&self.inner);
+        // The bootloader does not set the gas price of calls, so `tx.gasprice` is set directly - to the
+        // `max_fee_per_gas` of the call, or to the base fee if it is not set.
+        let gas_price = if l2_tx.common_data.fee.max_fee_per_gas.is_zero() {
+            let (base_fee, _) = derive_base_fee_and_gas_per_pubdata(
+                batch_env.l1_gas_price,
+                batch_env.fair_l2_gas_price,
+            );
+            U256::from(base_fee)
+        } else {
+            l2_tx.common_data.fee.max_fee_per_gas
+        };
+        storage.borrow_mut().set_value(
+            StorageKey::new(
+                AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+                H256::from_low_u64_be(SYSTEM_CONTEXT_GAS_PRICE_POSITION),
+            ),
+            u256_to_h256(gas_price),
+        );
 
-        Box::pin(async move {
-            let nonce_key = get_nonce_key(&address);
+        let mut vm = Vm::new(batch_env, system_env, storage, HistoryDisabled);
 
-            match inner.write() {
-                Ok(mut guard) => {
-                    let result = guard.fork_storage.read_value(&nonce_key);
-                    Ok(h256_to_u64(result).into())
-                }
-                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
-            }
-        })
-    }
+        let entry_address = l2_tx.recipient_account();
+        let tx: Transaction = l2_tx.into();
+        vm.push_transaction(tx);
 
-    /// Retrieves the transaction receipt for a given transaction hash.
-    ///
-    /// # Arguments
-    ///
-    /// * `hash` - The hash of the transaction to retrieve the receipt for.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` that resolves to an `Option` of a `TransactionReceipt` or an error.
-    fn get_transaction_receipt(
-        &self,
-        hash: zksync_basic_types::H256,
-    ) -> BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::TransactionReceipt>>> {
-        let inner = Arc::clone(&self.inner);
+        let call_tracer_result = Arc::new(OnceCell::default());
+        let call_depth_result = Arc::new(OnceCell::default());
 
-        Box::pin(async move {
-            let reader = match inner.read() {
-                Ok(r) => r,
-                Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)),
-            };
+        let mut custom_tracers =
+            vec![
+                Box::new(CallTracer::new(call_tracer_result.clone(), HistoryDisabled))
+                    as Box<dyn VmTracer<StorageView<ST>, HistoryDisabled>>,
+            ];
+        if let Some(max_call_depth) = inner.max_call_depth {
+            custom_tracers.push(Box::new(CallDepthTracer {
+                max_depth: max_call_depth,
+                entry_address,
+                exceeded: call_depth_result.clone(),
+            }));
+        }
+        if !inner.mocked_calls.is_empty() {
+            custom_tracers.push(Box::new(MockedCallsTracer::new(inner.mocked_calls.clone())));
+        }
 
-            let receipt = reader
-                .tx_results
-                .get(&hash)
-                .map(|info| info.receipt.clone());
-            Ok(receipt)
-        })
-    }
+        let mut tx_result = vm.inspect(custom_tracers, vm::VmExecutionMode::OneTx);
+        if let (Some(max_call_depth), Some(_)) = (inner.max_call_depth, call_depth_result.get()) {
+            tx_result.result = call_depth_exceeded(max_call_depth);
+        }
+        if let Some(err) = inner.fork_storage.take_read_error() {
+            tx_result.result = fork_read_failed(err);
+        }
 
-    /// Sends a raw transaction to the L2 network.
-    ///
-    /// # Arguments
-    ///
-    /// * `tx_bytes` - The transaction bytes to send.
-    ///
-    /// # Returns
-    ///
-    /// A future that resolves to the hash of the transaction if successful, or an error if the transaction is invalid or execution fails.
-    fn send_raw_transaction(
-        &self,
-        tx_bytes: zksync_basic_types::Bytes,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::H256>> {
-        let chain_id = match self.inner.read() {
-            Ok(reader) => reader.fork_storage.chain_id,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
-            }
-        };
+        let call_traces = Arc::try_unwrap(call_tracer_result)
+            .unwrap()
+            .take()
+            .unwrap_or_default();
 
-        let (tx_req, hash) = match TransactionRequest::from_bytes(&tx_bytes.0, chain_id.0) {
-            Ok(result) => result,
-            Err(e) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::SerializationError(e)))
-                    .boxed()
+        match &tx_result.result {
+            ExecutionResult::Success { output } => {
+                log::info!("Call: {}", "SUCCESS".green());
+                let output_bytes = zksync_basic_types::Bytes::from(output.clone());
+                log::info!("Output: {}", serde_json::to_string(&output_bytes).unwrap());
             }
-        };
-
-        let mut l2_tx: L2Tx = match L2Tx::from_request(tx_req, MAX_TX_SIZE) {
-            Ok(tx) => tx,
-            Err(e) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::SerializationError(e)))
-                    .boxed()
+            ExecutionResult::Revert { output } => {
+                log::info!("Call: {}: {}", "FAILED".red(), output);
             }
+            ExecutionResult::Halt { reason } => log::info!("Call: {} {}", "HALTED".red(), reason),
         };
 
-        l2_tx.set_input(tx_bytes.0, hash);
-        if hash != l2_tx.hash() {
-            return futures::future::err(into_jsrpc_error(Web3Error::InvalidTransactionData(
-                zksync_types::ethabi::Error::InvalidData,
-            )))
-            .boxed();
-        };
+        log::info!("=== Console Logs: ");
+        for call in &call_traces {
+            inner.console_log_handler.handle_call_recursive(call);
+        }
 
-        match self.run_l2_tx(l2_tx.clone(), TxExecutionMode::VerifyExecute) {
-            Ok(_) => Ok(hash).into_boxed_future(),
-            Err(e) => {
-                let error_message = format!("Execution error: {}", e);
-                futures::future::err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                    error_message,
-                    l2_tx.hash().as_bytes().to_vec(),
-                )))
-                .boxed()
-            }
+        log::info!("=== Call traces:");
+        for call in &call_traces {
+            formatter::print_call(call, 0, &inner.show_calls, inner.resolve_hashes);
         }
+
+        Ok(tx_result.result)
     }
 
-    /// Returns a block by its hash. Currently, only hashes for blocks in memory are supported.
-    ///
-    /// # Arguments
-    ///
-    /// * `hash` - A `H256` type representing the hash of the block to retrieve.
-    /// * `full_transactions` - A boolean value indicating whether to retrieve full transactions or not.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `Option` of `zksync_types::api::Block<zksync_types::api::TransactionVariant>`.
-    fn get_block_by_hash(
+    fn display_detailed_gas_info(
         &self,
-        hash: zksync_basic_types::H256,
-        full_transactions: bool,
-    ) -> jsonrpc_core::BoxFuture<
-        jsonrpc_core::Result<
-            Option<zksync_types::api::Block<zksync_types::api::TransactionVariant>>,
-        >,
-    > {
-        let inner = Arc::clone(&self.inner);
+        bootloader_debug_result: Option<&eyre::Result<BootloaderDebug, String>>,
+        spent_on_pubdata: u32,
+    ) -> eyre::Result<(), String> {
+        if let Some(bootloader_result) = bootloader_debug_result {
+            let debug = bootloader_result.clone()?;
 
-        Box::pin(async move {
-            let maybe_block = {
-                let reader = inner
-                    .read()
-                    .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            log::info!("┌─────────────────────────┐");
+            log::info!("│       GAS DETAILS       │");
+            log::info!("└─────────────────────────┘");
 
-                // try retrieving block from memory, and if unavailable subsequently from the fork
-                reader.blocks.get(&hash).cloned().or_else(|| {
-                    reader
-                        .fork_storage
-                        .inner
-                        .read()
-                        .expect("failed reading fork storage")
-                        .fork
-                        .as_ref()
-                        .and_then(|fork| {
-                            fork.fork_source
-                                .get_block_by_hash(hash, true)
-                                .ok()
-                                .flatten()
-                        })
-                })
-            };
+            // Total amount of gas (should match tx.gas_limit).
+            let total_gas_limit = debug
+                .total_gas_limit_from_user
+                .saturating_sub(debug.reserved_gas);
 
-            match maybe_block {
-                Some(mut block) => {
-                    let block_hash = block.hash;
-                    block.transactions = block
-                        .transactions
-                        .into_iter()
-                        .map(|transaction| match &transaction {
-                            TransactionVariant::Full(inner) => {
-                                if full_transactions {
-                                    transaction
-                                } else {
-                                    TransactionVariant::Hash(inner.hash)
-                                }
-                            }
-                            TransactionVariant::Hash(_) => {
-                                if full_transactions {
-                                    panic!(
-                                        "unexpected non full transaction for block {}",
-                                        block_hash
-                                    )
-                                } else {
-                                    transaction
-                                }
-                            }
-                        })
-                        .collect();
+            let intrinsic_gas = total_gas_limit - debug.gas_limit_after_intrinsic;
+            let gas_for_validation = debug.gas_limit_after_intrinsic - debug.gas_after_validation;
 
-                    Ok(Some(block))
-                }
-                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+            let gas_spent_on_compute =
+                debug.gas_spent_on_execution - debug.gas_spent_on_bytecode_preparation;
+
+            let gas_used = intrinsic_gas
+                + gas_for_validation
+                + debug.gas_spent_on_bytecode_preparation
+                + gas_spent_on_compute;
+
+            log::info!(
+                "Gas - Limit: {} | Used: {} | Refunded: {}",
+                to_human_size(total_gas_limit),
+                to_human_size(gas_used),
+                to_human_size(debug.refund_by_operator)
+            );
+
+            if debug.total_gas_limit_from_user != total_gas_limit {
+                log::info!(
+                    "{}",
+                    format!(
+                "  WARNING: user actually provided more gas {}, but system had a lower max limit.",
+                to_human_size(debug.total_gas_limit_from_user)
+            )
+                    .yellow()
+                );
+            }
+            if debug.refund_computed != debug.refund_by_operator {
+                log::info!(
+                    "{}",
+                    format!(
+                        "  WARNING: Refund by VM: {}, but operator refunded more: {}",
+                        to_human_size(debug.refund_computed),
+                        to_human_size(debug.refund_by_operator)
+                    )
+                    .yellow()
+                );
             }
-        })
-    }
 
-    /// Returns a future that resolves to an optional transaction with the given hash.
-    ///
-    /// # Arguments
-    ///
-    /// * `hash` - A 32-byte hash of the transaction.
-    ///
-    /// # Returns
-    ///
-    /// A `jsonrpc_core::BoxFuture` that resolves to a `jsonrpc_core::Result` containing an optional `zksync_types::api::Transaction`.
-    fn get_transaction_by_hash(
-        &self,
-        hash: zksync_basic_types::H256,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::Transaction>>> {
-        let inner = Arc::clone(&self.inner);
+            if debug.refund_computed + gas_used != total_gas_limit {
+                log::info!(
+                    "{}",
+                    format!(
+                        "  WARNING: Gas totals don't match. {} != {} , delta: {}",
+                        to_human_size(debug.refund_computed + gas_used),
+                        to_human_size(total_gas_limit),
+                        to_human_size(total_gas_limit.abs_diff(debug.refund_computed + gas_used))
+                    )
+                    .yellow()
+                );
+            }
 
-        Box::pin(async move {
-            let reader = inner
-                .read()
-                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            let bytes_published = spent_on_pubdata / debug.gas_per_pubdata.as_u32();
 
-            let maybe_result = {
-                // try retrieving transaction from memory, and if unavailable subsequently from the fork
-                reader.tx_results.get(&hash).and_then(|TransactionResult { info, .. }| {
-                    let input_data = info.tx.common_data.input.clone().or(None)?;
-                    let chain_id = info.tx.common_data.extract_chain_id().or(None)?;
-                    Some(zksync_types::api::Transaction {
-                        hash,
-                        nonce: U256::from(info.tx.common_data.nonce.0),
-                        block_hash: Some(hash),
-                        block_number: Some(U64::from(info.miniblock_number)),
-                        transaction_index: Some(U64::from(1)),
-                        from: Some(info.tx.initiator_account()),
-                        to: Some(info.tx.recipient_account()),
-                        value: info.tx.execute.value,
-                        gas_price: Default::default(),
-                        gas: Default::default(),
-                        input: input_data.data.into(),
-                        v: Some(chain_id.into()),
-                        r: Some(U256::zero()),
-                        s: Some(U256::zero()),
-                        raw: None,
-                        transaction_type: {
-                            let tx_type = match info.tx.common_data.transaction_type {
-                                zksync_types::l2::TransactionType::LegacyTransaction => 0,
-                                zksync_types::l2::TransactionType::EIP2930Transaction => 1,
-                                zksync_types::l2::TransactionType::EIP1559Transaction => 2,
-                                zksync_types::l2::TransactionType::EIP712Transaction => 113,
-                                zksync_types::l2::TransactionType::PriorityOpTransaction => 255,
-                                zksync_types::l2::TransactionType::ProtocolUpgradeTransaction => 254,
-                            };
-                            Some(tx_type.into())
-                        },
-                        access_list: None,
-                        max_fee_per_gas: Some(info.tx.common_data.fee.max_fee_per_gas),
-                        max_priority_fee_per_gas: Some(
-                            info.tx.common_data.fee.max_priority_fee_per_gas,
-                        ),
-                        chain_id: chain_id.into(),
-                        l1_batch_number: Some(U64::from(info.batch_number as u64)),
-                        l1_batch_tx_index: None,
-                    })
-                }).or_else(|| {
-                    reader
-                        .fork_storage
-                        .inner
-                        .read()
-                        .expect("failed reading fork storage")
-                        .fork
-                        .as_ref()
-                        .and_then(|fork| {
-                            fork.fork_source
-                                .get_transaction_by_hash(hash)
-                                .ok()
-                                .flatten()
-                        })
-                })
-            };
+            log::info!(
+                "During execution published {} bytes to L1, @{} each - in total {} gas",
+                to_human_size(bytes_published.into()),
+                to_human_size(debug.gas_per_pubdata),
+                to_human_size(spent_on_pubdata.into())
+            );
 
-            Ok(maybe_result)
-        })
-    }
+            log::info!("Out of {} gas used, we spent:", to_human_size(gas_used));
+            log::info!(
+                "  {:>15} gas ({:>2}%) for transaction setup",
+                to_human_size(intrinsic_gas),
+                to_human_size(intrinsic_gas * 100 / gas_used)
+            );
+            log::info!(
+                "  {:>15} gas ({:>2}%) for bytecode preparation (decompression etc)",
+                to_human_size(debug.gas_spent_on_bytecode_preparation),
+                to_human_size(debug.gas_spent_on_bytecode_preparation * 100 / gas_used)
+            );
+            log::info!(
+                "  {:>15} gas ({:>2}%) for account validation",
+                to_human_size(gas_for_validation),
+                to_human_size(gas_for_validation * 100 / gas_used)
+            );
+            log::info!(
+                "  {:>15} gas ({:>2}%) for computations (opcodes)",
+                to_human_size(gas_spent_on_compute),
+                to_human_size(gas_spent_on_compute * 100 / gas_used)
+            );
 
-    /// Returns the current block number as a `U64` wrapped in a `BoxFuture`.
-    fn get_block_number(
-        &self,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::U64>> {
-        let inner = Arc::clone(&self.inner);
+            log::info!("");
+            log::info!("");
+            log::info!(
+                "{}",
+                "=== Transaction setup cost breakdown ===".to_owned().bold(),
+            );
 
-        Box::pin(async move {
-            let reader = inner
-                .read()
-                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
-            Ok(U64::from(reader.current_miniblock))
-        })
+            log::info!("Total cost: {}", to_human_size(intrinsic_gas).bold());
+            log::info!(
+                "  {:>15} gas ({:>2}%) fixed cost",
+                to_human_size(debug.intrinsic_overhead),
+                to_human_size(debug.intrinsic_overhead * 100 / intrinsic_gas)
+            );
+            log::info!(
+                "  {:>15} gas ({:>2}%) operator cost",
+                to_human_size(debug.operator_overhead),
+                to_human_size(debug.operator_overhead * 100 / intrinsic_gas)
+            );
+
+            log::info!("");
+            log::info!(
+                "  FYI: operator could have charged up to: {}, so you got {}% discount",
+                to_human_size(debug.required_overhead),
+                to_human_size(
+                    (debug.required_overhead - debug.operator_overhead) * 100
+                        / debug.required_overhead
+                )
+            );
+
+            let publish_block_l1_bytes = BLOCK_OVERHEAD_PUBDATA;
+            log::info!(
+            "Publishing full block costs the operator up to: {}, where {} is due to {} bytes published to L1",
+            to_human_size(debug.total_overhead_for_block),
+            to_human_size(debug.gas_per_pubdata * publish_block_l1_bytes),
+            to_human_size(publish_block_l1_bytes.into())
+        );
+            log::info!("Your transaction has contributed to filling up the block in the following way (we take the max contribution as the cost):");
+            log::info!(
+                "  Circuits overhead:{:>15} ({}% of the full block: {})",
+                to_human_size(debug.overhead_for_circuits),
+                to_human_size(debug.overhead_for_circuits * 100 / debug.total_overhead_for_block),
+                to_human_size(debug.total_overhead_for_block)
+            );
+            log::info!(
+                "  Length overhead:  {:>15}",
+                to_human_size(debug.overhead_for_length)
+            );
+            log::info!(
+                "  Slot overhead:    {:>15}",
+                to_human_size(debug.overhead_for_slot)
+            );
+            Ok(())
+        } else {
+            Err("Booloader tracer didn't finish.".to_owned())
+        }
     }
 
-    /// Estimates the gas required for a given call request.
-    ///
-    /// # Arguments
-    ///
-    /// * `req` - A `CallRequest` struct representing the call request to estimate gas for.
-    /// * `_block` - An optional `BlockNumber` struct representing the block number to estimate gas for.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `Result` with a `U256` representing the estimated gas required.
-    fn estimate_gas(
+    /// Executes the given L2 transactions in a single block (of a new L1 batch), and returns all the VM logs.
+    pub fn run_l2_txs_inner(
         &self,
-        req: zksync_types::transaction_request::CallRequest,
-        _block: Option<zksync_types::api::BlockNumber>,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
-        let inner = Arc::clone(&self.inner);
-        let reader = match inner.read() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
-            }
-        };
+        txs: &[L2Tx],
+        execution_mode: TxExecutionMode,
+    ) -> Result<L2TxsResult, String> {
+        if txs.is_empty() {
+            return Err("no transactions to execute".to_string());
+        }
+        let inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
 
-        let result: jsonrpc_core::Result<Fee> = reader.estimate_gas_impl(req);
-        match result {
-            Ok(fee) => Ok(fee.gas_limit).into_boxed_future(),
-            Err(err) => return futures::future::err(err).boxed(),
+        let storage = StorageView::new(&inner.fork_storage).to_rc_ptr();
+        for (address, balance) in &inner.next_block_balances {
+            storage
+                .borrow_mut()
+                .set_value(storage_key_for_eth_balance(address), u256_to_h256(*balance));
         }
-    }
 
-    /// Returns the current gas price in U256 format.
-    fn gas_price(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
-        let fair_l2_gas_price: u64 = L2_GAS_PRICE;
-        Ok(U256::from(fair_l2_gas_price)).into_boxed_future()
-    }
+        let (mut batch_env, block_ctx) = inner.create_l1_batch_env(storage.clone());
+        // zero gas price transactions would fail the fee checks of the bootloader, so the block mining them is
+        // executed with a zero base fee - the same way the fee is enforced when tracing a transaction
+        if inner.allow_zero_gas_price
+            && txs
+                .iter()
+                .any(|l2_tx| l2_tx.common_data.fee.max_fee_per_gas.is_zero())
+        {
+            batch_env.enforced_base_fee = Some(0);
+        }
 
-    /// Creates a filter object, based on filter options, to notify when the state changes (logs).
-    /// To check if the state has changed, call `eth_getFilterChanges`.
-    ///
-    /// # Arguments
-    ///
-    /// * `filter`: The filter options -
-    ///     fromBlock: - Integer block number, or the string "latest", "earliest" or "pending".
-    ///     toBlock: - Integer block number, or the string "latest", "earliest" or "pending".
-    ///     address: - Contract address or a list of addresses from which the logs should originate.
-    ///     topics: - [H256] topics. Topics are order-dependent. Each topic can also be an array with "or" options.
-    ///
-    /// If the from `fromBlock` or `toBlock` option are equal to "latest" the filter continually appends logs for newly mined blocks.
-    /// Topics are order-dependent. A transaction with a log with topics [A, B] will be matched by the following topic filters:
-    ///     * \[\] "anything"
-    ///     * \[A\] "A in first position (and anything after)"
-    ///     * \[null, B\] "anything in first position AND B in second position (and anything after)"
-    ///     * \[A, B\] "A in first position AND B in second position (and anything after)"
-    ///     * \[\[A, B\], \[A, B\]\] "(A OR B) in first position AND (A OR B) in second position (and anything after)"
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
-    fn new_filter(&self, filter: Filter) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
-        let inner = Arc::clone(&self.inner);
-        let mut writer = match inner.write() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+        // if we are impersonating an account, we need to use non-verifying system contracts
+        let impersonated_accounts = txs
+            .iter()
+            .map(|l2_tx| l2_tx.common_data.initiator_address)
+            .filter(|address| inner.is_impersonated(address))
+            .collect::<Vec<_>>();
+        let nonverifying_contracts;
+        let bootloader_code = {
+            if !impersonated_accounts.is_empty() {
+                for address in impersonated_accounts {
+                    tracing::info!("🕵️ Executing tx from impersonated account {:?}", address);
+                    // impersonated contracts run as default accounts, which accept unsigned transactions - their
+                    // code is restored once the block is mined
+                    let code_key = get_code_key(&address);
+                    if !storage.borrow_mut().read_value(&code_key).is_zero() {
+                        storage.borrow_mut().set_value(code_key, H256::zero());
+                    }
+                }
+                nonverifying_contracts =
+                    SystemContracts::from_options(&Options::BuiltInWithoutSecurity);
+                nonverifying_contracts.contracts(execution_mode)
+            } else {
+                inner.system_contracts.contracts(execution_mode)
             }
         };
+        let system_env = inner.create_system_env(bootloader_code.clone(), execution_mode);
 
-        let from_block = filter
-            .from_block
-            .unwrap_or(zksync_types::api::BlockNumber::Latest);
-        let to_block = filter
-            .to_block
-            .unwrap_or(zksync_types::api::BlockNumber::Latest);
-        let addresses = filter.address.unwrap_or_default().0;
-        let mut topics: [Option<HashSet<H256>>; 4] = Default::default();
+        let mut vm = Vm::new(
+            batch_env.clone(),
+            system_env,
+            storage.clone(),
+            HistoryDisabled,
+        );
 
-        if let Some(filter_topics) = filter.topics {
-            filter_topics
-                .into_iter()
-                .take(4)
-                .enumerate()
-                .for_each(|(i, maybe_topic_set)| {
-                    if let Some(topic_set) = maybe_topic_set {
-                        topics[i] = Some(topic_set.0.into_iter().collect());
-                    }
+        let mut results = Vec::with_capacity(txs.len());
+        let mut bytecodes = HashMap::new();
+        let mut withheld_refunds = vec![];
+        let mut deployment_counter = inner.deployment_counter;
+        for l2_tx in txs {
+            let tx: Transaction = l2_tx.clone().into();
+
+            // Without nonce checking, the account nonce of the sender is set to the nonce of the transaction - so that
+            // it passes the validation of the account whatever the nonce of the sender was.
+            if !inner.nonce_check {
+                let nonce_key = get_nonce_key(&l2_tx.initiator_account());
+                let mut storage = storage.borrow_mut();
+                let full_nonce = h256_to_u256(storage.read_value(&nonce_key));
+                let (_, deployment_nonce) = decompose_full_nonce(full_nonce);
+                storage.set_value(
+                    nonce_key,
+                    u256_to_h256(nonces_to_full_nonce(
+                        U256::from(l2_tx.nonce().0),
+                        deployment_nonce,
+                    )),
+                );
+            }
+
+            // The deployed addresses are derived from the deployment nonce of the deployer, so it is replaced by the
+            // deployment counter - and the counter is advanced by the contracts the transaction deployed.
+            let deployer_nonce_key = get_nonce_key(&l2_tx.initiator_account());
+            let deterministic_deployment = inner.deterministic_addresses
+                && l2_tx.recipient_account() == CONTRACT_DEPLOYER_ADDRESS;
+            if deterministic_deployment {
+                let mut storage = storage.borrow_mut();
+                let full_nonce = h256_to_u256(storage.read_value(&deployer_nonce_key));
+                let (account_nonce, _) = decompose_full_nonce(full_nonce);
+                storage.set_value(
+                    deployer_nonce_key,
+                    u256_to_h256(nonces_to_full_nonce(
+                        account_nonce,
+                        U256::from(deployment_counter),
+                    )),
+                );
+            }
+
+            // only the reads of this transaction are checked
+            inner.fork_storage.take_read_error();
+            vm.push_transaction(tx.clone());
+
+            let call_tracer_result = Arc::new(OnceCell::default());
+            let bootloader_debug_result = Arc::new(OnceCell::default());
+
+            let call_depth_result = Arc::new(OnceCell::default());
+
+            let mut custom_tracers = vec![
+                Box::new(CallTracer::new(call_tracer_result.clone(), HistoryDisabled))
+                    as Box<dyn VmTracer<StorageView<&ForkStorage<S>>, HistoryDisabled>>,
+                Box::new(BootloaderDebugTracer {
+                    result: bootloader_debug_result.clone(),
                 })
-        }
+                    as Box<dyn VmTracer<StorageView<&ForkStorage<S>>, HistoryDisabled>>,
+            ];
+            if let Some(max_call_depth) = inner.max_call_depth {
+                custom_tracers.push(Box::new(CallDepthTracer {
+                    max_depth: max_call_depth,
+                    entry_address: l2_tx.recipient_account(),
+                    exceeded: call_depth_result.clone(),
+                }));
+            }
+            if !inner.mocked_calls.is_empty() {
+                custom_tracers.push(Box::new(MockedCallsTracer::new(inner.mocked_calls.clone())));
+            }
 
-        writer
-            .filters
-            .add_log_filter(from_block, to_block, addresses, topics)
-            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
-            .into_boxed_future()
-    }
+            let mut tx_result = vm.inspect(custom_tracers, vm::VmExecutionMode::OneTx);
+            // The execution was stopped in the middle of the transaction, so it is halted - to not persist it.
+            if let (Some(max_call_depth), Some(_)) = (inner.max_call_depth, call_depth_result.get())
+            {
+                tx_result.result = call_depth_exceeded(max_call_depth);
+            }
+            // A value failed to be read from the fork, and was read as zero - so the transaction is halted too.
+            if let Some(err) = inner.fork_storage.take_read_error() {
+                tx_result.result = fork_read_failed(err);
+            }
 
-    /// Creates a filter in the node, to notify when a new block arrives.
-    /// To check if the state has changed, call `eth_getFilterChanges`.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
-    fn new_block_filter(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
-        let inner = Arc::clone(&self.inner);
-        let mut writer = match inner.write() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            if deterministic_deployment {
+                let full_nonce = h256_to_u256(storage.borrow_mut().read_value(&deployer_nonce_key));
+                let (_, deployment_nonce) = decompose_full_nonce(full_nonce);
+                deployment_counter = deployment_counter.max(deployment_nonce.as_u64());
             }
-        };
 
-        writer
-            .filters
-            .add_block_filter()
-            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
-            .into_boxed_future()
-    }
+            // The bootloader reports the gas used by the validation - if it is above the limit, the transaction is
+            // halted, so its changes are not persisted.
+            if let Some(validation_gas_limit) = inner.validation_gas_limit {
+                match bootloader_debug_result.get() {
+                    Some(Ok(debug)) => {
+                        let validation_gas = debug
+                            .gas_limit_after_intrinsic
+                            .saturating_sub(debug.gas_after_validation);
+                        if validation_gas > U256::from(validation_gas_limit) {
+                            tx_result.result = ExecutionResult::Halt {
+                                reason: Halt::ValidationFailed(VmRevertReason::General {
+                                    msg: format!(
+                                        "validation used {} gas, above the limit of {}",
+                                        validation_gas, validation_gas_limit
+                                    ),
+                                    data: vec![],
+                                }),
+                            };
+                        }
+                    }
+                    _ => log::warn!(
+                        "failed reading the validation gas of transaction {:#x}, the validation gas limit is not enforced",
+                        l2_tx.hash()
+                    ),
+                }
+            }
 
-    /// Creates a filter in the node, to notify when new pending transactions arrive.
-    /// To check if the state has changed, call `eth_getFilterChanges`.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
-    fn new_pending_transaction_filter(
-        &self,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
-        let inner = Arc::clone(&self.inner);
-        let mut writer = match inner.write() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            // With refunds disabled, the refund computed by the VM is withheld from the payer once the batch is sealed,
+            // and the transaction is reported as having used its whole gas limit.
+            let withheld_refund = if inner.refunds_enabled {
+                0
+            } else {
+                std::mem::take(&mut tx_result.refunds.gas_refunded)
+            };
+
+            let call_traces = call_tracer_result.get().unwrap();
+
+            let spent_on_pubdata =
+                tx_result.statistics.gas_used - tx_result.statistics.computational_gas_used;
+
+            log::info!("┌─────────────────────────┐");
+            log::info!("│   TRANSACTION SUMMARY   │");
+            log::info!("└─────────────────────────┘");
+
+            match &tx_result.result {
+                ExecutionResult::Success { .. } => log::info!("Transaction: {}", "SUCCESS".green()),
+                ExecutionResult::Revert { .. } => log::info!("Transaction: {}", "FAILED".red()),
+                ExecutionResult::Halt { .. } => log::info!("Transaction: {}", "HALTED".red()),
+            }
+
+            log::info!("Initiator: {:?}", tx.initiator_account());
+            log::info!("Payer: {:?}", tx.payer());
+            log::info!(
+                "Gas - Limit: {} | Used: {} | Refunded: {}",
+                to_human_size(tx.gas_limit()),
+                to_human_size(tx.gas_limit() - tx_result.refunds.gas_refunded),
+                to_human_size(tx_result.refunds.gas_refunded.into())
+            );
+            if withheld_refund > 0 {
+                log::info!(
+                    "Refunds are disabled - {} gas was not refunded",
+                    to_human_size(withheld_refund.into())
+                );
+            }
+
+            match inner.show_gas_details {
+                ShowGasDetails::None => log::info!(
+                    "Use --show-gas-details flag or call config_setShowGasDetails to display more info"
+                ),
+                ShowGasDetails::All => {
+                    if self
+                        .display_detailed_gas_info(bootloader_debug_result.get(), spent_on_pubdata)
+                        .is_err()
+                    {
+                        log::info!(
+                            "{}",
+                            "!!! FAILED TO GET DETAILED GAS INFO !!!".to_owned().red()
+                        );
+                    }
+                }
             }
+
+            if inner.show_storage_logs != ShowStorageLogs::None {
+                log::info!("");
+                log::info!("┌──────────────────┐");
+                log::info!("│   STORAGE LOGS   │");
+                log::info!("└──────────────────┘");
+            }
+
+            for log_query in &tx_result.logs.storage_logs {
+                match inner.show_storage_logs {
+                    ShowStorageLogs::Write => {
+                        if matches!(
+                            log_query.log_type,
+                            StorageLogQueryType::RepeatedWrite | StorageLogQueryType::InitialWrite
+                        ) {
+                            formatter::print_logs(log_query);
+                        }
+                    }
+                    ShowStorageLogs::Read => {
+                        if log_query.log_type == StorageLogQueryType::Read {
+                            formatter::print_logs(log_query);
+                        }
+                    }
+                    ShowStorageLogs::All => {
+                        formatter::print_logs(log_query);
+                    }
+                    _ => {}
+                }
+            }
+
+            if inner.show_storage_logs == ShowStorageLogs::All {
+                log::info!("");
+                log::info!("┌──────────────────┐");
+                log::info!("│   STORAGE DIFF   │");
+                log::info!("└──────────────────┘");
+                formatter::print_storage_diff(
+                    &tx_result.logs.storage_logs,
+                    &[
+                        tx.initiator_account(),
+                        l2_tx.recipient_account(),
+                        batch_env.fee_account,
+                    ],
+                );
+            }
+
+            if inner.show_vm_details != ShowVMDetails::None {
+                formatter::print_vm_details(&tx_result, inner.resolve_hashes);
+            }
+
+            log::info!("");
+            log::info!("==== Console logs: ");
+            for call in call_traces {
+                inner.console_log_handler.handle_call_recursive(call);
+            }
+            log::info!("");
+            let call_traces_count = if !call_traces.is_empty() {
+                // All calls/sub-calls are stored within the first call trace
+                call_traces[0].calls.len()
+            } else {
+                0
+            };
+            log::info!(
+                "==== {} Use --show-calls flag or call config_setShowCalls to display more info.",
+                format!("{:?} call traces. ", call_traces_count).bold()
+            );
+
+            if inner.show_calls != ShowCalls::None {
+                for call in call_traces {
+                    formatter::print_call(call, 0, &inner.show_calls, inner.resolve_hashes);
+                }
+            }
+            log::info!("");
+            log::info!(
+                "==== {}",
+                format!("{} events", tx_result.logs.events.len()).bold()
+            );
+            for event in &tx_result.logs.events {
+                formatter::print_event(event, inner.resolve_hashes);
+            }
+
+            if withheld_refund > 0 {
+                withheld_refunds.push((tx.payer(), withheld_refund));
+            }
+            bytecodes.extend(
+                vm.get_last_tx_compressed_bytecodes()
+                    .iter()
+                    .map(|b| bytecode_to_factory_dep(b.original.clone())),
+            );
+            results.push((tx_result, call_traces.clone()));
+        }
+
+        // The computed block hash here will be different than that in production.
+        let hash = compute_hash(block_ctx.miniblock, txs[0].hash());
+
+        let transactions = txs
+            .iter()
+            .enumerate()
+            .map(|(index, l2_tx)| {
+                let mut transaction = zksync_types::api::Transaction::from(l2_tx.clone());
+                transaction.block_hash = Some(hash);
+                transaction.block_number = Some(U64::from(block_ctx.miniblock));
+                transaction.transaction_index = Some(U64::from(index));
+                TransactionVariant::Full(transaction)
+            })
+            .collect();
+        let gas_used = results
+            .iter()
+            .map(|(tx_result, _)| U256::from(tx_result.statistics.gas_used))
+            .fold(U256::zero(), |total, gas_used| total + gas_used);
+
+        let block = Block {
+            hash,
+            number: U64::from(block_ctx.miniblock),
+            timestamp: U256::from(batch_env.timestamp),
+            l1_batch_number: Some(U64::from(batch_env.number.0)),
+            transactions,
+            gas_used,
+            gas_limit: U256::from(BLOCK_GAS_LIMIT),
+            // consistent with the base fee reported by `eth_gasPrice` and `eth_feeHistory`
+            base_fee_per_gas: U256::from(inner.base_fee()),
+            author: batch_env.fee_account,
+            ..Default::default()
         };
 
-        writer
-            .filters
-            .add_pending_transaction_filter()
-            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
-            .into_boxed_future()
+        log::info!("");
+        log::info!("");
+
+        // the fees collected by the bootloader are transferred to the fee account when the batch is sealed
+        let bootloader_balance_key = storage_key_for_eth_balance(&BOOTLOADER_ADDRESS);
+        let collected_fees = h256_to_u256(storage.borrow_mut().read_value(&bootloader_balance_key));
+
+        vm.execute(vm::VmExecutionMode::Bootloader);
+
+        if !inner.fee_accrual {
+            let mut storage = storage.borrow_mut();
+            let fee_account_key = storage_key_for_eth_balance(&batch_env.fee_account);
+            let fee_account_balance = h256_to_u256(storage.read_value(&fee_account_key));
+            storage.set_value(
+                fee_account_key,
+                u256_to_h256(fee_account_balance.saturating_sub(collected_fees)),
+            );
+        }
+
+        if !withheld_refunds.is_empty() {
+            let (base_fee, _) = derive_base_fee_and_gas_per_pubdata(
+                batch_env.l1_gas_price,
+                batch_env.fair_l2_gas_price,
+            );
+            let mut storage = storage.borrow_mut();
+            for (payer, withheld_refund) in withheld_refunds {
+                let withheld_fee = U256::from(withheld_refund) * U256::from(base_fee);
+
+                let payer_key = storage_key_for_eth_balance(&payer);
+                let payer_balance = h256_to_u256(storage.read_value(&payer_key));
+                storage.set_value(
+                    payer_key,
+                    u256_to_h256(payer_balance.saturating_sub(withheld_fee)),
+                );
+
+                if inner.fee_accrual {
+                    let fee_account_key = storage_key_for_eth_balance(&batch_env.fee_account);
+                    let fee_account_balance = h256_to_u256(storage.read_value(&fee_account_key));
+                    storage.set_value(
+                        fee_account_key,
+                        u256_to_h256(fee_account_balance + withheld_fee),
+                    );
+                }
+            }
+        }
+
+        let modified_keys = storage.borrow().modified_storage_keys().clone();
+        Ok((
+            modified_keys,
+            results,
+            block,
+            bytecodes,
+            block_ctx,
+            deployment_counter,
+        ))
     }
 
-    /// Uninstalls a filter with given id. Should always be called when watch is no longer needed.
-    ///
-    /// # Arguments
-    ///
-    /// * `id`: The filter id
-    ///
-    /// # Returns
+    /// Runs L2 transaction and commits it to a new block.
+    fn run_l2_tx(&self, l2_tx: L2Tx, execution_mode: TxExecutionMode) -> Result<(), String> {
+        self.run_l2_txs(vec![l2_tx], execution_mode).map(|_| ())
+    }
+
+    /// Runs L2 transactions and commits them to a new block.
     ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
-    fn uninstall_filter(&self, id: U256) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<bool>> {
-        let inner = Arc::clone(&self.inner);
-        let mut writer = match inner.write() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+    /// Halted transactions are never persisted: a single halted transaction is reported as an error, while the
+    /// halted transactions of a larger batch are dropped, and the remaining ones are executed again without them.
+    /// If [InMemoryNodeInner::max_pubdata_per_block] is set, the block is sealed once the transactions reach it,
+    /// and the remaining ones are pushed to the following blocks.
+    /// Returns the number of executed transactions.
+    fn run_l2_txs(
+        &self,
+        mut txs: Vec<L2Tx>,
+        execution_mode: TxExecutionMode,
+    ) -> Result<usize, String> {
+        let pubdata_limit = {
+            let mut inner = self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            for l2_tx in &txs {
+                let tx_hash = l2_tx.hash();
+                log::info!("");
+                log::info!("Executing {}", format!("{:?}", tx_hash).bold());
+                inner.filters.notify_new_pending_transaction(tx_hash);
             }
+            inner.max_pubdata_per_block.map(|max_pubdata| {
+                let (_, gas_per_pubdata) =
+                    derive_base_fee_and_gas_per_pubdata(inner.l1_gas_price, L2_GAS_PRICE);
+                (max_pubdata, gas_per_pubdata)
+            })
         };
 
-        let result = writer.filters.remove_filter(id);
-        Ok(result).into_boxed_future()
+        let mut tx_count = 0;
+        while !txs.is_empty() {
+            let (remaining_txs, block_tx_count) =
+                self.run_l2_txs_in_block(txs, execution_mode, pubdata_limit)?;
+            tx_count += block_tx_count;
+            txs = remaining_txs;
+        }
+
+        Ok(tx_count)
     }
 
-    /// Returns an array of all logs matching a given filter.
+    /// Runs L2 transactions and commits them to a single new block - see [InMemoryNode::run_l2_txs].
     ///
     /// # Arguments
     ///
-    /// * `filter`: The filter options -
-    ///     fromBlock   - Integer block number, or the string "latest", "earliest" or "pending".
-    ///     toBlock     - Integer block number, or the string "latest", "earliest" or "pending".
-    ///     address     - Contract address or a list of addresses from which the logs should originate.
-    ///     topics      - [H256] topics. Topics are order-dependent. Each topic can also be an array with "or" options.
-    ///                   See `new_filter` documention for how to specify topics.
-    ///
-    /// # Returns
+    /// * `pubdata_limit` - The maximum pubdata of the block, along with the gas charged per pubdata byte.
     ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an array of logs.
-    fn get_logs(
+    /// Returns the transactions that did not fit into the block, and the number of transactions in the block.
+    fn run_l2_txs_in_block(
         &self,
-        filter: Filter,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Vec<zksync_types::api::Log>>> {
-        let reader = match self.inner.read() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
-            }
-        };
-        let from_block = filter
-            .from_block
-            .unwrap_or(zksync_types::api::BlockNumber::Earliest);
-        let to_block = filter
-            .to_block
-            .unwrap_or(zksync_types::api::BlockNumber::Latest);
-        let addresses = filter.address.unwrap_or_default().0;
-        let mut topics: [Option<HashSet<H256>>; 4] = Default::default();
+        mut txs: Vec<L2Tx>,
+        execution_mode: TxExecutionMode,
+        pubdata_limit: Option<(u64, u64)>,
+    ) -> Result<(Vec<L2Tx>, usize), String> {
+        let mut deferred_txs = vec![];
+        let (keys, results, block, bytecodes, block_ctx, deployment_counter) = loop {
+            let (keys, results, block, bytecodes, block_ctx, deployment_counter) =
+                self.run_l2_txs_inner(&txs, execution_mode)?;
 
-        if let Some(filter_topics) = filter.topics {
-            filter_topics
-                .into_iter()
-                .take(4)
+            // Halt means that something went really bad with the transaction execution (in most cases invalid signature,
+            // but it could also be bootloader panic etc).
+            // In such case, we should not persist the VM data, and we should pretend that transaction never existed.
+            let halted = results
+                .iter()
                 .enumerate()
-                .for_each(|(i, maybe_topic_set)| {
-                    if let Some(topic_set) = maybe_topic_set {
-                        topics[i] = Some(topic_set.0.into_iter().collect());
+                .filter_map(|(index, (result, _))| match &result.result {
+                    ExecutionResult::Halt { reason } => {
+                        let failure_reason =
+                            TxFailureReason::from_execution_result(&result.result, &[])
+                                .unwrap_or(TxFailureReason::Reverted);
+                        Some((index, format!("{}: {}", failure_reason, reason)))
                     }
+                    _ => None,
                 })
-        }
+                .collect::<Vec<_>>();
+            if halted.is_empty() {
+                // Seal the block once the pubdata of its transactions exceeds the limit - the first transaction
+                // is always included, so that a single large transaction can still be mined.
+                let overflow_index = pubdata_limit.and_then(|(max_pubdata, gas_per_pubdata)| {
+                    let mut block_pubdata = 0u64;
+                    results.iter().position(|(result, _)| {
+                        let pubdata_gas = result
+                            .statistics
+                            .gas_used
+                            .saturating_sub(result.statistics.computational_gas_used);
+                        block_pubdata += pubdata_gas as u64 / gas_per_pubdata.max(1);
+                        block_pubdata > max_pubdata
+                    })
+                });
+                if let Some(index) = overflow_index.filter(|index| *index > 0) {
+                    let mut overflowing_txs = txs.split_off(index);
+                    log::info!(
+                        "block reached the pubdata limit, deferring {} transaction(s) to the next block",
+                        overflowing_txs.len()
+                    );
+                    overflowing_txs.append(&mut deferred_txs);
+                    deferred_txs = overflowing_txs;
+                    continue;
+                }
 
-        let log_filter = LogFilter::new(from_block, to_block, addresses, topics);
+                break (
+                    keys,
+                    results,
+                    block,
+                    bytecodes,
+                    block_ctx,
+                    deployment_counter,
+                );
+            }
+            if txs.len() == 1 && deferred_txs.is_empty() {
+                return Err(format!("Transaction HALT: {}", halted[0].1));
+            }
+            for (index, reason) in halted.into_iter().rev() {
+                let l2_tx = txs.remove(index);
+                log::error!(
+                    "dropping transaction {:#x} from the block: Transaction HALT: {}",
+                    l2_tx.hash(),
+                    reason
+                );
+            }
+            if txs.is_empty() {
+                if !deferred_txs.is_empty() {
+                    return Ok((deferred_txs, 0));
+                }
+                return Err("all the transactions of the block halted".to_string());
+            }
+        };
 
-        let latest_block_number = U64::from(reader.current_miniblock);
-        let logs = reader
-            .tx_results
-            .values()
-            .flat_map(|tx_result| {
-                tx_result
-                    .receipt
-                    .logs
-                    .iter()
-                    .filter(|log| log_filter.matches(log, latest_block_number))
-                    .cloned()
-            })
-            .collect_vec();
+        // Write all the mutated keys (storage slots).
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
 
-        Ok(logs).into_boxed_future()
-    }
+        // archive the state of the latest block before it gets modified
+        if let Err(err) = inner.archive_state() {
+            log::error!(
+                "failed archiving state for block {}: {}",
+                inner.current_miniblock,
+                err
+            );
+        }
 
-    /// Returns an array of all logs matching filter with given id.
-    ///
-    /// # Arguments
-    ///
-    /// * `id`: The filter id
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an array of logs.
-    fn get_filter_logs(
-        &self,
-        id: U256,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<FilterChanges>> {
-        let reader = match self.inner.read() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
-            }
-        };
+        // the balances overridden for this block only, and the code of impersonated contracts, are restored once
+        // the block is mined
+        let mut overridden_keys = std::mem::take(&mut inner.next_block_balances)
+            .into_keys()
+            .map(|address| storage_key_for_eth_balance(&address))
+            .collect::<Vec<_>>();
+        overridden_keys.extend(
+            txs.iter()
+                .map(|tx| tx.initiator_account())
+                .filter(|address| inner.is_impersonated(address))
+                .map(|address| get_code_key(&address)),
+        );
+        let overridden_values = overridden_keys
+            .into_iter()
+            .map(|key| (key, (&inner.fork_storage).read_value(&key)))
+            .collect::<Vec<_>>();
+        for (key, value) in keys.iter() {
+            inner.fork_storage.set_value(*key, *value);
+        }
+        for (key, value) in overridden_values {
+            inner.fork_storage.set_value(key, value);
+        }
+        inner.deployment_counter = deployment_counter;
 
-        let latest_block_number = U64::from(reader.current_miniblock);
-        let logs = match reader.filters.get_filter(id) {
-            Some(FilterType::Log(f)) => reader
-                .tx_results
-                .values()
-                .flat_map(|tx_result| {
-                    tx_result
-                        .receipt
-                        .logs
-                        .iter()
-                        .filter(|log| f.matches(log, latest_block_number))
-                        .cloned()
-                })
-                .collect_vec(),
-            _ => return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed(),
-        };
+        // Write all the factory deps.
+        for (hash, code) in bytecodes.iter() {
+            inner.fork_storage.store_factory_dep(
+                u256_to_h256(*hash),
+                code.iter()
+                    .flat_map(|entry| {
+                        let mut bytes = vec![0u8; 32];
+                        entry.to_big_endian(&mut bytes);
+                        bytes.to_vec()
+                    })
+                    .collect(),
+            )
+        }
 
-        Ok(FilterChanges::Logs(logs)).into_boxed_future()
-    }
+        // every block of transactions is in its own batch, so the index of a transaction in the batch is its index in
+        // the block
+        let base_fee = inner.base_fee();
+        let tx_count = txs.len();
+        let mut block_log_idx = 0;
+        let mut block_l2_to_l1_log_idx = 0;
+        for (tx_idx, (l2_tx, (result, call_traces))) in txs.into_iter().zip(results).enumerate() {
+            let tx_hash = l2_tx.hash();
+            let logs = result
+                .logs
+                .events
+                .iter()
+                .enumerate()
+                .map(|(log_idx, event)| Log {
+                    address: event.address,
+                    topics: event.indexed_topics.clone(),
+                    data: Bytes(event.value.clone()),
+                    block_hash: Some(block.hash),
+                    block_number: Some(block.number),
+                    l1_batch_number: block.l1_batch_number,
+                    transaction_hash: Some(tx_hash),
+                    transaction_index: Some(U64::from(tx_idx)),
+                    log_index: Some(U256::from(block_log_idx + log_idx)),
+                    transaction_log_index: Some(U256::from(log_idx)),
+                    log_type: None,
+                    removed: None,
+                })
+                .collect::<Vec<_>>();
+            block_log_idx += logs.len();
+            let l2_to_l1_logs = result
+                .logs
+                .l2_to_l1_logs
+                .iter()
+                .enumerate()
+                .map(|(log_idx, log)| zksync_types::api::L2ToL1Log {
+                    block_hash: Some(block.hash),
+                    block_number: block.number,
+                    l1_batch_number: block.l1_batch_number,
+                    log_index: U256::from(block_l2_to_l1_log_idx + log_idx),
+                    transaction_index: U64::from(tx_idx),
+                    transaction_hash: tx_hash,
+                    transaction_log_index: U256::from(log_idx),
+                    shard_id: U64::from(log.shard_id),
+                    is_service: log.is_service,
+                    sender: log.sender,
+                    key: log.key,
+                    value: log.value,
+                })
+                .collect::<Vec<_>>();
+            block_l2_to_l1_log_idx += l2_to_l1_logs.len();
 
-    /// Polling method for a filter, which returns an array of logs, block hashes, or transaction hashes,
-    /// depending on the filter type, which occurred since last poll.
-    ///
-    /// # Arguments
-    ///
-    /// * `id`: The filter id
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an array of logs, block hashes, or transaction hashes,
-    /// depending on the filter type, which occurred since last poll.
-    /// * Filters created with `eth_newFilter` return [Log] objects.
-    /// * Filters created with `eth_newBlockFilter` return block hashes.
-    /// * Filters created with `eth_newPendingTransactionFilter` return transaction hashes.
-    fn get_filter_changes(
-        &self,
-        id: U256,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<FilterChanges>> {
-        let inner = Arc::clone(&self.inner);
-        let mut writer = match inner.write() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            for log in &logs {
+                inner.filters.notify_new_log(log, block.number);
             }
-        };
+            let tx_receipt = TransactionReceipt {
+                transaction_hash: tx_hash,
+                transaction_index: U64::from(tx_idx),
+                block_hash: Some(block.hash),
+                block_number: Some(block.number),
+                l1_batch_tx_index: Some(U64::from(tx_idx)),
+                l1_batch_number: block.l1_batch_number,
+                from: l2_tx.initiator_account(),
+                to: Some(l2_tx.recipient_account()),
+                cumulative_gas_used: Default::default(),
+                gas_used: Some(l2_tx.common_data.fee.gas_limit - result.refunds.gas_refunded),
+                contract_address: contract_address_from_tx_result(&result),
+                logs,
+                l2_to_l1_logs,
+                status: Some(if result.result.is_failed() {
+                    U64::from(0)
+                } else {
+                    U64::from(1)
+                }),
+                effective_gas_price: Some(
+                    l2_tx
+                        .common_data
+                        .fee
+                        .max_fee_per_gas
+                        .min(U256::from(base_fee)),
+                ),
+                transaction_type: Some(utils::transaction_type(&l2_tx)),
+                ..Default::default()
+            };
+            let debug =
+                create_debug_output(&l2_tx, &result, call_traces).expect("create debug output"); // OK to unwrap here as Halt is handled above
+            inner.tx_results.insert(
+                tx_hash,
+                TransactionResult {
+                    info: TxExecutionInfo {
+                        tx: l2_tx,
+                        batch_number: block.l1_batch_number.unwrap_or_default().as_u32(),
+                        miniblock_number: block.number.as_u64(),
+                        result,
+                    },
+                    receipt: tx_receipt,
+                    debug,
+                },
+            );
+        }
 
-        writer
-            .filters
-            .get_new_changes(id)
-            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
-            .into_boxed_future()
-    }
+        // With the introduction of 'l2 blocks' (and virtual blocks),
+        // we are adding one l2 block at the end of each batch (to handle things like remaining events etc).
+        //  You can look at insert_fictive_l2_block function in VM to see how this fake block is inserted.
+        let block_ctx = block_ctx.new_block();
+        let mut empty_block_at_end_of_batch =
+            create_empty_block(block_ctx.miniblock, block_ctx.timestamp, block_ctx.batch);
+        empty_block_at_end_of_batch.author = inner.coinbase;
+        empty_block_at_end_of_batch.base_fee_per_gas = U256::from(inner.base_fee());
 
-    fn get_block_transaction_count_by_number(
-        &self,
-        block_number: zksync_types::api::BlockNumber,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
-        let inner = Arc::clone(&self.inner);
+        inner.current_batch = inner.current_batch.saturating_add(1);
 
-        Box::pin(async move {
-            let maybe_result = {
-                let reader = match inner.read() {
-                    Ok(r) => r,
-                    Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)),
-                };
-                let number =
-                    utils::to_real_block_number(block_number, U64::from(reader.current_miniblock))
-                        .as_u64();
+        for block in vec![block, empty_block_at_end_of_batch] {
+            inner.current_miniblock = inner.current_miniblock.saturating_add(1);
+            inner.current_timestamp = inner.current_timestamp.saturating_add(1);
+            inner.next_block_timestamp_set = false;
 
-                reader
-                    .block_hashes
-                    .get(&number)
-                    .and_then(|hash| reader.blocks.get(hash))
-                    .map(|block| U256::from(block.transactions.len()))
-                    .or_else(|| {
-                        reader
-                            .fork_storage
-                            .inner
-                            .read()
-                            .expect("failed reading fork storage")
-                            .fork
-                            .as_ref()
-                            .and_then(|fork| {
-                                fork.fork_source
-                                    .get_block_transaction_count_by_number(block_number)
-                                    .ok()
-                                    .flatten()
-                            })
-                    })
-            };
+            let actual_l1_batch_number = block
+                .l1_batch_number
+                .expect("block must have a l1_batch_number");
+            if actual_l1_batch_number.as_u32() != inner.current_batch {
+                panic!(
+                    "expected next block to have batch_number {}, got {}",
+                    inner.current_batch,
+                    actual_l1_batch_number.as_u32()
+                );
+            }
 
-            match maybe_result {
-                Some(value) => Ok(Some(value)),
-                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+            if block.number.as_u64() != inner.current_miniblock {
+                panic!(
+                    "expected next block to have miniblock {}, got {}",
+                    inner.current_miniblock,
+                    block.number.as_u64()
+                );
             }
-        })
-    }
 
-    fn get_block_transaction_count_by_hash(
-        &self,
-        block_hash: zksync_basic_types::H256,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
-        let inner = Arc::clone(&self.inner);
+            if block.timestamp.as_u64() != inner.current_timestamp {
+                panic!(
+                    "expected next block to have timestamp {}, got {}",
+                    inner.current_timestamp,
+                    block.timestamp.as_u64()
+                );
+            }
 
-        Box::pin(async move {
-            let reader = inner
-                .read()
-                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            let block_hash = block.hash;
+            inner.current_miniblock_hash = block_hash;
+            inner.block_hashes.insert(block.number.as_u64(), block.hash);
+            inner.blocks.insert(block.hash, block);
+            inner.filters.notify_new_block(block_hash);
 
-            // try retrieving block from memory, and if unavailable subsequently from the fork
-            let maybe_result = reader
-                .blocks
-                .get(&block_hash)
-                .map(|block| U256::from(block.transactions.len()))
-                .or_else(|| {
-                    reader
-                        .fork_storage
-                        .inner
-                        .read()
-                        .expect("failed reading fork storage")
-                        .fork
-                        .as_ref()
-                        .and_then(|fork| {
-                            fork.fork_source
-                                .get_block_transaction_count_by_hash(block_hash)
-                                .ok()
-                                .flatten()
-                        })
-                });
+            // archive the state of the produced block for historical queries
+            if let Err(err) = inner.archive_state() {
+                log::error!(
+                    "failed archiving state for block {}: {}",
+                    inner.current_miniblock,
+                    err
+                );
+            }
+        }
 
-            match maybe_result {
-                Some(value) => Ok(Some(value)),
-                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+        Ok((deferred_txs, tx_count))
+    }
+}
+
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> InMemoryNode<S> {
+    /// Spawns a thread that mines the automine batch once its window elapses (see
+    /// [InMemoryNodeInner::automine_batch_window_ms]). The thread stops once the node is dropped.
+    pub fn spawn_automine_batch_miner(&self) -> std::thread::JoinHandle<()> {
+        let node = Arc::downgrade(&self.inner);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(AUTOMINE_BATCH_POLL_INTERVAL);
+            let inner = match node.upgrade() {
+                Some(inner) => inner,
+                None => break,
+            };
+            let due = match inner.read() {
+                Ok(reader) => reader
+                    .automine_batch_deadline
+                    .map_or(false, |deadline| deadline <= Instant::now()),
+                Err(_) => break,
+            };
+            if due {
+                if let Err(err) = InMemoryNode::from_inner(inner).mine_automine_batch() {
+                    log::error!("failed mining the automine batch: {}", err);
+                }
             }
         })
     }
 
-    /// Returns the value from a storage position at a given address.
+    /// Spawns a thread that mines a block every [InMemoryNodeInner::interval_mining_ms], while it is non-zero - as set
+    /// by `evm_setIntervalMining`. The thread stops once the node is dropped.
+    pub fn spawn_interval_miner(&self) -> std::thread::JoinHandle<()> {
+        let node = Arc::downgrade(&self.inner);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(INTERVAL_MINING_POLL_INTERVAL);
+            let inner = match node.upgrade() {
+                Some(inner) => inner,
+                None => break,
+            };
+            let due = match inner.write() {
+                Ok(mut writer) => match writer.interval_mining_deadline {
+                    Some(deadline) if deadline <= Instant::now() => {
+                        writer.interval_mining_deadline =
+                            Some(deadline + Duration::from_millis(writer.interval_mining_ms));
+                        true
+                    }
+                    _ => false,
+                },
+                Err(_) => break,
+            };
+            if due {
+                if let Err(err) = InMemoryNode::from_inner(inner).mine_block() {
+                    log::error!("failed mining the interval block: {}", err);
+                }
+            }
+        })
+    }
+
+    /// Spawns a thread that dumps the chain state into a new file in `dir` every `interval`, in the same format
+    /// as `hardhat_dumpState`. Only the latest `keep` dumps are kept. The thread stops once the node is dropped.
+    pub fn spawn_state_dumper(
+        &self,
+        dir: PathBuf,
+        interval: Duration,
+        keep: usize,
+    ) -> std::thread::JoinHandle<()> {
+        let node = Arc::downgrade(&self.inner);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let inner = match node.upgrade() {
+                Some(inner) => inner,
+                None => break,
+            };
+            let result = match inner.read() {
+                Ok(reader) => reader.dump_state_to_file(&dir.join(state_dump_file_name())),
+                Err(_) => break,
+            };
+            match result.and_then(|_| prune_state_dumps(&dir, keep)) {
+                Ok(_) => log::debug!("dumped state to {}", dir.display()),
+                Err(err) => log::error!("failed dumping state: {}", err),
+            }
+        })
+    }
+}
+
+/// Returns the name of a new periodic state dump file. The names sort in the order the dumps were made.
+fn state_dump_file_name() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    format!("state-{:020}.json", timestamp)
+}
+
+/// Returns the periodic state dumps in `dir`, from the oldest to the latest.
+pub fn state_dumps(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut dumps = std::fs::read_dir(dir)
+        .map_err(|err| format!("failed reading {}: {}", dir.display(), err))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map_or(false, |name| {
+                    name.starts_with("state-") && name.ends_with(".json")
+                })
+        })
+        .collect::<Vec<_>>();
+    dumps.sort();
+
+    Ok(dumps)
+}
+
+/// Removes all but the latest `keep` periodic state dumps in `dir`.
+fn prune_state_dumps(dir: &Path, keep: usize) -> Result<(), String> {
+    let dumps = state_dumps(dir)?;
+    for dump in dumps.iter().take(dumps.len().saturating_sub(keep)) {
+        std::fs::remove_file(dump)
+            .map_err(|err| format!("failed removing {}: {}", dump.display(), err))?;
+    }
+
+    Ok(())
+}
+
+/// Keeps track of a block's batch number, miniblock number and timestamp.
+/// Useful for keeping track of the current context when creating multiple blocks.
+pub struct BlockContext {
+    pub batch: u32,
+    pub miniblock: u64,
+    pub timestamp: u64,
+}
+
+impl BlockContext {
+    /// Create the current instance that represents the latest block.
+    pub fn from_current(batch: u32, miniblock: u64, timestamp: u64) -> Self {
+        Self {
+            batch,
+            miniblock,
+            timestamp,
+        }
+    }
+
+    /// Create the next batch instance that has all parameters incremented by `1`.
+    pub fn new_batch(&self) -> Self {
+        Self {
+            batch: self.batch.saturating_add(1),
+            miniblock: self.miniblock.saturating_add(1),
+            timestamp: self.timestamp.saturating_add(1),
+        }
+    }
+
+    /// Create the next batch instance that uses the same batch number, and has all other parameters incremented by `1`.
+    pub fn new_block(&self) -> BlockContext {
+        Self {
+            batch: self.batch,
+            miniblock: self.miniblock.saturating_add(1),
+            timestamp: self.timestamp.saturating_add(1),
+        }
+    }
+}
+
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EthNamespaceT for InMemoryNode<S> {
+    /// Returns the chain ID of the node.
+    fn chain_id(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::U64>> {
+        match self.inner.read() {
+            Ok(inner) => Ok(U64::from(inner.fork_storage.chain_id.0 as u64)).into_boxed_future(),
+            Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        }
+    }
+
+    /// Calls the specified function on the L2 contract with the given arguments.
     ///
     /// # Arguments
     ///
-    /// * `address`: Address of the storage
-    /// * `idx`: Integer of the position in the storage
-    /// * `block`: The block storage to target
+    /// * `req` - The call request containing the function name and arguments.
+    /// * `block` - The block ID variant of the state to execute the call against (optional, defaults to latest).
     ///
     /// # Returns
     ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to a [H256] value in the storage.
-    fn get_storage(
+    /// A boxed future containing the result of the function call.
+    fn call(
         &self,
-        address: zksync_basic_types::Address,
-        idx: U256,
+        mut req: zksync_types::transaction_request::CallRequest,
         block: Option<zksync_types::api::BlockIdVariant>,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::H256>> {
-        let inner = Arc::clone(&self.inner);
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::Bytes>> {
+        let (gas_metering, default_call_sender) = self
+            .inner
+            .read()
+            .map(|reader| (reader.gas_metering, reader.default_call_sender))
+            .unwrap_or((true, Address::zero()));
+        let expected_revert = self.take_expected_revert();
+        if req.from.is_none() {
+            req.from = Some(default_call_sender);
+        }
+        // the call is executed with the given gas limit, up to the gas limit of calls - unless gas metering is
+        // disabled, in which case it always gets the maximum
+        let gas_limit = req
+            .gas
+            .filter(|gas| gas_metering && !gas.is_zero())
+            .map(|gas| gas.min(ETH_CALL_GAS_LIMIT.into()))
+            .unwrap_or_else(|| ETH_CALL_GAS_LIMIT.into());
+        match L2Tx::from_request(req.into(), MAX_TX_SIZE) {
+            Ok(mut tx) => {
+                tx.common_data.fee.gas_limit = gas_limit;
+                let result = self.run_l2_call(tx, block);
 
-        Box::pin(async move {
-            let mut writer = match inner.write() {
-                Ok(r) => r,
-                Err(_) => {
-                    return Err(into_jsrpc_error(Web3Error::InternalError));
+                // with an expected revert, whether the call reverted as expected is returned as an ABI-encoded bool
+                if let (Some(expected_revert), Ok(execution_result)) = (&expected_revert, &result) {
+                    let resolve_hashes = self
+                        .inner
+                        .read()
+                        .map(|reader| reader.resolve_hashes)
+                        .unwrap_or_default();
+                    let met = expected_revert.is_met_by(execution_result, resolve_hashes);
+                    if !met {
+                        log::info!("{}", "expected the call to revert".on_red());
+                    }
+                    return Ok(H256::from_low_u64_be(met as u64).as_bytes().to_vec().into())
+                        .into_boxed_future();
                 }
-            };
 
-            let storage_key = StorageKey::new(AccountTreeId::new(address), u256_to_h256(idx));
-
-            let block_number = block
-                .map(|block| match block {
-                    zksync_types::api::BlockIdVariant::BlockNumber(block_number) => {
-                        Ok(utils::to_real_block_number(
-                            block_number,
-                            U64::from(writer.current_miniblock),
-                        ))
-                    }
-                    zksync_types::api::BlockIdVariant::BlockNumberObject(o) => {
-                        Ok(utils::to_real_block_number(
-                            o.block_number,
-                            U64::from(writer.current_miniblock),
-                        ))
-                    }
-                    zksync_types::api::BlockIdVariant::BlockHashObject(o) => writer
-                        .blocks
-                        .get(&o.block_hash)
-                        .map(|block| block.number)
-                        .ok_or_else(|| {
-                            log::error!("unable to map block number to hash #{:#x}", o.block_hash);
-                            into_jsrpc_error(Web3Error::InternalError)
-                        }),
-                })
-                .unwrap_or_else(|| Ok(U64::from(writer.current_miniblock)))?;
+                match result {
+                    Ok(execution_result) => match execution_result {
+                        ExecutionResult::Success { output } => {
+                            Ok(output.into()).into_boxed_future()
+                        }
+                        ExecutionResult::Revert { output } => {
+                            let resolve_hashes = self
+                                .inner
+                                .read()
+                                .map(|reader| reader.resolve_hashes)
+                                .unwrap_or_default();
+                            let message =
+                                formatter::revert_reason_to_human_readable(&output, resolve_hashes);
+                            let pretty_message = format!(
+                                "execution reverted{}{}",
+                                if message.is_empty() { "" } else { ": " },
+                                message
+                            );
 
-            if block_number.as_u64() == writer.current_miniblock {
-                Ok(H256(writer.fork_storage.read_value(&storage_key).0))
-            } else if writer.block_hashes.contains_key(&block_number.as_u64()) {
-                let value = writer
-                    .block_hashes
-                    .get(&block_number.as_u64())
-                    .and_then(|block_hash| writer.previous_states.get(block_hash))
-                    .and_then(|state| state.get(&storage_key))
-                    .cloned()
-                    .unwrap_or_default();
+                            log::info!("{}", pretty_message.on_red());
+                            Err(revert_error(pretty_message, &output)).into_boxed_future()
+                        }
+                        ExecutionResult::Halt { reason } => {
+                            let message = reason.to_string();
+                            let pretty_message = format!(
+                                "execution halted {}{}",
+                                if message.is_empty() { "" } else { ": " },
+                                message
+                            );
 
-                if value.is_zero() {
-                    Ok(H256(writer.fork_storage.read_value(&storage_key).0))
-                } else {
-                    Ok(value)
-                }
-            } else {
-                writer
-                    .fork_storage
-                    .inner
-                    .read()
-                    .expect("failed reading fork storage")
-                    .fork
-                    .as_ref()
-                    .and_then(|fork| fork.fork_source.get_storage_at(address, idx, block).ok())
-                    .ok_or_else(|| {
-                        log::error!(
-                            "unable to get storage at address {:?}, index {:?} for block {:?}",
-                            address,
-                            idx,
-                            block
+                            log::info!("{}", pretty_message.on_red());
+                            Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
+                                pretty_message,
+                                vec![],
+                            )))
+                            .into_boxed_future()
+                        }
+                    },
+                    Err(e) => {
+                        let error = Web3Error::InvalidTransactionData(
+                            zksync_types::ethabi::Error::InvalidName(e),
                         );
-                        into_jsrpc_error(Web3Error::InternalError)
-                    })
+                        Err(into_jsrpc_error(error)).into_boxed_future()
+                    }
+                }
             }
-        })
+            Err(e) => {
+                let error = Web3Error::SerializationError(e);
+                Err(into_jsrpc_error(error)).into_boxed_future()
+            }
+        }
     }
 
-    /// Returns information about a transaction by block hash and transaction index position.
+    /// Returns the balance of the specified address.
     ///
     /// # Arguments
     ///
-    /// * `block_hash`: Hash of a block
-    /// * `index`: Integer of the transaction index position
+    /// * `address` - The address to get the balance of.
+    /// * `block` - The block ID variant (optional), defaults to the latest block. Blocks after the latest one are rejected.
     ///
     /// # Returns
     ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that maybe resolves to a [zksync_types::api::Transaction], if found.
-    fn get_transaction_by_block_hash_and_index(
+    /// A `BoxFuture` that resolves to a `Result` containing the balance of the specified address as a `U256` or a `jsonrpc_core::Error` if an error occurred.
+    fn get_balance(
         &self,
-        block_hash: zksync_basic_types::H256,
-        index: zksync_basic_types::web3::types::Index,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::Transaction>>> {
+        address: zksync_basic_types::Address,
+        block: Option<zksync_types::api::BlockIdVariant>,
+    ) -> BoxFuture<Result<U256, jsonrpc_core::Error>> {
         let inner = Arc::clone(&self.inner);
 
         Box::pin(async move {
+            let balance_key = storage_key_for_standard_token_balance(
+                AccountTreeId::new(L2_ETH_TOKEN_ADDRESS),
+                &address,
+            );
+
             let reader = match inner.read() {
-                Ok(r) => r,
+                Ok(reader) => reader,
                 Err(_) => {
-                    return Err(into_jsrpc_error(Web3Error::InternalError));
+                    let web3_error = Web3Error::InternalError;
+                    return Err(into_jsrpc_error(web3_error));
                 }
             };
 
-            let maybe_tx = reader
-                .blocks
-                .get(&block_hash)
-                .and_then(|block| block.transactions.get(index.as_usize()))
-                .and_then(|tx| match tx {
-                    TransactionVariant::Full(tx) => Some(tx.clone()),
-                    TransactionVariant::Hash(tx_hash) => reader
-                        .fork_storage
-                        .inner
-                        .read()
-                        .expect("failed reading fork storage")
-                        .fork
-                        .as_ref()
-                        .and_then(|fork| {
-                            fork.fork_source
-                                .get_transaction_by_hash(*tx_hash)
-                                .ok()
-                                .flatten()
-                        }),
+            let block_number = reader.resolve_block_number(block.clone()).map_err(|err| {
+                log::error!("failed resolving block: {}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+            if block_number > reader.current_miniblock {
+                return Err(jsonrpc_core::Error::invalid_params(format!(
+                    "block #{} is after the latest block #{}",
+                    block_number, reader.current_miniblock
+                )));
+            }
+
+            reader
+                .read_value_at_block(&balance_key, block)
+                .map(h256_to_u256)
+                .map_err(|err| {
+                    log::error!("failed reading balance: {}", err);
+                    into_jsrpc_error(Web3Error::InternalError)
                 })
-                .or_else(|| {
-                    reader
+        })
+    }
+
+    /// Returns a block by its number.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_number` - A `BlockNumber` enum variant representing the block number to retrieve.
+    /// * `full_transactions` - A boolean value indicating whether to retrieve full transactions or not.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `Option` of `zksync_types::api::Block<zksync_types::api::TransactionVariant>`.
+    fn get_block_by_number(
+        &self,
+        block_number: zksync_types::api::BlockNumber,
+        full_transactions: bool,
+    ) -> BoxFuture<
+        jsonrpc_core::Result<
+            Option<zksync_types::api::Block<zksync_types::api::TransactionVariant>>,
+        >,
+    > {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let maybe_block = {
+                let reader = match inner.read() {
+                    Ok(r) => r,
+                    Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)),
+                };
+                let number = reader.to_real_block_number(block_number).as_u64();
+
+                reader
+                    .block_hashes
+                    .get(&number)
+                    .and_then(|hash| reader.blocks.get(hash))
+                    .cloned()
+                    .or_else(|| {
+                        reader
+                            .fork_storage
+                            .inner
+                            .read()
+                            .expect("failed reading fork storage")
+                            .fork
+                            .as_ref()
+                            .and_then(|fork| {
+                                fork.fork_source
+                                    .get_block_by_number(block_number, true)
+                                    .ok()
+                                    .flatten()
+                            })
+                    })
+                    .map(|mut block| {
+                        block.transactions =
+                            reader.block_transactions(block.transactions, full_transactions);
+                        block
+                    })
+            };
+
+            match maybe_block {
+                Some(block) => Ok(Some(block)),
+                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+            }
+        })
+    }
+
+    /// Returns the code stored at the specified address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to retrieve the code from.
+    /// * `_block` - An optional block ID variant.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing the result of the operation, which is a `jsonrpc_core::Result` containing
+    /// the code as a `zksync_basic_types::Bytes` object.
+    fn get_code(
+        &self,
+        address: zksync_basic_types::Address,
+        _block: Option<zksync_types::api::BlockIdVariant>,
+    ) -> BoxFuture<jsonrpc_core::Result<zksync_basic_types::Bytes>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let code_key = get_code_key(&address);
+
+            match inner.write() {
+                Ok(mut guard) => {
+                    let code_hash = guard.fork_storage.read_value(&code_key);
+
+                    let code = guard
                         .fork_storage
-                        .inner
-                        .read()
-                        .expect("failed reading fork storage")
-                        .fork
-                        .as_ref()
-                        .and_then(|fork| {
-                            fork.fork_source
-                                .get_transaction_by_block_hash_and_index(block_hash, index)
-                                .ok()
-                        })
-                        .flatten()
-                });
+                        .load_factory_dep_internal(code_hash)
+                        .unwrap_or_default();
 
-            Ok(maybe_tx)
+                    Ok(Bytes::from(code))
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
         })
     }
 
-    /// Returns information about a transaction by block number and transaction index position.
+    /// Returns the transaction count for a given address.
     ///
     /// # Arguments
     ///
-    /// * `block_number`: A block number, or the string "earliest", "latest" or "pending".
-    /// * `index`: Integer of the transaction index position
+    /// * `address` - The address to get the transaction count for.
+    /// * `block` - Optional block ID variant, defaults to the latest block. With the `pending` tag, the
+    ///   transactions of the address that are waiting in the pool (without nonce gaps) are counted as well.
     ///
     /// # Returns
     ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that maybe resolves to a [zksync_types::api::Transaction], if found.
-    fn get_transaction_by_block_number_and_index(
+    /// Returns a `BoxFuture` containing the transaction count as a `U256` wrapped in a `jsonrpc_core::Result`.
+    fn get_transaction_count(
         &self,
-        block_number: zksync_types::api::BlockNumber,
-        index: zksync_basic_types::web3::types::Index,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::Transaction>>> {
+        address: zksync_basic_types::Address,
+        block: Option<zksync_types::api::BlockIdVariant>,
+    ) -> BoxFuture<jsonrpc_core::Result<U256>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let nonce_key = get_nonce_key(&address);
+
+            match inner.read() {
+                Ok(reader) => {
+                    let is_pending = matches!(
+                        block,
+                        Some(BlockIdVariant::BlockNumber(BlockNumber::Pending))
+                    );
+                    let nonce = reader
+                        .read_value_at_block(&nonce_key, block)
+                        .map(h256_to_u64)
+                        .map_err(|err| {
+                            log::error!("failed reading transaction count: {}", err);
+                            into_jsrpc_error(Web3Error::InternalError)
+                        })?;
+                    // transactions waiting in the pool will be mined on top of the latest nonce
+                    let pending_count = if is_pending {
+                        reader.pool.pending_count(&address, Nonce(nonce as u32)) as u64
+                    } else {
+                        0
+                    };
+                    Ok(nonce.saturating_add(pending_count).into())
+                }
+                Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
+            }
+        })
+    }
+
+    /// Retrieves the transaction receipt for a given transaction hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash of the transaction to retrieve the receipt for.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` that resolves to an `Option` of a `TransactionReceipt` or an error.
+    fn get_transaction_receipt(
+        &self,
+        hash: zksync_basic_types::H256,
+    ) -> BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::TransactionReceipt>>> {
         let inner = Arc::clone(&self.inner);
 
         Box::pin(async move {
             let reader = match inner.read() {
                 Ok(r) => r,
-                Err(_) => {
-                    return Err(into_jsrpc_error(Web3Error::InternalError));
-                }
+                Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)),
             };
 
-            let real_block_number =
-                utils::to_real_block_number(block_number, U64::from(reader.current_miniblock));
-            let maybe_tx = reader
-                .block_hashes
-                .get(&real_block_number.as_u64())
-                .and_then(|block_hash| reader.blocks.get(block_hash))
-                .and_then(|block| block.transactions.get(index.as_usize()))
-                .and_then(|tx| match tx {
-                    TransactionVariant::Full(tx) => Some(tx.clone()),
-                    TransactionVariant::Hash(tx_hash) => reader
-                        .fork_storage
-                        .inner
-                        .read()
-                        .expect("failed reading fork storage")
-                        .fork
-                        .as_ref()
-                        .and_then(|fork| {
-                            fork.fork_source
-                                .get_transaction_by_hash(*tx_hash)
-                                .ok()
-                                .flatten()
-                        }),
-                })
+            // try retrieving the receipt from memory, and if unavailable subsequently from the fork
+            let receipt = reader
+                .tx_results
+                .get(&hash)
+                .map(|info| info.receipt.clone())
                 .or_else(|| {
                     reader
                         .fork_storage
@@ -2743,294 +4505,2251 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EthNamespaceT for
                         .as_ref()
                         .and_then(|fork| {
                             fork.fork_source
-                                .get_transaction_by_block_number_and_index(block_number, index)
+                                .get_transaction_receipt(hash)
                                 .ok()
+                                .flatten()
                         })
-                        .flatten()
                 });
-
-            Ok(maybe_tx)
+            Ok(receipt)
         })
     }
 
-    /// Returns the protocol version.
+    /// Sends a raw transaction to the L2 network.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_bytes` - The transaction bytes to send.
     ///
     /// # Returns
     ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to a hex `String` of the version number.
-    fn protocol_version(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<String>> {
-        Ok(String::from(PROTOCOL_VERSION)).into_boxed_future()
-    }
-
-    fn syncing(
+    /// A future that resolves to the hash of the transaction if successful, or an error if the transaction is invalid or execution fails.
+    fn send_raw_transaction(
         &self,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::web3::types::SyncState>>
-    {
-        Ok(zksync_basic_types::web3::types::SyncState::NotSyncing).into_boxed_future()
+        tx_bytes: zksync_basic_types::Bytes,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::H256>> {
+        let chain_id = match self.inner.read() {
+            Ok(reader) => reader.fork_storage.chain_id,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        let (tx_req, hash) = match decode_raw_tx_request(&tx_bytes.0, chain_id) {
+            Ok(result) => result,
+            Err(e) => return futures::future::err(into_jsrpc_error(e)).boxed(),
+        };
+
+        let mut l2_tx: L2Tx = match L2Tx::from_request(tx_req, MAX_TX_SIZE) {
+            Ok(tx) => tx,
+            Err(e) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::SerializationError(e)))
+                    .boxed()
+            }
+        };
+
+        l2_tx.set_input(tx_bytes.0, hash);
+        if hash != l2_tx.hash() {
+            return futures::future::err(into_jsrpc_error(Web3Error::InvalidTransactionData(
+                zksync_types::ethabi::Error::InvalidData,
+            )))
+            .boxed();
+        };
+
+        let expected_revert = self.take_expected_revert();
+        match self.submit_tx(l2_tx.clone()) {
+            Ok(_) => {
+                let reader = match self.inner.read() {
+                    Ok(r) => r,
+                    Err(_) => {
+                        return futures::future::err(into_jsrpc_error(Web3Error::InternalError))
+                            .boxed()
+                    }
+                };
+
+                // with an expected revert, the hash is returned only if the transaction reverted as expected
+                if let Some(expected_revert) = &expected_revert {
+                    let met = reader.tx_results.get(&hash).map_or(false, |tx_result| {
+                        expected_revert
+                            .is_met_by(&tx_result.info.result.result, reader.resolve_hashes)
+                    });
+                    if met {
+                        return Ok(hash).into_boxed_future();
+                    }
+                    let pretty_message = String::from("expected the transaction to revert");
+                    log::info!("{}", pretty_message.on_red());
+                    return futures::future::err(into_jsrpc_error(
+                        Web3Error::SubmitTransactionError(pretty_message, hash.as_bytes().to_vec()),
+                    ))
+                    .boxed();
+                }
+
+                // the transaction was mined, but reverted - surface the reason the same way `eth_call` does
+                match reader.tx_results.get(&hash).map(|tx_result| {
+                    (
+                        &tx_result.info.result.result,
+                        TxFailureReason::from_execution_result(
+                            &tx_result.info.result.result,
+                            &tx_result.debug.calls,
+                        ),
+                    )
+                }) {
+                    Some((ExecutionResult::Revert { output }, Some(TxFailureReason::OutOfGas))) => {
+                        let pretty_message = TxFailureReason::OutOfGas.to_string();
+
+                        log::info!("{}", pretty_message.on_red());
+                        futures::future::err(revert_error(pretty_message, output)).boxed()
+                    }
+                    Some((ExecutionResult::Revert { output }, _)) => {
+                        let message = formatter::revert_reason_to_human_readable(
+                            output,
+                            reader.resolve_hashes,
+                        );
+                        let pretty_message = format!(
+                            "execution reverted{}{}",
+                            if message.is_empty() { "" } else { ": " },
+                            message
+                        );
+
+                        log::info!("{}", pretty_message.on_red());
+                        futures::future::err(revert_error(pretty_message, output)).boxed()
+                    }
+                    _ => Ok(hash).into_boxed_future(),
+                }
+            }
+            Err(e) => {
+                let error_message = format!("Execution error: {}", e);
+                futures::future::err(into_jsrpc_error(Web3Error::SubmitTransactionError(
+                    error_message,
+                    l2_tx.hash().as_bytes().to_vec(),
+                )))
+                .boxed()
+            }
+        }
     }
-    /// Returns a list of available accounts.
-    ///
-    /// This function fetches the accounts from the inner state, and returns them as a list of addresses (`H160`).
+
+    /// Returns a block by its hash. Currently, only hashes for blocks in memory are supported.
     ///
-    /// # Errors
+    /// # Arguments
     ///
-    /// Returns a `jsonrpc_core::Result` error if acquiring a write lock on the inner state fails.
+    /// * `hash` - A `H256` type representing the hash of the block to retrieve.
+    /// * `full_transactions` - A boolean value indicating whether to retrieve full transactions or not.
     ///
     /// # Returns
     ///
-    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to a `Vec<H160>` of addresses.
-    fn accounts(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Vec<H160>>> {
-        let inner = Arc::clone(&self.inner);
-        let reader = match inner.read() {
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `Option` of `zksync_types::api::Block<zksync_types::api::TransactionVariant>`.
+    fn get_block_by_hash(
+        &self,
+        hash: zksync_basic_types::H256,
+        full_transactions: bool,
+    ) -> jsonrpc_core::BoxFuture<
+        jsonrpc_core::Result<
+            Option<zksync_types::api::Block<zksync_types::api::TransactionVariant>>,
+        >,
+    > {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let maybe_block = {
+                let reader = inner
+                    .read()
+                    .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+                // try retrieving block from memory, and if unavailable subsequently from the fork
+                reader
+                    .blocks
+                    .get(&hash)
+                    .cloned()
+                    .or_else(|| {
+                        reader
+                            .fork_storage
+                            .inner
+                            .read()
+                            .expect("failed reading fork storage")
+                            .fork
+                            .as_ref()
+                            .and_then(|fork| {
+                                fork.fork_source
+                                    .get_block_by_hash(hash, true)
+                                    .ok()
+                                    .flatten()
+                            })
+                    })
+                    .map(|mut block| {
+                        block.transactions =
+                            reader.block_transactions(block.transactions, full_transactions);
+                        block
+                    })
+            };
+
+            match maybe_block {
+                Some(block) => Ok(Some(block)),
+                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+            }
+        })
+    }
+
+    /// Returns a future that resolves to an optional transaction with the given hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - A 32-byte hash of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// A `jsonrpc_core::BoxFuture` that resolves to a `jsonrpc_core::Result` containing an optional `zksync_types::api::Transaction`.
+    fn get_transaction_by_hash(
+        &self,
+        hash: zksync_basic_types::H256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::Transaction>>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            let maybe_result = {
+                // try retrieving transaction from memory (mined or pending), and if unavailable subsequently from the fork
+                reader
+                    .tx_results
+                    .get(&hash)
+                    .and_then(|TransactionResult { info, receipt, .. }| {
+                        let input_data = info.tx.common_data.input.clone().or(None)?;
+                        let chain_id = info.tx.common_data.extract_chain_id().or(None)?;
+                        Some(zksync_types::api::Transaction {
+                            hash,
+                            nonce: U256::from(info.tx.common_data.nonce.0),
+                            block_hash: receipt.block_hash,
+                            block_number: Some(U64::from(info.miniblock_number)),
+                            transaction_index: Some(receipt.transaction_index),
+                            from: Some(info.tx.initiator_account()),
+                            to: Some(info.tx.recipient_account()),
+                            value: info.tx.execute.value,
+                            gas_price: Default::default(),
+                            gas: Default::default(),
+                            input: input_data.data.into(),
+                            v: Some(chain_id.into()),
+                            r: Some(U256::zero()),
+                            s: Some(U256::zero()),
+                            raw: None,
+                            transaction_type: Some(utils::transaction_type(&info.tx)),
+                            access_list: None,
+                            max_fee_per_gas: Some(info.tx.common_data.fee.max_fee_per_gas),
+                            max_priority_fee_per_gas: Some(
+                                info.tx.common_data.fee.max_priority_fee_per_gas,
+                            ),
+                            chain_id: chain_id.into(),
+                            l1_batch_number: Some(U64::from(info.batch_number as u64)),
+                            l1_batch_tx_index: None,
+                        })
+                    })
+                    .or_else(|| reader.pool.get(&hash).map(utils::pending_api_transaction))
+                    .or_else(|| {
+                        reader
+                            .fork_storage
+                            .inner
+                            .read()
+                            .expect("failed reading fork storage")
+                            .fork
+                            .as_ref()
+                            .and_then(|fork| {
+                                fork.fork_source
+                                    .get_transaction_by_hash(hash)
+                                    .ok()
+                                    .flatten()
+                            })
+                    })
+            };
+
+            Ok(maybe_result)
+        })
+    }
+
+    /// Returns the current block number as a `U64` wrapped in a `BoxFuture`.
+    fn get_block_number(
+        &self,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::U64>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            Ok(U64::from(reader.current_miniblock))
+        })
+    }
+
+    /// Estimates the gas required for a given call request.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A `CallRequest` struct representing the call request to estimate gas for.
+    /// * `_block` - An optional `BlockNumber` struct representing the block number to estimate gas for.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `U256` representing the estimated gas required.
+    fn estimate_gas(
+        &self,
+        req: zksync_types::transaction_request::CallRequest,
+        _block: Option<zksync_types::api::BlockNumber>,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
+        let inner = Arc::clone(&self.inner);
+        let reader = match inner.read() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        let result: jsonrpc_core::Result<Fee> = reader.estimate_gas_impl(req);
+        match result {
+            Ok(fee) => Ok(fee.gas_limit).into_boxed_future(),
+            Err(err) => return futures::future::err(err).boxed(),
+        }
+    }
+
+    /// Returns the current gas price in U256 format - the base fee of the next block, derived from the current L1
+    /// gas price.
+    fn gas_price(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
+        match self.inner.read() {
+            Ok(inner) => Ok(U256::from(inner.base_fee())).into_boxed_future(),
+            Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        }
+    }
+
+    /// Creates a filter object, based on filter options, to notify when the state changes (logs).
+    /// To check if the state has changed, call `eth_getFilterChanges`.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: The filter options -
+    ///     fromBlock: - Integer block number, or the string "latest", "earliest" or "pending".
+    ///     toBlock: - Integer block number, or the string "latest", "earliest" or "pending".
+    ///     address: - Contract address or a list of addresses from which the logs should originate.
+    ///     topics: - [H256] topics. Topics are order-dependent. Each topic can also be an array with "or" options.
+    ///
+    /// If the from `fromBlock` or `toBlock` option are equal to "latest" the filter continually appends logs for newly mined blocks.
+    /// Topics are order-dependent. A transaction with a log with topics [A, B] will be matched by the following topic filters:
+    ///     * \[\] "anything"
+    ///     * \[A\] "A in first position (and anything after)"
+    ///     * \[null, B\] "anything in first position AND B in second position (and anything after)"
+    ///     * \[A, B\] "A in first position AND B in second position (and anything after)"
+    ///     * \[\[A, B\], \[A, B\]\] "(A OR B) in first position AND (A OR B) in second position (and anything after)"
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
+    fn new_filter(&self, filter: Filter) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
+        let inner = Arc::clone(&self.inner);
+        let mut writer = match inner.write() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        let from_block = filter
+            .from_block
+            .unwrap_or(zksync_types::api::BlockNumber::Latest);
+        let to_block = filter
+            .to_block
+            .unwrap_or(zksync_types::api::BlockNumber::Latest);
+        let addresses = filter.address.unwrap_or_default().0;
+        let mut topics: [Option<HashSet<H256>>; 4] = Default::default();
+
+        if let Some(filter_topics) = filter.topics {
+            filter_topics
+                .into_iter()
+                .take(4)
+                .enumerate()
+                .for_each(|(i, maybe_topic_set)| {
+                    if let Some(topic_set) = maybe_topic_set {
+                        topics[i] = Some(topic_set.0.into_iter().collect());
+                    }
+                })
+        }
+
+        writer
+            .filters
+            .add_log_filter(from_block, to_block, addresses, topics)
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
+    }
+
+    /// Creates a filter in the node, to notify when a new block arrives.
+    /// To check if the state has changed, call `eth_getFilterChanges`.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
+    fn new_block_filter(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
+        let inner = Arc::clone(&self.inner);
+        let mut writer = match inner.write() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        writer
+            .filters
+            .add_block_filter()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
+    }
+
+    /// Creates a filter in the node, to notify when new pending transactions arrive.
+    /// To check if the state has changed, call `eth_getFilterChanges`.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
+    fn new_pending_transaction_filter(
+        &self,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
+        let inner = Arc::clone(&self.inner);
+        let mut writer = match inner.write() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        writer
+            .filters
+            .add_pending_transaction_filter()
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
+    }
+
+    /// Uninstalls a filter with given id. Should always be called when watch is no longer needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: The filter id
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an `U256` filter id.
+    fn uninstall_filter(&self, id: U256) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<bool>> {
+        let inner = Arc::clone(&self.inner);
+        let mut writer = match inner.write() {
             Ok(r) => r,
             Err(_) => {
                 return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
             }
         };
 
-        let accounts: Vec<H160> = reader.rich_accounts.clone().into_iter().collect();
-        futures::future::ok(accounts).boxed()
+        let result = writer.filters.remove_filter(id);
+        Ok(result).into_boxed_future()
+    }
+
+    /// Returns an array of all logs matching a given filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: The filter options -
+    ///     fromBlock   - Integer block number, or the string "latest", "earliest" or "pending".
+    ///     toBlock     - Integer block number, or the string "latest", "earliest" or "pending".
+    ///     address     - Contract address or a list of addresses from which the logs should originate.
+    ///     topics      - [H256] topics. Topics are order-dependent. Each topic can also be an array with "or" options.
+    ///                   See `new_filter` documention for how to specify topics.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an array of logs.
+    fn get_logs(
+        &self,
+        filter: Filter,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Vec<zksync_types::api::Log>>> {
+        let reader = match self.inner.read() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+        let upstream_filter = filter.clone();
+        // a block hash selects the logs of a single block, and can not be combined with a block range
+        let (from_block, to_block) = match filter.block_hash {
+            Some(_) if filter.from_block.is_some() || filter.to_block.is_some() => {
+                return futures::future::err(jsonrpc_core::Error::invalid_params(
+                    "cannot specify both blockHash and fromBlock/toBlock",
+                ))
+                .boxed()
+            }
+            Some(block_hash) => match reader.resolve_block_number(Some(
+                BlockIdVariant::BlockHashObject(zksync_types::api::BlockHashObject { block_hash }),
+            )) {
+                Ok(number) => {
+                    let block = zksync_types::api::BlockNumber::Number(U64::from(number));
+                    (block, block)
+                }
+                Err(err) => {
+                    return futures::future::err(jsonrpc_core::Error::invalid_params(err)).boxed()
+                }
+            },
+            None => (
+                filter
+                    .from_block
+                    .unwrap_or(zksync_types::api::BlockNumber::Earliest),
+                filter
+                    .to_block
+                    .unwrap_or(zksync_types::api::BlockNumber::Latest),
+            ),
+        };
+        let addresses = filter.address.unwrap_or_default().0;
+        let mut topics: [Option<HashSet<H256>>; 4] = Default::default();
+
+        if let Some(filter_topics) = filter.topics {
+            filter_topics
+                .into_iter()
+                .take(4)
+                .enumerate()
+                .for_each(|(i, maybe_topic_set)| {
+                    if let Some(topic_set) = maybe_topic_set {
+                        topics[i] = Some(topic_set.0.into_iter().collect());
+                    }
+                })
+        }
+
+        let log_filter = LogFilter::new(from_block, to_block, addresses, topics);
+
+        let latest_block_number = U64::from(reader.current_miniblock);
+        let mut logs = reader
+            .tx_results
+            .values()
+            .flat_map(|tx_result| {
+                tx_result
+                    .receipt
+                    .logs
+                    .iter()
+                    .filter(|log| log_filter.matches(log, latest_block_number))
+                    .cloned()
+            })
+            .collect_vec();
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+        // the logs of the blocks up to the fork block are fetched from the fork source - as the local blocks
+        // follow the fork block, the ranges never overlap
+        let upstream_logs = match reader
+            .fork_storage
+            .inner
+            .read()
+            .expect("failed reading fork storage")
+            .fork
+            .as_ref()
+        {
+            Some(fork) => {
+                let from = utils::to_real_block_number(from_block, latest_block_number);
+                let to = utils::to_real_block_number(to_block, latest_block_number)
+                    .min(U64::from(fork.l2_miniblock));
+                if from <= to {
+                    fork.fork_source.get_logs(Filter {
+                        from_block: Some(zksync_types::api::BlockNumber::Number(from)),
+                        to_block: Some(zksync_types::api::BlockNumber::Number(to)),
+                        block_hash: None,
+                        ..upstream_filter
+                    })
+                } else {
+                    Ok(vec![])
+                }
+            }
+            None => Ok(vec![]),
+        };
+
+        let result = match upstream_logs {
+            Ok(upstream_logs) => {
+                let logs = upstream_logs.into_iter().chain(logs).collect_vec();
+                match reader.max_logs_returned {
+                    Some(max_logs) if logs.len() > max_logs => {
+                        Err(jsonrpc_core::Error::invalid_params(format!(
+                            "query returned more than {} results, try with a narrower block range",
+                            max_logs
+                        )))
+                    }
+                    _ => Ok(logs),
+                }
+            }
+            Err(err) => {
+                log::error!("failed fetching logs from the fork source: {:?}", err);
+                Err(into_jsrpc_error(Web3Error::InternalError))
+            }
+        };
+        result.into_boxed_future()
+    }
+
+    /// Returns an array of all logs matching filter with given id.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: The filter id
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an array of logs.
+    fn get_filter_logs(
+        &self,
+        id: U256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<FilterChanges>> {
+        let reader = match self.inner.read() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        let latest_block_number = U64::from(reader.current_miniblock);
+        let logs = match reader.filters.get_filter(id) {
+            Some(FilterType::Log(f)) => reader
+                .tx_results
+                .values()
+                .flat_map(|tx_result| {
+                    tx_result
+                        .receipt
+                        .logs
+                        .iter()
+                        .filter(|log| f.matches(log, latest_block_number))
+                        .cloned()
+                })
+                .collect_vec(),
+            _ => return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed(),
+        };
+
+        Ok(FilterChanges::Logs(logs)).into_boxed_future()
+    }
+
+    /// Polling method for a filter, which returns an array of logs, block hashes, or transaction hashes,
+    /// depending on the filter type, which occurred since last poll.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: The filter id
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to an array of logs, block hashes, or transaction hashes,
+    /// depending on the filter type, which occurred since last poll.
+    /// * Filters created with `eth_newFilter` return [Log] objects.
+    /// * Filters created with `eth_newBlockFilter` return block hashes.
+    /// * Filters created with `eth_newPendingTransactionFilter` return transaction hashes.
+    fn get_filter_changes(
+        &self,
+        id: U256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<FilterChanges>> {
+        let inner = Arc::clone(&self.inner);
+        let mut writer = match inner.write() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        writer
+            .filters
+            .get_new_changes(id)
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
+    }
+
+    fn get_block_transaction_count_by_number(
+        &self,
+        block_number: zksync_types::api::BlockNumber,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let maybe_result = {
+                let reader = match inner.read() {
+                    Ok(r) => r,
+                    Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)),
+                };
+                let number =
+                    utils::to_real_block_number(block_number, U64::from(reader.current_miniblock))
+                        .as_u64();
+
+                reader
+                    .block_hashes
+                    .get(&number)
+                    .and_then(|hash| reader.blocks.get(hash))
+                    .map(|block| U256::from(block.transactions.len()))
+                    .or_else(|| {
+                        reader
+                            .fork_storage
+                            .inner
+                            .read()
+                            .expect("failed reading fork storage")
+                            .fork
+                            .as_ref()
+                            .and_then(|fork| {
+                                fork.fork_source
+                                    .get_block_transaction_count_by_number(block_number)
+                                    .ok()
+                                    .flatten()
+                            })
+                    })
+            };
+
+            match maybe_result {
+                Some(value) => Ok(Some(value)),
+                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+            }
+        })
+    }
+
+    fn get_block_transaction_count_by_hash(
+        &self,
+        block_hash: zksync_basic_types::H256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            // try retrieving block from memory, and if unavailable subsequently from the fork
+            let maybe_result = reader
+                .blocks
+                .get(&block_hash)
+                .map(|block| U256::from(block.transactions.len()))
+                .or_else(|| {
+                    reader
+                        .fork_storage
+                        .inner
+                        .read()
+                        .expect("failed reading fork storage")
+                        .fork
+                        .as_ref()
+                        .and_then(|fork| {
+                            fork.fork_source
+                                .get_block_transaction_count_by_hash(block_hash)
+                                .ok()
+                                .flatten()
+                        })
+                });
+
+            match maybe_result {
+                Some(value) => Ok(Some(value)),
+                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
+            }
+        })
+    }
+
+    /// Returns the value from a storage position at a given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: Address of the storage
+    /// * `idx`: Integer of the position in the storage
+    /// * `block`: The block storage to target - either a number, a tag, or an EIP-1898 `{ blockHash }` object.
+    ///   `requireCanonical` is always satisfied, as blocks of the node are never reorganized.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to a [H256] value in the storage.
+    fn get_storage(
+        &self,
+        address: zksync_basic_types::Address,
+        idx: U256,
+        block: Option<zksync_types::api::BlockIdVariant>,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::H256>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let mut writer = match inner.write() {
+                Ok(r) => r,
+                Err(_) => {
+                    return Err(into_jsrpc_error(Web3Error::InternalError));
+                }
+            };
+
+            let storage_key = StorageKey::new(AccountTreeId::new(address), u256_to_h256(idx));
+
+            let block_number = match block {
+                Some(zksync_types::api::BlockIdVariant::BlockNumber(block_number)) => {
+                    utils::to_real_block_number(block_number, U64::from(writer.current_miniblock))
+                }
+                Some(zksync_types::api::BlockIdVariant::BlockNumberObject(o)) => {
+                    utils::to_real_block_number(o.block_number, U64::from(writer.current_miniblock))
+                }
+                // block hashes are resolved against the local blocks first, then against the fork
+                Some(zksync_types::api::BlockIdVariant::BlockHashObject(_)) => writer
+                    .resolve_block_number(block)
+                    .map(U64::from)
+                    .map_err(|err| {
+                        log::error!("{}", err);
+                        jsonrpc_core::Error::invalid_params(err)
+                    })?,
+                None => U64::from(writer.current_miniblock),
+            };
+
+            if block_number.as_u64() == writer.current_miniblock {
+                Ok(H256(writer.fork_storage.read_value(&storage_key).0))
+            } else if writer.block_hashes.contains_key(&block_number.as_u64()) {
+                let value = writer
+                    .block_hashes
+                    .get(&block_number.as_u64())
+                    .and_then(|block_hash| writer.previous_states.get(block_hash))
+                    .and_then(|state| state.get(&storage_key))
+                    .cloned()
+                    .unwrap_or_default();
+
+                if value.is_zero() {
+                    Ok(H256(writer.fork_storage.read_value(&storage_key).0))
+                } else {
+                    Ok(value)
+                }
+            } else {
+                writer
+                    .fork_storage
+                    .inner
+                    .read()
+                    .expect("failed reading fork storage")
+                    .fork
+                    .as_ref()
+                    .and_then(|fork| fork.fork_source.get_storage_at(address, idx, block).ok())
+                    .ok_or_else(|| {
+                        log::error!(
+                            "unable to get storage at address {:?}, index {:?} for block {:?}",
+                            address,
+                            idx,
+                            block
+                        );
+                        into_jsrpc_error(Web3Error::InternalError)
+                    })
+            }
+        })
+    }
+
+    /// Returns information about a transaction by block hash and transaction index position.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_hash`: Hash of a block
+    /// * `index`: Integer of the transaction index position
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that maybe resolves to a [zksync_types::api::Transaction], if found.
+    fn get_transaction_by_block_hash_and_index(
+        &self,
+        block_hash: zksync_basic_types::H256,
+        index: zksync_basic_types::web3::types::Index,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::Transaction>>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let reader = match inner.read() {
+                Ok(r) => r,
+                Err(_) => {
+                    return Err(into_jsrpc_error(Web3Error::InternalError));
+                }
+            };
+
+            // out of range indices of local blocks are not looked up in the fork
+            let maybe_tx = match reader.blocks.get(&block_hash) {
+                Some(block) => reader.block_transaction(block, index.as_usize()),
+                None => reader
+                    .fork_storage
+                    .inner
+                    .read()
+                    .expect("failed reading fork storage")
+                    .fork
+                    .as_ref()
+                    .and_then(|fork| {
+                        fork.fork_source
+                            .get_transaction_by_block_hash_and_index(block_hash, index)
+                            .ok()
+                    })
+                    .flatten(),
+            };
+
+            Ok(maybe_tx)
+        })
+    }
+
+    /// Returns information about a transaction by block number and transaction index position.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_number`: A block number, or the string "earliest", "latest" or "pending".
+    /// * `index`: Integer of the transaction index position
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that maybe resolves to a [zksync_types::api::Transaction], if found.
+    fn get_transaction_by_block_number_and_index(
+        &self,
+        block_number: zksync_types::api::BlockNumber,
+        index: zksync_basic_types::web3::types::Index,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::Transaction>>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let reader = match inner.read() {
+                Ok(r) => r,
+                Err(_) => {
+                    return Err(into_jsrpc_error(Web3Error::InternalError));
+                }
+            };
+
+            let real_block_number =
+                utils::to_real_block_number(block_number, U64::from(reader.current_miniblock));
+            // out of range indices of local blocks are not looked up in the fork
+            let maybe_tx = match reader
+                .block_hashes
+                .get(&real_block_number.as_u64())
+                .and_then(|block_hash| reader.blocks.get(block_hash))
+            {
+                Some(block) => reader.block_transaction(block, index.as_usize()),
+                None => reader
+                    .fork_storage
+                    .inner
+                    .read()
+                    .expect("failed reading fork storage")
+                    .fork
+                    .as_ref()
+                    .and_then(|fork| {
+                        fork.fork_source
+                            .get_transaction_by_block_number_and_index(block_number, index)
+                            .ok()
+                    })
+                    .flatten(),
+            };
+
+            Ok(maybe_tx)
+        })
+    }
+
+    /// Returns the protocol version.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to a hex `String` of the version number.
+    fn protocol_version(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<String>> {
+        Ok(String::from(PROTOCOL_VERSION)).into_boxed_future()
+    }
+
+    /// Returns `false` as the node is always synced, unless syncing is simulated with `config_setSyncing`.
+    fn syncing(
+        &self,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::web3::types::SyncState>>
+    {
+        let reader = match self.inner.read() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        let state = match &reader.simulated_sync {
+            Some(sync_info) => {
+                let current_block = U256::from(reader.current_miniblock);
+                web3::types::SyncState::Syncing(web3::types::SyncInfo {
+                    starting_block: sync_info.starting_block,
+                    current_block,
+                    highest_block: cmp::max(sync_info.highest_block, current_block),
+                })
+            }
+            None => web3::types::SyncState::NotSyncing,
+        };
+        Ok(state).into_boxed_future()
+    }
+    /// Returns a list of available accounts.
+    ///
+    /// This function fetches the accounts from the inner state, and returns them as a list of addresses (`H160`).
+    ///
+    /// # Errors
+    ///
+    /// Returns a `jsonrpc_core::Result` error if acquiring a write lock on the inner state fails.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to a `Vec<H160>` of addresses.
+    fn accounts(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Vec<H160>>> {
+        let inner = Arc::clone(&self.inner);
+        let reader = match inner.read() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        let accounts: Vec<H160> = reader.rich_accounts.clone().into_iter().collect();
+        futures::future::ok(accounts).boxed()
+    }
+
+    /// Returns the address that is set as the operator (fee account) of the produced blocks.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to the coinbase `Address`.
+    fn coinbase(
+        &self,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::Address>> {
+        let inner = Arc::clone(&self.inner);
+        let reader = match inner.read() {
+            Ok(r) => r,
+            Err(_) => {
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+            }
+        };
+
+        futures::future::ok(reader.coinbase).boxed()
+    }
+
+    fn compilers(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Vec<String>>> {
+        not_implemented("eth_getCompilers")
+    }
+
+    fn hashrate(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
+        not_implemented("eth_hashrate")
+    }
+
+    fn get_uncle_count_by_block_hash(
+        &self,
+        _hash: zksync_basic_types::H256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
+        not_implemented("eth_getUncleCountByBlockHash")
+    }
+
+    fn get_uncle_count_by_block_number(
+        &self,
+        _number: zksync_types::api::BlockNumber,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
+        not_implemented("eth_getUncleCountByBlockNumber")
+    }
+
+    fn mining(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<bool>> {
+        not_implemented("eth_mining")
+    }
+
+    /// Returns the fee history for a given range of blocks.
+    ///
+    /// Note: This implementation is limited to using the base fee at the current L1 gas price
+    /// as the history gas price
+    ///
+    /// # Arguments
+    ///
+    /// * `block_count` - The number of blocks in the requested range. Between 1 and 1024 blocks can be requested in a single query. It will return less than the requested range if not all blocks are available.
+    /// * `newest_block` - The highest number block of the requested range. As this implementation is using hard-coded values, this argument is ignored.
+    /// * `reward_percentiles` - A list of percentile values with a monotonic increase in value.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `FeeHistory` representing the fee history of the specified range of blocks.
+    /// Empty blocks have a gas used ratio of 0, and zero rewards for every percentile.
+    fn fee_history(
+        &self,
+        block_count: U64,
+        _newest_block: zksync_types::api::BlockNumber,
+        reward_percentiles: Vec<f32>,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<FeeHistory>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            let block_count = block_count
+                .as_u64()
+                .min(1024)
+                // Can't be more than the total number of blocks
+                .clamp(1, reader.current_miniblock + 1);
+
+            let mut base_fee_per_gas = vec![U256::from(reader.base_fee()); block_count as usize];
+
+            let oldest_block = reader.current_miniblock + 1 - base_fee_per_gas.len() as u64;
+            let gas_used_ratio = (oldest_block..=reader.current_miniblock)
+                .map(|number| {
+                    reader
+                        .block_hashes
+                        .get(&number)
+                        .and_then(|hash| reader.blocks.get(hash))
+                        .filter(|block| !block.gas_limit.is_zero())
+                        .map_or(0.0, |block| {
+                            block.gas_used.as_u64() as f64 / block.gas_limit.as_u64() as f64
+                        })
+                })
+                .collect::<Vec<_>>();
+            // Effective priority gas price is currently 0.
+            let reward = Some(vec![
+                vec![U256::zero(); reward_percentiles.len()];
+                base_fee_per_gas.len()
+            ]);
+
+            // `base_fee_per_gas` for next miniblock cannot be calculated, appending last fee as a placeholder.
+            base_fee_per_gas.push(*base_fee_per_gas.last().unwrap());
+
+            Ok(FeeHistory {
+                oldest_block: web3::types::BlockNumber::Number(oldest_block.into()),
+                base_fee_per_gas,
+                gas_used_ratio,
+                reward,
+            })
+        })
+    }
+}
+
+/// Result of a transaction simulated by `eth_callBundle`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTransactionResult {
+    pub tx_hash: H256,
+    /// Gas used by the transaction, after refunds. Zero for halted transactions.
+    pub gas_used: U256,
+    /// Data returned by the transaction, if it succeeded.
+    pub return_data: Bytes,
+    /// Reason of the revert or halt, if the transaction did not succeed.
+    pub error: Option<String>,
+}
+
+/// Methods of the `eth` namespace that are not part of the zkSync API.
+#[rpc]
+pub trait EthExtNamespaceT {
+    /// Returns the Merkle proofs of the given storage slots of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The account to get the proofs for.
+    /// * `keys` - The storage slots to get the proofs for.
+    /// * `block` - Optional block ID variant. Only the latest block is supported.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the proofs, in the format of `zks_getProof`.
+    #[rpc(name = "eth_getProof")]
+    fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<H256>,
+        block: Option<BlockIdVariant>,
+    ) -> BoxFuture<jsonrpc_core::Result<Proof>>;
+
+    /// Simulates a bundle of transactions, executed in order on top of the state at `state_block`, without
+    /// committing them. Each transaction sees the effects of the previous ones - a reverted or halted transaction
+    /// is reported, and the following transactions are still executed.
+    ///
+    /// # Arguments
+    ///
+    /// * `transactions` - The raw (RLP-encoded and signed) transactions of the bundle.
+    /// * `block` - The block the bundle targets, which must follow `state_block`. The transactions are executed in
+    ///   the block right after `state_block`.
+    /// * `state_block` - Optional block ID variant of the state to execute the bundle on - default: the latest block.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the result of each transaction, in the bundle order.
+    #[rpc(name = "eth_callBundle")]
+    fn call_bundle(
+        &self,
+        transactions: Vec<Bytes>,
+        block: U64,
+        state_block: Option<BlockIdVariant>,
+    ) -> BoxFuture<jsonrpc_core::Result<Vec<BundleTransactionResult>>>;
+
+    /// Sends an unsigned transaction from an impersonated account - see `hardhat_impersonateAccount`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The transaction, whose `from` must be impersonated. The nonce, gas limit and fees default to
+    ///   the next nonce of the sender and the L2 gas price.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the hash of the transaction.
+    #[rpc(name = "eth_sendTransaction")]
+    fn send_transaction(
+        &self,
+        request: zksync_types::transaction_request::CallRequest,
+    ) -> BoxFuture<jsonrpc_core::Result<H256>>;
+}
+
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> EthExtNamespaceT for InMemoryNode<S> {
+    fn get_proof(
+        &self,
+        address: Address,
+        keys: Vec<H256>,
+        block: Option<BlockIdVariant>,
+    ) -> BoxFuture<jsonrpc_core::Result<Proof>> {
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            let block_number = reader
+                .resolve_block_number(block)
+                .map_err(jsonrpc_core::Error::invalid_params)?;
+            if block_number != reader.current_miniblock {
+                return Err(jsonrpc_core::Error::invalid_params(format!(
+                    "proofs are only available for the latest block #{}",
+                    reader.current_miniblock
+                )));
+            }
+
+            reader.get_proof(address, keys).map_err(|err| {
+                log::error!("failed getting proof: {}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+        })
+    }
+
+    fn call_bundle(
+        &self,
+        transactions: Vec<Bytes>,
+        block: U64,
+        state_block: Option<BlockIdVariant>,
+    ) -> BoxFuture<jsonrpc_core::Result<Vec<BundleTransactionResult>>> {
+        let txs = match transactions
+            .iter()
+            .map(|tx_bytes| self.decode_raw_tx(&tx_bytes.0))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(txs) => txs,
+            Err(err) => {
+                return futures::future::err(jsonrpc_core::Error::invalid_params(err)).boxed()
+            }
+        };
+        let result = self.simulate_bundle(txs, block.as_u64(), state_block);
+
+        Box::pin(async move { result.map_err(jsonrpc_core::Error::invalid_params) })
+    }
+
+    fn send_transaction(
+        &self,
+        request: zksync_types::transaction_request::CallRequest,
+    ) -> BoxFuture<jsonrpc_core::Result<H256>> {
+        let result = self.send_impersonated_tx(request);
+
+        Box::pin(async move { result.map_err(jsonrpc_core::Error::invalid_params) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cache::CacheConfig,
+        http_fork_source::HttpForkSource,
+        node::InMemoryNode,
+        testing::{
+            self, default_tx_debug_info, ForkBlockConfig, LogBuilder, MockServer,
+            TransactionResponseBuilder,
+        },
+    };
+    use maplit::hashmap;
+    use zksync_basic_types::{L2ChainId, Nonce};
+    use zksync_types::{
+        api::{BlockHashObject, BlockNumber, BlockNumberObject},
+        transaction_request::CallRequestBuilder,
+        utils::deployed_address_create,
+    };
+    use zksync_web3_decl::types::{SyncState, ValueOrArray};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_eth_syncing() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let syncing = node.syncing().await.expect("failed syncing");
+        assert!(matches!(syncing, SyncState::NotSyncing));
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_history_with_1_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let fee_history = node
+            .fee_history(U64::from(1), BlockNumber::Latest, vec![25.0, 50.0, 75.0])
+            .await
+            .expect("fee_history failed");
+
+        assert_eq!(
+            fee_history.oldest_block,
+            web3::types::BlockNumber::Number(U64::from(0))
+        );
+        assert_eq!(
+            fee_history.base_fee_per_gas,
+            vec![U256::from(L2_GAS_PRICE); 2]
+        );
+        assert_eq!(fee_history.gas_used_ratio, vec![0.0]);
+        assert_eq!(fee_history.reward, Some(vec![vec![U256::from(0); 3]]));
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_history_with_no_reward_percentiles() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let fee_history = node
+            .fee_history(U64::from(1), BlockNumber::Latest, vec![])
+            .await
+            .expect("fee_history failed");
+
+        assert_eq!(
+            fee_history.oldest_block,
+            web3::types::BlockNumber::Number(U64::from(0))
+        );
+        assert_eq!(
+            fee_history.base_fee_per_gas,
+            vec![U256::from(L2_GAS_PRICE); 2]
+        );
+        assert_eq!(fee_history.gas_used_ratio, vec![0.0]);
+        assert_eq!(fee_history.reward, Some(vec![vec![]]));
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_history_with_multiple_blocks() {
+        // Arrange
+        let node = InMemoryNode::<HttpForkSource>::default();
+        testing::apply_tx(&node, H256::repeat_byte(0x01));
+
+        // Act
+        let latest_block = node
+            .get_block_number()
+            .await
+            .expect("Block number fetch failed");
+        let fee_history = node
+            .fee_history(U64::from(2), BlockNumber::Latest, vec![25.0, 50.0, 75.0])
+            .await
+            .expect("fee_history failed");
+
+        // Assert
+        // We should receive 2 fees: from block 1 and 2.
+        assert_eq!(latest_block, U64::from(2));
+        assert_eq!(
+            fee_history.oldest_block,
+            web3::types::BlockNumber::Number(U64::from(1))
+        );
+        assert_eq!(
+            fee_history.base_fee_per_gas,
+            vec![U256::from(L2_GAS_PRICE); 3]
+        );
+        assert_eq!(fee_history.gas_used_ratio, vec![0.0, 0.0]);
+        assert_eq!(fee_history.reward, Some(vec![vec![U256::from(0); 3]; 2]));
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_history_over_empty_blocks_reports_zero_ratios_and_rewards() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx = testing::build_l2_tx(&H256::repeat_byte(0x1), Nonce(0));
+        node.set_rich_account(tx.initiator_account());
+        node.submit_tx(tx).expect("failed submitting tx");
+        // the transaction block is followed by the empty block of the end of its batch, and two mined empty blocks
+        node.mine_block().expect("failed mining block");
+        node.mine_block().expect("failed mining block");
+
+        let fee_history = node
+            .fee_history(U64::from(4), BlockNumber::Latest, vec![10.0, 90.0])
+            .await
+            .expect("fee_history failed");
+
+        assert_eq!(
+            web3::types::BlockNumber::Number(U64::from(1)),
+            fee_history.oldest_block
+        );
+        assert_eq!(
+            vec![U256::from(L2_GAS_PRICE); 5],
+            fee_history.base_fee_per_gas
+        );
+        assert_eq!(4, fee_history.gas_used_ratio.len());
+        assert!(fee_history.gas_used_ratio[0] > 0.0);
+        assert_eq!(vec![0.0; 3], fee_history.gas_used_ratio[1..].to_vec());
+        assert_eq!(Some(vec![vec![U256::zero(); 2]; 4]), fee_history.reward);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_hash_produces_no_block_error_for_non_existing_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let expected_err = into_jsrpc_error(Web3Error::NoBlock);
+        let result = node.get_block_by_hash(H256::repeat_byte(0x01), false).await;
+
+        assert_eq!(expected_err, result.unwrap_err());
+    }
+
+    #[tokio::test]
+    async fn test_node_run_has_genesis_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let block = node
+            .get_block_by_number(BlockNumber::Latest, false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+
+        assert_eq!(0, block.number.as_u64());
+        assert_eq!(compute_hash(0, H256::zero()), block.hash);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_hash_for_produced_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let (expected_block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x01));
+
+        let actual_block = node
+            .get_block_by_hash(expected_block_hash, false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+
+        assert_eq!(expected_block_hash, actual_block.hash);
+        assert_eq!(U64::from(1), actual_block.number);
+        assert_eq!(Some(U64::from(1)), actual_block.l1_batch_number);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_hash_returns_transactions_of_produced_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx_hash = H256::repeat_byte(0x01);
+        let (block_hash, block_number) = testing::apply_tx(&node, tx_hash);
+
+        let block = node
+            .get_block_by_hash(block_hash, false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+        assert_eq!(block_number, block.number);
+        match block.transactions.as_slice() {
+            [TransactionVariant::Hash(hash)] => assert_eq!(tx_hash, *hash),
+            _ => panic!("expected a single transaction hash"),
+        }
+
+        let block = node
+            .get_block_by_hash(block_hash, true)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+        assert_eq!(block_number, block.number);
+        match block.transactions.as_slice() {
+            [TransactionVariant::Full(tx)] => {
+                assert_eq!(tx_hash, tx.hash);
+                assert_eq!(Some(block_hash), tx.block_hash);
+                assert_eq!(Some(block_number), tx.block_number);
+            }
+            _ => panic!("expected a single full transaction"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_node_block_mapping_is_correctly_populated_when_using_fork_source() {
+        let input_block_number = 8;
+        let input_block_hash = H256::repeat_byte(0x01);
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: input_block_number,
+            hash: input_block_hash,
+            transaction_count: 0,
+        });
+
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let inner = node.inner.read().unwrap();
+        assert!(
+            inner.blocks.contains_key(&input_block_hash),
+            "block wasn't cached"
+        );
+        assert!(
+            inner.block_hashes.contains_key(&input_block_number),
+            "block number wasn't cached"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_hash_uses_fork_source() {
+        let input_block_hash = H256::repeat_byte(0x01);
+
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let mock_block_number = 8;
+        let block_response = testing::BlockResponseBuilder::new()
+            .set_hash(input_block_hash)
+            .set_number(mock_block_number)
+            .build();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockByHash",
+                "params": [
+                    format!("{input_block_hash:#x}"),
+                    true
+                ],
+            }),
+            block_response,
+        );
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let actual_block = node
+            .get_block_by_hash(input_block_hash, false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+
+        assert_eq!(input_block_hash, actual_block.hash);
+        assert_eq!(U64::from(mock_block_number), actual_block.number);
+        assert_eq!(Some(U64::from(6)), actual_block.l1_batch_number);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_produces_no_block_error_for_non_existing_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let expected_err = into_jsrpc_error(Web3Error::NoBlock);
+        let result = node
+            .get_block_by_number(BlockNumber::Number(U64::from(42)), false)
+            .await;
+
+        assert_eq!(expected_err, result.unwrap_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_for_produced_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        testing::apply_tx(&node, H256::repeat_byte(0x01));
+        let expected_block_number = 1;
+
+        let actual_block = node
+            .get_block_by_number(BlockNumber::Number(U64::from(expected_block_number)), false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+
+        assert_eq!(U64::from(expected_block_number), actual_block.number);
+        assert_eq!(1, actual_block.transactions.len());
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_returns_full_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx_hash = H256::repeat_byte(0x01);
+        let (block_hash, block_number) = testing::apply_tx(&node, tx_hash);
+        // transactions stored by their hash are hydrated from the executed transactions
+        node.get_inner()
+            .write()
+            .unwrap()
+            .blocks
+            .get_mut(&block_hash)
+            .expect("block must exist")
+            .transactions = vec![TransactionVariant::Hash(tx_hash)];
+
+        let block = node
+            .get_block_by_number(BlockNumber::Number(block_number), true)
+            .await
+            .expect("failed fetching block by number")
+            .expect("no block");
+
+        assert_eq!(1, block.transactions.len());
+        match &block.transactions[0] {
+            TransactionVariant::Full(tx) => {
+                assert_eq!(tx_hash, tx.hash);
+                assert_eq!(Some(block_hash), tx.block_hash);
+                assert_eq!(Some(block_number), tx.block_number);
+            }
+            TransactionVariant::Hash(_) => panic!("expected a full transaction"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_returns_transaction_hashes() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx_hash = H256::repeat_byte(0x01);
+        let (_, block_number) = testing::apply_tx(&node, tx_hash);
+
+        let block = node
+            .get_block_by_number(BlockNumber::Number(block_number), false)
+            .await
+            .expect("failed fetching block by number")
+            .expect("no block");
+
+        assert_eq!(1, block.transactions.len());
+        match &block.transactions[0] {
+            TransactionVariant::Hash(hash) => assert_eq!(tx_hash, *hash),
+            TransactionVariant::Full(_) => panic!("expected a transaction hash"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_uses_fork_source_if_missing_number() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let mock_block_number = 8;
+        let block_response = testing::BlockResponseBuilder::new()
+            .set_number(mock_block_number)
+            .build();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockByNumber",
+                "params": [
+                    "0x8",
+                    true
+                ],
+            }),
+            block_response,
+        );
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let actual_block = node
+            .get_block_by_number(BlockNumber::Number(U64::from(8)), false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+        assert_eq!(U64::from(mock_block_number), actual_block.number);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_for_latest_block_produced_locally() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        testing::apply_tx(&node, H256::repeat_byte(0x01));
+
+        // The latest block, will be the 'virtual' one with 0 transactions (block 2).
+        let virtual_block = node
+            .get_block_by_number(BlockNumber::Latest, true)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+
+        assert_eq!(U64::from(2), virtual_block.number);
+        assert_eq!(0, virtual_block.transactions.len());
+
+        let actual_block = node
+            .get_block_by_number(BlockNumber::Number(U64::from(1)), true)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+
+        assert_eq!(U64::from(1), actual_block.number);
+        assert_eq!(1, actual_block.transactions.len());
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_uses_locally_available_block_for_latest_block() {
+        let input_block_number = 10;
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: input_block_number,
+            hash: H256::repeat_byte(0x01),
+            transaction_count: 0,
+        });
+
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let actual_block = node
+            .get_block_by_number(BlockNumber::Latest, false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+        assert_eq!(U64::from(input_block_number), actual_block.number);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_uses_fork_source_for_earliest_block() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let input_block_number = 1;
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockByNumber",
+                "params": [
+                    "earliest",
+                    true
+                ],
+            }),
+            testing::BlockResponseBuilder::new()
+                .set_number(input_block_number)
+                .build(),
+        );
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let actual_block = node
+            .get_block_by_number(BlockNumber::Earliest, false)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no block");
+        assert_eq!(U64::from(input_block_number), actual_block.number);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_by_number_uses_locally_available_for_latest_alike_blocks() {
+        for block_number in [
+            BlockNumber::Pending,
+            BlockNumber::Committed,
+            BlockNumber::Finalized,
+        ] {
+            let input_block_number = 10;
+            let mock_server = MockServer::run_with_config(ForkBlockConfig {
+                number: input_block_number,
+                hash: H256::repeat_byte(0xab),
+                transaction_count: 0,
+            });
+            let node = InMemoryNode::<HttpForkSource>::new(
+                Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+                crate::node::ShowCalls::None,
+                ShowStorageLogs::None,
+                ShowVMDetails::None,
+                ShowGasDetails::None,
+                false,
+                &system_contracts::Options::BuiltIn,
+            );
+
+            let actual_block = node
+                .get_block_by_number(block_number, false)
+                .await
+                .expect("failed fetching block by hash")
+                .expect("no block");
+            assert_eq!(
+                U64::from(input_block_number),
+                actual_block.number,
+                "case {}",
+                block_number,
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_block_transaction_count_by_hash_for_produced_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let (expected_block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x01));
+        let actual_transaction_count = node
+            .get_block_transaction_count_by_hash(expected_block_hash)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no result");
+
+        assert_eq!(U256::from(1), actual_transaction_count);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_transaction_count_by_hash_uses_fork_source() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let input_block_hash = H256::repeat_byte(0x01);
+        let input_transaction_count = 1;
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockTransactionCountByHash",
+                "params": [
+                    format!("{:#x}", input_block_hash),
+                ],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", input_transaction_count),
+            }),
+        );
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let actual_transaction_count = node
+            .get_block_transaction_count_by_hash(input_block_hash)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no result");
+
+        assert_eq!(
+            U256::from(input_transaction_count),
+            actual_transaction_count
+        );
     }
 
-    fn coinbase(
-        &self,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::Address>> {
-        not_implemented("eth_coinbase")
-    }
+    #[tokio::test]
+    async fn test_get_block_transaction_count_by_number_for_produced_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
 
-    fn compilers(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Vec<String>>> {
-        not_implemented("eth_getCompilers")
-    }
+        testing::apply_tx(&node, H256::repeat_byte(0x01));
+        let actual_transaction_count = node
+            .get_block_transaction_count_by_number(BlockNumber::Number(U64::from(1)))
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no result");
 
-    fn hashrate(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<U256>> {
-        not_implemented("eth_hashrate")
+        assert_eq!(U256::from(1), actual_transaction_count);
     }
 
-    fn get_uncle_count_by_block_hash(
-        &self,
-        _hash: zksync_basic_types::H256,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
-        not_implemented("eth_getUncleCountByBlockHash")
+    #[tokio::test]
+    async fn test_get_block_transaction_count_for_produced_block_with_multiple_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0x1);
+        let txs = (0..2)
+            .map(|nonce| testing::build_l2_tx(&private_key, Nonce(nonce)))
+            .collect::<Vec<_>>();
+        node.set_rich_account(txs[0].initiator_account());
+        node.run_l2_txs(txs, TxExecutionMode::VerifyExecute)
+            .expect("failed running transactions");
+
+        let block_hash = *node
+            .get_inner()
+            .read()
+            .expect("failed reading inner")
+            .block_hashes
+            .get(&1)
+            .expect("block was not produced");
+        let count_by_number = node
+            .get_block_transaction_count_by_number(BlockNumber::Number(U64::from(1)))
+            .await
+            .expect("failed fetching block by number")
+            .expect("no result");
+        let count_by_hash = node
+            .get_block_transaction_count_by_hash(block_hash)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no result");
+
+        assert_eq!(U256::from(2), count_by_number);
+        assert_eq!(U256::from(2), count_by_hash);
     }
 
-    fn get_uncle_count_by_block_number(
-        &self,
-        _number: zksync_types::api::BlockNumber,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<U256>>> {
-        not_implemented("eth_getUncleCountByBlockNumber")
+    #[tokio::test]
+    async fn test_get_block_transaction_count_by_number_uses_fork_source() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let input_block_number = 1;
+        let input_transaction_count = 1;
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockTransactionCountByNumber",
+                "params": [
+                    format!("{:#x}", input_block_number),
+                ],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", input_transaction_count),
+            }),
+        );
+
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let actual_transaction_count = node
+            .get_block_transaction_count_by_number(BlockNumber::Number(U64::from(1)))
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no result");
+
+        assert_eq!(
+            U256::from(input_transaction_count),
+            actual_transaction_count
+        );
     }
 
-    fn mining(&self) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<bool>> {
-        not_implemented("eth_mining")
+    #[tokio::test]
+    async fn test_get_block_transaction_count_by_number_earliest_uses_fork_source() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let input_transaction_count = 1;
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockTransactionCountByNumber",
+                "params": [
+                    "earliest",
+                ],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", input_transaction_count),
+            }),
+        );
+
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+
+        let actual_transaction_count = node
+            .get_block_transaction_count_by_number(BlockNumber::Earliest)
+            .await
+            .expect("failed fetching block by hash")
+            .expect("no result");
+
+        assert_eq!(
+            U256::from(input_transaction_count),
+            actual_transaction_count
+        );
     }
 
-    /// Returns the fee history for a given range of blocks.
-    ///
-    /// Note: This implementation is limited to using the hard-coded value
-    /// of L2_GAS_PRICE as the history gas price
-    ///
-    /// # Arguments
-    ///
-    /// * `block_count` - The number of blocks in the requested range. Between 1 and 1024 blocks can be requested in a single query. It will return less than the requested range if not all blocks are available.
-    /// * `newest_block` - The highest number block of the requested range. As this implementation is using hard-coded values, this argument is ignored.
-    /// * `reward_percentiles` - A list of percentile values with a monotonic increase in value.
-    ///
-    /// # Returns
-    ///
-    /// A `BoxFuture` containing a `Result` with a `FeeHistory` representing the fee history of the specified range of blocks.
-    fn fee_history(
-        &self,
-        block_count: U64,
-        _newest_block: zksync_types::api::BlockNumber,
-        reward_percentiles: Vec<f32>,
-    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<FeeHistory>> {
-        let inner = Arc::clone(&self.inner);
+    #[tokio::test]
+    async fn test_get_block_transaction_count_by_number_latest_alike_uses_fork_source() {
+        for block_number in [
+            BlockNumber::Latest,
+            BlockNumber::Pending,
+            BlockNumber::Committed,
+            BlockNumber::Finalized,
+        ] {
+            let input_transaction_count = 1;
+            let mock_server = MockServer::run_with_config(ForkBlockConfig {
+                number: 10,
+                transaction_count: input_transaction_count,
+                hash: H256::repeat_byte(0xab),
+            });
 
-        Box::pin(async move {
-            let reader = inner
-                .read()
-                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            let node = InMemoryNode::<HttpForkSource>::new(
+                Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+                crate::node::ShowCalls::None,
+                ShowStorageLogs::None,
+                ShowVMDetails::None,
+                ShowGasDetails::None,
+                false,
+                &system_contracts::Options::BuiltIn,
+            );
 
-            let block_count = block_count
-                .as_u64()
-                .min(1024)
-                // Can't be more than the total number of blocks
-                .clamp(1, reader.current_miniblock + 1);
+            let actual_transaction_count = node
+                .get_block_transaction_count_by_number(block_number)
+                .await
+                .expect("failed fetching block by hash")
+                .expect("no result");
 
-            let mut base_fee_per_gas = vec![U256::from(L2_GAS_PRICE); block_count as usize];
+            assert_eq!(
+                U256::from(input_transaction_count),
+                actual_transaction_count,
+                "case {}",
+                block_number,
+            );
+        }
+    }
 
-            let oldest_block = reader.current_miniblock + 1 - base_fee_per_gas.len() as u64;
-            // We do not store gas used ratio for blocks, returns array of zeroes as a placeholder.
-            let gas_used_ratio = vec![0.0; base_fee_per_gas.len()];
-            // Effective priority gas price is currently 0.
-            let reward = Some(vec![
-                vec![U256::zero(); reward_percentiles.len()];
-                base_fee_per_gas.len()
-            ]);
+    #[tokio::test]
+    async fn test_get_transaction_receipt_uses_produced_block_hash() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx_hash = H256::repeat_byte(0x01);
+        let (expected_block_hash, _) = testing::apply_tx(&node, tx_hash);
 
-            // `base_fee_per_gas` for next miniblock cannot be calculated, appending last fee as a placeholder.
-            base_fee_per_gas.push(*base_fee_per_gas.last().unwrap());
+        let actual_tx_receipt = node
+            .get_transaction_receipt(tx_hash)
+            .await
+            .expect("failed fetching transaction receipt by hash")
+            .expect("no transaction receipt");
 
-            Ok(FeeHistory {
-                oldest_block: web3::types::BlockNumber::Number(oldest_block.into()),
-                base_fee_per_gas,
-                gas_used_ratio,
-                reward,
-            })
-        })
+        assert_eq!(Some(expected_block_hash), actual_tx_receipt.block_hash);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{
-        cache::CacheConfig,
-        http_fork_source::HttpForkSource,
-        node::InMemoryNode,
-        testing::{
-            self, default_tx_debug_info, ForkBlockConfig, LogBuilder, MockServer,
-            TransactionResponseBuilder,
-        },
-    };
-    use maplit::hashmap;
-    use zksync_basic_types::Nonce;
-    use zksync_types::{
-        api::{BlockHashObject, BlockNumber, BlockNumberObject},
-        utils::deployed_address_create,
-    };
-    use zksync_web3_decl::types::{SyncState, ValueOrArray};
+    #[tokio::test]
+    async fn test_new_block_filter_returns_filter_id() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let actual_filter_id = node
+            .new_block_filter()
+            .await
+            .expect("failed creating filter");
 
-    use super::*;
+        assert_eq!(U256::from(1), actual_filter_id);
+    }
 
     #[tokio::test]
-    async fn test_eth_syncing() {
+    async fn test_new_filter_returns_filter_id() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let syncing = node.syncing().await.expect("failed syncing");
-        assert!(matches!(syncing, SyncState::NotSyncing));
+
+        let actual_filter_id = node
+            .new_filter(Filter::default())
+            .await
+            .expect("failed creating filter");
+
+        assert_eq!(U256::from(1), actual_filter_id);
     }
 
     #[tokio::test]
-    async fn test_get_fee_history_with_1_block() {
+    async fn test_new_pending_transaction_filter_returns_filter_id() {
         let node = InMemoryNode::<HttpForkSource>::default();
 
-        let fee_history = node
-            .fee_history(U64::from(1), BlockNumber::Latest, vec![25.0, 50.0, 75.0])
+        let actual_filter_id = node
+            .new_pending_transaction_filter()
             .await
-            .expect("fee_history failed");
+            .expect("failed creating filter");
 
-        assert_eq!(
-            fee_history.oldest_block,
-            web3::types::BlockNumber::Number(U64::from(0))
-        );
-        assert_eq!(
-            fee_history.base_fee_per_gas,
-            vec![U256::from(L2_GAS_PRICE); 2]
-        );
-        assert_eq!(fee_history.gas_used_ratio, vec![0.0]);
-        assert_eq!(fee_history.reward, Some(vec![vec![U256::from(0); 3]]));
+        assert_eq!(U256::from(1), actual_filter_id);
     }
 
     #[tokio::test]
-    async fn test_get_fee_history_with_no_reward_percentiles() {
+    async fn test_uninstall_filter_returns_true_if_filter_exists() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let filter_id = node
+            .new_block_filter()
+            .await
+            .expect("failed creating filter");
 
-        let fee_history = node
-            .fee_history(U64::from(1), BlockNumber::Latest, vec![])
+        let actual_result = node
+            .uninstall_filter(filter_id)
             .await
-            .expect("fee_history failed");
+            .expect("failed creating filter");
 
-        assert_eq!(
-            fee_history.oldest_block,
-            web3::types::BlockNumber::Number(U64::from(0))
-        );
-        assert_eq!(
-            fee_history.base_fee_per_gas,
-            vec![U256::from(L2_GAS_PRICE); 2]
-        );
-        assert_eq!(fee_history.gas_used_ratio, vec![0.0]);
-        assert_eq!(fee_history.reward, Some(vec![vec![]]));
+        assert!(actual_result);
     }
 
     #[tokio::test]
-    async fn test_get_fee_history_with_multiple_blocks() {
-        // Arrange
+    async fn test_uninstall_filter_returns_false_if_filter_does_not_exist() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        testing::apply_tx(&node, H256::repeat_byte(0x01));
 
-        // Act
-        let latest_block = node
-            .get_block_number()
-            .await
-            .expect("Block number fetch failed");
-        let fee_history = node
-            .fee_history(U64::from(2), BlockNumber::Latest, vec![25.0, 50.0, 75.0])
+        let actual_result = node
+            .uninstall_filter(U256::from(100))
             .await
-            .expect("fee_history failed");
+            .expect("failed creating filter");
 
-        // Assert
-        // We should receive 2 fees: from block 1 and 2.
-        assert_eq!(latest_block, U64::from(2));
-        assert_eq!(
-            fee_history.oldest_block,
-            web3::types::BlockNumber::Number(U64::from(1))
-        );
-        assert_eq!(
-            fee_history.base_fee_per_gas,
-            vec![U256::from(L2_GAS_PRICE); 3]
-        );
-        assert_eq!(fee_history.gas_used_ratio, vec![0.0, 0.0]);
-        assert_eq!(fee_history.reward, Some(vec![vec![U256::from(0); 3]; 2]));
+        assert!(!actual_result);
     }
 
     #[tokio::test]
-    async fn test_get_block_by_hash_produces_no_block_error_for_non_existing_block() {
+    async fn test_get_filter_changes_returns_block_hash_updates_only_once() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let filter_id = node
+            .new_block_filter()
+            .await
+            .expect("failed creating filter");
+        let (block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x1));
 
-        let expected_err = into_jsrpc_error(Web3Error::NoBlock);
-        let result = node.get_block_by_hash(H256::repeat_byte(0x01), false).await;
+        match node
+            .get_filter_changes(filter_id)
+            .await
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Hashes(result) => {
+                // Get the block hash and the virtual block hash.
+                assert_eq!(2, result.len());
+                assert_eq!(block_hash, result[0]);
+            }
+            changes => panic!("unexpected filter changes: {:?}", changes),
+        }
 
-        assert_eq!(expected_err, result.unwrap_err());
+        match node
+            .get_filter_changes(filter_id)
+            .await
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Empty(_) => (),
+            changes => panic!("expected no changes in the second call, got {:?}", changes),
+        }
     }
 
     #[tokio::test]
-    async fn test_node_run_has_genesis_block() {
+    async fn test_get_filter_changes_returns_log_updates_only_once() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let filter_id = node
+            .new_filter(Filter {
+                from_block: None,
+                to_block: None,
+                address: None,
+                topics: None,
+                block_hash: None,
+            })
+            .await
+            .expect("failed creating filter");
+        testing::apply_tx(&node, H256::repeat_byte(0x1));
 
-        let block = node
-            .get_block_by_number(BlockNumber::Latest, false)
+        match node
+            .get_filter_changes(filter_id)
             .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Logs(result) => assert_eq!(3, result.len()),
+            changes => panic!("unexpected filter changes: {:?}", changes),
+        }
 
-        assert_eq!(0, block.number.as_u64());
-        assert_eq!(compute_hash(0, H256::zero()), block.hash);
+        match node
+            .get_filter_changes(filter_id)
+            .await
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Empty(_) => (),
+            changes => panic!("expected no changes in the second call, got {:?}", changes),
+        }
     }
 
     #[tokio::test]
-    async fn test_get_block_by_hash_for_produced_block() {
+    async fn test_get_filter_changes_returns_pending_transaction_updates_only_once() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let (expected_block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x01));
+        let filter_id = node
+            .new_pending_transaction_filter()
+            .await
+            .expect("failed creating filter");
+        testing::apply_tx(&node, H256::repeat_byte(0x1));
 
-        let actual_block = node
-            .get_block_by_hash(expected_block_hash, false)
+        match node
+            .get_filter_changes(filter_id)
             .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Hashes(result) => assert_eq!(vec![H256::repeat_byte(0x1)], result),
+            changes => panic!("unexpected filter changes: {:?}", changes),
+        }
 
-        assert_eq!(expected_block_hash, actual_block.hash);
-        assert_eq!(U64::from(1), actual_block.number);
-        assert_eq!(Some(U64::from(1)), actual_block.l1_batch_number);
+        match node
+            .get_filter_changes(filter_id)
+            .await
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Empty(_) => (),
+            changes => panic!("expected no changes in the second call, got {:?}", changes),
+        }
     }
 
     #[tokio::test]
-    async fn test_node_block_mapping_is_correctly_populated_when_using_fork_source() {
-        let input_block_number = 8;
-        let input_block_hash = H256::repeat_byte(0x01);
+    async fn test_produced_block_archives_previous_blocks() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let input_storage_key = StorageKey::new(
+            AccountTreeId::new(H160::repeat_byte(0x1)),
+            u256_to_h256(U256::zero()),
+        );
+        let input_storage_value = H256::repeat_byte(0xcd);
+        node.inner
+            .write()
+            .unwrap()
+            .fork_storage
+            .set_value(input_storage_key, input_storage_value);
+        let initial_miniblock = node.inner.read().unwrap().current_miniblock;
+
+        testing::apply_tx(&node, H256::repeat_byte(0x1));
+        let current_miniblock = node.inner.read().unwrap().current_miniblock;
+
+        let reader = node.inner.read().unwrap();
+        for miniblock in initial_miniblock..current_miniblock {
+            let actual_cached_value = reader
+                .block_hashes
+                .get(&miniblock)
+                .map(|hash| {
+                    reader
+                        .previous_states
+                        .get(hash)
+                        .unwrap_or_else(|| panic!("state was not cached for block {}", miniblock))
+                })
+                .and_then(|state| state.get(&input_storage_key))
+                .copied();
+
+            assert_eq!(
+                Some(input_storage_value),
+                actual_cached_value,
+                "unexpected cached state value for block {}",
+                miniblock
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_storage_fetches_zero_value_for_non_existent_key() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let value = node
+            .get_storage(H160::repeat_byte(0xf1), U256::from(1024), None)
+            .await
+            .expect("failed retrieving storage");
+        assert_eq!(H256::zero(), value);
+    }
+
+    #[tokio::test]
+    async fn test_get_storage_uses_fork_to_get_value_for_historical_block() {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: input_block_number,
-            hash: input_block_hash,
+            number: 10,
             transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
         });
+        let input_address = H160::repeat_byte(0x1);
+        let input_storage_value = H256::repeat_byte(0xcd);
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getStorageAt",
+                "params": [
+                    format!("{:#x}", input_address),
+                    "0x0",
+                    { "blockNumber": "0x2" },
+                ],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", input_storage_value),
+            }),
+        );
 
         let node = InMemoryNode::<HttpForkSource>::new(
             Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
@@ -3042,43 +6761,95 @@ mod tests {
             &system_contracts::Options::BuiltIn,
         );
 
-        let inner = node.inner.read().unwrap();
-        assert!(
-            inner.blocks.contains_key(&input_block_hash),
-            "block wasn't cached"
-        );
-        assert!(
-            inner.block_hashes.contains_key(&input_block_number),
-            "block number wasn't cached"
-        );
+        let actual_value = node
+            .get_storage(
+                input_address,
+                U256::zero(),
+                Some(zksync_types::api::BlockIdVariant::BlockNumberObject(
+                    BlockNumberObject {
+                        block_number: BlockNumber::Number(U64::from(2)),
+                    },
+                )),
+            )
+            .await
+            .expect("failed retrieving storage");
+        assert_eq!(input_storage_value, actual_value);
     }
 
     #[tokio::test]
-    async fn test_get_block_by_hash_uses_fork_source() {
-        let input_block_hash = H256::repeat_byte(0x01);
+    async fn test_get_storage_uses_archived_storage_to_get_value_for_missing_key() {
+        let input_address = H160::repeat_byte(0x1);
+        let input_storage_key = StorageKey::new(
+            AccountTreeId::new(input_address),
+            u256_to_h256(U256::zero()),
+        );
+        let input_storage_value = H256::repeat_byte(0xcd);
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        node.inner
+            .write()
+            .map(|mut writer| {
+                let historical_block = Block::<TransactionVariant> {
+                    hash: H256::repeat_byte(0x2),
+                    number: U64::from(2),
+                    ..Default::default()
+                };
+                writer.block_hashes.insert(2, historical_block.hash);
+
+                writer.previous_states.insert(
+                    historical_block.hash,
+                    hashmap! {
+                        input_storage_key => input_storage_value,
+                    },
+                );
+                writer
+                    .blocks
+                    .insert(historical_block.hash, historical_block);
+            })
+            .expect("failed setting storage for historical block");
+
+        let actual_value = node
+            .get_storage(
+                input_address,
+                U256::zero(),
+                Some(zksync_types::api::BlockIdVariant::BlockNumberObject(
+                    BlockNumberObject {
+                        block_number: BlockNumber::Number(U64::from(2)),
+                    },
+                )),
+            )
+            .await
+            .expect("failed retrieving storage");
+        assert_eq!(input_storage_value, actual_value);
+    }
 
+    #[tokio::test]
+    async fn test_get_storage_uses_fork_to_get_value_for_latest_block_for_missing_key() {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
             number: 10,
-            hash: H256::repeat_byte(0xab),
             transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
         });
-        let mock_block_number = 8;
-        let block_response = testing::BlockResponseBuilder::new()
-            .set_hash(input_block_hash)
-            .set_number(mock_block_number)
-            .build();
+        let input_address = H160::repeat_byte(0x1);
+        let input_storage_value = H256::repeat_byte(0xcd);
         mock_server.expect(
             serde_json::json!({
                 "jsonrpc": "2.0",
                 "id": 0,
-                "method": "eth_getBlockByHash",
+                "method": "eth_getStorageAt",
                 "params": [
-                    format!("{input_block_hash:#x}"),
-                    true
+                    format!("{:#x}", input_address),
+                    "0x0",
+                    "0xa",
                 ],
             }),
-            block_response,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", input_storage_value),
+            }),
         );
+
         let node = InMemoryNode::<HttpForkSource>::new(
             Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
             crate::node::ShowCalls::None,
@@ -3088,123 +6859,411 @@ mod tests {
             false,
             &system_contracts::Options::BuiltIn,
         );
+        node.inner
+            .write()
+            .map(|mut writer| {
+                let historical_block = Block::<TransactionVariant> {
+                    hash: H256::repeat_byte(0x2),
+                    number: U64::from(2),
+                    ..Default::default()
+                };
+                writer.block_hashes.insert(2, historical_block.hash);
+                writer
+                    .previous_states
+                    .insert(historical_block.hash, Default::default());
+                writer
+                    .blocks
+                    .insert(historical_block.hash, historical_block);
+            })
+            .expect("failed setting storage for historical block");
 
-        let actual_block = node
-            .get_block_by_hash(input_block_hash, false)
+        let actual_value = node
+            .get_storage(
+                input_address,
+                U256::zero(),
+                Some(zksync_types::api::BlockIdVariant::BlockNumberObject(
+                    BlockNumberObject {
+                        block_number: BlockNumber::Number(U64::from(2)),
+                    },
+                )),
+            )
+            .await
+            .expect("failed retrieving storage");
+        assert_eq!(input_storage_value, actual_value);
+    }
+
+    #[tokio::test]
+    async fn test_get_storage_fetches_state_for_deployed_smart_contract_in_current_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+
+        testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            None,
+            Nonce(0),
+        );
+
+        let number1 = node
+            .get_storage(deployed_address, U256::from(0), None)
+            .await
+            .expect("failed retrieving storage at slot 0");
+        assert_eq!(U256::from(1024), h256_to_u256(number1));
+
+        let number2 = node
+            .get_storage(deployed_address, U256::from(1), None)
+            .await
+            .expect("failed retrieving storage at slot 1");
+        assert_eq!(U256::MAX, h256_to_u256(number2));
+    }
+
+    #[tokio::test]
+    async fn test_get_storage_fetches_state_for_deployed_smart_contract_in_old_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+
+        let initial_block_hash = testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            None,
+            Nonce(0),
+        );
+
+        // simulate a tx modifying the storage
+        testing::apply_tx(&node, H256::repeat_byte(0x2));
+        let key = StorageKey::new(
+            AccountTreeId::new(deployed_address),
+            u256_to_h256(U256::from(0)),
+        );
+        node.inner
+            .write()
+            .unwrap()
+            .fork_storage
+            .inner
+            .write()
+            .unwrap()
+            .raw_storage
+            .state
+            .insert(key, u256_to_h256(U256::from(512)));
+
+        let number1_current = node
+            .get_storage(deployed_address, U256::from(0), None)
+            .await
+            .expect("failed retrieving storage at slot 0");
+        assert_eq!(U256::from(512), h256_to_u256(number1_current));
+
+        let number1_old = node
+            .get_storage(
+                deployed_address,
+                U256::from(0),
+                Some(zksync_types::api::BlockIdVariant::BlockHashObject(
+                    BlockHashObject {
+                        block_hash: initial_block_hash,
+                    },
+                )),
+            )
+            .await
+            .expect("failed retrieving storage at slot 0");
+        assert_eq!(U256::from(1024), h256_to_u256(number1_old));
+    }
+
+    #[tokio::test]
+    async fn test_get_storage_accepts_eip1898_block_hash_object() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+        let deployed_block_hash = testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            None,
+            Nonce(0),
+        );
+        // simulate a tx modifying the storage
+        testing::apply_tx(&node, H256::repeat_byte(0x2));
+        let key = StorageKey::new(
+            AccountTreeId::new(deployed_address),
+            u256_to_h256(U256::from(0)),
+        );
+        node.inner
+            .write()
+            .unwrap()
+            .fork_storage
+            .inner
+            .write()
+            .unwrap()
+            .raw_storage
+            .state
+            .insert(key, u256_to_h256(U256::from(512)));
+        let block_param = |block_hash: H256| -> BlockIdVariant {
+            serde_json::from_value(serde_json::json!({
+                "blockHash": block_hash,
+                "requireCanonical": true,
+            }))
+            .expect("failed parsing EIP-1898 block param")
+        };
+
+        let latest = node
+            .get_storage(deployed_address, U256::from(0), None)
             .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
+            .expect("failed retrieving latest storage");
+        let at_deployment = node
+            .get_storage(
+                deployed_address,
+                U256::from(0),
+                Some(block_param(deployed_block_hash)),
+            )
+            .await
+            .expect("failed retrieving storage at deployment");
+        let at_unknown = node
+            .get_storage(
+                deployed_address,
+                U256::from(0),
+                Some(block_param(H256::repeat_byte(0xab))),
+            )
+            .await;
 
-        assert_eq!(input_block_hash, actual_block.hash);
-        assert_eq!(U64::from(mock_block_number), actual_block.number);
-        assert_eq!(Some(U64::from(6)), actual_block.l1_batch_number);
+        assert_eq!(U256::from(512), h256_to_u256(latest));
+        assert_eq!(U256::from(1024), h256_to_u256(at_deployment));
+        assert!(at_unknown.is_err(), "unknown block hash must be rejected");
     }
 
     #[tokio::test]
-    async fn test_get_block_by_number_produces_no_block_error_for_non_existing_block() {
+    async fn test_call_uses_state_at_the_requested_block() {
         let node = InMemoryNode::<HttpForkSource>::default();
 
-        let expected_err = into_jsrpc_error(Web3Error::NoBlock);
-        let result = node
-            .get_block_by_number(BlockNumber::Number(U64::from(42)), false)
-            .await;
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
 
-        assert_eq!(expected_err, result.unwrap_err());
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+
+        let deployed_block_hash = testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            None,
+            Nonce(0),
+        );
+        let deployed_block_number = node
+            .inner
+            .read()
+            .unwrap()
+            .blocks
+            .get(&deployed_block_hash)
+            .map(|block| block.number)
+            .expect("deployment block must exist");
+
+        // modify the storage and produce a new block with it
+        let key = StorageKey::new(
+            AccountTreeId::new(deployed_address),
+            u256_to_h256(U256::from(0)),
+        );
+        node.inner
+            .write()
+            .unwrap()
+            .fork_storage
+            .set_value(key, u256_to_h256(U256::from(512)));
+        testing::apply_tx(&node, H256::repeat_byte(0x2));
+        let latest_block_number = U64::from(node.inner.read().unwrap().current_miniblock);
+
+        // retrieve1()
+        let request = CallRequestBuilder::default()
+            .to(deployed_address)
+            .data(hex::decode("ae2e2cce").unwrap().into())
+            .build();
+
+        let number1_old = node
+            .call(
+                request.clone(),
+                Some(BlockIdVariant::BlockNumber(BlockNumber::Number(
+                    deployed_block_number,
+                ))),
+            )
+            .await
+            .expect("failed calling at the deployment block");
+        assert_eq!(U256::from(1024), U256::from_big_endian(&number1_old.0));
+
+        let number1_current = node
+            .call(
+                request,
+                Some(BlockIdVariant::BlockNumber(BlockNumber::Number(
+                    latest_block_number,
+                ))),
+            )
+            .await
+            .expect("failed calling at the latest block");
+        assert_eq!(U256::from(512), U256::from_big_endian(&number1_current.0));
     }
 
     #[tokio::test]
-    async fn test_get_block_by_number_for_produced_block() {
+    async fn test_call_honors_gas_limit_and_gas_price() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        testing::apply_tx(&node, H256::repeat_byte(0x01));
-        let expected_block_number = 1;
 
-        let actual_block = node
-            .get_block_by_number(BlockNumber::Number(U64::from(expected_block_number)), false)
-            .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+        testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            None,
+            Nonce(0),
+        );
 
-        assert_eq!(U64::from(expected_block_number), actual_block.number);
-        assert_eq!(1, actual_block.transactions.len());
+        // retrieve1()
+        let retrieve = |gas: Option<u64>| {
+            let mut request = CallRequestBuilder::default()
+                .to(deployed_address)
+                .data(hex::decode("ae2e2cce").unwrap().into());
+            if let Some(gas) = gas {
+                request = request.gas(U256::from(gas));
+            }
+            node.call(request.build(), None)
+        };
+        retrieve(Some(1_000))
+            .await
+            .expect_err("call should have run out of gas");
+        retrieve(Some(10_000_000))
+            .await
+            .expect("failed calling contract with enough gas");
+        retrieve(None)
+            .await
+            .expect("failed calling contract with the default gas limit");
+
+        // `tx.gasprice` is read from the `gasPrice()` of the `SystemContext` system contract
+        let tx_gas_price = |gas_price: Option<u64>| {
+            let mut request = CallRequestBuilder::default()
+                .to(SYSTEM_CONTEXT_ADDRESS)
+                .data(hex::decode("fe173b97").unwrap().into());
+            if let Some(gas_price) = gas_price {
+                request = request.gas_price(U256::from(gas_price));
+            }
+            let result = node.call(request.build(), None);
+            async move { U256::from_big_endian(&result.await.expect("failed calling").0) }
+        };
+        let (base_fee, _) = derive_base_fee_and_gas_per_pubdata(L1_GAS_PRICE, L2_GAS_PRICE);
+        assert_eq!(
+            U256::from(1_000_000_000),
+            tx_gas_price(Some(1_000_000_000)).await
+        );
+        assert_eq!(
+            U256::from(2_000_000_000),
+            tx_gas_price(Some(2_000_000_000)).await
+        );
+        assert_eq!(U256::from(base_fee), tx_gas_price(None).await);
     }
 
     #[tokio::test]
-    async fn test_get_block_by_number_uses_fork_source_if_missing_number() {
-        let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: 10,
-            hash: H256::repeat_byte(0xab),
-            transaction_count: 0,
-        });
-        let mock_block_number = 8;
-        let block_response = testing::BlockResponseBuilder::new()
-            .set_number(mock_block_number)
+    async fn test_call_ignores_gas_limit_with_gas_metering_disabled() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .gas_metering(false)
             .build();
-        mock_server.expect(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getBlockByNumber",
-                "params": [
-                    "0x8",
-                    true
-                ],
-            }),
-            block_response,
-        );
-        let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        let deployed_address = deployed_address_create(from_account, U256::zero());
+        testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            None,
+            Nonce(0),
         );
 
-        let actual_block = node
-            .get_block_by_number(BlockNumber::Number(U64::from(8)), false)
+        // retrieve1() - runs out of gas with metering enabled (see above)
+        let request = CallRequestBuilder::default()
+            .to(deployed_address)
+            .data(hex::decode("ae2e2cce").unwrap().into())
+            .gas(U256::from(1_000))
+            .build();
+        let result = node
+            .call(request, None)
             .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
-        assert_eq!(U64::from(mock_block_number), actual_block.number);
+            .expect("call should succeed without gas metering");
+        assert_eq!(U256::from(1024), U256::from_big_endian(&result.0));
     }
 
     #[tokio::test]
-    async fn test_get_block_by_number_for_latest_block_produced_locally() {
+    async fn test_call_fails_once_exceeding_max_call_depth() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        testing::apply_tx(&node, H256::repeat_byte(0x01));
+        let (primary_address, _) = testing::deploy_test_contracts(&node);
 
-        // The latest block, will be the 'virtual' one with 0 transactions (block 2).
-        let virtual_block = node
-            .get_block_by_number(BlockNumber::Latest, true)
+        // calculate(42) - the `Primary` contract, at depth 1, calls the `Secondary` one, at depth 2
+        let request = CallRequestBuilder::default()
+            .to(primary_address)
+            .data(hex::decode(format!("b9d92de8{:064x}", 42)).unwrap().into())
+            .build();
+        assert_eq!(None, node.inner.read().unwrap().max_call_depth);
+        let result = node
+            .call(request.clone(), None)
             .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
-
-        assert_eq!(U64::from(2), virtual_block.number);
-        assert_eq!(0, virtual_block.transactions.len());
+            .expect("failed calling without a max call depth");
+        assert_eq!(U256::from(84), U256::from_big_endian(&result.0));
 
-        let actual_block = node
-            .get_block_by_number(BlockNumber::Number(U64::from(1)), true)
+        node.inner.write().unwrap().max_call_depth = Some(2);
+        let result = node
+            .call(request.clone(), None)
             .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
+            .expect("failed calling within the max call depth");
+        assert_eq!(U256::from(84), U256::from_big_endian(&result.0));
 
-        assert_eq!(U64::from(1), actual_block.number);
-        assert_eq!(1, actual_block.transactions.len());
+        node.inner.write().unwrap().max_call_depth = Some(1);
+        let error = node
+            .call(request, None)
+            .await
+            .expect_err("call should have exceeded the max call depth");
+        assert!(
+            error
+                .message
+                .contains("call depth exceeded the maximum of 1"),
+            "unexpected error: {}",
+            error.message
+        );
     }
 
     #[tokio::test]
-    async fn test_get_block_by_number_uses_locally_available_block_for_latest_block() {
-        let input_block_number = 10;
+    async fn test_call_fails_on_offline_fork_with_cold_storage_cache() {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: input_block_number,
-            hash: H256::repeat_byte(0x01),
+            number: 10,
+            hash: H256::repeat_byte(0xab),
             transaction_count: 0,
         });
-
+        let mut fork_details =
+            ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await;
+        fork_details.fork_source.set_offline(true);
         let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            Some(fork_details),
             crate::node::ShowCalls::None,
             ShowStorageLogs::None,
             ShowVMDetails::None,
@@ -3213,192 +7272,332 @@ mod tests {
             &system_contracts::Options::BuiltIn,
         );
 
-        let actual_block = node
-            .get_block_by_number(BlockNumber::Latest, false)
-            .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
-        assert_eq!(U64::from(input_block_number), actual_block.number);
+        // balanceOf(0x11..11) - none of the read values is cached, and none can be fetched while offline
+        let request = CallRequestBuilder::default()
+            .to(L2_ETH_TOKEN_ADDRESS)
+            .data(
+                hex::decode(format!(
+                    "70a08231{:0>64}",
+                    hex::encode(Address::repeat_byte(0x11))
+                ))
+                .unwrap()
+                .into(),
+            )
+            .build();
+        for _ in 0..2 {
+            // the same error every time - the failed reads were neither cached, nor poisoned the storage lock
+            let error = node
+                .call(request.clone(), None)
+                .await
+                .expect_err("call should have failed reading from the fork");
+            assert!(
+                error.message.contains("failed reading from the fork"),
+                "unexpected error: {}",
+                error.message
+            );
+        }
+        assert!(node.inner.read().unwrap().fork_storage.inner.read().is_ok());
     }
 
     #[tokio::test]
-    async fn test_get_block_by_number_uses_fork_source_for_earliest_block() {
-        let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: 10,
-            hash: H256::repeat_byte(0xab),
-            transaction_count: 0,
-        });
-        let input_block_number = 1;
-        mock_server.expect(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getBlockByNumber",
-                "params": [
-                    "earliest",
-                    true
-                ],
-            }),
-            testing::BlockResponseBuilder::new()
-                .set_number(input_block_number)
-                .build(),
-        );
-        let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
+    async fn test_call_without_from_uses_the_default_call_sender() {
+        let default_call_sender = Address::repeat_byte(0xde);
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .default_call_sender(default_call_sender)
+            .build();
+
+        // the `origin()` of the `SystemContext` is set to the sender of the call
+        let sender = |from: Option<Address>| {
+            let mut request = CallRequestBuilder::default()
+                .to(SYSTEM_CONTEXT_ADDRESS)
+                .data(hex::decode("938b5f32").unwrap().into());
+            if let Some(from) = from {
+                request = request.from(from);
+            }
+            let result = node.call(request.build(), None);
+            async move {
+                h256_to_account_address(&H256::from_slice(&result.await.expect("failed calling").0))
+            }
+        };
+        assert_eq!(default_call_sender, sender(None).await);
+        assert_eq!(
+            Address::repeat_byte(0x11),
+            sender(Some(Address::repeat_byte(0x11))).await
         );
+    }
+
+    #[tokio::test]
+    async fn test_get_filter_logs_returns_matching_logs_for_valid_id() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        // populate tx receipts with 2 tx each having logs
+        {
+            let mut writer = node.inner.write().unwrap();
+            writer.tx_results.insert(
+                H256::repeat_byte(0x1),
+                TransactionResult {
+                    info: testing::default_tx_execution_info(),
+                    receipt: TransactionReceipt {
+                        logs: vec![LogBuilder::new()
+                            .set_address(H160::repeat_byte(0xa1))
+                            .build()],
+                        ..Default::default()
+                    },
+                    debug: default_tx_debug_info(),
+                },
+            );
+            writer.tx_results.insert(
+                H256::repeat_byte(0x2),
+                TransactionResult {
+                    info: testing::default_tx_execution_info(),
+                    receipt: TransactionReceipt {
+                        logs: vec![
+                            LogBuilder::new()
+                                .set_address(H160::repeat_byte(0xa1))
+                                .build(),
+                            LogBuilder::new()
+                                .set_address(H160::repeat_byte(0xa2))
+                                .build(),
+                        ],
+                        ..Default::default()
+                    },
+                    debug: default_tx_debug_info(),
+                },
+            );
+        }
 
-        let actual_block = node
-            .get_block_by_number(BlockNumber::Earliest, false)
+        let filter_id = node
+            .new_filter(Filter {
+                address: Some(ValueOrArray(vec![H160::repeat_byte(0xa1)])),
+                ..Default::default()
+            })
             .await
-            .expect("failed fetching block by hash")
-            .expect("no block");
-        assert_eq!(U64::from(input_block_number), actual_block.number);
+            .expect("failed creating filter");
+
+        match node
+            .get_filter_logs(filter_id)
+            .await
+            .expect("failed getting filter changes")
+        {
+            FilterChanges::Logs(result) => assert_eq!(2, result.len()),
+            changes => panic!("unexpected filter changes: {:?}", changes),
+        }
     }
 
     #[tokio::test]
-    async fn test_get_block_by_number_uses_locally_available_for_latest_alike_blocks() {
-        for block_number in [
-            BlockNumber::Pending,
-            BlockNumber::Committed,
-            BlockNumber::Finalized,
-        ] {
-            let input_block_number = 10;
-            let mock_server = MockServer::run_with_config(ForkBlockConfig {
-                number: input_block_number,
-                hash: H256::repeat_byte(0xab),
-                transaction_count: 0,
-            });
-            let node = InMemoryNode::<HttpForkSource>::new(
-                Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-                crate::node::ShowCalls::None,
-                ShowStorageLogs::None,
-                ShowVMDetails::None,
-                ShowGasDetails::None,
-                false,
-                &system_contracts::Options::BuiltIn,
-            );
+    async fn test_get_filter_logs_returns_error_for_invalid_id() {
+        let node = InMemoryNode::<HttpForkSource>::default();
 
-            let actual_block = node
-                .get_block_by_number(block_number, false)
-                .await
-                .expect("failed fetching block by hash")
-                .expect("no block");
-            assert_eq!(
-                U64::from(input_block_number),
-                actual_block.number,
-                "case {}",
-                block_number,
+        // populate tx receipts with 2 tx each having logs
+        {
+            let mut writer = node.inner.write().unwrap();
+            writer.tx_results.insert(
+                H256::repeat_byte(0x1),
+                TransactionResult {
+                    info: testing::default_tx_execution_info(),
+                    receipt: TransactionReceipt {
+                        logs: vec![LogBuilder::new()
+                            .set_address(H160::repeat_byte(0xa1))
+                            .build()],
+                        ..Default::default()
+                    },
+                    debug: default_tx_debug_info(),
+                },
             );
         }
+
+        let invalid_filter_id = U256::from(100);
+        let result = node.get_filter_logs(invalid_filter_id).await;
+
+        assert!(result.is_err(), "expected an error for invalid filter id");
     }
 
     #[tokio::test]
-    async fn test_get_block_transaction_count_by_hash_for_produced_block() {
+    async fn test_get_logs_returns_matching_logs() {
         let node = InMemoryNode::<HttpForkSource>::default();
 
-        let (expected_block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x01));
-        let actual_transaction_count = node
-            .get_block_transaction_count_by_hash(expected_block_hash)
+        // populate tx receipts with 2 tx each having logs
+        {
+            let mut writer = node.inner.write().unwrap();
+            writer.tx_results.insert(
+                H256::repeat_byte(0x1),
+                TransactionResult {
+                    info: testing::default_tx_execution_info(),
+                    receipt: TransactionReceipt {
+                        logs: vec![LogBuilder::new()
+                            .set_address(H160::repeat_byte(0xa1))
+                            .build()],
+                        ..Default::default()
+                    },
+                    debug: testing::default_tx_debug_info(),
+                },
+            );
+            writer.tx_results.insert(
+                H256::repeat_byte(0x2),
+                TransactionResult {
+                    info: testing::default_tx_execution_info(),
+                    receipt: TransactionReceipt {
+                        logs: vec![
+                            LogBuilder::new()
+                                .set_address(H160::repeat_byte(0xa1))
+                                .build(),
+                            LogBuilder::new()
+                                .set_address(H160::repeat_byte(0xa2))
+                                .build(),
+                        ],
+                        ..Default::default()
+                    },
+                    debug: testing::default_tx_debug_info(),
+                },
+            );
+        }
+
+        let result = node
+            .get_logs(Filter {
+                address: Some(ValueOrArray(vec![H160::repeat_byte(0xa2)])),
+                ..Default::default()
+            })
             .await
-            .expect("failed fetching block by hash")
-            .expect("no result");
+            .expect("failed getting filter changes");
+        assert_eq!(1, result.len());
 
-        assert_eq!(U256::from(1), actual_transaction_count);
+        let result = node
+            .get_logs(Filter {
+                address: Some(ValueOrArray(vec![H160::repeat_byte(0xa1)])),
+                ..Default::default()
+            })
+            .await
+            .expect("failed getting filter changes");
+        assert_eq!(2, result.len());
+
+        let result = node
+            .get_logs(Filter {
+                address: Some(ValueOrArray(vec![H160::repeat_byte(0x11)])),
+                ..Default::default()
+            })
+            .await
+            .expect("failed getting filter changes");
+        assert_eq!(0, result.len());
     }
 
     #[tokio::test]
-    async fn test_get_block_transaction_count_by_hash_uses_fork_source() {
-        let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: 10,
-            hash: H256::repeat_byte(0xab),
-            transaction_count: 0,
-        });
-        let input_block_hash = H256::repeat_byte(0x01);
-        let input_transaction_count = 1;
-        mock_server.expect(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getBlockTransactionCountByHash",
-                "params": [
-                    format!("{:#x}", input_block_hash),
-                ],
-            }),
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "result": format!("{:#x}", input_transaction_count),
-            }),
-        );
-        let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
-        );
+    async fn test_get_logs_by_block_hash_returns_only_the_logs_of_that_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let (block_hash, block_number) = testing::apply_tx(&node, H256::repeat_byte(0x1));
+        testing::apply_tx(&node, H256::repeat_byte(0x2));
+        let block_logs = node
+            .get_logs(Filter {
+                from_block: Some(BlockNumber::Number(block_number)),
+                to_block: Some(BlockNumber::Number(block_number)),
+                ..Default::default()
+            })
+            .await
+            .expect("failed getting logs");
 
-        let actual_transaction_count = node
-            .get_block_transaction_count_by_hash(input_block_hash)
+        let result = node
+            .get_logs(Filter {
+                block_hash: Some(block_hash),
+                ..Default::default()
+            })
             .await
-            .expect("failed fetching block by hash")
-            .expect("no result");
+            .expect("failed getting logs");
+        let err = node
+            .get_logs(Filter {
+                from_block: Some(BlockNumber::Earliest),
+                block_hash: Some(block_hash),
+                ..Default::default()
+            })
+            .await
+            .expect_err("expected blockHash and fromBlock to be rejected");
 
+        assert!(!result.is_empty(), "the block must have logs");
+        assert!(result
+            .iter()
+            .all(|log| log.block_number == Some(block_number)));
+        assert_eq!(block_logs, result);
         assert_eq!(
-            U256::from(input_transaction_count),
-            actual_transaction_count
+            "cannot specify both blockHash and fromBlock/toBlock",
+            err.message
         );
     }
 
     #[tokio::test]
-    async fn test_get_block_transaction_count_by_number_for_produced_block() {
-        let node = InMemoryNode::<HttpForkSource>::default();
+    async fn test_get_logs_fails_when_exceeding_max_logs_returned() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .max_logs_returned(2)
+            .build();
+        node.inner.write().unwrap().tx_results.insert(
+            H256::repeat_byte(0x1),
+            TransactionResult {
+                info: testing::default_tx_execution_info(),
+                receipt: TransactionReceipt {
+                    logs: vec![
+                        LogBuilder::new()
+                            .set_address(H160::repeat_byte(0xa1))
+                            .build(),
+                        LogBuilder::new()
+                            .set_address(H160::repeat_byte(0xa1))
+                            .build(),
+                        LogBuilder::new()
+                            .set_address(H160::repeat_byte(0xa2))
+                            .build(),
+                    ],
+                    ..Default::default()
+                },
+                debug: testing::default_tx_debug_info(),
+            },
+        );
 
-        testing::apply_tx(&node, H256::repeat_byte(0x01));
-        let actual_transaction_count = node
-            .get_block_transaction_count_by_number(BlockNumber::Number(U64::from(1)))
+        let err = node
+            .get_logs(Filter::default())
             .await
-            .expect("failed fetching block by hash")
-            .expect("no result");
+            .expect_err("expected the query to exceed the cap");
+        assert_eq!(
+            "query returned more than 2 results, try with a narrower block range",
+            err.message
+        );
 
-        assert_eq!(U256::from(1), actual_transaction_count);
+        let result = node
+            .get_logs(Filter {
+                address: Some(ValueOrArray(vec![H160::repeat_byte(0xa1)])),
+                ..Default::default()
+            })
+            .await
+            .expect("failed getting logs");
+        assert_eq!(2, result.len());
     }
 
     #[tokio::test]
-    async fn test_get_block_transaction_count_by_number_uses_fork_source() {
+    async fn test_get_transaction_receipt_falls_back_to_fork_source_and_caches_it() {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
             number: 10,
             hash: H256::repeat_byte(0xab),
             transaction_count: 0,
         });
-        let input_block_number = 1;
-        let input_transaction_count = 1;
+        let input_tx_hash = H256::repeat_byte(0x1);
+        // the mock server only expects a single request, the second one is served from the cache
         mock_server.expect(
             serde_json::json!({
                 "jsonrpc": "2.0",
                 "id": 0,
-                "method": "eth_getBlockTransactionCountByNumber",
-                "params": [
-                    format!("{:#x}", input_block_number),
-                ],
+                "method": "eth_getTransactionReceipt",
+                "params": [input_tx_hash],
             }),
             serde_json::json!({
                 "jsonrpc": "2.0",
                 "id": 0,
-                "result": format!("{:#x}", input_transaction_count),
+                "result": serde_json::to_value(TransactionReceipt {
+                    transaction_hash: input_tx_hash,
+                    block_hash: Some(H256::repeat_byte(0xab)),
+                    block_number: Some(U64::from(10)),
+                    status: Some(U64::from(1)),
+                    ..Default::default()
+                })
+                .unwrap(),
             }),
         );
-
         let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::Memory).await),
             crate::node::ShowCalls::None,
             ShowStorageLogs::None,
             ShowVMDetails::None,
@@ -3407,42 +7606,43 @@ mod tests {
             &system_contracts::Options::BuiltIn,
         );
 
-        let actual_transaction_count = node
-            .get_block_transaction_count_by_number(BlockNumber::Number(U64::from(1)))
-            .await
-            .expect("failed fetching block by hash")
-            .expect("no result");
-
-        assert_eq!(
-            U256::from(input_transaction_count),
-            actual_transaction_count
-        );
+        for _ in 0..2 {
+            let receipt = node
+                .get_transaction_receipt(input_tx_hash)
+                .await
+                .expect("failed fetching receipt")
+                .expect("receipt exists");
+            assert_eq!(input_tx_hash, receipt.transaction_hash);
+            assert_eq!(Some(U64::from(10)), receipt.block_number);
+        }
     }
 
     #[tokio::test]
-    async fn test_get_block_transaction_count_by_number_earliest_uses_fork_source() {
+    async fn test_get_logs_merges_fork_source_and_local_logs() {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
             number: 10,
             hash: H256::repeat_byte(0xab),
             transaction_count: 0,
         });
-        let input_transaction_count = 1;
         mock_server.expect(
             serde_json::json!({
                 "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getBlockTransactionCountByNumber",
-                "params": [
-                    "earliest",
-                ],
+                "id": 0,
+                "method": "eth_getLogs",
+                "params": [{
+                    "fromBlock": "0x9",
+                    "toBlock": "0xa",
+                }],
             }),
             serde_json::json!({
                 "jsonrpc": "2.0",
                 "id": 0,
-                "result": format!("{:#x}", input_transaction_count),
+                "result": [
+                    LogBuilder::new().set_block(U64::from(9)).build(),
+                    LogBuilder::new().set_block(U64::from(10)).build(),
+                ],
             }),
         );
-
         let node = InMemoryNode::<HttpForkSource>::new(
             Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
             crate::node::ShowCalls::None,
@@ -3452,310 +7652,563 @@ mod tests {
             false,
             &system_contracts::Options::BuiltIn,
         );
+        {
+            let mut writer = node.inner.write().unwrap();
+            for (tx_hash, block_number) in
+                [(H256::repeat_byte(0x1), 12), (H256::repeat_byte(0x2), 11)]
+            {
+                writer.tx_results.insert(
+                    tx_hash,
+                    TransactionResult {
+                        info: testing::default_tx_execution_info(),
+                        receipt: TransactionReceipt {
+                            logs: vec![LogBuilder::new()
+                                .set_block(U64::from(block_number))
+                                .build()],
+                            ..Default::default()
+                        },
+                        debug: testing::default_tx_debug_info(),
+                    },
+                );
+            }
+            writer.current_miniblock = 12;
+        }
 
-        let actual_transaction_count = node
-            .get_block_transaction_count_by_number(BlockNumber::Earliest)
+        let logs = node
+            .get_logs(Filter {
+                from_block: Some(BlockNumber::Number(U64::from(9))),
+                ..Default::default()
+            })
             .await
-            .expect("failed fetching block by hash")
-            .expect("no result");
+            .expect("failed getting logs");
 
         assert_eq!(
-            U256::from(input_transaction_count),
-            actual_transaction_count
+            vec![9, 10, 11, 12],
+            logs.iter()
+                .map(|log| log.block_number.expect("block number is set").as_u64())
+                .collect_vec()
         );
     }
 
     #[tokio::test]
-    async fn test_get_block_transaction_count_by_number_latest_alike_uses_fork_source() {
-        for block_number in [
-            BlockNumber::Latest,
-            BlockNumber::Pending,
-            BlockNumber::Committed,
-            BlockNumber::Finalized,
-        ] {
-            let input_transaction_count = 1;
-            let mock_server = MockServer::run_with_config(ForkBlockConfig {
-                number: 10,
-                transaction_count: input_transaction_count,
-                hash: H256::repeat_byte(0xab),
-            });
+    async fn test_accounts() {
+        let node = InMemoryNode::<HttpForkSource>::default();
 
-            let node = InMemoryNode::<HttpForkSource>::new(
-                Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-                crate::node::ShowCalls::None,
-                ShowStorageLogs::None,
-                ShowVMDetails::None,
-                ShowGasDetails::None,
-                false,
-                &system_contracts::Options::BuiltIn,
-            );
+        let private_key = H256::repeat_byte(0x01);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key).unwrap();
+        node.set_rich_account(from_account);
 
-            let actual_transaction_count = node
-                .get_block_transaction_count_by_number(block_number)
-                .await
-                .expect("failed fetching block by hash")
-                .expect("no result");
+        let account_result = node.accounts().await;
+        let expected_accounts: Vec<H160> = vec![from_account];
 
-            assert_eq!(
-                U256::from(input_transaction_count),
-                actual_transaction_count,
-                "case {}",
-                block_number,
-            );
+        match account_result {
+            Ok(accounts) => {
+                assert_eq!(expected_accounts, accounts);
+            }
+            Err(e) => {
+                panic!("Failed to fetch accounts: {:?}", e);
+            }
         }
     }
 
     #[tokio::test]
-    async fn test_get_transaction_receipt_uses_produced_block_hash() {
+    async fn test_snapshot() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let tx_hash = H256::repeat_byte(0x01);
-        let (expected_block_hash, _) = testing::apply_tx(&node, tx_hash);
+        let mut inner = node.inner.write().unwrap();
 
-        let actual_tx_receipt = node
-            .get_transaction_receipt(tx_hash)
-            .await
-            .expect("failed fetching transaction receipt by hash")
-            .expect("no transaction receipt");
+        inner
+            .blocks
+            .insert(H256::repeat_byte(0x1), Default::default());
+        inner.block_hashes.insert(1, H256::repeat_byte(0x1));
+        inner.tx_results.insert(
+            H256::repeat_byte(0x1),
+            TransactionResult {
+                info: testing::default_tx_execution_info(),
+                receipt: Default::default(),
+                debug: testing::default_tx_debug_info(),
+            },
+        );
+        inner.current_batch = 1;
+        inner.current_miniblock = 1;
+        inner.current_miniblock_hash = H256::repeat_byte(0x1);
+        inner.current_timestamp = 1;
+        inner
+            .filters
+            .add_block_filter()
+            .expect("failed adding block filter");
+        inner.impersonated_accounts.insert(H160::repeat_byte(0x1));
+        inner.rich_accounts.insert(H160::repeat_byte(0x1));
+        inner
+            .previous_states
+            .insert(H256::repeat_byte(0x1), Default::default());
+        inner.fork_storage.set_value(
+            StorageKey::new(AccountTreeId::new(H160::repeat_byte(0x1)), H256::zero()),
+            H256::repeat_byte(0x1),
+        );
 
-        assert_eq!(Some(expected_block_hash), actual_tx_receipt.block_hash);
+        let storage = inner.fork_storage.inner.read().unwrap();
+        let expected_snapshot = Snapshot {
+            current_timestamp: inner.current_timestamp.clone(),
+            current_batch: inner.current_batch.clone(),
+            current_miniblock: inner.current_miniblock.clone(),
+            current_miniblock_hash: inner.current_miniblock_hash.clone(),
+            l1_gas_price: inner.l1_gas_price.clone(),
+            tx_results: inner.tx_results.clone(),
+            blocks: inner.blocks.clone(),
+            block_hashes: inner.block_hashes.clone(),
+            filters: inner.filters.clone(),
+            impersonated_accounts: inner.impersonated_accounts.clone(),
+            rich_accounts: inner.rich_accounts.clone(),
+            previous_states: inner.previous_states.clone(),
+            raw_storage: storage.raw_storage.clone(),
+            value_read_cache: storage.value_read_cache.clone(),
+            factory_dep_cache: storage.factory_dep_cache.clone(),
+        };
+        let actual_snapshot = inner.snapshot().expect("failed taking snapshot");
+
+        assert_eq!(
+            expected_snapshot.current_timestamp,
+            actual_snapshot.current_timestamp
+        );
+        assert_eq!(
+            expected_snapshot.current_batch,
+            actual_snapshot.current_batch
+        );
+        assert_eq!(
+            expected_snapshot.current_miniblock,
+            actual_snapshot.current_miniblock
+        );
+        assert_eq!(
+            expected_snapshot.current_miniblock_hash,
+            actual_snapshot.current_miniblock_hash
+        );
+        assert_eq!(expected_snapshot.l1_gas_price, actual_snapshot.l1_gas_price);
+        assert_eq!(
+            expected_snapshot.tx_results.keys().collect_vec(),
+            actual_snapshot.tx_results.keys().collect_vec()
+        );
+        assert_eq!(expected_snapshot.blocks, actual_snapshot.blocks);
+        assert_eq!(expected_snapshot.block_hashes, actual_snapshot.block_hashes);
+        assert_eq!(expected_snapshot.filters, actual_snapshot.filters);
+        assert_eq!(
+            expected_snapshot.impersonated_accounts,
+            actual_snapshot.impersonated_accounts
+        );
+        assert_eq!(
+            expected_snapshot.rich_accounts,
+            actual_snapshot.rich_accounts
+        );
+        assert_eq!(
+            expected_snapshot.previous_states,
+            actual_snapshot.previous_states
+        );
+        assert_eq!(expected_snapshot.raw_storage, actual_snapshot.raw_storage);
+        assert_eq!(
+            expected_snapshot.value_read_cache,
+            actual_snapshot.value_read_cache
+        );
+        assert_eq!(
+            expected_snapshot.factory_dep_cache,
+            actual_snapshot.factory_dep_cache
+        );
     }
 
     #[tokio::test]
-    async fn test_new_block_filter_returns_filter_id() {
+    async fn test_snapshot_restore() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let mut inner = node.inner.write().unwrap();
+
+        inner
+            .blocks
+            .insert(H256::repeat_byte(0x1), Default::default());
+        inner.block_hashes.insert(1, H256::repeat_byte(0x1));
+        inner.tx_results.insert(
+            H256::repeat_byte(0x1),
+            TransactionResult {
+                info: testing::default_tx_execution_info(),
+                receipt: Default::default(),
+                debug: testing::default_tx_debug_info(),
+            },
+        );
+        inner.current_batch = 1;
+        inner.current_miniblock = 1;
+        inner.current_miniblock_hash = H256::repeat_byte(0x1);
+        inner.current_timestamp = 1;
+        inner
+            .filters
+            .add_block_filter()
+            .expect("failed adding block filter");
+        inner.impersonated_accounts.insert(H160::repeat_byte(0x1));
+        inner.rich_accounts.insert(H160::repeat_byte(0x1));
+        inner
+            .previous_states
+            .insert(H256::repeat_byte(0x1), Default::default());
+        inner.fork_storage.set_value(
+            StorageKey::new(AccountTreeId::new(H160::repeat_byte(0x1)), H256::zero()),
+            H256::repeat_byte(0x1),
+        );
+
+        let expected_snapshot = {
+            let storage = inner.fork_storage.inner.read().unwrap();
+            Snapshot {
+                current_timestamp: inner.current_timestamp.clone(),
+                current_batch: inner.current_batch.clone(),
+                current_miniblock: inner.current_miniblock.clone(),
+                current_miniblock_hash: inner.current_miniblock_hash.clone(),
+                l1_gas_price: inner.l1_gas_price.clone(),
+                tx_results: inner.tx_results.clone(),
+                blocks: inner.blocks.clone(),
+                block_hashes: inner.block_hashes.clone(),
+                filters: inner.filters.clone(),
+                impersonated_accounts: inner.impersonated_accounts.clone(),
+                rich_accounts: inner.rich_accounts.clone(),
+                previous_states: inner.previous_states.clone(),
+                raw_storage: storage.raw_storage.clone(),
+                value_read_cache: storage.value_read_cache.clone(),
+                factory_dep_cache: storage.factory_dep_cache.clone(),
+            }
+        };
+
+        // snapshot and modify node state
+        let snapshot = inner.snapshot().expect("failed taking snapshot");
+        inner
+            .blocks
+            .insert(H256::repeat_byte(0x2), Default::default());
+        inner.block_hashes.insert(2, H256::repeat_byte(0x2));
+        inner.tx_results.insert(
+            H256::repeat_byte(0x2),
+            TransactionResult {
+                info: testing::default_tx_execution_info(),
+                receipt: Default::default(),
+                debug: default_tx_debug_info(),
+            },
+        );
+        inner.current_batch = 2;
+        inner.current_miniblock = 2;
+        inner.current_miniblock_hash = H256::repeat_byte(0x2);
+        inner.current_timestamp = 2;
+        inner
+            .filters
+            .add_pending_transaction_filter()
+            .expect("failed adding pending transaction filter");
+        inner.impersonated_accounts.insert(H160::repeat_byte(0x2));
+        inner.rich_accounts.insert(H160::repeat_byte(0x2));
+        inner
+            .previous_states
+            .insert(H256::repeat_byte(0x2), Default::default());
+        inner.fork_storage.set_value(
+            StorageKey::new(AccountTreeId::new(H160::repeat_byte(0x2)), H256::zero()),
+            H256::repeat_byte(0x2),
+        );
 
-        let actual_filter_id = node
-            .new_block_filter()
-            .await
-            .expect("failed creating filter");
+        // restore
+        inner
+            .restore_snapshot(snapshot)
+            .expect("failed restoring snapshot");
 
-        assert_eq!(U256::from(1), actual_filter_id);
+        let storage = inner.fork_storage.inner.read().unwrap();
+        assert_eq!(expected_snapshot.current_timestamp, inner.current_timestamp);
+        assert_eq!(expected_snapshot.current_batch, inner.current_batch);
+        assert_eq!(expected_snapshot.current_miniblock, inner.current_miniblock);
+        assert_eq!(
+            expected_snapshot.current_miniblock_hash,
+            inner.current_miniblock_hash
+        );
+        assert_eq!(expected_snapshot.l1_gas_price, inner.l1_gas_price);
+        assert_eq!(
+            expected_snapshot.tx_results.keys().collect_vec(),
+            inner.tx_results.keys().collect_vec()
+        );
+        assert_eq!(expected_snapshot.blocks, inner.blocks);
+        assert_eq!(expected_snapshot.block_hashes, inner.block_hashes);
+        assert_eq!(expected_snapshot.filters, inner.filters);
+        assert_eq!(
+            expected_snapshot.impersonated_accounts,
+            inner.impersonated_accounts
+        );
+        assert_eq!(expected_snapshot.rich_accounts, inner.rich_accounts);
+        assert_eq!(expected_snapshot.previous_states, inner.previous_states);
+        assert_eq!(expected_snapshot.raw_storage, storage.raw_storage);
+        assert_eq!(expected_snapshot.value_read_cache, storage.value_read_cache);
+        assert_eq!(
+            expected_snapshot.factory_dep_cache,
+            storage.factory_dep_cache
+        );
     }
 
     #[tokio::test]
-    async fn test_new_filter_returns_filter_id() {
+    async fn test_get_transaction_by_block_hash_and_index_returns_none_for_invalid_block_hash() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let input_tx_hash = H256::repeat_byte(0x01);
+        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
+        let invalid_block_hash = H256::repeat_byte(0xab);
+        assert_ne!(input_block_hash, invalid_block_hash);
 
-        let actual_filter_id = node
-            .new_filter(Filter::default())
+        let result = node
+            .get_transaction_by_block_hash_and_index(invalid_block_hash, U64::from(0))
             .await
-            .expect("failed creating filter");
+            .expect("failed fetching transaction");
 
-        assert_eq!(U256::from(1), actual_filter_id);
+        assert!(result.is_none());
     }
 
     #[tokio::test]
-    async fn test_new_pending_transaction_filter_returns_filter_id() {
+    async fn test_get_transaction_by_block_hash_and_index_returns_none_for_invalid_index() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let input_tx_hash = H256::repeat_byte(0x01);
+        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
 
-        let actual_filter_id = node
-            .new_pending_transaction_filter()
+        let result = node
+            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(10))
             .await
-            .expect("failed creating filter");
+            .expect("failed fetching transaction");
 
-        assert_eq!(U256::from(1), actual_filter_id);
+        assert!(result.is_none());
     }
 
     #[tokio::test]
-    async fn test_uninstall_filter_returns_true_if_filter_exists() {
+    async fn test_get_transaction_by_block_hash_and_index_returns_transaction_for_valid_input() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let filter_id = node
-            .new_block_filter()
-            .await
-            .expect("failed creating filter");
+        let input_tx_hash = H256::repeat_byte(0x01);
+        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
 
-        let actual_result = node
-            .uninstall_filter(filter_id)
+        let actual_tx = node
+            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(0))
             .await
-            .expect("failed creating filter");
+            .expect("failed fetching transaction")
+            .expect("no transaction");
 
-        assert!(actual_result);
+        assert_eq!(input_tx_hash, actual_tx.hash);
     }
 
     #[tokio::test]
-    async fn test_uninstall_filter_returns_false_if_filter_does_not_exist() {
-        let node = InMemoryNode::<HttpForkSource>::default();
-
-        let actual_result = node
-            .uninstall_filter(U256::from(100))
-            .await
-            .expect("failed creating filter");
-
-        assert!(!actual_result);
-    }
+    async fn test_get_transaction_by_block_hash_and_index_fetches_full_transaction_for_hash_from_fork(
+    ) {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
+        });
+        let input_block_hash = H256::repeat_byte(0x01);
+        let input_tx_hash = H256::repeat_byte(0x02);
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getTransactionByHash",
+                "params": [
+                    format!("{:#x}", input_tx_hash),
+                ],
+            }),
+            TransactionResponseBuilder::new()
+                .set_hash(input_tx_hash)
+                .set_block_hash(input_block_hash)
+                .set_block_number(U64::from(1))
+                .build(),
+        );
 
-    #[tokio::test]
-    async fn test_get_filter_changes_returns_block_hash_updates_only_once() {
-        let node = InMemoryNode::<HttpForkSource>::default();
-        let filter_id = node
-            .new_block_filter()
-            .await
-            .expect("failed creating filter");
-        let (block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x1));
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
 
-        match node
-            .get_filter_changes(filter_id)
-            .await
-            .expect("failed getting filter changes")
+        // store the block info with just the tx hash invariant
         {
-            FilterChanges::Hashes(result) => {
-                // Get the block hash and the virtual block hash.
-                assert_eq!(2, result.len());
-                assert_eq!(block_hash, result[0]);
-            }
-            changes => panic!("unexpected filter changes: {:?}", changes),
+            let mut writer = node.inner.write().unwrap();
+            writer.blocks.insert(
+                input_block_hash,
+                Block {
+                    transactions: vec![TransactionVariant::Hash(input_tx_hash)],
+                    ..Default::default()
+                },
+            );
         }
 
-        match node
-            .get_filter_changes(filter_id)
+        let actual_tx = node
+            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(0))
             .await
-            .expect("failed getting filter changes")
-        {
-            FilterChanges::Empty(_) => (),
-            changes => panic!("expected no changes in the second call, got {:?}", changes),
-        }
+            .expect("failed fetching transaction")
+            .expect("no transaction");
+
+        assert_eq!(input_tx_hash, actual_tx.hash);
+        assert_eq!(Some(U64::from(1)), actual_tx.block_number);
     }
 
     #[tokio::test]
-    async fn test_get_filter_changes_returns_log_updates_only_once() {
-        let node = InMemoryNode::<HttpForkSource>::default();
-        let filter_id = node
-            .new_filter(Filter {
-                from_block: None,
-                to_block: None,
-                address: None,
-                topics: None,
-                block_hash: None,
-            })
-            .await
-            .expect("failed creating filter");
-        testing::apply_tx(&node, H256::repeat_byte(0x1));
+    async fn test_get_transaction_by_block_hash_and_index_fetches_from_fork_if_block_missing() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
+        });
+        let input_block_hash = H256::repeat_byte(0x01);
+        let input_tx_hash = H256::repeat_byte(0x02);
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getTransactionByBlockHashAndIndex",
+                "params": [
+                    format!("{:#x}", input_block_hash),
+                    "0x1"
+                ],
+            }),
+            TransactionResponseBuilder::new()
+                .set_hash(input_tx_hash)
+                .set_block_hash(input_block_hash)
+                .set_block_number(U64::from(100))
+                .build(),
+        );
 
-        match node
-            .get_filter_changes(filter_id)
-            .await
-            .expect("failed getting filter changes")
-        {
-            FilterChanges::Logs(result) => assert_eq!(3, result.len()),
-            changes => panic!("unexpected filter changes: {:?}", changes),
-        }
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
 
-        match node
-            .get_filter_changes(filter_id)
+        let actual_tx = node
+            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(1))
             .await
-            .expect("failed getting filter changes")
-        {
-            FilterChanges::Empty(_) => (),
-            changes => panic!("expected no changes in the second call, got {:?}", changes),
-        }
+            .expect("failed fetching transaction")
+            .expect("no transaction");
+
+        assert_eq!(input_tx_hash, actual_tx.hash);
+        assert_eq!(Some(U64::from(100)), actual_tx.block_number);
     }
 
     #[tokio::test]
-    async fn test_get_filter_changes_returns_pending_transaction_updates_only_once() {
+    async fn test_get_transaction_by_block_number_and_index_returns_none_for_invalid_block_number()
+    {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let filter_id = node
-            .new_pending_transaction_filter()
-            .await
-            .expect("failed creating filter");
-        testing::apply_tx(&node, H256::repeat_byte(0x1));
-
-        match node
-            .get_filter_changes(filter_id)
-            .await
-            .expect("failed getting filter changes")
-        {
-            FilterChanges::Hashes(result) => assert_eq!(vec![H256::repeat_byte(0x1)], result),
-            changes => panic!("unexpected filter changes: {:?}", changes),
-        }
+        let input_tx_hash = H256::repeat_byte(0x01);
+        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
+        let invalid_block_hash = H256::repeat_byte(0xab);
+        assert_ne!(input_block_hash, invalid_block_hash);
 
-        match node
-            .get_filter_changes(filter_id)
+        let result = node
+            .get_transaction_by_block_number_and_index(
+                BlockNumber::Number(U64::from(100)),
+                U64::from(0),
+            )
             .await
-            .expect("failed getting filter changes")
-        {
-            FilterChanges::Empty(_) => (),
-            changes => panic!("expected no changes in the second call, got {:?}", changes),
-        }
+            .expect("failed fetching transaction");
+
+        assert!(result.is_none());
     }
 
     #[tokio::test]
-    async fn test_produced_block_archives_previous_blocks() {
+    async fn test_get_transaction_by_block_number_and_index_returns_none_for_invalid_index() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let input_tx_hash = H256::repeat_byte(0x01);
+        testing::apply_tx(&node, input_tx_hash);
 
-        let input_storage_key = StorageKey::new(
-            AccountTreeId::new(H160::repeat_byte(0x1)),
-            u256_to_h256(U256::zero()),
-        );
-        let input_storage_value = H256::repeat_byte(0xcd);
-        node.inner
-            .write()
-            .unwrap()
-            .fork_storage
-            .set_value(input_storage_key, input_storage_value);
-        let initial_miniblock = node.inner.read().unwrap().current_miniblock;
+        let result = node
+            .get_transaction_by_block_number_and_index(BlockNumber::Latest, U64::from(10))
+            .await
+            .expect("failed fetching transaction");
 
-        testing::apply_tx(&node, H256::repeat_byte(0x1));
-        let current_miniblock = node.inner.read().unwrap().current_miniblock;
+        assert!(result.is_none());
+    }
 
-        let reader = node.inner.read().unwrap();
-        for miniblock in initial_miniblock..current_miniblock {
-            let actual_cached_value = reader
-                .block_hashes
-                .get(&miniblock)
-                .map(|hash| {
-                    reader
-                        .previous_states
-                        .get(hash)
-                        .unwrap_or_else(|| panic!("state was not cached for block {}", miniblock))
-                })
-                .and_then(|state| state.get(&input_storage_key))
-                .copied();
+    #[tokio::test]
+    async fn test_get_transaction_by_block_number_and_index_returns_transaction_for_valid_input() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let input_tx_hash = H256::repeat_byte(0x01);
+        let (_, input_block_number) = testing::apply_tx(&node, input_tx_hash);
 
-            assert_eq!(
-                Some(input_storage_value),
-                actual_cached_value,
-                "unexpected cached state value for block {}",
-                miniblock
-            );
-        }
+        let actual_tx = node
+            .get_transaction_by_block_number_and_index(
+                BlockNumber::Number(input_block_number),
+                U64::from(0),
+            )
+            .await
+            .expect("failed fetching transaction")
+            .expect("no transaction");
+
+        assert_eq!(input_tx_hash, actual_tx.hash);
     }
 
     #[tokio::test]
-    async fn test_get_storage_fetches_zero_value_for_non_existent_key() {
+    async fn test_get_transaction_by_block_number_and_index_serves_local_block_transactions() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0x1);
+        let txs = (0..2)
+            .map(|nonce| testing::build_l2_tx(&private_key, Nonce(nonce)))
+            .collect::<Vec<_>>();
+        node.set_rich_account(txs[0].initiator_account());
+        node.run_l2_txs(txs.clone(), TxExecutionMode::VerifyExecute)
+            .expect("failed running transactions");
+        let block_hash = node.get_inner().read().unwrap().block_hashes[&1];
+
+        for (index, tx) in txs.iter().enumerate() {
+            let by_number = node
+                .get_transaction_by_block_number_and_index(
+                    BlockNumber::Number(U64::from(1)),
+                    U64::from(index),
+                )
+                .await
+                .expect("failed fetching transaction")
+                .expect("no transaction");
+            assert_eq!(tx.hash(), by_number.hash);
+            assert_eq!(Some(U64::from(index)), by_number.transaction_index);
 
-        let value = node
-            .get_storage(H160::repeat_byte(0xf1), U256::from(1024), None)
+            let by_hash = node
+                .get_transaction_by_block_hash_and_index(block_hash, U64::from(index))
+                .await
+                .expect("failed fetching transaction")
+                .expect("no transaction");
+            assert_eq!(tx.hash(), by_hash.hash);
+        }
+
+        assert!(node
+            .get_transaction_by_block_number_and_index(
+                BlockNumber::Number(U64::from(1)),
+                U64::from(2)
+            )
             .await
-            .expect("failed retrieving storage");
-        assert_eq!(H256::zero(), value);
+            .expect("failed fetching transaction")
+            .is_none());
+        assert!(node
+            .get_transaction_by_block_hash_and_index(block_hash, U64::from(2))
+            .await
+            .expect("failed fetching transaction")
+            .is_none());
     }
 
     #[tokio::test]
-    async fn test_get_storage_uses_fork_to_get_value_for_historical_block() {
+    async fn test_get_transaction_by_block_number_and_index_fetches_full_transaction_for_hash_from_fork(
+    ) {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
             number: 10,
             transaction_count: 0,
             hash: H256::repeat_byte(0xab),
         });
-        let input_address = H160::repeat_byte(0x1);
-        let input_storage_value = H256::repeat_byte(0xcd);
+        let input_block_hash = H256::repeat_byte(0x01);
+        let input_block_number = U64::from(100);
+        let input_tx_hash = H256::repeat_byte(0x02);
         mock_server.expect(
             serde_json::json!({
                 "jsonrpc": "2.0",
                 "id": 0,
-                "method": "eth_getStorageAt",
+                "method": "eth_getTransactionByHash",
                 "params": [
-                    format!("{:#x}", input_address),
-                    "0x0",
-                    { "blockNumber": "0x2" },
+                    format!("{:#x}", input_tx_hash),
                 ],
             }),
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "result": format!("{:#x}", input_storage_value),
-            }),
+            TransactionResponseBuilder::new()
+                .set_hash(input_tx_hash)
+                .set_block_hash(input_block_hash)
+                .set_block_number(input_block_number)
+                .build(),
         );
 
         let node = InMemoryNode::<HttpForkSource>::new(
@@ -3768,93 +8221,59 @@ mod tests {
             &system_contracts::Options::BuiltIn,
         );
 
-        let actual_value = node
-            .get_storage(
-                input_address,
-                U256::zero(),
-                Some(zksync_types::api::BlockIdVariant::BlockNumberObject(
-                    BlockNumberObject {
-                        block_number: BlockNumber::Number(U64::from(2)),
-                    },
-                )),
-            )
-            .await
-            .expect("failed retrieving storage");
-        assert_eq!(input_storage_value, actual_value);
-    }
-
-    #[tokio::test]
-    async fn test_get_storage_uses_archived_storage_to_get_value_for_missing_key() {
-        let input_address = H160::repeat_byte(0x1);
-        let input_storage_key = StorageKey::new(
-            AccountTreeId::new(input_address),
-            u256_to_h256(U256::zero()),
-        );
-        let input_storage_value = H256::repeat_byte(0xcd);
-
-        let node = InMemoryNode::<HttpForkSource>::default();
-        node.inner
-            .write()
-            .map(|mut writer| {
-                let historical_block = Block::<TransactionVariant> {
-                    hash: H256::repeat_byte(0x2),
-                    number: U64::from(2),
+        // store the block info with just the tx hash invariant
+        {
+            let mut writer = node.inner.write().unwrap();
+            writer
+                .block_hashes
+                .insert(input_block_number.as_u64(), input_block_hash);
+            writer.blocks.insert(
+                input_block_hash,
+                Block {
+                    transactions: vec![TransactionVariant::Hash(input_tx_hash)],
                     ..Default::default()
-                };
-                writer.block_hashes.insert(2, historical_block.hash);
-
-                writer.previous_states.insert(
-                    historical_block.hash,
-                    hashmap! {
-                        input_storage_key => input_storage_value,
-                    },
-                );
-                writer
-                    .blocks
-                    .insert(historical_block.hash, historical_block);
-            })
-            .expect("failed setting storage for historical block");
+                },
+            );
+        }
 
-        let actual_value = node
-            .get_storage(
-                input_address,
-                U256::zero(),
-                Some(zksync_types::api::BlockIdVariant::BlockNumberObject(
-                    BlockNumberObject {
-                        block_number: BlockNumber::Number(U64::from(2)),
-                    },
-                )),
+        let actual_tx = node
+            .get_transaction_by_block_number_and_index(
+                BlockNumber::Number(input_block_number),
+                U64::from(0),
             )
             .await
-            .expect("failed retrieving storage");
-        assert_eq!(input_storage_value, actual_value);
+            .expect("failed fetching transaction")
+            .expect("no transaction");
+
+        assert_eq!(input_tx_hash, actual_tx.hash);
+        assert_eq!(Some(U64::from(input_block_number)), actual_tx.block_number);
     }
 
     #[tokio::test]
-    async fn test_get_storage_uses_fork_to_get_value_for_latest_block_for_missing_key() {
+    async fn test_get_transaction_by_block_number_and_index_fetches_from_fork_if_block_missing() {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
             number: 10,
             transaction_count: 0,
             hash: H256::repeat_byte(0xab),
         });
-        let input_address = H160::repeat_byte(0x1);
-        let input_storage_value = H256::repeat_byte(0xcd);
+        let input_block_hash = H256::repeat_byte(0x01);
+        let input_block_number = U64::from(100);
+        let input_tx_hash = H256::repeat_byte(0x02);
         mock_server.expect(
             serde_json::json!({
                 "jsonrpc": "2.0",
                 "id": 0,
-                "method": "eth_getStorageAt",
+                "method": "eth_getTransactionByBlockNumberAndIndex",
                 "params": [
-                    format!("{:#x}", input_address),
-                    "0x0",
-                    "0xa",
+                    format!("{:#x}", input_block_number),
+                    "0x1"
                 ],
             }),
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "result": format!("{:#x}", input_storage_value),
-            }),
+            TransactionResponseBuilder::new()
+                .set_hash(input_tx_hash)
+                .set_block_hash(input_block_hash)
+                .set_block_number(U64::from(input_block_number))
+                .build(),
         );
 
         let node = InMemoryNode::<HttpForkSource>::new(
@@ -3866,856 +8285,1599 @@ mod tests {
             false,
             &system_contracts::Options::BuiltIn,
         );
-        node.inner
-            .write()
-            .map(|mut writer| {
-                let historical_block = Block::<TransactionVariant> {
-                    hash: H256::repeat_byte(0x2),
-                    number: U64::from(2),
-                    ..Default::default()
-                };
-                writer.block_hashes.insert(2, historical_block.hash);
-                writer
-                    .previous_states
-                    .insert(historical_block.hash, Default::default());
-                writer
-                    .blocks
-                    .insert(historical_block.hash, historical_block);
-            })
-            .expect("failed setting storage for historical block");
 
-        let actual_value = node
-            .get_storage(
-                input_address,
-                U256::zero(),
-                Some(zksync_types::api::BlockIdVariant::BlockNumberObject(
-                    BlockNumberObject {
-                        block_number: BlockNumber::Number(U64::from(2)),
-                    },
-                )),
+        let actual_tx = node
+            .get_transaction_by_block_number_and_index(
+                BlockNumber::Number(input_block_number),
+                U64::from(1),
             )
             .await
-            .expect("failed retrieving storage");
-        assert_eq!(input_storage_value, actual_value);
+            .expect("failed fetching transaction")
+            .expect("no transaction");
+
+        assert_eq!(input_tx_hash, actual_tx.hash);
+        assert_eq!(Some(input_block_number), actual_tx.block_number);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version_returns_currently_supported_version() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let expected_version = String::from(PROTOCOL_VERSION);
+        let actual_version = node
+            .protocol_version()
+            .await
+            .expect("failed creating filter");
+
+        assert_eq!(expected_version, actual_version);
     }
 
     #[tokio::test]
-    async fn test_get_storage_fetches_state_for_deployed_smart_contract_in_current_block() {
+    async fn test_protocol_version_returns_fixed_value() {
         let node = InMemoryNode::<HttpForkSource>::default();
 
-        let private_key = H256::repeat_byte(0xef);
-        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+        let actual_version = node
+            .protocol_version()
+            .await
+            .expect("failed fetching protocol version");
+
+        assert_eq!("0x41", actual_version);
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_returns_revert_reason() {
+        use crate::deps::system_contracts::bytecode_from_slice;
+        use ethers::{
+            abi::short_signature,
+            signers::{LocalWallet, Signer},
+            types::transaction::eip2718::TypedTransaction,
+        };
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0xee);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
             .expect("failed generating address");
         node.set_rich_account(from_account);
 
+        let secondary_bytecode = bytecode_from_slice(
+            "Secondary",
+            include_bytes!("deps/test-contracts/Secondary.json"),
+        );
         let deployed_address = deployed_address_create(from_account, U256::zero());
-
         testing::deploy_contract(
             &node,
             H256::repeat_byte(0x1),
             private_key,
-            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
-            None,
+            secondary_bytecode,
+            Some(ethers::abi::encode(&[ethers::abi::Token::Uint(
+                ethers::types::U256::from(2),
+            )])),
             Nonce(0),
         );
 
-        let number1 = node
-            .get_storage(deployed_address, U256::from(0), None)
+        let wallet = LocalWallet::from_bytes(private_key.as_bytes())
+            .expect("failed creating wallet")
+            .with_chain_id(260u64);
+        let tx: TypedTransaction = ethers::types::TransactionRequest::new()
+            .from(wallet.address())
+            .to(ethers::types::Address::from_slice(
+                deployed_address.as_bytes(),
+            ))
+            .data(short_signature("shouldRevert()", &[]).to_vec())
+            .gas(3_000_000)
+            .gas_price(250_000_000)
+            .nonce(1)
+            .chain_id(260)
+            .into();
+        let signature = wallet
+            .sign_transaction_sync(&tx)
+            .expect("failed signing tx");
+        let raw_tx = tx.rlp_signed(&signature);
+
+        let error = node
+            .send_raw_transaction(Bytes(raw_tx.to_vec()))
             .await
-            .expect("failed retrieving storage at slot 0");
-        assert_eq!(U256::from(1024), h256_to_u256(number1));
+            .expect_err("transaction should revert");
 
-        let number2 = node
-            .get_storage(deployed_address, U256::from(1), None)
-            .await
-            .expect("failed retrieving storage at slot 1");
-        assert_eq!(U256::MAX, h256_to_u256(number2));
+        assert!(
+            error.message.contains("This should revert"),
+            "unexpected error message: {}",
+            error.message
+        );
     }
 
     #[tokio::test]
-    async fn test_get_storage_fetches_state_for_deployed_smart_contract_in_old_block() {
-        let node = InMemoryNode::<HttpForkSource>::default();
+    async fn test_call_returns_revert_data_in_error_data() {
+        use crate::deps::system_contracts::bytecode_from_slice;
+        use ethers::abi::{short_signature, ParamType};
 
-        let private_key = H256::repeat_byte(0xef);
-        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0xee);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
             .expect("failed generating address");
         node.set_rich_account(from_account);
 
+        let secondary_bytecode = bytecode_from_slice(
+            "Secondary",
+            include_bytes!("deps/test-contracts/Secondary.json"),
+        );
         let deployed_address = deployed_address_create(from_account, U256::zero());
-
-        let initial_block_hash = testing::deploy_contract(
+        testing::deploy_contract(
             &node,
             H256::repeat_byte(0x1),
             private_key,
-            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
-            None,
+            secondary_bytecode,
+            Some(ethers::abi::encode(&[ethers::abi::Token::Uint(
+                ethers::types::U256::from(2),
+            )])),
             Nonce(0),
         );
 
-        // simulate a tx modifying the storage
-        testing::apply_tx(&node, H256::repeat_byte(0x2));
-        let key = StorageKey::new(
-            AccountTreeId::new(deployed_address),
-            u256_to_h256(U256::from(0)),
-        );
-        node.inner
-            .write()
-            .unwrap()
-            .fork_storage
-            .inner
-            .write()
-            .unwrap()
-            .raw_storage
-            .state
-            .insert(key, u256_to_h256(U256::from(512)));
-
-        let number1_current = node
-            .get_storage(deployed_address, U256::from(0), None)
-            .await
-            .expect("failed retrieving storage at slot 0");
-        assert_eq!(U256::from(512), h256_to_u256(number1_current));
-
-        let number1_old = node
-            .get_storage(
-                deployed_address,
-                U256::from(0),
-                Some(zksync_types::api::BlockIdVariant::BlockHashObject(
-                    BlockHashObject {
-                        block_hash: initial_block_hash,
-                    },
-                )),
+        let error = node
+            .call(
+                CallRequestBuilder::default()
+                    .to(deployed_address)
+                    .data(short_signature("shouldRevert()", &[]).to_vec().into())
+                    .build(),
+                None,
             )
             .await
-            .expect("failed retrieving storage at slot 0");
-        assert_eq!(U256::from(1024), h256_to_u256(number1_old));
+            .expect_err("call should revert");
+
+        // the data is the raw `Error(string)` payload, and the message its decoded reason
+        let data = error
+            .data
+            .as_ref()
+            .and_then(|data| data.as_str())
+            .expect("no revert data");
+        assert!(data.starts_with("0x08c379a0"), "unexpected data: {}", data);
+        let reason = ethers::abi::decode(
+            &[ParamType::String],
+            &hex::decode(&data[10..]).expect("invalid revert data"),
+        )
+        .expect("failed decoding revert data")
+        .pop()
+        .and_then(|token| token.into_string())
+        .expect("no revert reason");
+        assert!(reason.contains("This should revert"));
+        assert_eq!(format!("execution reverted: {}", reason), error.message);
     }
 
     #[tokio::test]
-    async fn test_get_filter_logs_returns_matching_logs_for_valid_id() {
-        let node = InMemoryNode::<HttpForkSource>::default();
-
-        // populate tx receipts with 2 tx each having logs
-        {
-            let mut writer = node.inner.write().unwrap();
-            writer.tx_results.insert(
-                H256::repeat_byte(0x1),
-                TransactionResult {
-                    info: testing::default_tx_execution_info(),
-                    receipt: TransactionReceipt {
-                        logs: vec![LogBuilder::new()
-                            .set_address(H160::repeat_byte(0xa1))
-                            .build()],
-                        ..Default::default()
-                    },
-                    debug: default_tx_debug_info(),
-                },
-            );
-            writer.tx_results.insert(
-                H256::repeat_byte(0x2),
-                TransactionResult {
-                    info: testing::default_tx_execution_info(),
-                    receipt: TransactionReceipt {
-                        logs: vec![
-                            LogBuilder::new()
-                                .set_address(H160::repeat_byte(0xa1))
-                                .build(),
-                            LogBuilder::new()
-                                .set_address(H160::repeat_byte(0xa2))
-                                .build(),
-                        ],
-                        ..Default::default()
-                    },
-                    debug: default_tx_debug_info(),
-                },
-            );
-        }
-
-        let filter_id = node
-            .new_filter(Filter {
-                address: Some(ValueOrArray(vec![H160::repeat_byte(0xa1)])),
-                ..Default::default()
-            })
-            .await
-            .expect("failed creating filter");
+    async fn test_call_decodes_the_registered_error_of_a_revert() {
+        use ethers::abi::{short_signature, ParamType, Token};
 
-        match node
-            .get_filter_logs(filter_id)
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let (_, secondary_address) = testing::deploy_test_contracts(&node);
+        // the overflow of `multiply` reverts with the `Panic(uint256)` error of solidity - encoded like any custom
+        // error, so it is decoded once its selector is registered
+        let selector = format!(
+            "0x{}",
+            hex::encode(short_signature("Panic", &[ParamType::Uint(256)]))
+        );
+        crate::resolver::register_function_selector(&selector, "Panic(uint256)").await;
+
+        let mut calldata = short_signature("multiply", &[ParamType::Uint(256)]).to_vec();
+        calldata.extend(ethers::abi::encode(&[Token::Uint(
+            ethers::types::U256::MAX,
+        )]));
+        let error = node
+            .call(
+                CallRequestBuilder::default()
+                    .to(secondary_address)
+                    .data(calldata.into())
+                    .build(),
+                None,
+            )
             .await
-            .expect("failed getting filter changes")
-        {
-            FilterChanges::Logs(result) => assert_eq!(2, result.len()),
-            changes => panic!("unexpected filter changes: {:?}", changes),
-        }
+            .expect_err("call should revert");
+
+        assert_eq!("execution reverted: Panic(17)", error.message);
+        let data = error
+            .data
+            .as_ref()
+            .and_then(|data| data.as_str())
+            .expect("no revert data");
+        assert!(data.starts_with(&selector), "unexpected data: {}", data);
     }
 
-    #[tokio::test]
-    async fn test_get_filter_logs_returns_error_for_invalid_id() {
-        let node = InMemoryNode::<HttpForkSource>::default();
+    #[test]
+    fn test_revert_error_holds_the_raw_custom_error() {
+        let selector = ethabi::short_signature(
+            "InsufficientBalance",
+            &[ethabi::ParamType::Uint(256), ethabi::ParamType::Address],
+        );
+        let args = ethabi::encode(&[
+            ethabi::Token::Uint(ethabi::Uint::from(42)),
+            ethabi::Token::Address(ethabi::Address::repeat_byte(0x1)),
+        ]);
+        let output = VmRevertReason::Unknown {
+            function_selector: selector.to_vec(),
+            data: args.clone(),
+        };
 
-        // populate tx receipts with 2 tx each having logs
-        {
-            let mut writer = node.inner.write().unwrap();
-            writer.tx_results.insert(
-                H256::repeat_byte(0x1),
-                TransactionResult {
-                    info: testing::default_tx_execution_info(),
-                    receipt: TransactionReceipt {
-                        logs: vec![LogBuilder::new()
-                            .set_address(H160::repeat_byte(0xa1))
-                            .build()],
-                        ..Default::default()
-                    },
-                    debug: default_tx_debug_info(),
-                },
-            );
-        }
+        let error = revert_error(String::from("execution reverted"), &output);
 
-        let invalid_filter_id = U256::from(100);
-        let result = node.get_filter_logs(invalid_filter_id).await;
+        assert_eq!("execution reverted", error.message);
+        assert_eq!(
+            Some(serde_json::Value::String(format!(
+                "0x{}{}",
+                hex::encode(selector),
+                hex::encode(args)
+            ))),
+            error.data
+        );
+    }
 
-        assert!(result.is_err(), "expected an error for invalid filter id");
+    #[test]
+    fn test_node_handle_executes_transfer_without_network() {
+        let handle = InMemoryNodeBuilder::<HttpForkSource>::default().build_handle();
+        handle
+            .set_automine(false)
+            .expect("failed disabling automine");
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        handle.node().set_rich_account(from_account);
+        let initial_balance = handle.balance(from_account).expect("balance");
+
+        let tx = testing::build_l2_tx(&private_key, Nonce(0));
+        let recipient = tx.recipient_account();
+        let tx_hash = handle.submit_tx(tx).expect("failed submitting tx");
+        assert_eq!(U256::zero(), handle.balance(recipient).expect("balance"));
+
+        handle.mine().expect("failed mining");
+
+        let receipt = handle
+            .transaction_receipt(tx_hash)
+            .expect("receipt")
+            .expect("transaction must be executed");
+        assert_eq!(Some(U64::from(1)), receipt.status);
+        assert_eq!(U256::one(), handle.balance(recipient).expect("balance"));
+        assert!(handle.balance(from_account).expect("balance") < initial_balance - U256::one());
+        assert_eq!(Nonce(1), handle.nonce(from_account).expect("nonce"));
     }
 
     #[tokio::test]
-    async fn test_get_logs_returns_matching_logs() {
-        let node = InMemoryNode::<HttpForkSource>::default();
-
-        // populate tx receipts with 2 tx each having logs
-        {
-            let mut writer = node.inner.write().unwrap();
-            writer.tx_results.insert(
-                H256::repeat_byte(0x1),
-                TransactionResult {
-                    info: testing::default_tx_execution_info(),
-                    receipt: TransactionReceipt {
-                        logs: vec![LogBuilder::new()
-                            .set_address(H160::repeat_byte(0xa1))
-                            .build()],
-                        ..Default::default()
-                    },
-                    debug: testing::default_tx_debug_info(),
-                },
-            );
-            writer.tx_results.insert(
-                H256::repeat_byte(0x2),
-                TransactionResult {
-                    info: testing::default_tx_execution_info(),
-                    receipt: TransactionReceipt {
-                        logs: vec![
-                            LogBuilder::new()
-                                .set_address(H160::repeat_byte(0xa1))
-                                .build(),
-                            LogBuilder::new()
-                                .set_address(H160::repeat_byte(0xa2))
-                                .build(),
-                        ],
-                        ..Default::default()
-                    },
-                    debug: testing::default_tx_debug_info(),
-                },
-            );
-        }
+    async fn test_genesis_timestamp_and_block_number_are_configurable() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .genesis_timestamp(1_700_000_000)
+            .genesis_block_number(100)
+            .build();
 
-        let result = node
-            .get_logs(Filter {
-                address: Some(ValueOrArray(vec![H160::repeat_byte(0xa2)])),
-                ..Default::default()
-            })
+        let genesis_block = node
+            .get_block_by_number(BlockNumber::Latest, false)
             .await
-            .expect("failed getting filter changes");
-        assert_eq!(1, result.len());
+            .expect("failed fetching block")
+            .expect("block exists");
+        assert_eq!(U64::from(100), genesis_block.number);
+        assert_eq!(U256::from(1_700_000_000), genesis_block.timestamp);
 
-        let result = node
-            .get_logs(Filter {
-                address: Some(ValueOrArray(vec![H160::repeat_byte(0xa1)])),
-                ..Default::default()
-            })
+        testing::apply_tx(&node, H256::repeat_byte(0x01));
+
+        let first_mined_block = node
+            .get_block_by_number(BlockNumber::Number(U64::from(101)), false)
             .await
-            .expect("failed getting filter changes");
-        assert_eq!(2, result.len());
+            .expect("failed fetching block")
+            .expect("block exists");
+        assert_eq!(U256::from(1_700_000_001), first_mined_block.timestamp);
+        assert_eq!(1, first_mined_block.transactions.len());
+        assert_eq!(Some(U64::from(101)), first_mined_block.l1_batch_number);
+    }
+
+    #[test]
+    fn test_validate_fork_genesis_rejects_overrides_before_the_fork_block() {
+        validate_fork_genesis(10, 1_000, None, None).expect("no override is valid");
+        validate_fork_genesis(10, 1_000, Some(1_000), Some(10))
+            .expect("the fork block itself is valid");
+        validate_fork_genesis(10, 1_000, Some(2_000), Some(20))
+            .expect("overrides after the fork block are valid");
+
+        let error = validate_fork_genesis(10, 1_000, Some(999), None)
+            .expect_err("timestamp before the fork block was accepted");
+        assert!(
+            error.contains("genesis timestamp 999 is before the fork block timestamp 1000"),
+            "unexpected error: {}",
+            error
+        );
+        let error = validate_fork_genesis(10, 1_000, None, Some(9))
+            .expect_err("block number before the fork block was accepted");
+        assert!(
+            error.contains("genesis block number 9 is before the fork block number 10"),
+            "unexpected error: {}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_genesis_block_number_is_applied_after_the_fork_block() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
+        );
+        let mut inner = node.inner.write().unwrap();
+        let block_info_key = StorageKey::new(
+            AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+            SYSTEM_CONTEXT_CURRENT_L2_BLOCK_INFO_POSITION,
+        );
+        let fork_timestamp = inner.current_timestamp;
+        inner.fork_storage.set_value(
+            block_info_key,
+            u256_to_h256(pack_block_info(10, fork_timestamp)),
+        );
+        inner.fork_storage.set_value(
+            StorageKey::new(
+                AccountTreeId::new(SYSTEM_CONTEXT_ADDRESS),
+                u256_to_h256(
+                    h256_to_u256(SYSTEM_CONTEXT_CURRENT_L2_BLOCK_HASHES_POSITION)
+                        + U256::from(10 % SYSTEM_CONTEXT_STORED_L2_BLOCK_HASHES as u64),
+                ),
+            ),
+            H256::repeat_byte(0xab),
+        );
+        let fork_batch = inner.current_batch;
 
-        let result = node
-            .get_logs(Filter {
-                address: Some(ValueOrArray(vec![H160::repeat_byte(0x11)])),
-                ..Default::default()
-            })
-            .await
-            .expect("failed getting filter changes");
-        assert_eq!(0, result.len());
+        inner
+            .override_genesis(true, None, Some(5))
+            .expect_err("genesis block number before the fork block was applied");
+        assert_eq!(10, inner.current_miniblock);
+
+        inner
+            .override_genesis(true, None, Some(100))
+            .expect("failed overriding genesis");
+        assert_eq!(100, inner.current_miniblock);
+        assert_eq!(fork_batch, inner.current_batch);
+        let (last_number, _) = unpack_block_info(h256_to_u256(
+            (&inner.fork_storage).read_value(&block_info_key),
+        ));
+        assert_eq!(100, last_number);
     }
 
     #[tokio::test]
-    async fn test_accounts() {
-        let node = InMemoryNode::<HttpForkSource>::default();
-
-        let private_key = H256::repeat_byte(0x01);
-        let from_account = PackedEthSignature::address_from_private_key(&private_key).unwrap();
-        node.set_rich_account(from_account);
+    async fn test_block_time_sets_the_timestamp_increment_of_empty_blocks() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .block_time(12)
+            .build();
 
-        let account_result = node.accounts().await;
-        let expected_accounts: Vec<H160> = vec![from_account];
+        node.mine_block().expect("failed mining block");
+        node.mine_block().expect("failed mining block");
 
-        match account_result {
-            Ok(accounts) => {
-                assert_eq!(expected_accounts, accounts);
-            }
-            Err(e) => {
-                panic!("Failed to fetch accounts: {:?}", e);
-            }
-        }
+        let first_block = node
+            .get_block_by_number(BlockNumber::Number(U64::from(1)), false)
+            .await
+            .expect("failed fetching block")
+            .expect("block exists");
+        let second_block = node
+            .get_block_by_number(BlockNumber::Number(U64::from(2)), false)
+            .await
+            .expect("failed fetching block")
+            .expect("block exists");
+        assert_eq!(
+            U256::from(12),
+            second_block.timestamp - first_block.timestamp
+        );
     }
 
     #[tokio::test]
-    async fn test_snapshot() {
+    async fn test_get_balance_at_historical_blocks() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let mut inner = node.inner.write().unwrap();
-
-        inner
-            .blocks
-            .insert(H256::repeat_byte(0x1), Default::default());
-        inner.block_hashes.insert(1, H256::repeat_byte(0x1));
-        inner.tx_results.insert(
-            H256::repeat_byte(0x1),
-            TransactionResult {
-                info: testing::default_tx_execution_info(),
-                receipt: Default::default(),
-                debug: testing::default_tx_debug_info(),
-            },
-        );
-        inner.current_batch = 1;
-        inner.current_miniblock = 1;
-        inner.current_miniblock_hash = H256::repeat_byte(0x1);
-        inner.current_timestamp = 1;
-        inner
-            .filters
-            .add_block_filter()
-            .expect("failed adding block filter");
-        inner.impersonated_accounts.insert(H160::repeat_byte(0x1));
-        inner.rich_accounts.insert(H160::repeat_byte(0x1));
-        inner
-            .previous_states
-            .insert(H256::repeat_byte(0x1), Default::default());
-        inner.fork_storage.set_value(
-            StorageKey::new(AccountTreeId::new(H160::repeat_byte(0x1)), H256::zero()),
-            H256::repeat_byte(0x1),
-        );
-
-        let storage = inner.fork_storage.inner.read().unwrap();
-        let expected_snapshot = Snapshot {
-            current_timestamp: inner.current_timestamp.clone(),
-            current_batch: inner.current_batch.clone(),
-            current_miniblock: inner.current_miniblock.clone(),
-            current_miniblock_hash: inner.current_miniblock_hash.clone(),
-            l1_gas_price: inner.l1_gas_price.clone(),
-            tx_results: inner.tx_results.clone(),
-            blocks: inner.blocks.clone(),
-            block_hashes: inner.block_hashes.clone(),
-            filters: inner.filters.clone(),
-            impersonated_accounts: inner.impersonated_accounts.clone(),
-            rich_accounts: inner.rich_accounts.clone(),
-            previous_states: inner.previous_states.clone(),
-            raw_storage: storage.raw_storage.clone(),
-            value_read_cache: storage.value_read_cache.clone(),
-            factory_dep_cache: storage.factory_dep_cache.clone(),
+        let funder_key = H256::repeat_byte(0x1);
+        let funder = PackedEthSignature::address_from_private_key(&funder_key)
+            .expect("failed generating address");
+        let account_key = H256::repeat_byte(0x2);
+        let account = PackedEthSignature::address_from_private_key(&account_key)
+            .expect("failed generating address");
+        node.set_rich_account(funder);
+        let transfer = |private_key: &H256, to: Address, value: U256| {
+            let mut tx = L2Tx::new_signed(
+                to,
+                vec![],
+                Nonce(0),
+                Fee {
+                    gas_limit: U256::from(1_000_000),
+                    max_fee_per_gas: U256::from(250_000_000),
+                    max_priority_fee_per_gas: U256::from(250_000_000),
+                    gas_per_pubdata_limit: U256::from(20000),
+                },
+                value,
+                L2ChainId(260),
+                private_key,
+                None,
+                Default::default(),
+            )
+            .unwrap();
+            tx.set_input(vec![], H256::random());
+            node.submit_tx(tx).expect("failed submitting tx");
+            node.get_inner().read().unwrap().current_miniblock
+        };
+        let balance_at = |block_number: u64| {
+            node.get_balance(
+                account,
+                Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
+                    block_number,
+                )))),
+            )
         };
-        let actual_snapshot = inner.snapshot().expect("failed taking snapshot");
+
+        let funded = U256::from(10u64.pow(18));
+        let withdrawn = U256::from(10u64.pow(17));
+        let before_funding_block = node.get_inner().read().unwrap().current_miniblock;
+        let funded_block = transfer(&funder_key, account, funded);
+        let withdrawn_block = transfer(&account_key, Address::repeat_byte(0x3), withdrawn);
 
         assert_eq!(
-            expected_snapshot.current_timestamp,
-            actual_snapshot.current_timestamp
+            U256::zero(),
+            balance_at(before_funding_block).await.unwrap()
         );
+        assert_eq!(funded, balance_at(funded_block).await.unwrap());
+        let balance_after_withdrawal = balance_at(withdrawn_block).await.unwrap();
+        // the withdrawal also pays for the fees
+        assert!(balance_after_withdrawal < funded - withdrawn);
         assert_eq!(
-            expected_snapshot.current_batch,
-            actual_snapshot.current_batch
+            balance_after_withdrawal,
+            node.get_balance(account, None).await.unwrap()
         );
+
+        let result = balance_at(withdrawn_block + 1).await;
+        assert!(result.is_err(), "blocks after the latest must be rejected");
+    }
+
+    #[tokio::test]
+    async fn test_earliest_resolves_to_genesis_block() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .genesis_block_number(100)
+            .build();
+        let private_key = H256::repeat_byte(0x1);
+        let sender = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(sender);
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(0)))
+            .expect("failed submitting tx");
+
+        let earliest_block = node
+            .get_block_by_number(BlockNumber::Earliest, false)
+            .await
+            .expect("failed fetching block")
+            .expect("block exists");
+        assert_eq!(U64::from(100), earliest_block.number);
+
+        let earliest = Some(BlockIdVariant::BlockNumber(BlockNumber::Earliest));
         assert_eq!(
-            expected_snapshot.current_miniblock,
-            actual_snapshot.current_miniblock
+            U256::zero(),
+            node.get_transaction_count(sender, earliest.clone())
+                .await
+                .expect("failed fetching nonce")
         );
         assert_eq!(
-            expected_snapshot.current_miniblock_hash,
-            actual_snapshot.current_miniblock_hash
+            U256::one(),
+            node.get_transaction_count(sender, None)
+                .await
+                .expect("failed fetching nonce")
         );
-        assert_eq!(expected_snapshot.l1_gas_price, actual_snapshot.l1_gas_price);
-        assert_eq!(
-            expected_snapshot.tx_results.keys().collect_vec(),
-            actual_snapshot.tx_results.keys().collect_vec()
+        assert!(
+            node.get_balance(sender, earliest)
+                .await
+                .expect("failed fetching balance")
+                > node
+                    .get_balance(sender, None)
+                    .await
+                    .expect("failed fetching balance")
         );
-        assert_eq!(expected_snapshot.blocks, actual_snapshot.blocks);
-        assert_eq!(expected_snapshot.block_hashes, actual_snapshot.block_hashes);
-        assert_eq!(expected_snapshot.filters, actual_snapshot.filters);
-        assert_eq!(
-            expected_snapshot.impersonated_accounts,
-            actual_snapshot.impersonated_accounts
+    }
+
+    #[tokio::test]
+    async fn test_get_proof_overrides_locally_modified_slots_of_fork() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            hash: H256::repeat_byte(0xab),
+            transaction_count: 0,
+        });
+        let address = H160::repeat_byte(0x1);
+        let upstream_key = H256::from_low_u64_be(1);
+        let local_key = H256::from_low_u64_be(2);
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "zks_getProof",
+                "params": [
+                    format!("{:#x}", address),
+                    [format!("{:#x}", upstream_key), format!("{:#x}", local_key)],
+                    1,
+                ],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": {
+                    "address": format!("{:#x}", address),
+                    "storageProof": [
+                        {
+                            "key": format!("{:#x}", upstream_key),
+                            "proof": [format!("{:#x}", H256::repeat_byte(0x11))],
+                            "value": format!("{:#x}", H256::from_low_u64_be(100)),
+                            "index": 5,
+                        },
+                        {
+                            "key": format!("{:#x}", local_key),
+                            "proof": [format!("{:#x}", H256::repeat_byte(0x22))],
+                            "value": format!("{:#x}", H256::from_low_u64_be(200)),
+                            "index": 6,
+                        },
+                    ],
+                },
+            }),
         );
-        assert_eq!(
-            expected_snapshot.rich_accounts,
-            actual_snapshot.rich_accounts
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            crate::node::ShowCalls::None,
+            ShowStorageLogs::None,
+            ShowVMDetails::None,
+            ShowGasDetails::None,
+            false,
+            &system_contracts::Options::BuiltIn,
         );
-        assert_eq!(
-            expected_snapshot.previous_states,
-            actual_snapshot.previous_states
+        node.get_inner().write().unwrap().fork_storage.set_value(
+            StorageKey::new(AccountTreeId::new(address), local_key),
+            H256::from_low_u64_be(300),
         );
-        assert_eq!(expected_snapshot.raw_storage, actual_snapshot.raw_storage);
+
+        let proof =
+            EthExtNamespaceT::get_proof(&node, address, vec![upstream_key, local_key], None)
+                .await
+                .expect("failed getting proof");
+
+        assert_eq!(address, proof.address);
         assert_eq!(
-            expected_snapshot.value_read_cache,
-            actual_snapshot.value_read_cache
+            StorageProof {
+                key: upstream_key,
+                proof: vec![H256::repeat_byte(0x11)],
+                value: H256::from_low_u64_be(100),
+                index: 5,
+            },
+            proof.storage_proof[0]
         );
         assert_eq!(
-            expected_snapshot.factory_dep_cache,
-            actual_snapshot.factory_dep_cache
+            StorageProof {
+                key: local_key,
+                proof: vec![],
+                value: H256::from_low_u64_be(300),
+                index: 0,
+            },
+            proof.storage_proof[1]
         );
     }
 
     #[tokio::test]
-    async fn test_snapshot_restore() {
+    async fn test_send_transaction_from_impersonated_contract_uses_it_as_msg_sender() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let mut inner = node.inner.write().unwrap();
-
-        inner
-            .blocks
-            .insert(H256::repeat_byte(0x1), Default::default());
-        inner.block_hashes.insert(1, H256::repeat_byte(0x1));
-        inner.tx_results.insert(
-            H256::repeat_byte(0x1),
-            TransactionResult {
-                info: testing::default_tx_execution_info(),
-                receipt: Default::default(),
-                debug: testing::default_tx_debug_info(),
-            },
-        );
-        inner.current_batch = 1;
-        inner.current_miniblock = 1;
-        inner.current_miniblock_hash = H256::repeat_byte(0x1);
-        inner.current_timestamp = 1;
-        inner
-            .filters
-            .add_block_filter()
-            .expect("failed adding block filter");
-        inner.impersonated_accounts.insert(H160::repeat_byte(0x1));
-        inner.rich_accounts.insert(H160::repeat_byte(0x1));
-        inner
-            .previous_states
-            .insert(H256::repeat_byte(0x1), Default::default());
-        inner.fork_storage.set_value(
-            StorageKey::new(AccountTreeId::new(H160::repeat_byte(0x1)), H256::zero()),
-            H256::repeat_byte(0x1),
-        );
-
-        let expected_snapshot = {
-            let storage = inner.fork_storage.inner.read().unwrap();
-            Snapshot {
-                current_timestamp: inner.current_timestamp.clone(),
-                current_batch: inner.current_batch.clone(),
-                current_miniblock: inner.current_miniblock.clone(),
-                current_miniblock_hash: inner.current_miniblock_hash.clone(),
-                l1_gas_price: inner.l1_gas_price.clone(),
-                tx_results: inner.tx_results.clone(),
-                blocks: inner.blocks.clone(),
-                block_hashes: inner.block_hashes.clone(),
-                filters: inner.filters.clone(),
-                impersonated_accounts: inner.impersonated_accounts.clone(),
-                rich_accounts: inner.rich_accounts.clone(),
-                previous_states: inner.previous_states.clone(),
-                raw_storage: storage.raw_storage.clone(),
-                value_read_cache: storage.value_read_cache.clone(),
-                factory_dep_cache: storage.factory_dep_cache.clone(),
-            }
-        };
-
-        // snapshot and modify node state
-        let snapshot = inner.snapshot().expect("failed taking snapshot");
-        inner
-            .blocks
-            .insert(H256::repeat_byte(0x2), Default::default());
-        inner.block_hashes.insert(2, H256::repeat_byte(0x2));
-        inner.tx_results.insert(
-            H256::repeat_byte(0x2),
-            TransactionResult {
-                info: testing::default_tx_execution_info(),
-                receipt: Default::default(),
-                debug: default_tx_debug_info(),
-            },
-        );
-        inner.current_batch = 2;
-        inner.current_miniblock = 2;
-        inner.current_miniblock_hash = H256::repeat_byte(0x2);
-        inner.current_timestamp = 2;
-        inner
-            .filters
-            .add_pending_transaction_filter()
-            .expect("failed adding pending transaction filter");
-        inner.impersonated_accounts.insert(H160::repeat_byte(0x2));
-        inner.rich_accounts.insert(H160::repeat_byte(0x2));
-        inner
-            .previous_states
-            .insert(H256::repeat_byte(0x2), Default::default());
-        inner.fork_storage.set_value(
-            StorageKey::new(AccountTreeId::new(H160::repeat_byte(0x2)), H256::zero()),
-            H256::repeat_byte(0x2),
+        let private_key = H256::repeat_byte(0xef);
+        let deployer = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(deployer);
+        let bytecode = hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap();
+        testing::deploy_contract(
+            &node,
+            H256::repeat_byte(0x1),
+            private_key,
+            bytecode.clone(),
+            None,
+            Nonce(0),
         );
+        let impersonated = deployed_address_create(deployer, U256::zero());
+        node.set_rich_account(impersonated);
+        node.inner
+            .write()
+            .unwrap()
+            .set_impersonated_account(impersonated);
+        let (_, deployment_nonce) = decompose_full_nonce(h256_to_u256(
+            (&node.inner.read().unwrap().fork_storage).read_value(&get_nonce_key(&impersonated)),
+        ));
+
+        // `ContractDeployer.create` derives the address of the new contract from `msg.sender`
+        let create_calldata = [
+            hex::decode("9c4d535b").unwrap(),
+            H256::zero().as_bytes().to_vec(),
+            hash_bytecode(&bytecode).as_bytes().to_vec(),
+            u256_to_h256(U256::from(0x60)).as_bytes().to_vec(),
+            H256::zero().as_bytes().to_vec(),
+        ]
+        .concat();
+        let request = serde_json::from_value(serde_json::json!({
+            "from": impersonated,
+            "to": CONTRACT_DEPLOYER_ADDRESS,
+            "data": Bytes(create_calldata),
+            "transactionType": format!("{:#x}", EIP_712_TX_TYPE),
+            "eip712Meta": {
+                "gasPerPubdata": "0xc350",
+                "factoryDeps": [bytecode],
+            },
+        }))
+        .expect("failed parsing request");
+        let tx_hash = EthExtNamespaceT::send_transaction(&node, request)
+            .await
+            .expect("failed sending transaction");
 
-        // restore
-        inner
-            .restore_snapshot(snapshot)
-            .expect("failed restoring snapshot");
+        let receipt = node
+            .get_transaction_receipt(tx_hash)
+            .await
+            .expect("failed getting receipt")
+            .expect("missing receipt");
+        assert_eq!(Some(U64::from(1)), receipt.status);
+        let created = deployed_address_create(impersonated, deployment_nonce);
+        let created_code = node
+            .get_code(created, None)
+            .await
+            .expect("failed getting code");
+        assert!(!created_code.0.is_empty());
+        let impersonated_code = node
+            .get_code(impersonated, None)
+            .await
+            .expect("failed getting code");
+        assert!(
+            !impersonated_code.0.is_empty(),
+            "the code of the impersonated contract must be restored"
+        );
+    }
 
-        let storage = inner.fork_storage.inner.read().unwrap();
-        assert_eq!(expected_snapshot.current_timestamp, inner.current_timestamp);
-        assert_eq!(expected_snapshot.current_batch, inner.current_batch);
-        assert_eq!(expected_snapshot.current_miniblock, inner.current_miniblock);
+    #[test]
+    fn test_impersonated_tx_hash_covers_all_the_fields_of_the_transaction() {
+        let tx = testing::build_l2_tx(&H256::repeat_byte(0xef), Nonce(0));
+        assert_eq!(impersonated_tx_hash(&tx), impersonated_tx_hash(&tx));
+
+        let mut with_value = tx.clone();
+        with_value.execute.value += U256::one();
+        let mut with_gas_limit = tx.clone();
+        with_gas_limit.common_data.fee.gas_limit += U256::one();
+        let mut with_max_fee = tx.clone();
+        with_max_fee.common_data.fee.max_fee_per_gas += U256::one();
+        let mut with_paymaster = tx.clone();
+        with_paymaster.common_data.paymaster_params.paymaster = Address::repeat_byte(0x01);
+        let mut with_paymaster_input = tx.clone();
+        with_paymaster_input
+            .common_data
+            .paymaster_params
+            .paymaster_input = vec![0x01];
+        for changed in [
+            with_value,
+            with_gas_limit,
+            with_max_fee,
+            with_paymaster,
+            with_paymaster_input,
+        ] {
+            assert_ne!(impersonated_tx_hash(&tx), impersonated_tx_hash(&changed));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_transaction_fails_for_accounts_that_are_not_impersonated() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let result = EthExtNamespaceT::send_transaction(
+            &node,
+            CallRequestBuilder::default()
+                .from(H160::repeat_byte(0x1))
+                .to(H160::repeat_byte(0x2))
+                .build(),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_transaction_with_auto_impersonate_accepts_any_sender() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .auto_impersonate(true)
+            .build();
+        let sender = H160::repeat_byte(0x1);
+        let recipient = H160::repeat_byte(0x2);
+        node.set_rich_account(sender);
+
+        let tx_hash = EthExtNamespaceT::send_transaction(
+            &node,
+            CallRequestBuilder::default()
+                .from(sender)
+                .to(recipient)
+                .value(U256::from(100))
+                .build(),
+        )
+        .await
+        .expect("failed sending transaction from a never impersonated account");
+
+        let receipt = node
+            .get_transaction_receipt(tx_hash)
+            .await
+            .expect("failed fetching receipt")
+            .expect("receipt exists");
+        assert_eq!(Some(U64::from(1)), receipt.status);
         assert_eq!(
-            expected_snapshot.current_miniblock_hash,
-            inner.current_miniblock_hash
+            U256::from(100),
+            node.get_balance(recipient, None)
+                .await
+                .expect("failed fetching balance")
         );
-        assert_eq!(expected_snapshot.l1_gas_price, inner.l1_gas_price);
-        assert_eq!(
-            expected_snapshot.tx_results.keys().collect_vec(),
-            inner.tx_results.keys().collect_vec()
+    }
+
+    #[tokio::test]
+    async fn test_call_bundle_executes_transactions_in_order_without_committing() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let funder_key = H256::repeat_byte(0xee);
+        let funded_key = H256::repeat_byte(0xef);
+        let funded = PackedEthSignature::address_from_private_key(&funded_key)
+            .expect("failed generating address");
+        node.set_rich_account(
+            PackedEthSignature::address_from_private_key(&funder_key)
+                .expect("failed generating address"),
         );
-        assert_eq!(expected_snapshot.blocks, inner.blocks);
-        assert_eq!(expected_snapshot.block_hashes, inner.block_hashes);
-        assert_eq!(expected_snapshot.filters, inner.filters);
-        assert_eq!(
-            expected_snapshot.impersonated_accounts,
-            inner.impersonated_accounts
+        let fund = testing::raw_transfer(
+            funder_key,
+            testing::TransferParams {
+                to: funded,
+                value: 1_000_000_000_000_000_000,
+                ..Default::default()
+            },
         );
-        assert_eq!(expected_snapshot.rich_accounts, inner.rich_accounts);
-        assert_eq!(expected_snapshot.previous_states, inner.previous_states);
-        assert_eq!(expected_snapshot.raw_storage, storage.raw_storage);
-        assert_eq!(expected_snapshot.value_read_cache, storage.value_read_cache);
+        let spend = testing::raw_transfer(funded_key, Default::default());
+
+        let results = node
+            .call_bundle(vec![fund, spend.clone()], U64::from(1), None)
+            .await
+            .expect("failed calling bundle");
         assert_eq!(
-            expected_snapshot.factory_dep_cache,
-            storage.factory_dep_cache
+            vec![None, None],
+            results
+                .iter()
+                .map(|result| result.error.clone())
+                .collect::<Vec<_>>()
         );
+        assert!(results.iter().all(|result| !result.gas_used.is_zero()));
+
+        // without the funding transaction, the funded account can not pay for the transfer
+        let results = node
+            .call_bundle(vec![spend], U64::from(1), None)
+            .await
+            .expect("failed calling bundle");
+        assert!(results[0].error.is_some());
+
+        assert_eq!(0, node.get_inner().read().unwrap().current_miniblock);
+        let balance = node
+            .get_balance(funded, None)
+            .await
+            .expect("failed getting balance");
+        assert_eq!(U256::zero(), balance);
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_hash_and_index_returns_none_for_invalid_block_hash() {
-        let node = InMemoryNode::<HttpForkSource>::default();
-        let input_tx_hash = H256::repeat_byte(0x01);
-        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
-        let invalid_block_hash = H256::repeat_byte(0xab);
-        assert_ne!(input_block_hash, invalid_block_hash);
+    async fn test_send_raw_transaction_enforces_max_tx_gas() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .max_tx_gas(3_000_000)
+            .build();
+        let private_key = H256::repeat_byte(0xee);
+        node.set_rich_account(
+            PackedEthSignature::address_from_private_key(&private_key)
+                .expect("failed generating address"),
+        );
+        let raw_transfer = |gas: u64, nonce: u64| {
+            testing::raw_transfer(
+                private_key,
+                testing::TransferParams {
+                    gas,
+                    nonce,
+                    ..Default::default()
+                },
+            )
+        };
 
-        let result = node
-            .get_transaction_by_block_hash_and_index(invalid_block_hash, U64::from(0))
+        let error = node
+            .send_raw_transaction(raw_transfer(3_000_001, 0))
             .await
-            .expect("failed fetching transaction");
+            .expect_err("transaction above the cap should be rejected");
+        assert!(
+            error
+                .message
+                .contains("transaction gas limit 3000001 exceeds the maximum of 3000000"),
+            "unexpected error message: {}",
+            error.message
+        );
+        assert_eq!(0, node.get_inner().read().unwrap().current_miniblock);
 
-        assert!(result.is_none());
+        node.send_raw_transaction(raw_transfer(3_000_000, 0))
+            .await
+            .expect("transaction at the cap should be accepted");
+        assert_eq!(2, node.get_inner().read().unwrap().current_miniblock);
+    }
+
+    /// Returns a signed transfer of no value with a zero gas price, from an account without any balance.
+    fn raw_zero_gas_price_transfer() -> (Bytes, H160) {
+        let private_key = H256::repeat_byte(0xed);
+        let raw_tx = testing::raw_transfer(
+            private_key,
+            testing::TransferParams {
+                value: 0,
+                gas_price: 0,
+                ..Default::default()
+            },
+        );
+        (
+            raw_tx,
+            PackedEthSignature::address_from_private_key(&private_key)
+                .expect("failed generating address"),
+        )
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_hash_and_index_returns_none_for_invalid_index() {
+    async fn test_send_raw_transaction_rejects_zero_gas_price_by_default() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let input_tx_hash = H256::repeat_byte(0x01);
-        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
+        let (raw_tx, _) = raw_zero_gas_price_transfer();
 
-        let result = node
-            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(10))
+        let error = node
+            .send_raw_transaction(raw_tx)
             .await
-            .expect("failed fetching transaction");
+            .expect_err("zero gas price transaction should be rejected");
+        assert!(
+            error.message.contains(
+                "transaction gas price is zero, which is only accepted with --allow-zero-gas-price"
+            ),
+            "unexpected error message: {}",
+            error.message
+        );
+        assert_eq!(0, node.get_inner().read().unwrap().current_miniblock);
+    }
 
-        assert!(result.is_none());
+    #[tokio::test]
+    async fn test_send_raw_transaction_accepts_zero_gas_price_when_allowed() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .allow_zero_gas_price(true)
+            .build();
+        let (raw_tx, sender) = raw_zero_gas_price_transfer();
+
+        let tx_hash = node
+            .send_raw_transaction(raw_tx)
+            .await
+            .expect("zero gas price transaction should be accepted");
+        let receipt = node
+            .get_transaction_receipt(tx_hash)
+            .await
+            .expect("failed fetching receipt")
+            .expect("no receipt");
+        assert_eq!(Some(U64::from(1)), receipt.status);
+        assert_eq!(Some(U256::zero()), receipt.effective_gas_price);
+        // the sender has no balance, and is not charged anything
+        assert_eq!(
+            U256::zero(),
+            node.get_balance(sender, None)
+                .await
+                .expect("failed fetching balance")
+        );
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_hash_and_index_returns_transaction_for_valid_input() {
+    async fn test_get_transaction_count_pending_includes_pool_transactions() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let input_tx_hash = H256::repeat_byte(0x01);
-        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
-
-        let actual_tx = node
-            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(0))
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(0)))
+            .expect("failed submitting tx");
+
+        node.get_inner().write().unwrap().automine = false;
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(1)))
+            .expect("failed submitting tx");
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(2)))
+            .expect("failed submitting tx");
+        // nonce-gapped transactions can't be mined yet, and are not counted
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(4)))
+            .expect("failed submitting tx");
+
+        let latest_nonce = node
+            .get_transaction_count(from_account, None)
             .await
-            .expect("failed fetching transaction")
-            .expect("no transaction");
+            .expect("failed getting transaction count");
+        let pending_nonce = node
+            .get_transaction_count(
+                from_account,
+                Some(BlockIdVariant::BlockNumber(BlockNumber::Pending)),
+            )
+            .await
+            .expect("failed getting transaction count");
 
-        assert_eq!(input_tx_hash, actual_tx.hash);
+        assert_eq!(U256::from(1), latest_nonce);
+        assert_eq!(latest_nonce + 2, pending_nonce);
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_hash_and_index_fetches_full_transaction_for_hash_from_fork(
-    ) {
-        let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: 10,
-            transaction_count: 0,
-            hash: H256::repeat_byte(0xab),
-        });
-        let input_block_hash = H256::repeat_byte(0x01);
-        let input_tx_hash = H256::repeat_byte(0x02);
-        mock_server.expect(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getTransactionByHash",
-                "params": [
-                    format!("{:#x}", input_tx_hash),
-                ],
-            }),
-            TransactionResponseBuilder::new()
-                .set_hash(input_tx_hash)
-                .set_block_hash(input_block_hash)
-                .set_block_number(U64::from(1))
-                .build(),
+    async fn test_submit_tx_accepts_any_nonce_without_nonce_check() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0x1);
+        let tx = testing::build_l2_tx(&private_key, Nonce(0));
+        let sender = tx.initiator_account();
+        node.set_rich_account(sender);
+        node.submit_tx(tx).expect("failed submitting tx");
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(1)))
+            .expect("failed submitting tx");
+
+        let reused_nonce_tx = testing::build_l2_tx(&private_key, Nonce(0));
+        let error = node
+            .submit_tx(reused_nonce_tx.clone())
+            .expect_err("transaction with a used nonce should be rejected");
+        assert!(
+            error.contains("nonce too low"),
+            "unexpected error: {}",
+            error
         );
 
-        let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
-        );
+        node.get_inner().write().unwrap().nonce_check = false;
+        node.submit_tx(reused_nonce_tx.clone())
+            .expect("failed submitting tx without nonce check");
 
-        // store the block info with just the tx hash invariant
-        {
-            let mut writer = node.inner.write().unwrap();
-            writer.blocks.insert(
-                input_block_hash,
-                Block {
-                    transactions: vec![TransactionVariant::Hash(input_tx_hash)],
-                    ..Default::default()
-                },
-            );
-        }
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        assert!(reader.tx_results.contains_key(&reused_nonce_tx.hash()));
+        // the account nonce follows the nonce of the latest transaction
+        assert_eq!(Nonce(1), reader.get_account_nonce(&sender));
+    }
 
-        let actual_tx = node
-            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(0))
+    #[tokio::test]
+    async fn test_submit_tx_rejects_or_evicts_once_mempool_is_full() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .mempool_max(2)
+            .build();
+        node.get_inner().write().unwrap().automine = false;
+        let build_tx = |private_key: u8, max_priority_fee_per_gas: u64| {
+            let mut tx = testing::build_l2_tx(&H256::repeat_byte(private_key), Nonce(0));
+            tx.common_data.fee.max_priority_fee_per_gas = U256::from(max_priority_fee_per_gas);
+            tx
+        };
+        let cheapest_tx = build_tx(0x1, 2);
+        node.submit_tx(cheapest_tx.clone())
+            .expect("failed submitting tx");
+        node.submit_tx(build_tx(0x2, 3))
+            .expect("failed submitting tx");
+
+        let rejected = node.submit_tx(build_tx(0x3, 1));
+        let evicting_tx = build_tx(0x4, 4);
+        node.submit_tx(evicting_tx.clone())
+            .expect("failed submitting tx");
+
+        assert!(rejected.unwrap_err().contains("txpool is full"));
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        assert_eq!(2, reader.pool.len());
+        assert!(reader.pool.get(&cheapest_tx.hash()).is_none());
+        assert!(reader.pool.get(&evicting_tx.hash()).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_rejects_wrong_chain_id() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let (tx, signature) = testing::signed_transfer(
+            H256::repeat_byte(0xee),
+            testing::TransferParams {
+                chain_id: 261,
+                ..Default::default()
+            },
+        );
+
+        let error = node
+            .send_raw_transaction(Bytes(tx.rlp_signed(&signature).to_vec()))
             .await
-            .expect("failed fetching transaction")
-            .expect("no transaction");
+            .expect_err("transaction for another chain should be rejected");
 
-        assert_eq!(input_tx_hash, actual_tx.hash);
-        assert_eq!(Some(U64::from(1)), actual_tx.block_number);
+        assert!(
+            error
+                .message
+                .contains("invalid chain id: expected 260, got 261"),
+            "unexpected error message: {}",
+            error.message
+        );
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_hash_and_index_fetches_from_fork_if_block_missing() {
-        let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: 10,
-            transaction_count: 0,
-            hash: H256::repeat_byte(0xab),
-        });
-        let input_block_hash = H256::repeat_byte(0x01);
-        let input_tx_hash = H256::repeat_byte(0x02);
-        mock_server.expect(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getTransactionByBlockHashAndIndex",
-                "params": [
-                    format!("{:#x}", input_block_hash),
-                    "0x1"
-                ],
-            }),
-            TransactionResponseBuilder::new()
-                .set_hash(input_tx_hash)
-                .set_block_hash(input_block_hash)
-                .set_block_number(U64::from(100))
-                .build(),
+    async fn test_send_raw_transaction_rejects_high_s_signature() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let (tx, signature) = testing::signed_transfer(H256::repeat_byte(0xee), Default::default());
+        // the complementary signature (n - s, with the flipped recovery id) is valid, but malleable
+        let curve_order = ethers::types::U256::from_str_radix(
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            16,
+        )
+        .unwrap();
+        let high_s_signature = ethers::types::Signature {
+            r: signature.r,
+            s: curve_order - signature.s,
+            v: if (signature.v - 35) % 2 == 0 {
+                signature.v + 1
+            } else {
+                signature.v - 1
+            },
+        };
+
+        let error = node
+            .send_raw_transaction(Bytes(tx.rlp_signed(&high_s_signature).to_vec()))
+            .await
+            .expect_err("malleable signature should be rejected");
+
+        assert!(
+            error
+                .message
+                .contains("invalid signature: `s` value is in the upper half of the curve order"),
+            "unexpected error message: {}",
+            error.message
         );
+        assert!(!error.message.contains("invalid chain id"));
+    }
 
-        let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
+    #[tokio::test]
+    async fn test_no_refunds_charges_the_whole_gas_limit() {
+        async fn charged_amount_and_gas_used(refunds_enabled: bool, tx: L2Tx) -> (U256, U256) {
+            let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+                .refunds_enabled(refunds_enabled)
+                .build();
+            let sender = tx.initiator_account();
+            node.set_rich_account(sender);
+
+            let balance_before = node
+                .get_balance(sender, None)
+                .await
+                .expect("failed fetching balance");
+            node.submit_tx(tx.clone()).expect("failed submitting tx");
+            let balance_after = node
+                .get_balance(sender, None)
+                .await
+                .expect("failed fetching balance");
+            let receipt = node
+                .get_transaction_receipt(tx.hash())
+                .await
+                .expect("failed fetching receipt")
+                .expect("receipt exists");
+
+            (
+                balance_before - balance_after,
+                receipt.gas_used.expect("gas used is set"),
+            )
+        }
+
+        let tx = testing::build_l2_tx(&H256::repeat_byte(0x1), Nonce(0));
+        let gas_limit = tx.common_data.fee.gas_limit;
+        let (base_fee, _) = derive_base_fee_and_gas_per_pubdata(L1_GAS_PRICE, L2_GAS_PRICE);
+        let base_fee = U256::from(base_fee);
+
+        let (charged_with_refunds, gas_used_with_refunds) =
+            charged_amount_and_gas_used(true, tx.clone()).await;
+        let (charged_without_refunds, gas_used_without_refunds) =
+            charged_amount_and_gas_used(false, tx.clone()).await;
+
+        assert!(gas_used_with_refunds < gas_limit);
+        assert_eq!(gas_limit, gas_used_without_refunds);
+        assert_eq!(
+            tx.execute.value + gas_limit * base_fee,
+            charged_without_refunds
+        );
+        assert_eq!(
+            (gas_limit - gas_used_with_refunds) * base_fee,
+            charged_without_refunds - charged_with_refunds
         );
+    }
 
-        let actual_tx = node
-            .get_transaction_by_block_hash_and_index(input_block_hash, U64::from(1))
-            .await
-            .expect("failed fetching transaction")
-            .expect("no transaction");
+    #[tokio::test]
+    async fn test_fees_are_credited_to_the_coinbase() {
+        async fn charged_fee_and_coinbase_balance(fee_accrual: bool, tx: L2Tx) -> (U256, U256) {
+            let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+                .fee_accrual(fee_accrual)
+                .build();
+            let coinbase = Address::repeat_byte(0xc0);
+            node.get_inner().write().unwrap().coinbase = coinbase;
+            let sender = tx.initiator_account();
+            node.set_rich_account(sender);
+
+            let balance_before = node
+                .get_balance(sender, None)
+                .await
+                .expect("failed fetching balance");
+            node.submit_tx(tx.clone()).expect("failed submitting tx");
+            let balance_after = node
+                .get_balance(sender, None)
+                .await
+                .expect("failed fetching balance");
+            let coinbase_balance = node
+                .get_balance(coinbase, None)
+                .await
+                .expect("failed fetching balance");
 
-        assert_eq!(input_tx_hash, actual_tx.hash);
-        assert_eq!(Some(U64::from(100)), actual_tx.block_number);
+            (
+                balance_before - balance_after - tx.execute.value,
+                coinbase_balance,
+            )
+        }
+
+        let tx = testing::build_l2_tx(&H256::repeat_byte(0x1), Nonce(0));
+
+        let (charged_fee, coinbase_balance) =
+            charged_fee_and_coinbase_balance(true, tx.clone()).await;
+        assert!(!charged_fee.is_zero());
+        assert_eq!(charged_fee, coinbase_balance);
+
+        let (charged_fee, coinbase_balance) = charged_fee_and_coinbase_balance(false, tx).await;
+        assert!(!charged_fee.is_zero());
+        assert_eq!(U256::zero(), coinbase_balance);
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_number_and_index_returns_none_for_invalid_block_number()
-    {
-        let node = InMemoryNode::<HttpForkSource>::default();
-        let input_tx_hash = H256::repeat_byte(0x01);
-        let (input_block_hash, _) = testing::apply_tx(&node, input_tx_hash);
-        let invalid_block_hash = H256::repeat_byte(0xab);
-        assert_ne!(input_block_hash, invalid_block_hash);
+    async fn test_max_pubdata_per_block_splits_transactions_across_blocks() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .max_pubdata_per_block(1)
+            .build();
+        let private_key = H256::repeat_byte(0x1);
+        let txs = (0..3)
+            .map(|nonce| testing::build_l2_tx(&private_key, Nonce(nonce)))
+            .collect::<Vec<_>>();
+        node.set_rich_account(txs[0].initiator_account());
+
+        let tx_count = node
+            .run_l2_txs(txs.clone(), TxExecutionMode::VerifyExecute)
+            .expect("failed running transactions");
+
+        assert_eq!(3, tx_count);
+        let inner = node.get_inner().read().unwrap();
+        let block_numbers = txs
+            .iter()
+            .map(|tx| inner.tx_results[&tx.hash()].info.miniblock_number)
+            .collect::<Vec<_>>();
+        // every transaction exceeds the limit on its own, and is followed by the empty end-of-batch block
+        assert_eq!(vec![1, 3, 5], block_numbers);
+    }
 
-        let result = node
-            .get_transaction_by_block_number_and_index(
-                BlockNumber::Number(U64::from(100)),
-                U64::from(0),
-            )
+    #[tokio::test]
+    async fn test_gas_price_rises_with_l1_gas_price() {
+        let high_l1_gas_price = L1_GAS_PRICE * 200;
+        let default_node = InMemoryNode::<HttpForkSource>::default();
+        let expensive_l1_node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .l1_gas_price(high_l1_gas_price)
+            .build();
+
+        let default_gas_price = default_node
+            .gas_price()
             .await
-            .expect("failed fetching transaction");
+            .expect("failed fetching gas price");
+        let expensive_l1_gas_price = expensive_l1_node
+            .gas_price()
+            .await
+            .expect("failed fetching gas price");
 
-        assert!(result.is_none());
+        assert_eq!(U256::from(L2_GAS_PRICE), default_gas_price);
+        assert!(expensive_l1_gas_price > default_gas_price);
+        let (expected_base_fee, _) =
+            derive_base_fee_and_gas_per_pubdata(high_l1_gas_price, L2_GAS_PRICE);
+        assert_eq!(U256::from(expected_base_fee), expensive_l1_gas_price);
+
+        // the mined blocks report the same base fee
+        expensive_l1_node.mine_block().expect("failed mining block");
+        let block = expensive_l1_node
+            .get_block_by_number(BlockNumber::Latest, false)
+            .await
+            .expect("failed fetching block")
+            .expect("block exists");
+        assert_eq!(expensive_l1_gas_price, block.base_fee_per_gas);
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_number_and_index_returns_none_for_invalid_index() {
+    async fn test_send_raw_transaction_accepts_access_list_and_eip1559_transactions() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let input_tx_hash = H256::repeat_byte(0x01);
-        testing::apply_tx(&node, input_tx_hash);
+        let private_key = H256::repeat_byte(0xee);
+        node.set_rich_account(
+            PackedEthSignature::address_from_private_key(&private_key)
+                .expect("failed generating address"),
+        );
 
-        let result = node
-            .get_transaction_by_block_number_and_index(BlockNumber::Latest, U64::from(10))
-            .await
-            .expect("failed fetching transaction");
+        for (nonce, expected_type) in [(0u64, 1u64), (1u64, 2u64)] {
+            let tx_hash = node
+                .send_raw_transaction(testing::raw_transfer(
+                    private_key,
+                    testing::TransferParams {
+                        gas_price: 500_000_000,
+                        nonce,
+                        tx_type: expected_type,
+                        ..Default::default()
+                    },
+                ))
+                .await
+                .expect("failed sending raw transaction");
 
-        assert!(result.is_none());
+            let receipt = node
+                .get_transaction_receipt(tx_hash)
+                .await
+                .expect("failed fetching receipt")
+                .expect("receipt exists");
+            assert_eq!(Some(U64::from(1)), receipt.status);
+            assert_eq!(Some(U64::from(expected_type)), receipt.transaction_type);
+
+            let transaction = node
+                .get_transaction_by_hash(tx_hash)
+                .await
+                .expect("failed fetching transaction")
+                .expect("transaction exists");
+            assert_eq!(Some(U64::from(expected_type)), transaction.transaction_type);
+        }
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_number_and_index_returns_transaction_for_valid_input() {
+    async fn test_get_transaction_by_hash_finds_pending_and_mined_transactions() {
         let node = InMemoryNode::<HttpForkSource>::default();
-        let input_tx_hash = H256::repeat_byte(0x01);
-        let (_, input_block_number) = testing::apply_tx(&node, input_tx_hash);
+        let private_key = H256::repeat_byte(0x1);
+        let sender = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(sender);
+        node.get_inner().write().unwrap().automine = false;
+        let tx = testing::build_l2_tx(&private_key, Nonce(0));
+        node.submit_tx(tx.clone()).expect("failed submitting tx");
 
-        let actual_tx = node
-            .get_transaction_by_block_number_and_index(
-                BlockNumber::Number(input_block_number),
-                U64::from(0),
-            )
+        let pending = node
+            .get_transaction_by_hash(tx.hash())
             .await
             .expect("failed fetching transaction")
-            .expect("no transaction");
+            .expect("pending transaction exists");
+        assert_eq!(tx.hash(), pending.hash);
+        assert_eq!(None, pending.block_hash);
+        assert_eq!(None, pending.block_number);
 
-        assert_eq!(input_tx_hash, actual_tx.hash);
+        node.mine_block().expect("failed mining block");
+
+        let mined = node
+            .get_transaction_by_hash(tx.hash())
+            .await
+            .expect("failed fetching transaction")
+            .expect("mined transaction exists");
+        let receipt = node
+            .get_transaction_receipt(tx.hash())
+            .await
+            .expect("failed fetching receipt")
+            .expect("receipt exists");
+        assert!(mined.block_number.is_some());
+        assert_eq!(receipt.block_number, mined.block_number);
+        assert_eq!(receipt.block_hash, mined.block_hash);
     }
 
     #[tokio::test]
-    async fn test_get_transaction_by_block_number_and_index_fetches_full_transaction_for_hash_from_fork(
-    ) {
-        let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: 10,
-            transaction_count: 0,
-            hash: H256::repeat_byte(0xab),
-        });
-        let input_block_hash = H256::repeat_byte(0x01);
-        let input_block_number = U64::from(100);
-        let input_tx_hash = H256::repeat_byte(0x02);
-        mock_server.expect(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getTransactionByHash",
-                "params": [
-                    format!("{:#x}", input_tx_hash),
-                ],
-            }),
-            TransactionResponseBuilder::new()
-                .set_hash(input_tx_hash)
-                .set_block_hash(input_block_hash)
-                .set_block_number(input_block_number)
-                .build(),
-        );
+    async fn test_automine_batch_window_mines_transactions_in_a_single_block() {
+        async fn receipt_block_numbers(automine_batch_window_ms: u64) -> Vec<U64> {
+            let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+                .automine_batch_window_ms(automine_batch_window_ms)
+                .build();
+            node.spawn_automine_batch_miner();
+            let private_key = H256::repeat_byte(0x1);
+            let txs = (0..3)
+                .map(|nonce| testing::build_l2_tx(&private_key, Nonce(nonce)))
+                .collect::<Vec<_>>();
+            node.set_rich_account(txs[0].initiator_account());
+            for tx in &txs {
+                node.submit_tx(tx.clone()).expect("failed submitting tx");
+            }
 
-        let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
+            let mut block_numbers = vec![];
+            for tx in &txs {
+                let mut receipt = None;
+                for _ in 0..500 {
+                    receipt = node
+                        .get_transaction_receipt(tx.hash())
+                        .await
+                        .expect("failed fetching receipt");
+                    if receipt.is_some() {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                let receipt = receipt.expect("transaction was not mined");
+                block_numbers.push(receipt.block_number.expect("block number is set"));
+            }
+            block_numbers
+        }
+
+        let batched = receipt_block_numbers(500).await;
+        assert_eq!(vec![batched[0]; 3], batched);
+
+        let unbatched = receipt_block_numbers(0).await;
+        assert_eq!(3, unbatched.iter().unique().count());
+    }
+
+    #[test]
+    fn test_state_dumper_writes_loadable_dumps_and_prunes_old_ones() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0x1);
+        let sender = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(sender);
+        node.apply_txs(vec![testing::build_l2_tx(&private_key, Nonce(0))])
+            .expect("failed applying tx");
+        let current_miniblock = node.get_inner().read().unwrap().current_miniblock;
+        let temp_dir = tempdir::TempDir::new("state").expect("failed creating temporary dir");
+
+        node.spawn_state_dumper(temp_dir.path().to_path_buf(), Duration::from_millis(20), 2);
+        let mut dumps = vec![];
+        for _ in 0..100 {
+            std::thread::sleep(Duration::from_millis(20));
+            dumps = state_dumps(temp_dir.path()).expect("failed listing dumps");
+            if dumps.len() == 2 {
+                break;
+            }
+        }
+        drop(node);
+        assert_eq!(2, dumps.len());
+
+        // the latest dump may still be being written
+        let oldest = dumps.first().expect("dump must exist");
+        let state: SerializableState =
+            serde_json::from_slice(&std::fs::read(oldest).expect("failed reading dump"))
+                .expect("failed parsing dump");
+        let loaded = InMemoryNode::<HttpForkSource>::default();
+        loaded
+            .get_inner()
+            .write()
+            .unwrap()
+            .load_state(state)
+            .expect("failed loading state");
+        assert_eq!(
+            current_miniblock,
+            loaded.get_inner().read().unwrap().current_miniblock
         );
+    }
 
-        // store the block info with just the tx hash invariant
+    #[test]
+    fn test_load_state_clears_values_read_from_the_fork() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let state = node
+            .get_inner()
+            .read()
+            .unwrap()
+            .dump_state()
+            .expect("failed dumping state");
+        let cached_key = StorageKey::new(AccountTreeId::new(H160::repeat_byte(0x1)), H256::zero());
         {
-            let mut writer = node.inner.write().unwrap();
-            writer
-                .block_hashes
-                .insert(input_block_number.as_u64(), input_block_hash);
-            writer.blocks.insert(
-                input_block_hash,
-                Block {
-                    transactions: vec![TransactionVariant::Hash(input_tx_hash)],
-                    ..Default::default()
-                },
-            );
+            let inner = node.get_inner().read().unwrap();
+            let mut storage = inner.fork_storage.inner.write().unwrap();
+            storage
+                .value_read_cache
+                .insert(cached_key, H256::repeat_byte(0xcd));
+            storage
+                .factory_dep_cache
+                .insert(H256::repeat_byte(0xcd), Some(vec![0xcd]));
         }
 
-        let actual_tx = node
-            .get_transaction_by_block_number_and_index(
-                BlockNumber::Number(input_block_number),
-                U64::from(0),
-            )
-            .await
-            .expect("failed fetching transaction")
-            .expect("no transaction");
+        node.get_inner()
+            .write()
+            .unwrap()
+            .load_state(state)
+            .expect("failed loading state");
 
-        assert_eq!(input_tx_hash, actual_tx.hash);
-        assert_eq!(Some(U64::from(input_block_number)), actual_tx.block_number);
+        let inner = node.get_inner().read().unwrap();
+        let storage = inner.fork_storage.inner.read().unwrap();
+        assert!(storage.value_read_cache.is_empty());
+        assert!(storage.factory_dep_cache.is_empty());
     }
 
-    #[tokio::test]
-    async fn test_get_transaction_by_block_number_and_index_fetches_from_fork_if_block_missing() {
-        let mock_server = MockServer::run_with_config(ForkBlockConfig {
-            number: 10,
-            transaction_count: 0,
-            hash: H256::repeat_byte(0xab),
-        });
-        let input_block_hash = H256::repeat_byte(0x01);
-        let input_block_number = U64::from(100);
-        let input_tx_hash = H256::repeat_byte(0x02);
-        mock_server.expect(
-            serde_json::json!({
-                "jsonrpc": "2.0",
-                "id": 0,
-                "method": "eth_getTransactionByBlockNumberAndIndex",
-                "params": [
-                    format!("{:#x}", input_block_number),
-                    "0x1"
-                ],
-            }),
-            TransactionResponseBuilder::new()
-                .set_hash(input_tx_hash)
-                .set_block_hash(input_block_hash)
-                .set_block_number(U64::from(input_block_number))
-                .build(),
+    #[test]
+    fn test_submit_tx_classifies_out_of_gas_and_validation_failures() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0x1);
+        let sender = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(sender);
+
+        let mut out_of_gas_tx = testing::build_l2_tx(&private_key, Nonce(0));
+        out_of_gas_tx.common_data.fee.gas_limit = U256::from(1_000);
+        let out_of_gas_err = node
+            .submit_tx(out_of_gas_tx)
+            .expect_err("transaction should have halted");
+
+        // signed by another account, so the sender's validation rejects the signature
+        let mut invalid_signature_tx = testing::build_l2_tx(&H256::repeat_byte(0x2), Nonce(0));
+        invalid_signature_tx.common_data.initiator_address = sender;
+        let validation_err = node
+            .submit_tx(invalid_signature_tx)
+            .expect_err("transaction should have halted");
+
+        assert!(
+            out_of_gas_err.starts_with(&format!("Transaction HALT: {}", TxFailureReason::OutOfGas)),
+            "unexpected error: {}",
+            out_of_gas_err
         );
+        assert!(
+            validation_err.starts_with(&format!(
+                "Transaction HALT: {}",
+                TxFailureReason::ValidationFailed
+            )),
+            "unexpected error: {}",
+            validation_err
+        );
+    }
 
-        let node = InMemoryNode::<HttpForkSource>::new(
-            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
-            crate::node::ShowCalls::None,
-            ShowStorageLogs::None,
-            ShowVMDetails::None,
-            ShowGasDetails::None,
-            false,
-            &system_contracts::Options::BuiltIn,
+    #[test]
+    fn test_validation_gas_limit_halts_transactions_overspending_in_validation() {
+        let private_key = H256::repeat_byte(0x1);
+        let submit_tx = |validation_gas_limit: u64| {
+            let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+                .validation_gas_limit(validation_gas_limit)
+                .build();
+            let tx = testing::build_l2_tx(&private_key, Nonce(0));
+            node.set_rich_account(tx.initiator_account());
+            node.submit_tx(tx)
+        };
+
+        let validation_err =
+            submit_tx(1).expect_err("transaction should have failed the validation");
+
+        assert!(
+            validation_err.starts_with(&format!(
+                "Transaction HALT: {}",
+                TxFailureReason::ValidationFailed
+            )),
+            "unexpected error: {}",
+            validation_err
         );
+        // the validation can never use more gas than the transaction's gas limit
+        submit_tx(1_000_000).expect("transaction should have been executed");
+    }
 
-        let actual_tx = node
-            .get_transaction_by_block_number_and_index(
-                BlockNumber::Number(input_block_number),
-                U64::from(1),
+    #[tokio::test]
+    async fn test_deterministic_addresses_do_not_depend_on_the_deployment_nonce() {
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        let deploy_contracts = |deployment_nonce: u64| async move {
+            let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+                .deterministic_addresses(true)
+                .build();
+            node.set_rich_account(from_account);
+            // as if the account had already deployed contracts in the forked state
+            node.get_inner()
+                .write()
+                .expect("failed acquiring write lock")
+                .fork_storage
+                .set_value(
+                    get_nonce_key(&from_account),
+                    u256_to_h256(nonces_to_full_nonce(
+                        U256::zero(),
+                        U256::from(deployment_nonce),
+                    )),
+                );
+
+            let mut addresses = vec![];
+            for nonce in 0..2 {
+                let tx_hash = H256::repeat_byte(nonce as u8 + 1);
+                testing::deploy_contract(
+                    &node,
+                    tx_hash,
+                    private_key,
+                    hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+                    None,
+                    Nonce(nonce),
+                );
+                let receipt = node
+                    .get_transaction_receipt(tx_hash)
+                    .await
+                    .expect("failed fetching receipt")
+                    .expect("receipt exists");
+                addresses.push(receipt.contract_address.expect("contract was deployed"));
+            }
+            addresses
+        };
+
+        let addresses = deploy_contracts(0).await;
+
+        assert_ne!(addresses[0], addresses[1]);
+        assert_eq!(addresses, deploy_contracts(0).await);
+        assert_eq!(addresses, deploy_contracts(5).await);
+    }
+
+    #[test]
+    fn test_storage_diff_includes_sender_and_recipient_balances() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx = testing::build_l2_tx(&H256::repeat_byte(0x1), Nonce(0));
+        let sender = tx.initiator_account();
+        let recipient = tx.recipient_account();
+        node.set_rich_account(sender);
+        node.submit_tx(tx.clone()).expect("failed submitting tx");
+
+        let inner = node.get_inner();
+        let inner = inner.read().expect("failed reading inner");
+        let storage_logs = &inner.tx_results[&tx.hash()].info.result.logs.storage_logs;
+        let accounts = [sender, recipient];
+        let diff = formatter::storage_diff(storage_logs)
+            .iter()
+            .map(|diff| formatter::format_storage_diff(diff, &accounts))
+            .collect::<Vec<_>>();
+
+        assert!(
+            diff.contains(&format!("Balance of {:?}: 0x0 -> 0x1", recipient)),
+            "unexpected storage diff: {:?}",
+            diff
+        );
+        assert!(
+            diff.iter()
+                .any(|line| line.starts_with(&format!("Balance of {:?}: ", sender))),
+            "unexpected storage diff: {:?}",
+            diff
+        );
+    }
+
+    #[tokio::test]
+    async fn test_receipt_reports_effective_gas_price_and_batch() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx = testing::build_l2_tx(&H256::repeat_byte(0x1), Nonce(0));
+        node.set_rich_account(tx.initiator_account());
+        node.submit_tx(tx.clone()).expect("failed submitting tx");
+
+        let receipt = node
+            .get_transaction_receipt(tx.hash())
+            .await
+            .expect("failed fetching receipt")
+            .expect("receipt exists");
+        let block = node
+            .get_block_by_number(
+                BlockNumber::Number(receipt.block_number.expect("block number is set")),
+                false,
             )
             .await
-            .expect("failed fetching transaction")
-            .expect("no transaction");
-
-        assert_eq!(input_tx_hash, actual_tx.hash);
-        assert_eq!(Some(input_block_number), actual_tx.block_number);
+            .expect("failed fetching block")
+            .expect("block exists");
+
+        let (base_fee, _) = derive_base_fee_and_gas_per_pubdata(L1_GAS_PRICE, L2_GAS_PRICE);
+        assert_eq!(Some(U256::from(base_fee)), receipt.effective_gas_price);
+        assert!(!receipt.effective_gas_price.unwrap().is_zero());
+        assert!(block.l1_batch_number.is_some());
+        assert_eq!(block.l1_batch_number, receipt.l1_batch_number);
+        assert_eq!(Some(U64::zero()), receipt.l1_batch_tx_index);
     }
 
     #[tokio::test]
-    async fn test_protocol_version_returns_currently_supported_version() {
+    async fn test_mined_block_reports_base_fee_gas_used_and_gas_limit() {
         let node = InMemoryNode::<HttpForkSource>::default();
+        let tx = testing::build_l2_tx(&H256::repeat_byte(0x1), Nonce(0));
+        node.set_rich_account(tx.initiator_account());
+        node.submit_tx(tx.clone()).expect("failed submitting tx");
+        let receipt = node
+            .get_transaction_receipt(tx.hash())
+            .await
+            .expect("failed fetching receipt")
+            .expect("receipt exists");
 
-        let expected_version = String::from(PROTOCOL_VERSION);
-        let actual_version = node
-            .protocol_version()
+        let block = node
+            .get_block_by_number(
+                BlockNumber::Number(receipt.block_number.expect("block number is set")),
+                false,
+            )
             .await
-            .expect("failed creating filter");
+            .expect("failed fetching block")
+            .expect("block exists");
+        // the transaction block is followed by the empty block of the end of its batch
+        let fee_history = node
+            .fee_history(U64::from(2), BlockNumber::Latest, vec![])
+            .await
+            .expect("failed fetching fee history");
 
-        assert_eq!(expected_version, actual_version);
+        assert_eq!(
+            web3::types::BlockNumber::Number(block.number),
+            fee_history.oldest_block
+        );
+        assert_eq!(fee_history.base_fee_per_gas[0], block.base_fee_per_gas);
+        assert_eq!(
+            block.gas_used.as_u64() as f64 / block.gas_limit.as_u64() as f64,
+            fee_history.gas_used_ratio[0]
+        );
+        assert_eq!(U256::from(BLOCK_GAS_LIMIT), block.gas_limit);
+        assert!(block.gas_used > U256::zero());
+        assert!(block.gas_used <= tx.common_data.fee.gas_limit);
     }
 }
@@ -0,0 +1,398 @@
+//! Pool of transactions that were submitted to the node, but were not executed yet.
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, HashMap, VecDeque},
+};
+
+use zksync_types::{l2::L2Tx, Address, Nonce, H256};
+
+/// Transactions of a single sender, ordered by their nonce.
+pub type TxsByNonce<'a> = BTreeMap<Nonce, &'a L2Tx>;
+
+/// The order in which the pending transactions of different senders are taken from the pool to be mined.
+/// The transactions of a single sender are always taken in the order of their nonce.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MempoolOrder {
+    /// In the order the transactions were submitted.
+    #[default]
+    Fifo,
+    /// Highest priority fee first, then in the order the transactions were submitted.
+    FeePriority,
+    /// Lowest nonce first, then highest priority fee.
+    NonceThenFee,
+}
+
+/// Holds the transactions waiting for execution, grouped by their sender and nonce.
+///
+/// A transaction is considered *pending* if it can be executed on top of the current state,
+/// i.e. its nonce follows the sender's account nonce without gaps (taking into account the
+/// sender's other pending transactions). All other transactions are *queued*.
+#[derive(Debug, Default, Clone)]
+pub struct TxPool {
+    txs: HashMap<Address, BTreeMap<Nonce, L2Tx>>,
+    /// The order in which the transactions were submitted, by their hash.
+    arrivals: HashMap<H256, u64>,
+    next_arrival: u64,
+}
+
+impl TxPool {
+    /// Adds a transaction to the pool. A previous transaction from the same sender with the same nonce
+    /// is replaced, and returned.
+    pub fn add(&mut self, tx: L2Tx) -> Option<L2Tx> {
+        self.arrivals.insert(tx.hash(), self.next_arrival);
+        self.next_arrival += 1;
+        let replaced = self
+            .txs
+            .entry(tx.initiator_account())
+            .or_default()
+            .insert(tx.nonce(), tx);
+        if let Some(replaced) = &replaced {
+            self.arrivals.remove(&replaced.hash());
+        }
+        replaced
+    }
+
+    /// Adds a transaction to a pool holding at most `max_size` transactions. Once the pool is full, the transaction
+    /// with the lowest priority fee is evicted (and returned) if the new one pays more, otherwise the new one is
+    /// rejected. Replacing a transaction with the same sender and nonce is always accepted.
+    pub fn add_bounded(&mut self, tx: L2Tx, max_size: usize) -> Result<Option<L2Tx>, String> {
+        let replaces = self
+            .txs
+            .get(&tx.initiator_account())
+            .map_or(false, |txs| txs.contains_key(&tx.nonce()));
+        if replaces || self.len() < max_size {
+            return Ok(self.add(tx));
+        }
+
+        let priority_fee = |tx: &L2Tx| tx.common_data.fee.max_priority_fee_per_gas;
+        let cheapest = self
+            .iter()
+            .min_by_key(|tx| priority_fee(tx))
+            .filter(|cheapest| priority_fee(cheapest) < priority_fee(&tx))
+            .map(|cheapest| cheapest.hash())
+            .ok_or_else(|| {
+                format!(
+                    "txpool is full: it holds the maximum of {} transactions",
+                    max_size
+                )
+            })?;
+        let evicted = self.remove(&cheapest);
+        self.add(tx);
+        Ok(evicted)
+    }
+
+    /// Removes the transaction with the given hash from the pool, and returns it.
+    pub fn remove(&mut self, hash: &H256) -> Option<L2Tx> {
+        let (sender, nonce) = self
+            .iter()
+            .find(|tx| tx.hash() == *hash)
+            .map(|tx| (tx.initiator_account(), tx.nonce()))?;
+        let txs = self.txs.get_mut(&sender)?;
+        let removed = txs.remove(&nonce);
+        if txs.is_empty() {
+            self.txs.remove(&sender);
+        }
+        self.arrivals.remove(hash);
+        removed
+    }
+
+    /// Removes and returns all the transactions, in the order they were submitted - regardless of their nonce.
+    pub fn take_all(&mut self) -> Vec<L2Tx> {
+        let mut txs = self
+            .txs
+            .drain()
+            .flat_map(|(_, txs)| txs.into_values())
+            .collect::<Vec<_>>();
+        let arrivals = std::mem::take(&mut self.arrivals);
+        txs.sort_by_key(|tx| arrivals.get(&tx.hash()).copied().unwrap_or_default());
+        txs
+    }
+
+    /// Returns the total number of transactions in the pool.
+    pub fn len(&self) -> usize {
+        self.txs.values().map(|txs| txs.len()).sum()
+    }
+
+    /// Returns `true` if the pool holds no transactions.
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    /// Returns the transaction with the given hash, if it is in the pool.
+    pub fn get(&self, hash: &H256) -> Option<&L2Tx> {
+        self.txs
+            .values()
+            .flat_map(|txs| txs.values())
+            .find(|tx| tx.hash() == *hash)
+    }
+
+    /// Returns all the transactions in the pool, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &L2Tx> {
+        self.txs.values().flat_map(|txs| txs.values())
+    }
+
+    /// Returns the addresses of all the senders with transactions in the pool.
+    pub fn senders(&self) -> impl Iterator<Item = &Address> {
+        self.txs.keys()
+    }
+
+    /// Returns the number of pending transactions of a sender, i.e. the ones that follow its account nonce
+    /// without gaps.
+    pub fn pending_count(&self, sender: &Address, account_nonce: Nonce) -> usize {
+        let txs = match self.txs.get(sender) {
+            Some(txs) => txs,
+            None => return 0,
+        };
+        let mut next_nonce = account_nonce;
+        while txs.contains_key(&next_nonce) {
+            next_nonce = Nonce(next_nonce.0.saturating_add(1));
+        }
+        (next_nonce.0 - account_nonce.0) as usize
+    }
+
+    /// Splits the transactions in the pool into `(pending, queued)`, grouped by sender.
+    ///
+    /// # Arguments
+    ///
+    /// * `account_nonce` - Returns the current account nonce of a sender.
+    pub fn split<F>(
+        &self,
+        account_nonce: F,
+    ) -> (
+        HashMap<Address, TxsByNonce<'_>>,
+        HashMap<Address, TxsByNonce<'_>>,
+    )
+    where
+        F: Fn(&Address) -> Nonce,
+    {
+        let mut pending: HashMap<Address, TxsByNonce<'_>> = Default::default();
+        let mut queued: HashMap<Address, TxsByNonce<'_>> = Default::default();
+
+        for (sender, txs) in self.txs.iter() {
+            let mut next_nonce = account_nonce(sender);
+            for (nonce, tx) in txs.iter() {
+                let target = if *nonce == next_nonce {
+                    next_nonce = Nonce(next_nonce.0.saturating_add(1));
+                    &mut pending
+                } else {
+                    &mut queued
+                };
+                target.entry(*sender).or_default().insert(*nonce, tx);
+            }
+        }
+
+        (pending, queued)
+    }
+
+    /// Removes the pending transactions from the pool, and returns them in the order they should be executed.
+    /// Transactions with a nonce lower than the sender's account nonce can never be executed and are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `order` - The order of the transactions of different senders.
+    /// * `account_nonce` - Returns the current account nonce of a sender.
+    pub fn take_pending<F>(&mut self, order: MempoolOrder, account_nonce: F) -> Vec<L2Tx>
+    where
+        F: Fn(&Address) -> Nonce,
+    {
+        let mut pending_by_sender = vec![];
+        for (sender, txs) in self.txs.iter_mut() {
+            let mut next_nonce = account_nonce(sender);
+            let arrivals = &mut self.arrivals;
+            txs.retain(|nonce, tx| {
+                if *nonce < next_nonce {
+                    log::warn!(
+                        "dropping transaction {:#x} from the pool: nonce {} is too low",
+                        tx.hash(),
+                        nonce.0
+                    );
+                    arrivals.remove(&tx.hash());
+                }
+                *nonce >= next_nonce
+            });
+
+            let mut pending = VecDeque::new();
+            while let Some(tx) = txs.remove(&next_nonce) {
+                pending.push_back(tx);
+                next_nonce = Nonce(next_nonce.0.saturating_add(1));
+            }
+            if !pending.is_empty() {
+                pending_by_sender.push(pending);
+            }
+        }
+        self.txs.retain(|_, txs| !txs.is_empty());
+
+        // repeatedly take the first transaction among the next transactions of each sender
+        let arrival = |tx: &L2Tx| self.arrivals.get(&tx.hash()).copied().unwrap_or_default();
+        let compare = |a: &L2Tx, b: &L2Tx| -> Ordering {
+            let priority_fee = |tx: &L2Tx| Reverse(tx.common_data.fee.max_priority_fee_per_gas);
+            match order {
+                MempoolOrder::Fifo => arrival(a).cmp(&arrival(b)),
+                MempoolOrder::FeePriority => {
+                    (priority_fee(a), arrival(a)).cmp(&(priority_fee(b), arrival(b)))
+                }
+                MempoolOrder::NonceThenFee => (a.nonce(), priority_fee(a), arrival(a)).cmp(&(
+                    b.nonce(),
+                    priority_fee(b),
+                    arrival(b),
+                )),
+            }
+        };
+        let mut pending = vec![];
+        while let Some(next) = pending_by_sender
+            .iter()
+            .enumerate()
+            .filter_map(|(index, txs)| txs.front().map(|tx| (index, tx)))
+            .min_by(|(_, a), (_, b)| compare(*a, *b))
+            .map(|(index, _)| index)
+        {
+            if let Some(tx) = pending_by_sender[next].pop_front() {
+                pending.push(tx);
+            }
+            if pending_by_sender[next].is_empty() {
+                pending_by_sender.swap_remove(next);
+            }
+        }
+        for tx in &pending {
+            self.arrivals.remove(&tx.hash());
+        }
+
+        pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use zksync_basic_types::{H256, U256};
+
+    #[test]
+    fn test_split_separates_pending_from_nonce_gapped_transactions() {
+        let private_key = H256::repeat_byte(0x1);
+        let mut pool = TxPool::default();
+        for nonce in [0, 1, 3] {
+            pool.add(testing::build_l2_tx(&private_key, Nonce(nonce)));
+        }
+        let sender = pool.senders().next().copied().expect("sender must exist");
+
+        let (pending, queued) = pool.split(|_| Nonce(0));
+
+        assert_eq!(
+            vec![Nonce(0), Nonce(1)],
+            pending[&sender].keys().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Nonce(3)],
+            queued[&sender].keys().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_take_pending_removes_only_executable_transactions() {
+        let private_key = H256::repeat_byte(0x1);
+        let mut pool = TxPool::default();
+        for nonce in [0, 1, 2, 4] {
+            pool.add(testing::build_l2_tx(&private_key, Nonce(nonce)));
+        }
+
+        let pending = pool.take_pending(MempoolOrder::Fifo, |_| Nonce(1));
+
+        assert_eq!(
+            vec![Nonce(1), Nonce(2)],
+            pending.iter().map(|tx| tx.nonce()).collect::<Vec<_>>()
+        );
+        assert_eq!(1, pool.len());
+    }
+
+    #[test]
+    fn test_take_all_returns_every_transaction_in_submission_order() {
+        let private_key = H256::repeat_byte(0x1);
+        let mut pool = TxPool::default();
+        for nonce in [4, 0, 2] {
+            pool.add(testing::build_l2_tx(&private_key, Nonce(nonce)));
+        }
+
+        let txs = pool.take_all();
+
+        assert_eq!(
+            vec![Nonce(4), Nonce(0), Nonce(2)],
+            txs.iter().map(|tx| tx.nonce()).collect::<Vec<_>>()
+        );
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_take_pending_orders_senders_by_fee_priority() {
+        let build_tx = |private_key: &H256, max_priority_fee_per_gas: u64| {
+            let mut tx = testing::build_l2_tx(private_key, Nonce(0));
+            tx.common_data.fee.max_priority_fee_per_gas = U256::from(max_priority_fee_per_gas);
+            tx
+        };
+        let low_fee_tx = build_tx(&H256::repeat_byte(0x1), 1);
+        let high_fee_tx = build_tx(&H256::repeat_byte(0x2), 2);
+
+        let take_pending = |order: MempoolOrder| {
+            let mut pool = TxPool::default();
+            pool.add(low_fee_tx.clone());
+            pool.add(high_fee_tx.clone());
+            pool.take_pending(order, |_| Nonce(0))
+                .iter()
+                .map(|tx| tx.hash())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![low_fee_tx.hash(), high_fee_tx.hash()],
+            take_pending(MempoolOrder::Fifo)
+        );
+        assert_eq!(
+            vec![high_fee_tx.hash(), low_fee_tx.hash()],
+            take_pending(MempoolOrder::FeePriority)
+        );
+    }
+
+    #[test]
+    fn test_take_pending_keeps_the_nonce_order_of_a_sender() {
+        let private_key = H256::repeat_byte(0x1);
+        let mut pool = TxPool::default();
+        let mut low_fee_tx = testing::build_l2_tx(&private_key, Nonce(0));
+        low_fee_tx.common_data.fee.max_priority_fee_per_gas = U256::from(1);
+        let mut high_fee_tx = testing::build_l2_tx(&private_key, Nonce(1));
+        high_fee_tx.common_data.fee.max_priority_fee_per_gas = U256::from(2);
+        pool.add(high_fee_tx);
+        pool.add(low_fee_tx);
+
+        let pending = pool.take_pending(MempoolOrder::FeePriority, |_| Nonce(0));
+
+        assert_eq!(
+            vec![Nonce(0), Nonce(1)],
+            pending.iter().map(|tx| tx.nonce()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_add_bounded_rejects_or_evicts_once_full() {
+        let build_tx = |private_key: &H256, max_priority_fee_per_gas: u64| {
+            let mut tx = testing::build_l2_tx(private_key, Nonce(0));
+            tx.common_data.fee.max_priority_fee_per_gas = U256::from(max_priority_fee_per_gas);
+            tx
+        };
+        let cheapest_tx = build_tx(&H256::repeat_byte(0x1), 2);
+        let mut pool = TxPool::default();
+        pool.add_bounded(cheapest_tx.clone(), 2)
+            .expect("failed adding tx");
+        pool.add_bounded(build_tx(&H256::repeat_byte(0x2), 3), 2)
+            .expect("failed adding tx");
+
+        let rejected = pool.add_bounded(build_tx(&H256::repeat_byte(0x3), 1), 2);
+        let evicted = pool
+            .add_bounded(build_tx(&H256::repeat_byte(0x4), 4), 2)
+            .expect("failed adding tx");
+
+        assert!(rejected.unwrap_err().contains("txpool is full"));
+        assert_eq!(Some(cheapest_tx.hash()), evicted.map(|tx| tx.hash()));
+        assert_eq!(2, pool.len());
+        assert!(pool.get(&cheapest_tx.hash()).is_none());
+    }
+}
@@ -0,0 +1,190 @@
+//! Recording of the incoming JSON-RPC requests to a file, and replaying them into a node - to reproduce a client
+//! session deterministically.
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::future::{self, Either};
+use futures::Future;
+use jsonrpc_core::{middleware, Call, Metadata, Middleware, Output, Params};
+use serde::{Deserialize, Serialize};
+
+use crate::rpc_filter::method_matches;
+
+/// A single recorded JSON-RPC request - stored as a line of JSON in the request log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecordedRequest {
+    /// Unix timestamp (in milliseconds) of when the request was received.
+    pub timestamp: u64,
+    pub method: String,
+    pub params: Params,
+    /// If true - the params of the request were not recorded.
+    #[serde(default)]
+    pub redacted: bool,
+}
+
+/// Appends every incoming JSON-RPC request to a file. Does nothing if no file is set.
+///
+/// The params of the methods matching the redacted patterns - either by their full name (`eth_sendRawTransaction`),
+/// or by their namespace (`eth_*`) - are not recorded.
+#[derive(Clone, Debug, Default)]
+pub struct RequestRecorder {
+    file: Option<Arc<Mutex<File>>>,
+    redact: Vec<String>,
+}
+
+impl RequestRecorder {
+    /// Creates a recorder appending to the file at the given path.
+    pub fn new(path: &Path, redact: Vec<String>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Some(Arc::new(Mutex::new(file))),
+            redact,
+        })
+    }
+
+    fn record(&self, method: &str, params: &Params) {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return,
+        };
+        let redacted = self
+            .redact
+            .iter()
+            .any(|pattern| method_matches(pattern, method));
+        let request = RecordedRequest {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            method: method.to_string(),
+            params: if redacted {
+                Params::None
+            } else {
+                params.clone()
+            },
+            redacted,
+        };
+
+        let line = match serde_json::to_string(&request) {
+            Ok(line) => line,
+            Err(err) => {
+                log::error!("failed serializing {} request: {}", method, err);
+                return;
+            }
+        };
+        match file.lock() {
+            Ok(mut file) => {
+                if let Err(err) = writeln!(file, "{}", line) {
+                    log::error!("failed recording {} request: {}", method, err);
+                }
+            }
+            Err(err) => log::error!("failed acquiring request log lock: {}", err),
+        }
+    }
+}
+
+/// Request Recording Middleware for all in-bound calls
+impl<M: Metadata> Middleware<M> for RequestRecorder {
+    type Future = middleware::NoopFuture;
+    type CallFuture = future::Ready<Option<Output>>;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: FnOnce(Call, M) -> X + Send,
+        X: Future<Output = Option<Output>> + Send + 'static,
+    {
+        match &call {
+            Call::MethodCall(method_call) => self.record(&method_call.method, &method_call.params),
+            Call::Notification(notification) => {
+                self.record(&notification.method, &notification.params)
+            }
+            Call::Invalid { .. } => {}
+        }
+        Either::Right(next(call, meta))
+    }
+}
+
+/// Reads the requests recorded by a [RequestRecorder].
+pub fn read_requests(path: &Path) -> anyhow::Result<Vec<RecordedRequest>> {
+    let file = File::open(path)?;
+    BufReader::new(file)
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| -> anyhow::Result<RecordedRequest> { Ok(serde_json::from_str(&line?)?) })
+        .collect()
+}
+
+/// Replays the requests recorded in the given file in order, by sending them to the node at `url`.
+/// Requests with redacted params are skipped, and failing requests are logged - neither stops the replay.
+///
+/// Returns the number of replayed requests.
+pub async fn replay_requests(path: &Path, url: &str) -> anyhow::Result<usize> {
+    let client = reqwest::Client::new();
+    let mut replayed = 0;
+    for (id, request) in read_requests(path)?.into_iter().enumerate() {
+        if request.redacted {
+            log::warn!("skipping {} request with redacted params", request.method);
+            continue;
+        }
+
+        let response = client
+            .post(url)
+            .header("content-type", "application/json")
+            .body(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": request.method,
+                    "params": request.params,
+                })
+                .to_string(),
+            )
+            .send()
+            .await?
+            .text()
+            .await?;
+        let response: serde_json::Value = serde_json::from_str(&response)?;
+        if let Some(error) = response.get("error") {
+            log::warn!("replayed {} request failed: {}", request.method, error);
+        }
+        replayed += 1;
+    }
+
+    Ok(replayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_redacts_params_of_matching_methods() {
+        let temp_dir = tempdir::TempDir::new("requests").expect("failed creating temporary dir");
+        let path = temp_dir.path().join("requests.jsonl");
+        let recorder = RequestRecorder::new(&path, vec!["eth_send*".to_string()])
+            .expect("failed creating recorder");
+
+        recorder.record("eth_chainId", &Params::Array(vec![]));
+        recorder.record(
+            "eth_sendRawTransaction",
+            &Params::Array(vec![serde_json::json!("0x00")]),
+        );
+
+        let requests = read_requests(&path).expect("failed reading requests");
+        assert_eq!(
+            vec![
+                ("eth_chainId".to_string(), Params::Array(vec![]), false),
+                ("eth_sendRawTransaction".to_string(), Params::None, true),
+            ],
+            requests
+                .into_iter()
+                .map(|request| (request.method, request.params, request.redacted))
+                .collect::<Vec<_>>()
+        );
+    }
+}
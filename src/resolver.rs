@@ -230,6 +230,24 @@ pub async fn decode_function_selector(selector: &str) -> eyre::Result<Option<Str
     result
 }
 
+/// Returns the signature for a function (or custom error) selector that is already known - either built-in,
+/// registered, or resolved before - without contacting sig.eth.samczsun.com
+pub async fn known_function_selector(selector: &str) -> Option<String> {
+    {
+        let cache = CACHE.read().await;
+        if let Some(Some(result)) = cache.get(selector) {
+            return Some(result.clone());
+        }
+    }
+    KNOWN_SIGNATURES.get(selector).cloned()
+}
+
+/// Registers the signature for a function (or custom error) selector, so it can be decoded locally
+pub async fn register_function_selector(selector: &str, signature: &str) {
+    let mut cache = CACHE.write().await;
+    cache.insert(selector.to_string(), Some(signature.to_string()));
+}
+
 pub async fn decode_event_selector(selector: &str) -> eyre::Result<Option<String>> {
     {
         let cache = CACHE.read().await;
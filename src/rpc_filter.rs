@@ -0,0 +1,84 @@
+use futures::future::{self, Either};
+use futures::Future;
+use jsonrpc_core::{middleware, Call, Error, Metadata, Middleware, Output};
+
+/// Restricts the JSON-RPC methods that can be called, e.g. to prevent state manipulation on a shared node.
+///
+/// Methods are matched either by their full name (`hardhat_setBalance`), or by their namespace (`hardhat_*`).
+#[derive(Clone, Debug, Default)]
+pub struct RpcMethodFilter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl RpcMethodFilter {
+    /// Creates a filter from the allowed and denied method patterns.
+    /// An empty `allow` list allows all the methods that are not denied.
+    pub fn new(allow: Vec<String>, deny: Vec<String>) -> Self {
+        Self { allow, deny }
+    }
+
+    /// Returns true if the given method can be called.
+    pub fn allows(&self, method: &str) -> bool {
+        let matches = |pattern: &String| method_matches(pattern, method);
+
+        (self.allow.is_empty() || self.allow.iter().any(matches)) && !self.deny.iter().any(matches)
+    }
+}
+
+/// Returns true if the method matches the pattern - either its full name (`hardhat_setBalance`), or its
+/// namespace (`hardhat_*`).
+pub fn method_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => method.starts_with(prefix),
+        None => pattern == method,
+    }
+}
+
+/// Method Filtering Middleware for all in-bound calls
+/// Calls to methods that are not allowed fail with a `method not found` error, as if the method did not exist
+impl<M: Metadata> Middleware<M> for RpcMethodFilter {
+    type Future = middleware::NoopFuture;
+    type CallFuture = future::Ready<Option<Output>>;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: FnOnce(Call, M) -> X + Send,
+        X: Future<Output = Option<Output>> + Send + 'static,
+    {
+        match &call {
+            Call::MethodCall(method_call) if !self.allows(&method_call.method) => {
+                log::warn!("{} is not allowed", method_call.method);
+                Either::Left(future::ready(Some(Output::from(
+                    Err(Error::method_not_found()),
+                    method_call.id.clone(),
+                    method_call.jsonrpc,
+                ))))
+            }
+            Call::Notification(notification) if !self.allows(&notification.method) => {
+                log::warn!("{} is not allowed", notification.method);
+                Either::Left(future::ready(None))
+            }
+            _ => Either::Right(next(call, meta)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_matches_methods_and_namespaces() {
+        let filter = RpcMethodFilter::new(
+            vec!["eth_*".to_string(), "hardhat_*".to_string()],
+            vec!["hardhat_setBalance".to_string()],
+        );
+
+        assert!(filter.allows("eth_chainId"));
+        assert!(filter.allows("hardhat_mine"));
+        assert!(!filter.allows("hardhat_setBalance"));
+        assert!(!filter.allows("evm_snapshot"));
+        assert!(RpcMethodFilter::default().allows("evm_snapshot"));
+    }
+}
@@ -10,18 +10,23 @@ use crate::{fork::ForkSource, node::compute_hash};
 
 use ethers::contract;
 use httptest::{
-    matchers::{eq, json_decoded, request},
-    responders::json_encoded,
+    all_of,
+    matchers::{contains, eq, json_decoded, request},
+    responders::{cycle, delay_and_then, json_encoded, Responder},
     Expectation, Server,
 };
 use itertools::Itertools;
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::{Mutex, Once},
+    time::Duration,
+};
 use vm::VmExecutionResultAndLogs;
 use zksync_basic_types::{H160, U64};
 use zksync_types::api::{DebugCall, DebugCallType, Log};
 use zksync_types::{
-    fee::Fee, l2::L2Tx, Address, L2ChainId, Nonce, PackedEthSignature, ProtocolVersionId, H256,
-    U256,
+    fee::Fee, l2::L2Tx, Address, Bytes, L2ChainId, Nonce, PackedEthSignature, ProtocolVersionId,
+    H256, U256,
 };
 
 /// Configuration for the [MockServer]'s initial block.
@@ -159,6 +164,54 @@ impl MockServer {
                 .respond_with(json_encoded(response)),
         );
     }
+
+    /// Assert an exactly single call expectation with a given request sent with the given header (in lowercase),
+    /// and the provided response.
+    pub fn expect_with_header(
+        &self,
+        header: (&'static str, &'static str),
+        request: serde_json::Value,
+        response: serde_json::Value,
+    ) {
+        self.inner.expect(
+            Expectation::matching(all_of![
+                request::headers(contains(header)),
+                request::body(json_decoded(eq(request))),
+            ])
+            .respond_with(json_encoded(response)),
+        );
+    }
+
+    /// Assert an exactly single call expectation with a given request, responding after the given `delay`.
+    pub fn expect_with_delay(
+        &self,
+        request: serde_json::Value,
+        response: serde_json::Value,
+        delay: Duration,
+    ) {
+        self.inner.expect(
+            Expectation::matching(request::body(json_decoded(eq(request))))
+                .respond_with(delay_and_then(delay, json_encoded(response))),
+        );
+    }
+
+    /// Assert a call expectation with a given request for each of the provided responses, which are returned in order.
+    pub fn expect_with_responses(
+        &self,
+        request: serde_json::Value,
+        responses: Vec<serde_json::Value>,
+    ) {
+        let times = responses.len();
+        let responders = responses
+            .into_iter()
+            .map(|response| Box::new(json_encoded(response)) as Box<dyn Responder>)
+            .collect();
+        self.inner.expect(
+            Expectation::matching(request::body(json_decoded(eq(request))))
+                .times(times)
+                .respond_with(cycle(responders)),
+        );
+    }
 }
 
 /// A mock response builder for a block
@@ -396,6 +449,129 @@ pub fn apply_tx<T: ForkSource + std::fmt::Debug>(
     (produced_block_hash, U64::from(next_miniblock))
 }
 
+/// Builds a signed transaction from the account of `private_key` with the given `nonce`.
+pub fn build_l2_tx(private_key: &H256, nonce: Nonce) -> L2Tx {
+    let mut tx = L2Tx::new_signed(
+        Address::random(),
+        vec![],
+        nonce,
+        Fee {
+            gas_limit: U256::from(1_000_000),
+            max_fee_per_gas: U256::from(250_000_000),
+            max_priority_fee_per_gas: U256::from(250_000_000),
+            gas_per_pubdata_limit: U256::from(20000),
+        },
+        U256::from(1),
+        L2ChainId(260),
+        private_key,
+        None,
+        Default::default(),
+    )
+    .unwrap();
+    tx.set_input(vec![], H256::random());
+    tx
+}
+
+/// The fields of a transfer signed by [signed_transfer]. Defaults to a legacy transfer of 1 wei on the default chain.
+#[derive(Debug, Clone)]
+pub struct TransferParams {
+    pub chain_id: u64,
+    pub to: Address,
+    pub value: u64,
+    pub gas: u64,
+    /// The gas price, or the max fee per gas of an EIP-1559 transaction.
+    pub gas_price: u64,
+    pub nonce: u64,
+    /// The calldata of the transaction, for calling a contract.
+    pub data: Vec<u8>,
+    /// The EIP-2718 type of the transaction - `0` (legacy), `1` (EIP-2930) or `2` (EIP-1559).
+    pub tx_type: u64,
+}
+
+impl Default for TransferParams {
+    fn default() -> Self {
+        Self {
+            chain_id: 260,
+            to: Address::repeat_byte(0x1),
+            value: 1,
+            gas: 3_000_000,
+            gas_price: 250_000_000,
+            nonce: 0,
+            data: vec![],
+            tx_type: 0,
+        }
+    }
+}
+
+/// Signs a transfer from the account of `private_key`, and returns the signature with the signed transaction.
+pub fn signed_transfer(
+    private_key: H256,
+    params: TransferParams,
+) -> (
+    ethers::types::transaction::eip2718::TypedTransaction,
+    ethers::types::Signature,
+) {
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::{
+            transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+            Eip1559TransactionRequest, Eip2930TransactionRequest, TransactionRequest,
+        },
+    };
+
+    let wallet = LocalWallet::from_bytes(private_key.as_bytes())
+        .expect("failed creating wallet")
+        .with_chain_id(params.chain_id);
+    let to = ethers::types::Address::from_slice(params.to.as_bytes());
+    let tx: TypedTransaction = match params.tx_type {
+        0 => TransactionRequest::new()
+            .from(wallet.address())
+            .to(to)
+            .value(params.value)
+            .gas(params.gas)
+            .gas_price(params.gas_price)
+            .nonce(params.nonce)
+            .data(params.data)
+            .chain_id(params.chain_id)
+            .into(),
+        1 => Eip2930TransactionRequest::new(
+            TransactionRequest::new()
+                .from(wallet.address())
+                .to(to)
+                .value(params.value)
+                .gas(params.gas)
+                .gas_price(params.gas_price)
+                .nonce(params.nonce)
+                .data(params.data)
+                .chain_id(params.chain_id),
+            AccessList::default(),
+        )
+        .into(),
+        2 => Eip1559TransactionRequest::new()
+            .from(wallet.address())
+            .to(to)
+            .value(params.value)
+            .gas(params.gas)
+            .max_fee_per_gas(params.gas_price)
+            .max_priority_fee_per_gas(0)
+            .nonce(params.nonce)
+            .data(params.data)
+            .chain_id(params.chain_id)
+            .into(),
+        tx_type => panic!("unsupported transaction type {}", tx_type),
+    };
+    let signature = wallet
+        .sign_transaction_sync(&tx)
+        .expect("failed signing tx");
+    (tx, signature)
+}
+
+/// Returns the RLP encoding of a transfer signed by [signed_transfer], as accepted by `eth_sendRawTransaction`.
+pub fn raw_transfer(private_key: H256, params: TransferParams) -> Bytes {
+    let (tx, signature) = signed_transfer(private_key, params);
+    Bytes(tx.rlp_signed(&signature).to_vec())
+}
+
 /// Deploys a contract with the given bytecode.
 pub fn deploy_contract<T: ForkSource + std::fmt::Debug>(
     node: &InMemoryNode<T>,
@@ -404,6 +580,98 @@ pub fn deploy_contract<T: ForkSource + std::fmt::Debug>(
     bytecode: Vec<u8>,
     calldata: Option<Vec<u8>>,
     nonce: Nonce,
+) -> H256 {
+    deploy_contract_with(
+        node,
+        "create",
+        H256::zero(),
+        tx_hash,
+        private_key,
+        bytecode,
+        calldata,
+        nonce,
+    )
+}
+
+/// Deploys the `Secondary` test contract (whose `multiply(value)` returns `value * 2`), and the `Primary` one (whose
+/// `calculate(value)` returns the `multiply(value)` of the `Secondary`) - returning their addresses.
+pub fn deploy_test_contracts<T: ForkSource + std::fmt::Debug>(
+    node: &InMemoryNode<T>,
+) -> (Address, Address) {
+    use crate::deps::system_contracts::bytecode_from_slice;
+    use ethers::abi::AbiEncode;
+    use zksync_types::utils::deployed_address_create;
+
+    let private_key = H256::repeat_byte(0xee);
+    let from_account = PackedEthSignature::address_from_private_key(&private_key)
+        .expect("failed generating address");
+    node.set_rich_account(from_account);
+
+    // first, deploy secondary contract
+    let secondary_bytecode = bytecode_from_slice(
+        "Secondary",
+        include_bytes!("deps/test-contracts/Secondary.json"),
+    );
+    let secondary_deployed_address = deployed_address_create(from_account, U256::zero());
+    deploy_contract(
+        node,
+        H256::repeat_byte(0x1),
+        private_key,
+        secondary_bytecode,
+        Some((U256::from(2),).encode()),
+        Nonce(0),
+    );
+
+    // deploy primary contract using the secondary contract address as a constructor parameter
+    let primary_bytecode = bytecode_from_slice(
+        "Primary",
+        include_bytes!("deps/test-contracts/Primary.json"),
+    );
+    let primary_deployed_address = deployed_address_create(from_account, U256::one());
+    deploy_contract(
+        node,
+        H256::repeat_byte(0x1),
+        private_key,
+        primary_bytecode,
+        Some((secondary_deployed_address).encode()),
+        Nonce(1),
+    );
+    (primary_deployed_address, secondary_deployed_address)
+}
+
+/// Deploys a contract with the given bytecode using `CREATE2` with the given salt.
+pub fn deploy_contract_create2<T: ForkSource + std::fmt::Debug>(
+    node: &InMemoryNode<T>,
+    salt: H256,
+    tx_hash: H256,
+    private_key: H256,
+    bytecode: Vec<u8>,
+    calldata: Option<Vec<u8>>,
+    nonce: Nonce,
+) -> H256 {
+    deploy_contract_with(
+        node,
+        "create2",
+        salt,
+        tx_hash,
+        private_key,
+        bytecode,
+        calldata,
+        nonce,
+    )
+}
+
+/// Deploys a contract by calling the given `ContractDeployer` method (`create` or `create2`).
+#[allow(clippy::too_many_arguments)]
+fn deploy_contract_with<T: ForkSource + std::fmt::Debug>(
+    node: &InMemoryNode<T>,
+    method: &str,
+    salt: H256,
+    tx_hash: H256,
+    private_key: H256,
+    bytecode: Vec<u8>,
+    calldata: Option<Vec<u8>>,
+    nonce: Nonce,
 ) -> H256 {
     use ethers::abi::Function;
     use ethers::types::Bytes;
@@ -416,10 +684,10 @@ pub fn deploy_contract<T: ForkSource + std::fmt::Debug>(
         .expect("failed getting current batch number");
     let produced_block_hash = compute_hash(next_miniblock, tx_hash);
 
-    let salt = [0u8; 32];
+    let salt = salt.to_fixed_bytes();
     let bytecode_hash = eip712::hash_bytecode(&bytecode).expect("invalid bytecode");
     let call_data: Bytes = calldata.unwrap_or_default().into();
-    let create: Function = serde_json::from_str(
+    let mut create: Function = serde_json::from_str(
         r#"{
             "inputs": [
               {
@@ -451,6 +719,7 @@ pub fn deploy_contract<T: ForkSource + std::fmt::Debug>(
           }"#,
     )
     .unwrap();
+    create.name = method.to_string();
 
     let data = contract::encode_function_data(&create, (salt, bytecode_hash, call_data))
         .expect("failed encoding function data");
@@ -599,6 +868,85 @@ pub fn default_tx_debug_info() -> DebugCall {
     }
 }
 
+/// The lines captured for each marker passed to [capture_logs], that are still being captured.
+static CAPTURED_LOGS: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(Vec::new());
+
+/// A logger storing the logged lines containing a captured marker in [CAPTURED_LOGS].
+struct CapturingLogger;
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        if let Ok(mut captures) = CAPTURED_LOGS.lock() {
+            if captures.is_empty() {
+                return;
+            }
+            let line = record.args().to_string();
+            for (marker, lines) in captures.iter_mut() {
+                if line.contains(marker.as_str()) {
+                    lines.push(line.clone());
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The lines captured by [capture_logs]. The capture stops, and the lines are dropped, when it is dropped.
+pub struct LogCapture {
+    marker: String,
+}
+
+impl LogCapture {
+    /// Returns the lines logged since the capture started that contain its marker.
+    pub fn lines(&self) -> Vec<String> {
+        CAPTURED_LOGS
+            .lock()
+            .ok()
+            .and_then(|captures| {
+                captures
+                    .iter()
+                    .find(|(marker, _)| *marker == self.marker)
+                    .map(|(_, lines)| lines.clone())
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for LogCapture {
+    fn drop(&mut self) {
+        if let Ok(mut captures) = CAPTURED_LOGS.lock() {
+            if let Some(index) = captures
+                .iter()
+                .position(|(marker, _)| *marker == self.marker)
+            {
+                captures.remove(index);
+            }
+        }
+    }
+}
+
+/// Starts capturing the logged lines that contain `marker`, which should be unique to the calling test - as the
+/// tests run concurrently, and share the logger.
+pub fn capture_logs(marker: impl Into<String>) -> LogCapture {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        log::set_logger(&CapturingLogger).expect("failed setting logger");
+        log::set_max_level(log::LevelFilter::Info);
+    });
+
+    let marker = marker.into();
+    CAPTURED_LOGS
+        .lock()
+        .expect("failed acquiring captured logs lock")
+        .push((marker.clone(), vec![]));
+    LogCapture { marker }
+}
+
 mod test {
     use super::*;
     use crate::http_fork_source::HttpForkSource;
@@ -0,0 +1,216 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
+
+use crate::{fork::ForkSource, node::InMemoryNodeInner, pool::TxsByNonce, utils};
+use jsonrpc_core::{BoxFuture, Result};
+use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{Address, U64};
+use zksync_core::api_server::web3::backend_jsonrpc::error::into_jsrpc_error;
+use zksync_types::api::Transaction;
+use zksync_web3_decl::error::Web3Error;
+
+/// Transactions grouped by their sender address and nonce (as a decimal string), mirroring Geth's format.
+pub type TxsBySenderAndNonce = BTreeMap<Address, BTreeMap<String, Transaction>>;
+
+/// Content of the transaction pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxPoolContent {
+    /// Transactions that are ready to be executed.
+    pub pending: TxsBySenderAndNonce,
+    /// Transactions that are waiting for a nonce gap to be filled.
+    pub queued: TxsBySenderAndNonce,
+}
+
+/// Number of transactions in the transaction pool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxPoolStatus {
+    /// Number of transactions that are ready to be executed.
+    pub pending: U64,
+    /// Number of transactions that are waiting for a nonce gap to be filled.
+    pub queued: U64,
+}
+
+/// Implementation of TxPoolNamespace
+pub struct TxPoolNamespaceImpl<S> {
+    node: Arc<RwLock<InMemoryNodeInner<S>>>,
+}
+
+impl<S> TxPoolNamespaceImpl<S> {
+    /// Creates a new `TxPool` instance with the given `node`.
+    pub fn new(node: Arc<RwLock<InMemoryNodeInner<S>>>) -> Self {
+        Self { node }
+    }
+}
+
+#[rpc]
+pub trait TxPoolNamespaceT {
+    /// Returns the transactions that were submitted to the node, but were not executed yet.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the `pending` and `queued` transactions,
+    /// grouped by their sender and nonce.
+    #[rpc(name = "txpool_content")]
+    fn content(&self) -> BoxFuture<Result<TxPoolContent>>;
+
+    /// Returns the number of transactions that were submitted to the node, but were not executed yet.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the number of `pending` and `queued` transactions.
+    #[rpc(name = "txpool_status")]
+    fn status(&self) -> BoxFuture<Result<TxPoolStatus>>;
+}
+
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> TxPoolNamespaceT
+    for TxPoolNamespaceImpl<S>
+{
+    fn content(&self) -> BoxFuture<Result<TxPoolContent>> {
+        let inner = Arc::clone(&self.node);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            let (pending, queued) = reader.pool.split(|sender| reader.get_account_nonce(sender));
+
+            Ok(TxPoolContent {
+                pending: to_api_transactions(pending),
+                queued: to_api_transactions(queued),
+            })
+        })
+    }
+
+    fn status(&self) -> BoxFuture<Result<TxPoolStatus>> {
+        let inner = Arc::clone(&self.node);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            let (pending, queued) = reader.pool.split(|sender| reader.get_account_nonce(sender));
+            let count = |txs: HashMap<Address, TxsByNonce<'_>>| {
+                U64::from(txs.values().map(|txs| txs.len()).sum::<usize>())
+            };
+
+            Ok(TxPoolStatus {
+                pending: count(pending),
+                queued: count(queued),
+            })
+        })
+    }
+}
+
+fn to_api_transactions<'a>(
+    txs: impl IntoIterator<Item = (Address, TxsByNonce<'a>)>,
+) -> TxsBySenderAndNonce {
+    txs.into_iter()
+        .map(|(sender, txs)| {
+            let txs = txs
+                .into_iter()
+                .map(|(nonce, tx)| (nonce.0.to_string(), utils::pending_api_transaction(tx)))
+                .collect();
+            (sender, txs)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode, testing};
+    use zksync_basic_types::H256;
+    use zksync_types::{Nonce, PackedEthSignature};
+
+    #[tokio::test]
+    async fn test_content_groups_transactions_by_pending_and_queued() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let txpool = TxPoolNamespaceImpl::new(node.get_inner());
+        node.get_inner().write().unwrap().automine = false;
+
+        let private_key = H256::repeat_byte(0x1);
+        let sender = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        let executable_tx = testing::build_l2_tx(&private_key, Nonce(0));
+        let gapped_tx = testing::build_l2_tx(&private_key, Nonce(2));
+        node.submit_tx(executable_tx.clone())
+            .expect("failed submitting tx");
+        node.submit_tx(gapped_tx.clone())
+            .expect("failed submitting tx");
+
+        let content = txpool.content().await.expect("failed getting content");
+
+        assert_eq!(
+            vec![("0".to_string(), executable_tx.hash())],
+            content.pending[&sender]
+                .iter()
+                .map(|(nonce, tx)| (nonce.clone(), tx.hash))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![("2".to_string(), gapped_tx.hash())],
+            content.queued[&sender]
+                .iter()
+                .map(|(nonce, tx)| (nonce.clone(), tx.hash))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_content_with_automine_queues_only_nonce_gapped_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let txpool = TxPoolNamespaceImpl::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0x1);
+        let sender = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(sender);
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(0)))
+            .expect("failed submitting tx");
+        node.submit_tx(testing::build_l2_tx(&private_key, Nonce(2)))
+            .expect("failed submitting tx");
+
+        let content = txpool.content().await.expect("failed getting content");
+
+        assert!(content.pending.is_empty());
+        assert_eq!(
+            vec!["2".to_string()],
+            content.queued[&sender].keys().cloned().collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_counts_pending_and_queued_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let txpool = TxPoolNamespaceImpl::new(node.get_inner());
+        node.get_inner().write().unwrap().automine = false;
+
+        let private_key = H256::repeat_byte(0x1);
+        for nonce in [0, 1, 3, 5] {
+            node.submit_tx(testing::build_l2_tx(&private_key, Nonce(nonce)))
+                .expect("failed submitting tx");
+        }
+        let other_private_key = H256::repeat_byte(0x2);
+        node.submit_tx(testing::build_l2_tx(&other_private_key, Nonce(0)))
+            .expect("failed submitting tx");
+
+        let status = txpool.status().await.expect("failed getting status");
+        let content = txpool.content().await.expect("failed getting content");
+
+        assert_eq!(U64::from(3), status.pending);
+        assert_eq!(U64::from(2), status.queued);
+        assert_eq!(
+            status.pending.as_usize(),
+            content.pending.values().map(|txs| txs.len()).sum::<usize>()
+        );
+        assert_eq!(
+            status.queued.as_usize(),
+            content.queued.values().map(|txs| txs.len()).sum::<usize>()
+        );
+    }
+}
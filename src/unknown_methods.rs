@@ -0,0 +1,154 @@
+use std::sync::{Arc, RwLock};
+
+use futures::future::{self, Either};
+use futures::Future;
+use jsonrpc_core::{middleware, Call, Error, Metadata, Middleware, Output};
+
+/// Answers the calls to methods that are not registered with a `Method not found` error naming the method.
+///
+/// In strict mode, the error data additionally holds the closest known method - to help spotting typos.
+#[derive(Clone, Debug)]
+pub struct UnknownMethodHandler {
+    strict: bool,
+    known_methods: Arc<RwLock<Vec<String>>>,
+}
+
+impl UnknownMethodHandler {
+    /// Creates a handler that does not know any method yet - see [UnknownMethodHandler::set_known_methods].
+    pub fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            known_methods: Default::default(),
+        }
+    }
+
+    /// Sets the registered methods, once all of them were added to the handler of the requests.
+    pub fn set_known_methods(&self, methods: impl IntoIterator<Item = String>) {
+        match self.known_methods.write() {
+            Ok(mut known_methods) => *known_methods = methods.into_iter().collect(),
+            Err(err) => log::error!("failed acquiring known methods lock: {}", err),
+        }
+    }
+
+    /// Returns the error for the given method, or `None` if it is registered.
+    fn error(&self, method: &str) -> Option<Error> {
+        let known_methods = match self.known_methods.read() {
+            Ok(known_methods) => known_methods,
+            Err(err) => {
+                log::error!("failed acquiring known methods lock: {}", err);
+                return None;
+            }
+        };
+        if known_methods.iter().any(|known| known == method) {
+            return None;
+        }
+
+        let mut error = Error::method_not_found();
+        error.message = format!("Method not found: {}", method);
+        if self.strict {
+            error.data = known_methods
+                .iter()
+                .min_by_key(|known| edit_distance(known, method))
+                .map(|closest| serde_json::json!(format!("did you mean {}?", closest)));
+        }
+        Some(error)
+    }
+}
+
+/// Returns the Levenshtein distance between two method names.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_diagonal + usize::from(a_char != *b_char);
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(distances[j + 1] + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+/// Unknown Method Middleware for all in-bound calls
+impl<M: Metadata> Middleware<M> for UnknownMethodHandler {
+    type Future = middleware::NoopFuture;
+    type CallFuture = future::Ready<Option<Output>>;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: FnOnce(Call, M) -> X + Send,
+        X: Future<Output = Option<Output>> + Send + 'static,
+    {
+        match &call {
+            Call::MethodCall(method_call) => match self.error(&method_call.method) {
+                Some(error) => {
+                    log::warn!("{} does not exist", method_call.method);
+                    Either::Left(future::ready(Some(Output::from(
+                        Err(error),
+                        method_call.id.clone(),
+                        method_call.jsonrpc,
+                    ))))
+                }
+                None => Either::Right(next(call, meta)),
+            },
+            Call::Notification(notification) if self.error(&notification.method).is_some() => {
+                log::warn!("{} does not exist", notification.method);
+                Either::Left(future::ready(None))
+            }
+            _ => Either::Right(next(call, meta)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpc_core::{ErrorCode, MetaIoHandler, Value};
+
+    fn handle(strict: bool, method: &str) -> serde_json::Value {
+        let handler = UnknownMethodHandler::new(strict);
+        let mut io = MetaIoHandler::<(), _>::with_middleware(handler.clone());
+        io.add_method("eth_chainId", |_| async { Ok(Value::from("0x104")) });
+        handler.set_known_methods(io.iter().map(|(name, _)| name.clone()));
+
+        let response = io
+            .handle_request_sync(
+                &serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": []})
+                    .to_string(),
+                (),
+            )
+            .expect("no response");
+        serde_json::from_str(&response).expect("failed parsing response")
+    }
+
+    #[test]
+    fn test_unknown_method_is_reported_by_name() {
+        let response = handle(false, "eth_chainID");
+
+        assert_eq!(
+            serde_json::json!(ErrorCode::MethodNotFound.code()),
+            response["error"]["code"]
+        );
+        assert_eq!(
+            serde_json::json!("Method not found: eth_chainID"),
+            response["error"]["message"]
+        );
+        assert_eq!(serde_json::Value::Null, response["error"]["data"]);
+        assert_eq!(
+            serde_json::json!("0x104"),
+            handle(false, "eth_chainId")["result"]
+        );
+    }
+
+    #[test]
+    fn test_strict_mode_suggests_the_closest_method() {
+        let response = handle(true, "eth_chainID");
+
+        assert_eq!(
+            serde_json::json!("did you mean eth_chainId?"),
+            response["error"]["data"]
+        );
+    }
+}
@@ -1,20 +1,25 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::pin::Pin;
+use std::str::FromStr;
+
+use coins_bip32::path::DerivationPath;
+use coins_bip39::{English, Mnemonic};
 
 use futures::Future;
-use vm::{ExecutionResult, VmExecutionResultAndLogs};
+use vm::{ExecutionResult, Halt, VmExecutionResultAndLogs};
 use vm::{HistoryDisabled, Vm};
-use zksync_basic_types::{U256, U64};
+use zksync_basic_types::{Address, H256, U256, U64};
 use zksync_state::StorageView;
 use zksync_state::WriteStorage;
-use zksync_types::api::{BlockNumber, DebugCall, DebugCallType};
+use zksync_types::api::{self, BlockNumber, DebugCall, DebugCallType};
 use zksync_types::l2::L2Tx;
 use zksync_types::vm_trace::Call;
-use zksync_types::CONTRACT_DEPLOYER_ADDRESS;
+use zksync_types::{PackedEthSignature, CONTRACT_DEPLOYER_ADDRESS};
 use zksync_utils::u256_to_h256;
 use zksync_web3_decl::error::Web3Error;
 
-use crate::node::create_empty_block;
+use crate::node::{create_empty_block, BlockContext};
 use crate::{fork::ForkSource, node::InMemoryNodeInner};
 use vm::utils::fee::derive_base_fee_and_gas_per_pubdata;
 
@@ -65,6 +70,31 @@ pub fn adjust_l1_gas_price_for_tx(
     }
 }
 
+/// The mnemonic that Hardhat derives its default dev accounts from.
+pub const DEFAULT_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+/// Derives `count` dev accounts (address, private key) from the `mnemonic`, along the BIP-44
+/// `m/44'/60'/0'/0/i` path - the same way as Hardhat does.
+pub fn derive_dev_accounts(mnemonic: &str, count: u32) -> Result<Vec<(Address, H256)>, String> {
+    let mnemonic = Mnemonic::<English>::new_from_phrase(mnemonic)
+        .map_err(|err| format!("invalid mnemonic: {}", err))?;
+
+    (0..count)
+        .map(|index| {
+            let path = DerivationPath::from_str(&format!("m/44'/60'/0'/0/{}", index))
+                .map_err(|err| format!("invalid derivation path: {}", err))?;
+            let derived_key = mnemonic
+                .derive_key(path, None)
+                .map_err(|err| format!("failed deriving key #{}: {}", index, err))?;
+            let signing_key: &coins_bip32::prelude::SigningKey = derived_key.as_ref();
+            let private_key = H256::from_slice(&signing_key.to_bytes());
+            let address = PackedEthSignature::address_from_private_key(&private_key)
+                .map_err(|err| format!("failed deriving address #{}: {}", index, err))?;
+            Ok((address, private_key))
+        })
+        .collect()
+}
+
 /// Takes long integers and returns them in human friendly format with "_".
 /// For example: 12_334_093
 pub fn to_human_size(input: U256) -> String {
@@ -97,25 +127,56 @@ pub fn bytecode_to_factory_dep(bytecode: Vec<u8>) -> (U256, Vec<U256>) {
 /// The blocks will be empty (contain no transactions).
 /// Currently this is quite slow - as we invoke the VM for each operation, in the future we might want to optimise it
 /// by adding a way to set state via some system contract call.
+///
+/// With [InMemoryNodeInner::empty_seal_fast], only the last block is sealed by the VM: the preceding ones are
+/// synthesized - they are the same as regular empty blocks, and only their L2 block is started in the system
+/// context, without running the bootloader.
+///
+/// Fails if the bootloader halts while sealing a block - the blocks before it are kept.
 pub fn mine_empty_blocks<S: std::fmt::Debug + ForkSource>(
     node: &mut InMemoryNodeInner<S>,
     num_blocks: u64,
     interval_ms: u64,
-) {
+) -> Result<(), String> {
+    // the balances overridden for the next block expire with it, even if it is empty
+    if num_blocks > 0 {
+        node.next_block_balances.clear();
+    }
+
     // build and insert new blocks
     for i in 0..num_blocks {
-        // roll the vm
-        let (keys, bytecodes, block_ctx) = {
+        // the timestamp follows the interval for subsequent blocks, and the block time for the first one - unless
+        // it was set explicitly
+        let timestamp = if i != 0 {
+            node.current_timestamp.saturating_add(interval_ms)
+        } else if !node.next_block_timestamp_set {
+            node.current_timestamp.saturating_add(node.block_time)
+        } else {
+            node.current_timestamp.saturating_add(1)
+        };
+
+        let (keys, bytecodes, block_ctx) = if node.empty_seal_fast && i + 1 < num_blocks {
+            // synthesize the block - the state is rolled by the last block only
+            let mut block_ctx = BlockContext::from_current(
+                node.current_batch,
+                node.current_miniblock,
+                node.current_timestamp,
+            )
+            .new_batch();
+            block_ctx.timestamp = timestamp;
+            node.start_system_context_l2_block(block_ctx.miniblock, block_ctx.timestamp);
+            (HashMap::new(), HashMap::new(), block_ctx)
+        } else {
+            // roll the vm
             let storage = StorageView::new(&node.fork_storage).to_rc_ptr();
 
             // system_contract.contracts_for_l2_call() will give playground contracts
             // we need these to use the unsafeOverrideBlock method in SystemContext.sol
             let bootloader_code = node.system_contracts.contracts_for_l2_call();
-            let (batch_env, mut block_ctx) = node.create_l1_batch_env(storage.clone());
-            // override the next block's timestamp to match up with interval for subsequent blocks
-            if i != 0 {
-                block_ctx.timestamp = node.current_timestamp.saturating_add(interval_ms);
-            }
+            let (mut batch_env, mut block_ctx) = node.create_l1_batch_env(storage.clone());
+            block_ctx.timestamp = timestamp;
+            batch_env.timestamp = block_ctx.timestamp;
+            batch_env.first_l2_block.timestamp = block_ctx.timestamp;
 
             // init vm
             let system_env =
@@ -123,7 +184,13 @@ pub fn mine_empty_blocks<S: std::fmt::Debug + ForkSource>(
 
             let mut vm = Vm::new(batch_env, system_env, storage.clone(), HistoryDisabled);
 
-            vm.execute(vm::VmExecutionMode::Bootloader);
+            let result = vm.execute(vm::VmExecutionMode::Bootloader);
+            if let ExecutionResult::Halt { reason } = result.result {
+                return Err(format!(
+                    "failed sealing empty block #{}: {}",
+                    block_ctx.miniblock, reason
+                ));
+            }
 
             let bytecodes: HashMap<U256, Vec<U256>> = vm
                 .get_last_tx_compressed_bytecodes()
@@ -134,6 +201,15 @@ pub fn mine_empty_blocks<S: std::fmt::Debug + ForkSource>(
             (modified_keys, bytecodes, block_ctx)
         };
 
+        // archive the state of the latest block before it gets modified
+        if let Err(err) = node.archive_state() {
+            log::error!(
+                "failed archiving state for block {}: {}",
+                node.current_miniblock,
+                err
+            );
+        }
+
         for (key, value) in keys.iter() {
             node.fork_storage.set_value(*key, *value);
         }
@@ -152,16 +228,24 @@ pub fn mine_empty_blocks<S: std::fmt::Debug + ForkSource>(
             )
         }
 
-        let block = create_empty_block(block_ctx.miniblock, block_ctx.timestamp, block_ctx.batch);
+        let mut block =
+            create_empty_block(block_ctx.miniblock, block_ctx.timestamp, block_ctx.batch);
+        block.author = node.coinbase;
+        block.base_fee_per_gas = U256::from(node.base_fee());
 
+        node.current_miniblock_hash = block.hash;
         node.block_hashes.insert(block.number.as_u64(), block.hash);
+        node.filters.notify_new_block(block.hash);
         node.blocks.insert(block.hash, block);
 
         // leave node state ready for next interaction
         node.current_batch = block_ctx.batch;
         node.current_miniblock = block_ctx.miniblock;
         node.current_timestamp = block_ctx.timestamp;
+        node.next_block_timestamp_set = false;
     }
+
+    Ok(())
 }
 
 /// Returns the actual [U64] block number from [BlockNumber].
@@ -198,7 +282,63 @@ pub fn not_implemented<T: Send + 'static>(
     .into_boxed_future()
 }
 
+/// Classification of the reason a transaction failed, to tell apart the most common causes of failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxFailureReason {
+    /// The transaction ran out of gas during its execution.
+    OutOfGas,
+    /// The bootloader ran out of gas while processing the transaction.
+    BootloaderOutOfGas,
+    /// The account (or paymaster) validation of the transaction failed.
+    ValidationFailed,
+    /// The transaction reverted, or halted, for any other reason.
+    Reverted,
+}
+
+impl TxFailureReason {
+    /// Classifies the result of a transaction, using its call traces to detect the calls that ran out of gas.
+    /// Returns `None` if the transaction succeeded.
+    pub fn from_execution_result(result: &ExecutionResult, calls: &[DebugCall]) -> Option<Self> {
+        match result {
+            ExecutionResult::Success { .. } => None,
+            ExecutionResult::Revert { .. } => {
+                if calls.iter().any(ran_out_of_gas) {
+                    Some(TxFailureReason::OutOfGas)
+                } else {
+                    Some(TxFailureReason::Reverted)
+                }
+            }
+            ExecutionResult::Halt { reason } => Some(match reason {
+                Halt::BootloaderOutOfGas => TxFailureReason::BootloaderOutOfGas,
+                Halt::NotEnoughGasProvided => TxFailureReason::OutOfGas,
+                Halt::ValidationFailed(_)
+                | Halt::PaymasterValidationFailed(_)
+                | Halt::PrePaymasterPreparationFailed(_)
+                | Halt::FromIsNotAnAccount => TxFailureReason::ValidationFailed,
+                _ => TxFailureReason::Reverted,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for TxFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxFailureReason::OutOfGas => write!(f, "out of gas"),
+            TxFailureReason::BootloaderOutOfGas => write!(f, "bootloader out of gas"),
+            TxFailureReason::ValidationFailed => write!(f, "account validation failed"),
+            TxFailureReason::Reverted => write!(f, "execution reverted"),
+        }
+    }
+}
+
+/// Returns true if the call, or any of its subcalls, failed after using up all of its gas.
+fn ran_out_of_gas(call: &DebugCall) -> bool {
+    (call.error.is_some() && call.gas_used >= call.gas) || call.calls.iter().any(ran_out_of_gas)
+}
+
 /// Creates a [DebugCall] from a [L2Tx], [VmExecutionResultAndLogs] and a list of [Call]s.
+/// The `error` of a failed transaction is set to its [TxFailureReason].
 pub fn create_debug_output(
     l2_tx: &L2Tx,
     result: &VmExecutionResultAndLogs,
@@ -209,6 +349,8 @@ pub fn create_debug_output(
     } else {
         DebugCallType::Call
     };
+    let calls: Vec<DebugCall> = traces.into_iter().map(Into::into).collect();
+    let failure_reason = TxFailureReason::from_execution_result(&result.result, &calls);
     match &result.result {
         ExecutionResult::Success { output } => Ok(DebugCall {
             gas_used: result.statistics.gas_used.into(),
@@ -221,7 +363,7 @@ pub fn create_debug_output(
             input: l2_tx.execute.calldata().into(),
             error: None,
             revert_reason: None,
-            calls: traces.into_iter().map(Into::into).collect(),
+            calls,
         }),
         ExecutionResult::Revert { output } => Ok(DebugCall {
             gas_used: result.statistics.gas_used.into(),
@@ -232,22 +374,72 @@ pub fn create_debug_output(
             gas: l2_tx.common_data.fee.gas_limit,
             value: l2_tx.execute.value,
             input: l2_tx.execute.calldata().into(),
-            error: None,
+            error: failure_reason.map(|reason| reason.to_string()),
             revert_reason: Some(output.to_string()),
-            calls: traces.into_iter().map(Into::into).collect(),
+            calls,
         }),
         ExecutionResult::Halt { reason } => Err(Web3Error::SubmitTransactionError(
-            reason.to_string(),
+            format!(
+                "{}: {}",
+                failure_reason.unwrap_or(TxFailureReason::Reverted),
+                reason
+            ),
             vec![],
         )),
     }
 }
 
+/// Returns the EIP-2718 type of the [L2Tx], e.g. `2` for EIP-1559 or `113` for EIP-712 transactions.
+pub fn transaction_type(l2_tx: &L2Tx) -> U64 {
+    let tx_type = match l2_tx.common_data.transaction_type {
+        zksync_types::l2::TransactionType::LegacyTransaction => 0,
+        zksync_types::l2::TransactionType::EIP2930Transaction => 1,
+        zksync_types::l2::TransactionType::EIP1559Transaction => 2,
+        zksync_types::l2::TransactionType::EIP712Transaction => 113,
+        zksync_types::l2::TransactionType::PriorityOpTransaction => 255,
+        zksync_types::l2::TransactionType::ProtocolUpgradeTransaction => 254,
+    };
+    U64::from(tx_type)
+}
+
+/// Creates an [api::Transaction] for a [L2Tx] that was not included in a block yet.
+pub fn pending_api_transaction(l2_tx: &L2Tx) -> api::Transaction {
+    let chain_id = l2_tx.common_data.extract_chain_id().unwrap_or_default();
+    api::Transaction {
+        hash: l2_tx.hash(),
+        nonce: U256::from(l2_tx.common_data.nonce.0),
+        block_hash: None,
+        block_number: None,
+        transaction_index: None,
+        from: Some(l2_tx.initiator_account()),
+        to: Some(l2_tx.recipient_account()),
+        value: l2_tx.execute.value,
+        gas_price: l2_tx.common_data.fee.max_fee_per_gas,
+        gas: l2_tx.common_data.fee.gas_limit,
+        input: l2_tx.execute.calldata().into(),
+        v: Some(chain_id.into()),
+        r: Some(U256::zero()),
+        s: Some(U256::zero()),
+        raw: None,
+        transaction_type: Some(transaction_type(l2_tx)),
+        access_list: None,
+        max_fee_per_gas: Some(l2_tx.common_data.fee.max_fee_per_gas),
+        max_priority_fee_per_gas: Some(l2_tx.common_data.fee.max_priority_fee_per_gas),
+        chain_id: chain_id.into(),
+        l1_batch_number: None,
+        l1_batch_tx_index: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use zksync_basic_types::{H256, U256};
 
-    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode, testing};
+    use crate::{
+        http_fork_source::HttpForkSource,
+        node::{InMemoryNode, InMemoryNodeBuilder},
+        testing,
+    };
 
     use super::*;
 
@@ -317,7 +509,7 @@ mod tests {
 
         {
             let mut writer = inner.write().expect("failed acquiring write lock");
-            mine_empty_blocks(&mut writer, 1, 1000);
+            mine_empty_blocks(&mut writer, 1, 1000).expect("failed mining empty blocks");
         }
 
         let reader = inner.read().expect("failed acquiring reader");
@@ -351,7 +543,7 @@ mod tests {
 
         {
             let mut writer = inner.write().expect("failed acquiring write lock");
-            mine_empty_blocks(&mut writer, 2, 1000);
+            mine_empty_blocks(&mut writer, 2, 1000).expect("failed mining empty blocks");
         }
 
         let reader = inner.read().expect("failed acquiring reader");
@@ -394,7 +586,7 @@ mod tests {
 
         {
             let mut writer = inner.write().expect("failed acquiring write lock");
-            mine_empty_blocks(&mut writer, 2, 1000);
+            mine_empty_blocks(&mut writer, 2, 1000).expect("failed mining empty blocks");
         }
 
         {
@@ -431,4 +623,134 @@ mod tests {
             assert_eq!(U256::from(2002), tx_block_3.timestamp);
         }
     }
+
+    /// Mines `num_blocks` empty blocks on a new node, and returns the serialized blocks along with the time it took.
+    fn mine_empty_blocks_timed(
+        empty_seal_fast: bool,
+        num_blocks: u64,
+    ) -> (Vec<serde_json::Value>, std::time::Duration) {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .empty_seal_fast(empty_seal_fast)
+            .build();
+        let inner = node.get_inner();
+        let mut writer = inner.write().expect("failed acquiring write lock");
+
+        let started = std::time::Instant::now();
+        mine_empty_blocks(&mut writer, num_blocks, 1000).expect("failed mining empty blocks");
+        let elapsed = started.elapsed();
+
+        let blocks = (1..=num_blocks)
+            .map(|number| {
+                let block = writer
+                    .block_hashes
+                    .get(&number)
+                    .and_then(|hash| writer.blocks.get(hash))
+                    .expect("failed finding block");
+                serde_json::to_value(block).expect("failed serializing block")
+            })
+            .collect();
+        (blocks, elapsed)
+    }
+
+    #[test]
+    fn test_mine_empty_blocks_with_empty_seal_fast_produces_the_same_blocks() {
+        let (sealed_blocks, _) = mine_empty_blocks_timed(false, 5);
+        let (fast_blocks, _) = mine_empty_blocks_timed(true, 5);
+
+        assert_eq!(5, fast_blocks.len());
+        assert_eq!(sealed_blocks, fast_blocks);
+    }
+
+    /// Benchmark of [InMemoryNodeInner::empty_seal_fast] - run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_mine_empty_blocks_with_empty_seal_fast() {
+        let (_, sealed) = mine_empty_blocks_timed(false, 200);
+        let (_, fast) = mine_empty_blocks_timed(true, 200);
+
+        println!(
+            "mining 200 empty blocks: {:?} sealed, {:?} with empty-seal-fast ({:.1}x)",
+            sealed,
+            fast,
+            sealed.as_secs_f64() / fast.as_secs_f64()
+        );
+        assert!(fast < sealed);
+    }
+
+    #[test]
+    fn test_derive_dev_accounts_matches_hardhat_accounts() {
+        let accounts = derive_dev_accounts(DEFAULT_MNEMONIC, 2).expect("failed deriving accounts");
+
+        assert_eq!(
+            vec![
+                (
+                    Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266").unwrap(),
+                    H256::from_str(
+                        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcd6e2df4a6b5f96e6"
+                    )
+                    .unwrap()
+                ),
+                (
+                    Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap(),
+                    H256::from_str(
+                        "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+                    )
+                    .unwrap()
+                ),
+            ],
+            accounts
+        );
+    }
+
+    #[test]
+    fn test_tx_failure_reason_classifies_out_of_gas_calls_and_halts() {
+        let revert = ExecutionResult::Revert {
+            output: vm::VmRevertReason::Unknown {
+                function_selector: vec![],
+                data: vec![],
+            },
+        };
+        let mut out_of_gas_trace = testing::default_tx_debug_info();
+        out_of_gas_trace.calls[0].gas = U256::from(100);
+        out_of_gas_trace.calls[0].gas_used = U256::from(100);
+        out_of_gas_trace.calls[0].error = Some("Panic".to_string());
+        let mut reverted_trace = testing::default_tx_debug_info();
+        reverted_trace.calls[0].gas = U256::from(100);
+        reverted_trace.calls[0].gas_used = U256::from(10);
+        reverted_trace.calls[0].revert_reason = Some("reverted".to_string());
+
+        assert_eq!(
+            None,
+            TxFailureReason::from_execution_result(
+                &ExecutionResult::Success { output: vec![] },
+                &[]
+            )
+        );
+        assert_eq!(
+            Some(TxFailureReason::OutOfGas),
+            TxFailureReason::from_execution_result(&revert, &[out_of_gas_trace])
+        );
+        assert_eq!(
+            Some(TxFailureReason::Reverted),
+            TxFailureReason::from_execution_result(&revert, &[reverted_trace])
+        );
+        assert_eq!(
+            Some(TxFailureReason::BootloaderOutOfGas),
+            TxFailureReason::from_execution_result(
+                &ExecutionResult::Halt {
+                    reason: Halt::BootloaderOutOfGas
+                },
+                &[]
+            )
+        );
+        assert_eq!(
+            Some(TxFailureReason::ValidationFailed),
+            TxFailureReason::from_execution_result(
+                &ExecutionResult::Halt {
+                    reason: Halt::FromIsNotAnAccount
+                },
+                &[]
+            )
+        );
+    }
 }
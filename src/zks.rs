@@ -2,14 +2,21 @@ use std::sync::{Arc, RwLock};
 
 use bigdecimal::BigDecimal;
 use futures::FutureExt;
-use zksync_basic_types::{MiniblockNumber, U256};
+use jsonrpc_derive::rpc;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{
+    web3::signing::keccak256, Address, L1BatchNumber, MiniblockNumber, H256, U256,
+};
 use zksync_core::api_server::web3::backend_jsonrpc::{
     error::into_jsrpc_error, namespaces::zks::ZksNamespaceT,
 };
 use zksync_types::{
-    api::{BridgeAddresses, ProtocolVersion},
+    api::{self, BlockIdVariant, BridgeAddresses, ProtocolVersion},
     fee::Fee,
+    get_nonce_key,
+    utils::{decompose_full_nonce, deployed_address_create},
 };
+use zksync_utils::{address_to_h256, h256_to_u256};
 use zksync_web3_decl::{
     error::Web3Error,
     types::{Filter, Log},
@@ -17,11 +24,98 @@ use zksync_web3_decl::{
 
 use crate::{
     fork::ForkSource,
-    node::InMemoryNodeInner,
+    node::{InMemoryNodeInner, L2_GAS_PRICE},
     utils::{not_implemented, IntoBoxedFuture},
 };
 use colored::Colorize;
 
+/// Both nonces of an account, as stored in the `NonceHolder` system contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountNonces {
+    /// Number of transactions sent by the account.
+    pub transaction_nonce: U256,
+    /// Number of contracts deployed by the account, used to derive `CREATE` addresses.
+    pub deployment_nonce: U256,
+}
+
+/// Methods of the `zks` namespace that are specific to the test node.
+#[rpc]
+pub trait ZksExtNamespaceT {
+    /// Returns the transaction nonce and the deployment nonce of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The account to read the nonces of.
+    /// * `block` - Optional block ID variant, defaults to the latest block.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the `AccountNonces` of the account.
+    #[rpc(name = "zks_getAccountNonces")]
+    fn get_account_nonces(
+        &self,
+        address: Address,
+        block: Option<BlockIdVariant>,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<AccountNonces>>;
+
+    /// Returns the address of a contract deployed with `CREATE`, following zkSync's address derivation.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The account deploying the contract.
+    /// * `nonce` - The deployment nonce of the sender at the time of deployment.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the address of the contract.
+    #[rpc(name = "zks_getContractAddress")]
+    fn get_contract_address(
+        &self,
+        sender: Address,
+        nonce: U256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Address>>;
+
+    /// Returns the address of a contract deployed with `CREATE2`, following zkSync's address derivation.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The account deploying the contract.
+    /// * `salt` - The salt used for the deployment.
+    /// * `bytecode_hash` - The zkSync bytecode hash of the contract.
+    /// * `constructor_input_hash` - The keccak256 hash of the constructor calldata.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the address of the contract.
+    #[rpc(name = "zks_getCreate2Address")]
+    fn get_create2_address(
+        &self,
+        sender: Address,
+        salt: H256,
+        bytecode_hash: H256,
+        constructor_input_hash: H256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Address>>;
+}
+
+/// Computes the address of a contract deployed with `CREATE2`, mirroring the `ContractDeployer` system contract:
+/// `keccak256(keccak256("zksyncCreate2") ++ sender ++ salt ++ bytecode_hash ++ constructor_input_hash)[12..]`.
+pub fn create2_address(
+    sender: Address,
+    salt: H256,
+    bytecode_hash: H256,
+    constructor_input_hash: H256,
+) -> Address {
+    let mut digest = Vec::with_capacity(5 * 32);
+    digest.extend_from_slice(&keccak256(b"zksyncCreate2"));
+    digest.extend_from_slice(address_to_h256(&sender).as_bytes());
+    digest.extend_from_slice(salt.as_bytes());
+    digest.extend_from_slice(bytecode_hash.as_bytes());
+    digest.extend_from_slice(constructor_input_hash.as_bytes());
+
+    Address::from_slice(&keccak256(&digest)[12..])
+}
+
 /// Mock implementation of ZksNamespace - used only in the test node.
 pub struct ZkMockNamespaceImpl<S> {
     node: Arc<RwLock<InMemoryNodeInner<S>>>,
@@ -90,10 +184,34 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> ZksNamespaceT
         not_implemented("zks_getTestnetPaymaster")
     }
 
+    /// Returns the addresses of the default bridge contracts of the forked chain.
+    /// The result is cached by the fork source, so only the first call reaches the network.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the `BridgeAddresses`.
     fn get_bridge_contracts(
         &self,
     ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<BridgeAddresses>> {
-        not_implemented("zks_getBridgeContracts")
+        let reader = match self.node.read() {
+            Ok(r) => r,
+            Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        };
+        let storage = match reader.fork_storage.inner.read() {
+            Ok(r) => r,
+            Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        };
+        match storage.fork.as_ref() {
+            Some(fork) => fork
+                .fork_source
+                .get_bridge_contracts()
+                .map_err(|err| {
+                    log::error!("failed fetching bridge contracts from fork: {:?}", err);
+                    into_jsrpc_error(Web3Error::InternalError)
+                })
+                .into_boxed_future(),
+            None => not_implemented("zks_getBridgeContracts"),
+        }
     }
 
     fn l1_chain_id(
@@ -180,12 +298,60 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> ZksNamespaceT
         not_implemented("zks_L1BatchNumber")
     }
 
+    /// Returns additional zkSync-specific information about a locally produced L2 block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_number` - The number of the block.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the `BlockDetails`, or `None` if the block is not known locally.
     fn get_block_details(
         &self,
-        _block_number: zksync_basic_types::MiniblockNumber,
+        block_number: zksync_basic_types::MiniblockNumber,
     ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<zksync_types::api::BlockDetails>>>
     {
-        not_implemented("zks_getBlockDetails")
+        let inner = Arc::clone(&self.node);
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            let maybe_block = reader
+                .block_hashes
+                .get(&(block_number.0 as u64))
+                .and_then(|hash| reader.blocks.get(hash))
+                .map(|block| api::BlockDetails {
+                    number: MiniblockNumber(block.number.as_u32()),
+                    l1_batch_number: L1BatchNumber(
+                        block.l1_batch_number.unwrap_or_default().as_u32(),
+                    ),
+                    base: api::BlockDetailsBase {
+                        timestamp: block.timestamp.as_u64(),
+                        l1_tx_count: 0,
+                        l2_tx_count: block.transactions.len(),
+                        root_hash: Some(block.hash),
+                        status: api::BlockStatus::Verified,
+                        commit_tx_hash: None,
+                        committed_at: None,
+                        prove_tx_hash: None,
+                        proven_at: None,
+                        execute_tx_hash: None,
+                        executed_at: None,
+                        l1_gas_price: reader.l1_gas_price,
+                        l2_fair_gas_price: L2_GAS_PRICE,
+                        base_system_contracts_hashes: reader
+                            .system_contracts
+                            .baseline_contracts
+                            .hashes(),
+                    },
+                    operator_address: block.author,
+                    protocol_version: None,
+                });
+
+            Ok(maybe_block)
+        })
     }
 
     fn get_miniblock_range(
@@ -240,11 +406,53 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> ZksNamespaceT
         not_implemented("zks_getL1GasPrice")
     }
 
+    /// Returns the details of the given protocol version - or of the current one, if no version is given.
+    /// When forking, the details are fetched from the forked chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `version_id` - Optional protocol version id, defaults to the current protocol version.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the `ProtocolVersion`, or `None` if the version is not known.
     fn get_protocol_version(
         &self,
-        _version_id: Option<u16>,
+        version_id: Option<u16>,
     ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Option<ProtocolVersion>>> {
-        not_implemented("zks_getProtocolVersion")
+        let reader = match self.node.read() {
+            Ok(r) => r,
+            Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        };
+        let storage = match reader.fork_storage.inner.read() {
+            Ok(r) => r,
+            Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        };
+        if let Some(fork) = storage.fork.as_ref() {
+            return fork
+                .fork_source
+                .get_protocol_version(version_id)
+                .map_err(|err| {
+                    log::error!("failed fetching protocol version from fork: {:?}", err);
+                    into_jsrpc_error(Web3Error::InternalError)
+                })
+                .into_boxed_future();
+        }
+
+        let current_version_id = reader.protocol_version as u16;
+        let protocol_version = match version_id {
+            Some(version_id) if version_id != current_version_id => None,
+            _ => Some(ProtocolVersion {
+                version_id: current_version_id,
+                // the node runs the same protocol version from genesis
+                timestamp: 0,
+                verification_keys_hashes: Default::default(),
+                base_system_contracts: reader.system_contracts.baseline_contracts.hashes(),
+                l2_system_upgrade_tx_hash: None,
+            }),
+        };
+
+        Ok(protocol_version).into_boxed_future()
     }
 
     fn get_logs_with_virtual_blocks(
@@ -255,17 +463,77 @@ impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> ZksNamespaceT
     }
 }
 
+impl<S: Send + Sync + 'static + ForkSource + std::fmt::Debug> ZksExtNamespaceT
+    for ZkMockNamespaceImpl<S>
+{
+    fn get_account_nonces(
+        &self,
+        address: Address,
+        block: Option<BlockIdVariant>,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<AccountNonces>> {
+        let inner = Arc::clone(&self.node);
+
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+            let full_nonce = reader
+                .read_value_at_block(&get_nonce_key(&address), block)
+                .map_err(|err| {
+                    log::error!("failed reading account nonces: {}", err);
+                    into_jsrpc_error(Web3Error::InternalError)
+                })?;
+            let (transaction_nonce, deployment_nonce) =
+                decompose_full_nonce(h256_to_u256(full_nonce));
+
+            Ok(AccountNonces {
+                transaction_nonce,
+                deployment_nonce,
+            })
+        })
+    }
+
+    fn get_contract_address(
+        &self,
+        sender: Address,
+        nonce: U256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Address>> {
+        Ok(deployed_address_create(sender, nonce)).into_boxed_future()
+    }
+
+    fn get_create2_address(
+        &self,
+        sender: Address,
+        salt: H256,
+        bytecode_hash: H256,
+        constructor_input_hash: H256,
+    ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<Address>> {
+        Ok(create2_address(
+            sender,
+            salt,
+            bytecode_hash,
+            constructor_input_hash,
+        ))
+        .into_boxed_future()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
     use crate::node::ShowCalls;
     use crate::system_contracts;
-    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode};
+    use crate::{
+        http_fork_source::HttpForkSource,
+        node::{InMemoryNode, InMemoryNodeBuilder},
+        testing,
+    };
 
     use super::*;
-    use zksync_basic_types::Address;
-    use zksync_types::transaction_request::CallRequest;
+    use zksync_basic_types::Nonce;
+    use zksync_core::api_server::web3::backend_jsonrpc::namespaces::eth::EthNamespaceT;
+    use zksync_types::{transaction_request::CallRequest, ProtocolVersionId};
 
     #[tokio::test]
     async fn test_estimate_fee() {
@@ -358,4 +626,182 @@ mod tests {
         // Assert
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_get_block_details_returns_configured_operator() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let namespace = ZkMockNamespaceImpl::new(node.get_inner());
+        let coinbase = Address::from_str("0x36615Cf349d7F6344891B1e7CA7C72883F5dc049")
+            .expect("Failed to parse address");
+        {
+            let mut writer = node.get_inner().write().unwrap();
+            writer.coinbase = coinbase;
+            crate::utils::mine_empty_blocks(&mut writer, 1, 1000)
+                .expect("failed mining empty blocks");
+        }
+
+        let details = namespace
+            .get_block_details(MiniblockNumber(1))
+            .await
+            .expect("get block details")
+            .expect("block details exist");
+
+        assert_eq!(MiniblockNumber(1), details.number);
+        assert_eq!(coinbase, details.operator_address);
+        assert!(namespace
+            .get_block_details(MiniblockNumber(100))
+            .await
+            .expect("get block details")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_version_returns_configured_version() {
+        let node = InMemoryNodeBuilder::<HttpForkSource>::default()
+            .protocol_version(ProtocolVersionId::Version14)
+            .build();
+        let namespace = ZkMockNamespaceImpl::new(node.get_inner());
+
+        let protocol_version = namespace
+            .get_protocol_version(None)
+            .await
+            .expect("get protocol version")
+            .expect("protocol version exists");
+
+        assert_eq!(
+            ProtocolVersionId::Version14 as u16,
+            protocol_version.version_id
+        );
+        assert_eq!(
+            node.get_inner()
+                .read()
+                .unwrap()
+                .system_contracts
+                .baseline_contracts
+                .hashes(),
+            protocol_version.base_system_contracts
+        );
+        assert!(namespace
+            .get_protocol_version(Some(ProtocolVersionId::Version13 as u16))
+            .await
+            .expect("get protocol version")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_account_nonces_tracks_deployment_nonce() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let namespace = ZkMockNamespaceImpl::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+
+        let nonces = namespace
+            .get_account_nonces(from_account, None)
+            .await
+            .expect("get account nonces");
+        assert_eq!(U256::zero(), nonces.transaction_nonce);
+        assert_eq!(U256::zero(), nonces.deployment_nonce);
+
+        for nonce in 0..2 {
+            testing::deploy_contract(
+                &node,
+                H256::repeat_byte(nonce as u8 + 1),
+                private_key,
+                hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+                None,
+                Nonce(nonce),
+            );
+
+            let nonces = namespace
+                .get_account_nonces(from_account, None)
+                .await
+                .expect("get account nonces");
+            assert_eq!(U256::from(nonce + 1), nonces.transaction_nonce);
+            assert_eq!(U256::from(nonce + 1), nonces.deployment_nonce);
+        }
+    }
+
+    async fn deployed_contract_address(
+        node: &InMemoryNode<HttpForkSource>,
+        tx_hash: H256,
+    ) -> Address {
+        node.get_transaction_receipt(tx_hash)
+            .await
+            .expect("get transaction receipt")
+            .expect("receipt exists")
+            .contract_address
+            .expect("contract was deployed")
+    }
+
+    #[tokio::test]
+    async fn test_get_contract_address_matches_create_deployment() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let namespace = ZkMockNamespaceImpl::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+
+        for nonce in 0..2 {
+            let predicted = namespace
+                .get_contract_address(from_account, U256::from(nonce))
+                .await
+                .expect("get contract address");
+
+            let tx_hash = H256::repeat_byte(nonce as u8 + 1);
+            testing::deploy_contract(
+                &node,
+                tx_hash,
+                private_key,
+                hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+                None,
+                Nonce(nonce),
+            );
+
+            assert_eq!(predicted, deployed_contract_address(&node, tx_hash).await);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_create2_address_matches_create2_deployment() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let namespace = ZkMockNamespaceImpl::new(node.get_inner());
+
+        let private_key = H256::repeat_byte(0xef);
+        let from_account = zksync_types::PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from_account);
+
+        let bytecode = hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap();
+        let bytecode_hash = zksync_utils::bytecode::hash_bytecode(&bytecode);
+        let salt = H256::repeat_byte(0x42);
+        let predicted = namespace
+            .get_create2_address(from_account, salt, bytecode_hash, H256(keccak256(&[])))
+            .await
+            .expect("get create2 address");
+
+        let tx_hash = H256::repeat_byte(0x1);
+        testing::deploy_contract_create2(
+            &node,
+            salt,
+            tx_hash,
+            private_key,
+            bytecode,
+            None,
+            Nonce(0),
+        );
+
+        assert_eq!(predicted, deployed_contract_address(&node, tx_hash).await);
+        assert_ne!(
+            predicted,
+            namespace
+                .get_contract_address(from_account, U256::zero())
+                .await
+                .expect("get contract address")
+        );
+    }
 }